@@ -0,0 +1,163 @@
+use crate::error::SqlError;
+use crate::schema::{ColumnSchema, ColumnType};
+use crate::value::{Value, date_key};
+// aggregate.rs
+
+/// Aggregate functions usable in a SELECT projection.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AggregateFunc {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggregateFunc {
+    /// Looks up an aggregate function by name (case-insensitive).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_uppercase().as_str() {
+            "COUNT" => Some(AggregateFunc::Count),
+            "SUM" => Some(AggregateFunc::Sum),
+            "AVG" => Some(AggregateFunc::Avg),
+            "MIN" => Some(AggregateFunc::Min),
+            "MAX" => Some(AggregateFunc::Max),
+            _ => None,
+        }
+    }
+
+    /// The SQL name of the function.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AggregateFunc::Count => "COUNT",
+            AggregateFunc::Sum => "SUM",
+            AggregateFunc::Avg => "AVG",
+            AggregateFunc::Min => "MIN",
+            AggregateFunc::Max => "MAX",
+        }
+    }
+}
+
+/// An aggregate call over a single column, e.g. `AVG(price)`, or `COUNT(*)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Aggregate {
+    pub func: AggregateFunc,
+    /// The aggregated column, or `None` for `*`.
+    pub column: Option<String>,
+}
+
+impl Aggregate {
+    /// Parses `FUNC(column)` or `FUNC(*)`. Returns `None` if `expr` is not an aggregate call.
+    pub fn parse(expr: &str) -> Option<Self> {
+        let expr = expr.trim();
+        let open = expr.find('(')?;
+        let inner = expr[open + 1..].strip_suffix(')')?.trim();
+        let func = AggregateFunc::from_name(expr[..open].trim())?;
+        let column = if inner == "*" {
+            None
+        } else {
+            Some(inner.to_string())
+        };
+        Some(Aggregate { func, column })
+    }
+
+    /// The column header for this aggregate, e.g. `COUNT(*)`.
+    pub fn label(&self) -> String {
+        format!(
+            "{}({})",
+            self.func.name(),
+            self.column.as_deref().unwrap_or("*")
+        )
+    }
+
+    /// The type of the aggregate's result over rows laid out according to `columns`, or
+    /// `None` if the aggregated column does not exist.
+    pub fn result_type(&self, columns: &[ColumnSchema]) -> Option<ColumnType> {
+        let col_type = || {
+            let name = self.column.as_ref()?;
+            columns.iter().find(|c| &c.name == name).map(|c| c.col_type.clone())
+        };
+        match self.func {
+            AggregateFunc::Count => Some(ColumnType::Int),
+            AggregateFunc::Sum => match col_type()? {
+                ColumnType::Int => Some(ColumnType::Int),
+                _ => Some(ColumnType::Float),
+            },
+            AggregateFunc::Avg => Some(ColumnType::Float),
+            AggregateFunc::Min | AggregateFunc::Max => col_type(),
+        }
+    }
+
+    /// Computes the aggregate over full table rows laid out according to `columns`.
+    /// NULL values are ignored; SUM/AVG/MIN/MAX of no values yield NULL, as does a SUM of
+    /// integers that overflows, like integer arithmetic.
+    pub fn evaluate(
+        &self,
+        columns: &[ColumnSchema],
+        rows: &[Vec<Value>],
+    ) -> Result<Value, SqlError> {
+        let Some(col_name) = &self.column else {
+            return match self.func {
+                AggregateFunc::Count => Ok(Value::Int(rows.len() as i64)),
+                _ => Err(SqlError::InvalidAggregate(format!(
+                    "{}(*) is not supported",
+                    self.func.name()
+                ))),
+            };
+        };
+        let idx = columns
+            .iter()
+            .position(|c| &c.name == col_name)
+            .ok_or_else(|| SqlError::ColumnNotFound(col_name.clone()))?;
+        let col_type = &columns[idx].col_type;
+        let values: Vec<&Value> = rows
+            .iter()
+            .filter_map(|row| row.get(idx))
+            .filter(|v| !v.is_null())
+            .collect();
+
+        match self.func {
+            AggregateFunc::Count => Ok(Value::Int(values.len() as i64)),
+            AggregateFunc::Sum | AggregateFunc::Avg => {
+                if !matches!(col_type, ColumnType::Int | ColumnType::Float) {
+                    return Err(SqlError::InvalidAggregate(format!(
+                        "{} requires a numeric column, but '{}' is {:?}",
+                        self.func.name(),
+                        col_name,
+                        col_type
+                    )));
+                }
+                if values.is_empty() {
+                    return Ok(Value::Null);
+                }
+                if self.func == AggregateFunc::Sum && *col_type == ColumnType::Int {
+                    let sum = values
+                        .iter()
+                        .filter_map(|v| v.as_int())
+                        .try_fold(0i64, |sum, n| sum.checked_add(n));
+                    return Ok(sum.map_or(Value::Null, Value::Int));
+                }
+                let sum: f64 = values.iter().filter_map(|v| v.as_float()).sum();
+                if self.func == AggregateFunc::Sum {
+                    Ok(Value::Float(sum))
+                } else {
+                    Ok(Value::Float(sum / values.len() as f64))
+                }
+            }
+            AggregateFunc::Min | AggregateFunc::Max => {
+                // Dates order chronologically, whichever separator they were written with
+                let date = |v: &Value| v.as_str().and_then(date_key);
+                let order = |a: &&Value, b: &&Value| match col_type {
+                    ColumnType::Date => date(a).cmp(&date(b)).then_with(|| a.compare(b)),
+                    _ => a.compare(b),
+                };
+                let value = if self.func == AggregateFunc::Min {
+                    values.into_iter().min_by(order)
+                } else {
+                    values.into_iter().max_by(order)
+                };
+                Ok(value.cloned().unwrap_or(Value::Null))
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use crate::error::SqlError;
+use crate::schema::{ColumnSchema, ColumnType};
+use crate::value::Value;
+// arithmetic.rs
+
+/// A binary arithmetic operator.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArithOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl ArithOp {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '+' => Some(ArithOp::Add),
+            '-' => Some(ArithOp::Sub),
+            '*' => Some(ArithOp::Mul),
+            '/' => Some(ArithOp::Div),
+            _ => None,
+        }
+    }
+}
+
+/// One side of an arithmetic expression.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operand {
+    Column(String),
+    Literal(Value),
+}
+
+impl Operand {
+    /// Parses a number or a (possibly qualified) column name.
+    fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if text.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.') {
+            return match text.parse::<i64>() {
+                Ok(n) => Some(Operand::Literal(Value::Int(n))),
+                Err(_) => text.parse::<f64>().ok().map(|n| Operand::Literal(Value::Float(n))),
+            };
+        }
+        let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '.';
+        match text.chars().next() {
+            Some(first) if first.is_alphabetic() || first == '_' => {
+                text.chars().all(is_ident).then(|| Operand::Column(text.to_string()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A computed projection such as `price * stock` or `price / 2`: one operator between two
+/// operands, each a column or a numeric literal.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Arithmetic {
+    pub left: Operand,
+    pub op: ArithOp,
+    pub right: Operand,
+}
+
+impl Arithmetic {
+    /// Parses `a <op> b`. Returns `None` if `expr` is not a single binary operation.
+    pub fn parse(expr: &str) -> Option<Self> {
+        // The first operator not at the start of an operand, so `-1` stays a literal
+        let mut after_operand = false;
+        let (pos, op) = expr.char_indices().find_map(|(i, c)| {
+            let op = ArithOp::from_char(c).filter(|_| after_operand);
+            if !c.is_whitespace() {
+                after_operand = op.is_none();
+            }
+            op.map(|op| (i, op))
+        })?;
+        Some(Arithmetic {
+            left: Operand::parse(&expr[..pos])?,
+            op,
+            right: Operand::parse(&expr[pos + 1..])?,
+        })
+    }
+
+    /// Names of the columns the expression reads.
+    pub fn columns(&self) -> impl Iterator<Item = &String> {
+        [&self.left, &self.right].into_iter().filter_map(|operand| match operand {
+            Operand::Column(name) => Some(name),
+            Operand::Literal(_) => None,
+        })
+    }
+
+    /// The type of the result for rows laid out according to `columns`: Int if both operands
+    /// are integers, otherwise Float.
+    pub fn result_type(&self, columns: &[ColumnSchema]) -> ColumnType {
+        let is_int = |operand: &Operand| match operand {
+            Operand::Literal(value) => matches!(value, Value::Int(_)),
+            Operand::Column(name) => {
+                columns.iter().any(|c| &c.name == name && c.col_type == ColumnType::Int)
+            }
+        };
+        if is_int(&self.left) && is_int(&self.right) {
+            ColumnType::Int
+        } else {
+            ColumnType::Float
+        }
+    }
+
+    /// Computes the expression for a row laid out according to `columns`. Two integers give
+    /// an integer (division truncates); otherwise the result is a float. A NULL operand,
+    /// division by zero and integer overflow yield NULL.
+    pub fn evaluate(&self, columns: &[ColumnSchema], row: &[Value]) -> Result<Value, SqlError> {
+        let resolve = |operand: &Operand| match operand {
+            Operand::Literal(value) => Ok(value.clone()),
+            Operand::Column(name) => {
+                let idx = columns
+                    .iter()
+                    .position(|c| &c.name == name)
+                    .ok_or_else(|| SqlError::ColumnNotFound(name.clone()))?;
+                let col_type = &columns[idx].col_type;
+                if !matches!(col_type, ColumnType::Int | ColumnType::Float) {
+                    return Err(SqlError::Parse(format!(
+                        "Arithmetic requires numeric columns, but '{}' is {:?}",
+                        name, col_type
+                    )));
+                }
+                Ok(row.get(idx).cloned().unwrap_or(Value::Null))
+            }
+        };
+        let (left, right) = (resolve(&self.left)?, resolve(&self.right)?);
+        if let (Some(a), Some(b)) = (left.as_int(), right.as_int()) {
+            let result = match self.op {
+                ArithOp::Add => a.checked_add(b),
+                ArithOp::Sub => a.checked_sub(b),
+                ArithOp::Mul => a.checked_mul(b),
+                ArithOp::Div => a.checked_div(b),
+            };
+            return Ok(result.map_or(Value::Null, Value::Int));
+        }
+        let (Some(a), Some(b)) = (left.as_float(), right.as_float()) else {
+            return Ok(Value::Null);
+        };
+        Ok(match self.op {
+            ArithOp::Add => Value::Float(a + b),
+            ArithOp::Sub => Value::Float(a - b),
+            ArithOp::Mul => Value::Float(a * b),
+            ArithOp::Div if b == 0.0 => Value::Null,
+            ArithOp::Div => Value::Float(a / b),
+        })
+    }
+}
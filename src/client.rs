@@ -1,5 +1,11 @@
+use crate::metrics::MetricsSnapshot;
+use crate::server::{CursorPage, HealthStatus, ServerInfo, TypedQueryResponse};
+use crate::sql::PreparedStatement;
+use crate::table::TableDescription;
+use crate::value::Value;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
+use std::time::Duration;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct QueryResponse {
@@ -21,7 +27,8 @@ struct JsonRpcResponse {
     jsonrpc: String,
     result: Option<serde_json::Value>,
     error: Option<JsonRpcError>,
-    id: u64,
+    /// `None` when the server rejects a request before reading it, e.g. without auth.
+    id: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -30,57 +37,431 @@ struct JsonRpcError {
     message: String,
 }
 
+impl JsonRpcRequest {
+    fn new(method: &str, params: serde_json::Value) -> Self {
+        JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params,
+            id: 1,
+        }
+    }
+}
+
+impl JsonRpcResponse {
+    /// Returns the call's result, or the server's error message.
+    fn into_result(self) -> std::result::Result<serde_json::Value, Box<dyn Error>> {
+        match (self.result, self.error) {
+            (Some(result), _) => Ok(result),
+            (None, Some(error)) => Err(error.message.into()),
+            _ => Err("Invalid response from server".into()),
+        }
+    }
+}
+
+/// Connection settings for `RustDBClient`.
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// Maximum time to wait for a single request.
+    pub timeout: Duration,
+    /// Extra attempts for read-only calls that fail to connect or time out.
+    pub retries: u32,
+    /// Delay before the first retry; doubled for each further retry.
+    pub backoff: Duration,
+    /// PEM certificate to trust for `https://` endpoints, e.g. a self-signed server cert.
+    pub ca_cert: Option<String>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            retries: 2,
+            backoff: Duration::from_millis(200),
+            ca_cert: None,
+        }
+    }
+}
+
 pub struct RustDBClient {
     client: reqwest::blocking::Client,
     endpoint: String,
+    config: ClientConfig,
+    auth_token: Option<String>,
 }
 
 impl RustDBClient {
     pub fn new(host: &str, port: u16) -> Self {
-        RustDBClient {
-            client: reqwest::blocking::Client::new(),
-            endpoint: format!("http://{}:{}", host, port),
+        Self::with_config(host, port, ClientConfig::default())
+    }
+
+    pub fn with_config(host: &str, port: u16, config: ClientConfig) -> Self {
+        Self::connect(&format!("http://{}:{}", host, port), config)
+            .expect("Failed to build HTTP client")
+    }
+
+    /// Connects to a full endpoint URL such as `https://db.example.com:3030`.
+    /// Fails if `config.ca_cert` cannot be read or is not a valid PEM certificate.
+    pub fn connect(endpoint: &str, config: ClientConfig) -> std::result::Result<Self, Box<dyn Error>> {
+        let mut builder = reqwest::blocking::Client::builder().timeout(config.timeout);
+        if let Some(path) = &config.ca_cert {
+            let pem = std::fs::read(path)?;
+            builder = builder.add_root_certificate(reqwest::Certificate::from_pem(&pem)?);
         }
+        Ok(RustDBClient {
+            client: builder.build()?,
+            endpoint: endpoint.to_string(),
+            config,
+            auth_token: None,
+        })
     }
 
-    fn send_request(&self, method: &str, params: serde_json::Value) -> std::result::Result<serde_json::Value, Box<dyn Error>> {
-        let request = JsonRpcRequest {
-            jsonrpc: "2.0".to_string(),
-            method: method.to_string(),
-            params,
-            id: 1,
-        };
+    /// Sends `token` as a bearer token with every request, for servers started with
+    /// `--auth-token`.
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.auth_token = Some(token.to_string());
+        self
+    }
 
-        let response: JsonRpcResponse = self.client
-            .post(&self.endpoint)
-            .json(&request)
-            .send()?
-            .json()?;
+    /// Sends a call, retrying with backoff on connection errors and timeouts if the call
+    /// is `idempotent`. Writes are sent once so a lost response never applies them twice.
+    fn send_request(&self, method: &str, params: serde_json::Value, idempotent: bool) -> std::result::Result<serde_json::Value, Box<dyn Error>> {
+        let request = JsonRpcRequest::new(method, params);
+        let attempts = if idempotent { self.config.retries + 1 } else { 1 };
+        let mut delay = self.config.backoff;
 
-        match (response.result, response.error) {
-            (Some(result), _) => Ok(result),
-            (None, Some(error)) => Err(error.message.into()),
-            _ => Err("Invalid response from server".into()),
+        for attempt in 1..=attempts {
+            let mut http_request = self.client.post(&self.endpoint).json(&request);
+            if let Some(token) = &self.auth_token {
+                http_request = http_request.bearer_auth(token);
+            }
+            match http_request.send() {
+                Ok(response) => {
+                    let response: JsonRpcResponse = response.json()?;
+                    return response.into_result();
+                }
+                Err(e) if (e.is_connect() || e.is_timeout()) && attempt < attempts => {
+                    std::thread::sleep(delay);
+                    delay *= 2;
+                }
+                Err(e) => {
+                    return Err(format!("Request failed after {} attempt(s): {}", attempt, e).into());
+                }
+            }
         }
+        unreachable!("at least one attempt is always made")
     }
 
     pub fn execute(&self, query: &str) -> std::result::Result<QueryResponse, Box<dyn Error>> {
         let params = serde_json::json!([query]);
-        let result = self.send_request("execute", params)?;
+        let is_read = crate::sql::is_read_only(query);
+        let result = self.send_request("execute", params, is_read)?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Executes a query like `execute`, but a SELECT's rows come back as typed values
+    /// together with the column names and types.
+    pub fn execute_typed(
+        &self,
+        query: &str,
+    ) -> std::result::Result<TypedQueryResponse, Box<dyn Error>> {
+        let params = serde_json::json!([query]);
+        let is_read = crate::sql::is_read_only(query);
+        let result = self.send_request("execute_typed", params, is_read)?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Executes a prepared statement with its placeholders bound to `params`. The server
+    /// binds the parameters, so they are never spliced into SQL text here.
+    pub fn execute_prepared(
+        &self,
+        stmt: &PreparedStatement,
+        params: Vec<Value>,
+    ) -> std::result::Result<QueryResponse, Box<dyn Error>> {
+        if params.len() != stmt.param_count() {
+            return Err(format!(
+                "Expected {} parameter(s) but got {}",
+                stmt.param_count(),
+                params.len()
+            )
+            .into());
+        }
+        let params = serde_json::json!([stmt.sql(), params]);
+        let is_read = crate::sql::is_read_only(stmt.sql());
+        let result = self.send_request("execute_prepared", params, is_read)?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Counts the rows of a table, or only those matching `where_clause`, without fetching
+    /// them. Fails with the server's message if the table does not exist.
+    pub fn count(
+        &self,
+        table: &str,
+        where_clause: Option<&str>,
+    ) -> std::result::Result<u64, Box<dyn Error>> {
+        let query = match where_clause {
+            Some(condition) => format!("SELECT COUNT(*) FROM {} WHERE {}", table, condition),
+            None => format!("SELECT COUNT(*) FROM {}", table),
+        };
+        let response = self.execute(&query)?;
+        if !response.success {
+            return Err(response.message.into());
+        }
+        match response.rows.as_deref() {
+            Some([row]) if row.len() == 1 => Ok(row[0].parse()?),
+            _ => Err("Invalid COUNT response from server".into()),
+        }
+    }
+
+    /// Runs a SELECT on the server and returns a cursor id to page through its rows with
+    /// `select_fetch`.
+    pub fn select_open(&self, query: &str) -> std::result::Result<u64, Box<dyn Error>> {
+        let params = serde_json::json!([query]);
+        let result = self.send_request("select_open", params, false)?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Reads up to `n` more rows of a cursor. The cursor is closed once `done` is returned.
+    pub fn select_fetch(
+        &self,
+        cursor_id: u64,
+        n: usize,
+    ) -> std::result::Result<CursorPage, Box<dyn Error>> {
+        let params = serde_json::json!([cursor_id, n]);
+        let result = self.send_request("select_fetch", params, false)?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Frees a cursor that will not be read to the end.
+    pub fn select_close(&self, cursor_id: u64) -> std::result::Result<bool, Box<dyn Error>> {
+        let params = serde_json::json!([cursor_id]);
+        let result = self.send_request("select_close", params, true)?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Runs a SELECT and returns an iterator over its rows in pages of up to `page_size`,
+    /// so a large result never has to be transferred at once.
+    pub fn select_pages(
+        &self,
+        query: &str,
+        page_size: usize,
+    ) -> std::result::Result<SelectPages<'_>, Box<dyn Error>> {
+        Ok(SelectPages {
+            client: self,
+            cursor_id: self.select_open(query)?,
+            page_size: page_size.max(1),
+            done: false,
+        })
+    }
+
+    /// Executes several statements in order in one request, returning one response each.
+    /// Batches are never retried, as they may contain writes.
+    pub fn execute_batch(&self, queries: &[&str]) -> std::result::Result<Vec<QueryResponse>, Box<dyn Error>> {
+        let params = serde_json::json!([queries]);
+        let result = self.send_request("execute_batch", params, false)?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Like `execute_batch`, but all or nothing: if a statement fails, the ones before it
+    /// are rolled back and the rest are not run.
+    pub fn execute_batch_atomic(
+        &self,
+        queries: &[&str],
+    ) -> std::result::Result<Vec<QueryResponse>, Box<dyn Error>> {
+        let params = serde_json::json!([queries, true]);
+        let result = self.send_request("execute_batch", params, false)?;
         Ok(serde_json::from_value(result)?)
     }
 
     pub fn ping(&self) -> std::result::Result<String, Box<dyn Error>> {
         let params = serde_json::json!([]);
-        let result = self.send_request("ping", params)?;
+        let result = self.send_request("ping", params, true)?;
         Ok(result.as_str()
             .ok_or("Invalid response type")?
             .to_string())
     }
 
+    /// Returns the server's version, protocol version and uptime, logging a warning if
+    /// its protocol differs from this client's.
+    pub fn server_info(&self) -> std::result::Result<ServerInfo, Box<dyn Error>> {
+        let params = serde_json::json!([]);
+        let result = self.send_request("server_info", params, true)?;
+        let info: ServerInfo = serde_json::from_value(result)?;
+        if !info.is_compatible() {
+            log::warn!(
+                "Server {} speaks protocol {}, but this client speaks {}",
+                info.version,
+                info.protocol_version,
+                crate::server::PROTOCOL_VERSION
+            );
+        }
+        Ok(info)
+    }
+
+    /// Returns the server's role, size and replication position.
+    pub fn health(&self) -> std::result::Result<HealthStatus, Box<dyn Error>> {
+        let params = serde_json::json!([]);
+        let result = self.send_request("health", params, true)?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Returns the server's query and replication counters.
+    pub fn metrics(&self) -> std::result::Result<MetricsSnapshot, Box<dyn Error>> {
+        let params = serde_json::json!([]);
+        let result = self.send_request("metrics", params, true)?;
+        Ok(serde_json::from_value(result)?)
+    }
+
     pub fn list_tables(&self) -> std::result::Result<Vec<String>, Box<dyn Error>> {
         let params = serde_json::json!([]);
-        let result = self.send_request("list_tables", params)?;
+        let result = self.send_request("list_tables", params, true)?;
+
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Returns a table's columns, types and constraints; fails if the table does not exist.
+    pub fn describe_table(&self, name: &str) -> std::result::Result<TableDescription, Box<dyn Error>> {
+        let params = serde_json::json!([name]);
+        let result = self.send_request("describe_table", params, true)?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Returns the number of rows in a table; fails if the table does not exist.
+    pub fn table_row_count(&self, name: &str) -> std::result::Result<usize, Box<dyn Error>> {
+        let params = serde_json::json!([name]);
+        let result = self.send_request("table_row_count", params, true)?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Returns the hex SHA-256 digest of the server's tables, schemas and rows.
+    pub fn replication_checksum(&self) -> std::result::Result<String, Box<dyn Error>> {
+        let params = serde_json::json!([]);
+        let result = self.send_request("replication_checksum", params, true)?;
+        Ok(result.as_str()
+            .ok_or("Invalid response type")?
+            .to_string())
+    }
+
+    /// Promotes a replica to primary so it accepts writes. Returns `false` if the server
+    /// already was a primary. Only do this once the old primary is down.
+    pub fn promote(&self) -> std::result::Result<bool, Box<dyn Error>> {
+        let params = serde_json::json!([]);
+        let result = self.send_request("promote", params, false)?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Wipes every table on the primary and its replicas. Only servers started with an
+    /// auth token allow this.
+    pub fn admin_reset(&self) -> std::result::Result<(), Box<dyn Error>> {
+        let params = serde_json::json!([]);
+        self.send_request("admin_reset", params, false)?;
+        Ok(())
+    }
+}
+
+/// Pages of rows read from a server-side cursor; see `RustDBClient::select_pages`. Dropping
+/// it before the last page closes the cursor.
+pub struct SelectPages<'a> {
+    client: &'a RustDBClient,
+    cursor_id: u64,
+    page_size: usize,
+    done: bool,
+}
+
+impl Iterator for SelectPages<'_> {
+    type Item = std::result::Result<Vec<Vec<String>>, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.client.select_fetch(self.cursor_id, self.page_size) {
+            Ok(page) => {
+                self.done = page.done;
+                // An empty result comes back as a single empty, final page
+                (!page.rows.is_empty()).then_some(Ok(page.rows))
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl Drop for SelectPages<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = self.client.select_close(self.cursor_id);
+        }
+    }
+}
+
+/// Returns whether `replica` currently holds exactly the same data as `primary`, by
+/// comparing their replication checksums.
+pub fn verify_replica(
+    primary: &RustDBClient,
+    replica: &RustDBClient,
+) -> std::result::Result<bool, Box<dyn Error>> {
+    Ok(primary.replication_checksum()? == replica.replication_checksum()?)
+}
+
+/// Non-blocking counterpart of `RustDBClient` for use in async applications.
+pub struct AsyncRustDBClient {
+    client: reqwest::Client,
+    endpoint: String,
+    auth_token: Option<String>,
+}
+
+impl AsyncRustDBClient {
+    pub fn new(host: &str, port: u16) -> Self {
+        AsyncRustDBClient {
+            client: reqwest::Client::new(),
+            endpoint: format!("http://{}:{}", host, port),
+            auth_token: None,
+        }
+    }
+
+    /// Sends `token` as a bearer token with every request, for servers started with
+    /// `--auth-token`.
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.auth_token = Some(token.to_string());
+        self
+    }
+
+    async fn send_request(&self, method: &str, params: serde_json::Value) -> std::result::Result<serde_json::Value, Box<dyn Error>> {
+        let request = JsonRpcRequest::new(method, params);
+
+        let mut http_request = self.client.post(&self.endpoint).json(&request);
+        if let Some(token) = &self.auth_token {
+            http_request = http_request.bearer_auth(token);
+        }
+        let response: JsonRpcResponse = http_request
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        response.into_result()
+    }
+
+    pub async fn execute(&self, query: &str) -> std::result::Result<QueryResponse, Box<dyn Error>> {
+        let params = serde_json::json!([query]);
+        let result = self.send_request("execute", params).await?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    pub async fn ping(&self) -> std::result::Result<String, Box<dyn Error>> {
+        let params = serde_json::json!([]);
+        let result = self.send_request("ping", params).await?;
+        Ok(result.as_str()
+            .ok_or("Invalid response type")?
+            .to_string())
+    }
+
+    pub async fn list_tables(&self) -> std::result::Result<Vec<String>, Box<dyn Error>> {
+        let params = serde_json::json!([]);
+        let result = self.send_request("list_tables", params).await?;
 
         Ok(serde_json::from_value(result)?)
     }
@@ -0,0 +1,126 @@
+// csv.rs
+// Minimal RFC 4180 CSV reading and writing used for table import and export.
+
+use crate::error::SqlError;
+use crate::value::Value;
+use std::fmt;
+
+/// Errors produced while importing a CSV file.
+#[derive(Debug)]
+pub enum CsvError {
+    /// The file could not be read.
+    Io(std::io::Error),
+    /// The table does not exist or the header does not match its schema.
+    Schema(SqlError),
+    /// A record could not be parsed or inserted. `line` is 1-based.
+    Row { line: usize, error: SqlError },
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::Io(e) => write!(f, "I/O error: {}", e),
+            CsvError::Schema(e) => write!(f, "{}", e),
+            CsvError::Row { line, error } => write!(f, "Line {}: {}", line, error),
+        }
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+impl From<std::io::Error> for CsvError {
+    fn from(e: std::io::Error) -> Self {
+        CsvError::Io(e)
+    }
+}
+
+/// Quotes a field if it contains a comma, quote or line break, doubling embedded quotes.
+pub fn escape_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Formats one record as a CSV line, including the trailing CRLF.
+pub fn format_record<S: AsRef<str>>(fields: &[S]) -> String {
+    let fields: Vec<String> = fields.iter().map(|f| escape_field(f.as_ref())).collect();
+    format!("{}\r\n", fields.join(","))
+}
+
+/// Formats a row of values as a CSV line. NULL is written as an empty field and an empty
+/// string as `""`, so `parse_records` reads them back apart.
+pub fn format_values(values: &[Value]) -> String {
+    let fields: Vec<String> = values
+        .iter()
+        .map(|value| match value {
+            Value::Null => String::new(),
+            Value::Str(s) if s.is_empty() => "\"\"".to_string(),
+            value => escape_field(&value.to_string()),
+        })
+        .collect();
+    format!("{}\r\n", fields.join(","))
+}
+
+/// Ends the field being read, returning `None` if it was empty and unquoted.
+fn finish_field(field: &mut String, quoted: &mut bool) -> Option<String> {
+    let field = std::mem::take(field);
+    (std::mem::take(quoted) || !field.is_empty()).then_some(field)
+}
+
+/// The fields of one record. An empty unquoted field is `None`, telling it apart from a
+/// quoted empty one (`""`).
+pub type Record = Vec<Option<String>>;
+
+/// Splits CSV text into records, each paired with the 1-based line it starts on.
+/// Quoted fields may contain commas, doubled quotes and line breaks. Blank lines are skipped.
+pub fn parse_records(text: &str) -> Result<Vec<(usize, Record)>, CsvError> {
+    let mut records = vec![];
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut start_line = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            line += 1;
+        }
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if field.is_empty() => {
+                in_quotes = true;
+                quoted = true;
+            }
+            _ if in_quotes => field.push(c),
+            ',' => fields.push(finish_field(&mut field, &mut quoted)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                fields.push(finish_field(&mut field, &mut quoted));
+                if fields.len() > 1 || fields[0].is_some() {
+                    records.push((start_line, std::mem::take(&mut fields)));
+                }
+                fields.clear();
+                start_line = line;
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(CsvError::Row {
+            line: start_line,
+            error: SqlError::Parse("Unterminated quoted field".to_string()),
+        });
+    }
+    if !fields.is_empty() || !field.is_empty() || quoted {
+        fields.push(finish_field(&mut field, &mut quoted));
+        records.push((start_line, fields));
+    }
+    Ok(records)
+}
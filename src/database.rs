@@ -1,46 +1,126 @@
 use serde::{Deserialize, Serialize};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
 // database.rs
 pub trait DatabaseInterface {
     fn create_table_with_constraints(
         &mut self,
         table_name: &str,
         columns: Vec<ColumnSchema>,
-        primary_key: Option<String>,
-        unique_columns: Vec<String>,
+        primary_key: Vec<String>,
+        unique_columns: Vec<Vec<String>>,
     );
     fn create_table(&mut self, table_name: &str, columns: Vec<ColumnSchema>);
-    fn list_tables(&self, tables: &Vec<String>);
-    fn insert(&mut self, table_name: &str, values: Vec<String>);
-    /// Updates all rows matching the predicate with new values.
-    fn update<F>(&mut self, table_name: &str, set_values: Vec<String>, predicate: F)
+    fn list_tables(&self, tables: &[String]);
+    fn insert(&mut self, table_name: &str, values: Vec<String>) -> Result<(), SqlError>;
+    /// Inserts a row of already typed values, which may include NULLs.
+    fn insert_values(&mut self, table_name: &str, values: Vec<Value>) -> Result<(), SqlError>;
+    /// Updates all rows matching the predicate, returning how many changed. `set_values` has
+    /// one entry per column; `None` leaves that column unchanged.
+    fn update<F>(
+        &mut self,
+        table_name: &str,
+        set_values: Vec<Option<String>>,
+        predicate: F,
+    ) -> Result<usize, SqlError>
+    where
+        F: Fn(&Vec<Value>) -> bool;
+    /// Updates all rows matching the predicate with new values keyed by column name,
+    /// returning how many changed.
+    fn update_named<F>(
+        &mut self,
+        table_name: &str,
+        assignments: &HashMap<String, Assignment>,
+        predicate: F,
+    ) -> Result<usize, SqlError>
     where
-        F: Fn(&Vec<String>) -> bool;
-    /// Deletes all rows matching the predicate.
-    fn delete<F>(&mut self, table_name: &str, predicate: F)
+        F: Fn(&Vec<Value>) -> bool;
+    /// Deletes all rows matching the predicate, returning how many were removed.
+    fn delete<F>(&mut self, table_name: &str, predicate: F) -> Result<usize, SqlError>
     where
-        F: Fn(&Vec<String>) -> bool;
+        F: Fn(&Vec<Value>) -> bool;
     /// Selects and prints all rows matching the predicate.
     fn select<F>(&self, table_name: &str, columns: Vec<String>, predicate: F)
     where
-        F: Fn(&Vec<String>) -> bool;
+        F: Fn(&Vec<Value>) -> bool;
+    /// Selects and returns the projected values of all rows matching the predicate.
+    fn select_rows<F>(&self, table_name: &str, columns: Vec<String>, predicate: F) -> Vec<Vec<String>>
+    where
+        F: Fn(&Vec<Value>) -> bool;
 }
 
-use crate::row::RowInterface;
-use crate::schema::{ColumnSchema, Schema};
-use crate::table::{Table, TableInterface};
-use std::collections::HashMap;
+use crate::csv;
+use crate::error::SqlError;
+use crate::row::{Row, RowInterface};
+use crate::schema::{ColumnSchema, ColumnType, ForeignKey, Schema};
+use crate::table::{Assignment, Table, TableInterface, typed_values};
+use crate::value::Value;
+use crate::wal::{self, WalEntry};
+use std::collections::{HashMap, HashSet};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Database {
     pub tables: HashMap<String, Table>,
+    /// Tables as they were at BEGIN, restored on ROLLBACK. `None` outside a transaction.
+    #[serde(skip)]
+    transaction: Option<HashMap<String, Table>>,
+    /// Write-ahead log that mutating statements are appended to, if enabled.
+    #[serde(skip)]
+    wal_path: Option<String>,
+}
+
+/// Options for `Database::save_to_file_with_opts`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SaveOptions {
+    /// Create missing parent directories instead of failing.
+    pub create_parent_dirs: bool,
+}
+
+/// What `Database::merge` does with an incoming row whose primary key is already present,
+/// or an incoming table whose columns or primary key differ from the same-named one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Keep the existing row or table.
+    Skip,
+    /// Replace the existing row or table with the incoming one.
+    Overwrite,
+    /// Fail the whole merge.
+    Error,
+}
+
+impl Default for Database {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Database {
+    /// Returns the table with the given name, if it exists.
+    pub fn get_table(&self, name: &str) -> Option<&Table> {
+        self.tables.get(name)
+    }
+
+    /// Returns the table with the given name for modification, if it exists.
+    pub fn get_table_mut(&mut self, name: &str) -> Option<&mut Table> {
+        self.tables.get_mut(name)
+    }
+
+    /// Returns the table with the given name, or `TableNotFound` if it does not exist.
+    pub fn table(&self, name: &str) -> Result<&Table, SqlError> {
+        self.get_table(name).ok_or_else(|| SqlError::TableNotFound(name.to_string()))
+    }
+
+    /// Like `table`, but returns the table for modification.
+    pub fn table_mut(&mut self, name: &str) -> Result<&mut Table, SqlError> {
+        self.get_table_mut(name).ok_or_else(|| SqlError::TableNotFound(name.to_string()))
+    }
+
     /// Returns the columns of the table with the given name, or an empty vec if not found.
     pub fn get_table_columns(&self, table_name: &str) -> Vec<String> {
-        if let Some(table) = self.tables.get(table_name) {
+        if let Some(table) = self.get_table(table_name) {
             table
                 .schema
                 .columns
@@ -51,34 +131,713 @@ impl Database {
             vec![]
         }
     }
+
+    /// Inner-joins two tables on `left.left_column == right.right_column`, returning the
+    /// combined columns (qualified as `Table.col`) and one concatenated row per match.
+    pub fn join_rows(
+        &self,
+        left: &str,
+        left_column: &str,
+        right: &str,
+        right_column: &str,
+    ) -> Result<(Vec<ColumnSchema>, Vec<Vec<Value>>), SqlError> {
+        self.joined(left, left_column, right, right_column, false)
+    }
+
+    /// Like `join_rows`, but a left row without any match is kept once, with NULL for every
+    /// column of the right table.
+    pub fn left_join_rows(
+        &self,
+        left: &str,
+        left_column: &str,
+        right: &str,
+        right_column: &str,
+    ) -> Result<(Vec<ColumnSchema>, Vec<Vec<Value>>), SqlError> {
+        self.joined(left, left_column, right, right_column, true)
+    }
+
+    fn joined(
+        &self,
+        left: &str,
+        left_column: &str,
+        right: &str,
+        right_column: &str,
+        keep_unmatched: bool,
+    ) -> Result<(Vec<ColumnSchema>, Vec<Vec<Value>>), SqlError> {
+        let left_table = self.table(left)?;
+        let right_table = self.table(right)?;
+        let position = |table: &Table, table_name: &str, column: &str| {
+            table
+                .schema
+                .columns
+                .iter()
+                .position(|c| c.name == column)
+                .ok_or_else(|| SqlError::ColumnNotFound(format!("{}.{}", table_name, column)))
+        };
+        let l = position(left_table, left, left_column)?;
+        let r = position(right_table, right, right_column)?;
+
+        let qualify = |table: &Table, table_name: &str| {
+            table
+                .schema
+                .columns
+                .iter()
+                .map(|c| ColumnSchema {
+                    name: format!("{}.{}", table_name, c.name),
+                    ..c.clone()
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut columns = qualify(left_table, left);
+        let right_columns = qualify(right_table, right);
+        let right_width = right_columns.len();
+        columns.extend(right_columns.into_iter().map(|c| ColumnSchema {
+            nullable: c.nullable || keep_unmatched,
+            ..c
+        }));
+
+        let mut rows = vec![];
+        for left_row in &left_table.rows {
+            let left_values = left_row.get_values();
+            let before = rows.len();
+            for right_row in &right_table.rows {
+                let right_values = right_row.get_values();
+                if left_values.get(l) == right_values.get(r) {
+                    rows.push([left_values.as_slice(), right_values.as_slice()].concat());
+                }
+            }
+            if keep_unmatched && rows.len() == before {
+                let mut row = left_values.clone();
+                row.resize(left_values.len() + right_width, Value::Null);
+                rows.push(row);
+            }
+        }
+        Ok((columns, rows))
+    }
+
     pub fn new() -> Self {
         Database {
             tables: HashMap::new(),
+            transaction: None,
+            wal_path: None,
         }
     }
 
-    /// Save the database to a file as JSON
-    pub fn save_to_file(&self, path: &str) -> std::io::Result<()> {
-        // Validate path and parent directory
-        let path = std::path::Path::new(path);
-        if let Some(parent) = path.parent() {
-            if !parent.exists() {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::NotFound,
-                    "Parent directory does not exist",
-                ));
+    /// Starts appending every mutating statement to the write-ahead log at `path`.
+    pub fn enable_wal(&mut self, path: &str) {
+        self.wal_path = Some(path.to_string());
+    }
+
+    /// Appends a statement to the write-ahead log, if one is enabled.
+    pub fn log_statement(&self, sql: &str) -> Result<(), SqlError> {
+        match &self.wal_path {
+            Some(path) => {
+                wal::append(path, &WalEntry::new(sql)).map_err(|e| SqlError::Wal(e.to_string()))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Replays the write-ahead log at `path` onto this database (normally the last
+    /// snapshot), returning how many statements were replayed. Statements that failed
+    /// originally fail again and are ignored; a transaction left open by a crash is
+    /// rolled back.
+    pub fn recover_from_wal(&mut self, path: &str) -> std::io::Result<usize> {
+        let entries = wal::read_entries(path)?;
+        // Replayed statements are already in the log
+        let wal_path = self.wal_path.take();
+        for entry in &entries {
+            let _ = crate::sql::execute_sql(self, &entry.query);
+        }
+        if self.in_transaction() {
+            let _ = self.rollback();
+        }
+        self.wal_path = wal_path;
+        Ok(entries.len())
+    }
+
+    /// Saves a full snapshot to `path` and empties the write-ahead log, whose statements
+    /// the snapshot now contains. Not allowed while a transaction is in progress.
+    pub fn checkpoint(&self, path: &str) -> std::io::Result<()> {
+        if self.in_transaction() {
+            return Err(std::io::Error::other(
+                "Cannot checkpoint while a transaction is in progress",
+            ));
+        }
+        self.save_to_file(path)?;
+        match &self.wal_path {
+            Some(wal_path) => wal::truncate(wal_path),
+            None => Ok(()),
+        }
+    }
+
+    /// Starts a transaction. Statements keep applying to `tables`; `rollback` restores the
+    /// state from this point. Nested transactions are not supported.
+    pub fn begin_transaction(&mut self) -> Result<(), SqlError> {
+        if self.transaction.is_some() {
+            return Err(SqlError::Transaction(
+                "a transaction is already in progress".to_string(),
+            ));
+        }
+        self.transaction = Some(self.tables.clone());
+        Ok(())
+    }
+
+    /// Makes the changes since `begin_transaction` permanent.
+    pub fn commit(&mut self) -> Result<(), SqlError> {
+        match self.transaction.take() {
+            Some(_) => Ok(()),
+            None => Err(SqlError::Transaction("no transaction in progress".to_string())),
+        }
+    }
+
+    /// Discards the changes since `begin_transaction`.
+    pub fn rollback(&mut self) -> Result<(), SqlError> {
+        match self.transaction.take() {
+            Some(snapshot) => {
+                self.tables = snapshot;
+                Ok(())
+            }
+            None => Err(SqlError::Transaction("no transaction in progress".to_string())),
+        }
+    }
+
+    /// Whether a transaction has been started and not yet committed or rolled back.
+    pub fn in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+
+    /// Returns the number of rows in a table, or `None` if the table does not exist.
+    pub fn row_count(&self, table: &str) -> Option<usize> {
+        self.get_table(table).map(|t| t.len())
+    }
+
+    /// Names of the other tables with a foreign key referencing `table`.
+    pub fn referencing_tables(&self, table: &str) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .references_to(table)
+            .into_iter()
+            .map(|(name, _)| name)
+            .filter(|name| name != table)
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Foreign keys referencing `table`, with the name of the table that declares each one.
+    fn references_to(&self, table: &str) -> Vec<(String, ForeignKey)> {
+        self.tables
+            .iter()
+            .flat_map(|(name, t)| {
+                t.foreign_keys
+                    .iter()
+                    .filter(|fk| fk.ref_table == table)
+                    .map(move |fk| (name.clone(), fk.clone()))
+            })
+            .collect()
+    }
+
+    /// Fails if a row of `table_name` has a (non-NULL) foreign key value that is not a key of
+    /// the referenced table.
+    fn check_foreign_keys(&self, table_name: &str) -> Result<(), SqlError> {
+        let table = self.table(table_name)?;
+        for fk in &table.foreign_keys {
+            let Some(idx) = table.schema.columns.iter().position(|c| c.name == fk.column) else {
+                continue;
+            };
+            let parent = self.table(&fk.ref_table)?;
+            for row in &table.rows {
+                let value = &row.get_values()[idx];
+                if !value.is_null() && parent.find_by_pk(&value.to_string()).is_none() {
+                    return Err(SqlError::ForeignKeyViolation(format!(
+                        "{}.{} = {} has no matching {}.{}",
+                        table_name, fk.column, value, fk.ref_table, fk.ref_column
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails unless each non-NULL foreign key value of `rows`, which are about to be written
+    /// to `table_name`, is the key of a row in the referenced table. Only these rows are
+    /// checked, each with one primary key lookup. For a key referencing `table_name` itself,
+    /// `removed` and `added` are the keys the same write takes away and adds.
+    fn check_parent_keys(
+        &self,
+        table_name: &str,
+        rows: &[&Vec<Value>],
+        removed: &HashSet<String>,
+        added: &HashSet<String>,
+    ) -> Result<(), SqlError> {
+        let table = self.table(table_name)?;
+        for fk in &table.foreign_keys {
+            let Some(idx) = table.schema.columns.iter().position(|c| c.name == fk.column) else {
+                continue;
+            };
+            let parent = self.table(&fk.ref_table)?;
+            let self_reference = fk.ref_table == table_name;
+            for value in rows.iter().filter_map(|row| row.get(idx)).filter(|v| !v.is_null()) {
+                let key = value.to_string();
+                let exists = if self_reference {
+                    added.contains(&key)
+                        || (parent.find_by_pk(&key).is_some() && !removed.contains(&key))
+                } else {
+                    parent.find_by_pk(&key).is_some()
+                };
+                if !exists {
+                    return Err(SqlError::ForeignKeyViolation(format!(
+                        "{}.{} = {} has no matching {}.{}",
+                        table_name, fk.column, value, fk.ref_table, fk.ref_column
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails if a row of a table with a foreign key referencing `table_name` still refers to
+    /// one of `removed`, keys a write is about to take away from it. A child's index on the
+    /// foreign key column answers each key directly. `own_rows` are the rows of `table_name`
+    /// as they will be after the write, checked for keys referencing the table itself.
+    fn check_unreferenced(
+        &self,
+        table_name: &str,
+        removed: &HashSet<String>,
+        own_rows: &[Row],
+    ) -> Result<(), SqlError> {
+        if removed.is_empty() {
+            return Ok(());
+        }
+        for (child, fk) in self.references_to(table_name) {
+            let child_table = self.table(&child)?;
+            let Some(idx) = child_table.schema.columns.iter().position(|c| c.name == fk.column)
+            else {
+                continue;
+            };
+            let refers = |row: &Row| {
+                let value = row.get_values().get(idx);
+                value.is_some_and(|v| !v.is_null() && removed.contains(&v.to_string()))
+            };
+            let referenced = if child == table_name {
+                own_rows.iter().any(refers)
+            } else if child_table.indexes.values().any(|column| *column == fk.column) {
+                removed.iter().any(|key| {
+                    child_table.lookup_index(&fk.column, key).is_some_and(|rows| !rows.is_empty())
+                })
+            } else {
+                child_table.rows.iter().any(refers)
+            };
+            if referenced {
+                return Err(SqlError::ForeignKeyViolation(format!(
+                    "rows of {} are still referenced by {}.{}",
+                    table_name, child, fk.column
+                )));
             }
         }
+        Ok(())
+    }
 
+    /// Adds a row to a table once it satisfies the table's constraints and foreign keys.
+    fn insert_checked(&mut self, table_name: &str, values: Vec<Value>) -> Result<(), SqlError> {
+        let table = self.table(table_name)?;
+        let row = table.check_new_row(values)?;
+        if !table.foreign_keys.is_empty() {
+            let added: HashSet<String> = row.key().cloned().into_iter().collect();
+            self.check_parent_keys(table_name, &[row.values()], &HashSet::new(), &added)?;
+        }
+        self.table_mut(table_name)?.push_checked(row);
+        Ok(())
+    }
+
+    /// Updates the matching rows of a table once the result satisfies the table's
+    /// constraints and foreign keys: the new values of the changed rows must reference
+    /// existing rows, and keys the update takes away must no longer be referenced.
+    fn update_checked(
+        &mut self,
+        table_name: &str,
+        set_values: Vec<Option<Assignment>>,
+        predicate: &dyn Fn(&Vec<Value>) -> bool,
+    ) -> Result<usize, SqlError> {
+        let table = self.table(table_name)?;
+        let update = table.check_update(set_values, predicate)?;
+        let referenced = !self.references_to(table_name).is_empty();
+        if referenced || !table.foreign_keys.is_empty() {
+            let (mut removed, mut added) = (HashSet::new(), HashSet::new());
+            for (pos, new_values) in update.changed() {
+                let old_key = table.pk_key(table.rows[pos].get_values());
+                let new_key = table.pk_key(new_values);
+                if old_key != new_key {
+                    removed.extend(old_key);
+                    added.extend(new_key);
+                }
+            }
+            // A key moved from one row to another still exists
+            removed.retain(|key| !added.contains(key));
+            let changed: Vec<&Vec<Value>> = update.changed().map(|(_, values)| values).collect();
+            self.check_parent_keys(table_name, &changed, &removed, &added)?;
+            if referenced {
+                self.check_unreferenced(table_name, &removed, update.rows())?;
+            }
+        }
+        Ok(self.table_mut(table_name)?.apply_update(update))
+    }
+
+    /// Empties a table like `Table::truncate`, refusing if other tables still reference
+    /// its rows.
+    pub fn truncate(&mut self, table_name: &str) -> Result<usize, SqlError> {
+        let table = self.table(table_name)?;
+        let keys: HashSet<String> =
+            table.rows.iter().filter_map(|row| table.pk_key(row.get_values())).collect();
+        self.check_unreferenced(table_name, &keys, &[])?;
+        Ok(self.table_mut(table_name)?.truncate())
+    }
+
+    /// Deletes the matching rows of a table, then applies each foreign key referencing it:
+    /// referencing rows are deleted too with ON DELETE CASCADE, and otherwise their
+    /// existence makes the whole delete fail. Nothing is changed on failure.
+    fn delete_cascading(
+        &mut self,
+        table_name: &str,
+        predicate: &dyn Fn(&Vec<Value>) -> bool,
+    ) -> Result<usize, SqlError> {
+        let references = self.references_to(table_name);
+        if references.is_empty() {
+            return Ok(self.table_mut(table_name)?.delete_rows(predicate));
+        }
+        let backup = self.tables.clone();
+        let result = self.delete_referenced(table_name, predicate, references);
+        if result.is_err() {
+            self.tables = backup;
+        }
+        result
+    }
+
+    fn delete_referenced(
+        &mut self,
+        table_name: &str,
+        predicate: &dyn Fn(&Vec<Value>) -> bool,
+        references: Vec<(String, ForeignKey)>,
+    ) -> Result<usize, SqlError> {
+        let table = self.table(table_name)?;
+        let columns = &table.schema.columns;
+        let key_index = columns.iter().position(|c| table.primary_key == [c.name.as_str()]);
+        let keys: HashSet<String> = table
+            .rows
+            .iter()
+            .map(|row| row.get_values())
+            .filter(|values| predicate(values))
+            .filter_map(|values| key_index.and_then(|i| values.get(i)))
+            .map(|v| v.to_string())
+            .collect();
+        let deleted = self.table_mut(table_name)?.delete_rows(predicate);
+        if keys.is_empty() {
+            return Ok(deleted);
+        }
+        for (child, fk) in references {
+            let child_table = self.table(&child)?;
+            let Some(idx) = child_table.schema.columns.iter().position(|c| c.name == fk.column)
+            else {
+                continue;
+            };
+            let references_deleted = |values: &Vec<Value>| {
+                values.get(idx).is_some_and(|v| !v.is_null() && keys.contains(&v.to_string()))
+            };
+            if fk.on_delete_cascade {
+                self.delete_cascading(&child, &references_deleted)?;
+            } else if child_table.rows.iter().any(|row| references_deleted(row.get_values())) {
+                return Err(SqlError::ForeignKeyViolation(format!(
+                    "rows of {} are still referenced by {}.{}",
+                    table_name, child, fk.column
+                )));
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Removes a table and all its rows, returning whether it existed.
+    pub fn drop_table(&mut self, name: &str) -> bool {
+        self.tables.remove(name).is_some()
+    }
+
+    /// Renames a table, keeping its rows, indexes and constraints. Foreign keys of other
+    /// tables that reference it follow the rename.
+    pub fn rename_table(&mut self, old: &str, new: &str) -> Result<(), SqlError> {
+        if self.tables.contains_key(new) {
+            return Err(SqlError::Parse(format!("Table '{}' already exists", new)));
+        }
+        let mut table = self
+            .tables
+            .remove(old)
+            .ok_or_else(|| SqlError::TableNotFound(old.to_string()))?;
+        table.rename(new);
+        self.tables.insert(new.to_string(), table);
+        for fk in self.tables.values_mut().flat_map(|t| t.foreign_keys.iter_mut()) {
+            if fk.ref_table == old {
+                fk.ref_table = new.to_string();
+            }
+        }
+        Ok(())
+    }
+
+    /// Renames a column of a table (see `Table::rename_column`), along with the foreign keys
+    /// of other tables that reference it.
+    pub fn rename_column(&mut self, table: &str, old: &str, new: &str) -> Result<(), SqlError> {
+        let table_ref = self.table_mut(table)?;
+        if !table_ref.rename_column(old, new) {
+            if !table_ref.schema.columns.iter().any(|c| c.name == old) {
+                return Err(SqlError::ColumnNotFound(old.to_string()));
+            }
+            return Err(SqlError::Parse(format!(
+                "Column '{}' already exists in table '{}'",
+                new, table
+            )));
+        }
+        for fk in self.tables.values_mut().flat_map(|t| t.foreign_keys.iter_mut()) {
+            if fk.ref_table == table && fk.ref_column == old {
+                fk.ref_column = new.to_string();
+            }
+        }
+        Ok(())
+    }
+
+    /// Compacts every table in memory (see `Table::vacuum`). No file is written: saves and
+    /// checkpoints never include deleted rows, so there is nothing to compact on disk.
+    pub fn vacuum(&mut self) {
+        for table in self.tables.values_mut() {
+            table.vacuum();
+        }
+    }
+
+    /// Merges the tables of `other` into this database, returning how many rows were added
+    /// or replaced. Tables only in `other` are added whole. Rows of a table present in both
+    /// are added with the usual constraint checks, with primary key collisions resolved by
+    /// `on_conflict`. A table present in both with different columns or a different primary
+    /// key is a collision too: it is kept or replaced whole. Fails, changing nothing, if a
+    /// row breaks a constraint, or on a collision under `MergePolicy::Error`.
+    pub fn merge(&mut self, other: Database, on_conflict: MergePolicy) -> Result<usize, SqlError> {
+        let backup = self.tables.clone();
+        let result = self.merge_tables(other, on_conflict);
+        if result.is_err() {
+            self.tables = backup;
+        }
+        result
+    }
+
+    fn merge_tables(
+        &mut self,
+        other: Database,
+        on_conflict: MergePolicy,
+    ) -> Result<usize, SqlError> {
+        let mut merged = 0;
+        let mut names: Vec<String> = other.tables.keys().cloned().collect();
+        names.sort();
+        let mut incoming = other.tables;
+        let mut replaced = vec![];
+        for name in &names {
+            let mut table = incoming.remove(name).expect("name was taken from the map");
+            let Some(existing) = self.tables.get_mut(name) else {
+                table.rebuild_index();
+                merged += table.len();
+                self.tables.insert(name.clone(), table);
+                continue;
+            };
+            let layout = |t: &Table| {
+                let columns: Vec<_> =
+                    t.schema.columns.iter().map(|c| (c.name.clone(), c.col_type.clone())).collect();
+                (columns, t.primary_key.clone())
+            };
+            if layout(existing) != layout(&table) {
+                match on_conflict {
+                    MergePolicy::Skip => continue,
+                    MergePolicy::Overwrite => {
+                        table.rebuild_index();
+                        merged += table.len();
+                        *existing = table;
+                        replaced.push(name);
+                        continue;
+                    }
+                    MergePolicy::Error => {
+                        return Err(SqlError::Parse(format!(
+                            "Cannot merge table '{}': its columns or primary key differ",
+                            name
+                        )));
+                    }
+                }
+            }
+            merged += existing.merge_rows(std::mem::take(&mut table.rows), on_conflict)?;
+        }
+        // A replaced table may have lost keys that other tables reference
+        let mut checked: Vec<&String> = self
+            .tables
+            .iter()
+            .filter(|(name, table)| {
+                names.contains(name)
+                    || table.foreign_keys.iter().any(|fk| replaced.contains(&&fk.ref_table))
+            })
+            .map(|(name, _)| name)
+            .collect();
+        checked.sort();
+        for name in checked {
+            self.check_foreign_keys(name)?;
+        }
+        Ok(merged)
+    }
+
+    /// Creates a secondary index on `table.column`. Index names are unique across the database.
+    pub fn create_index(&mut self, name: &str, table: &str, column: &str) -> Result<(), SqlError> {
+        if self.tables.values().any(|t| t.indexes.contains_key(name)) {
+            return Err(SqlError::Parse(format!("Index '{}' already exists", name)));
+        }
+        self.table_mut(table)?.create_index(name, column)
+    }
+
+    /// Removes the secondary index with the given name, returning whether it existed.
+    pub fn drop_index(&mut self, name: &str) -> bool {
+        self.tables.values_mut().any(|t| t.drop_index(name))
+    }
+
+    /// Save the database to a file as JSON. Fails if the parent directory does not exist.
+    pub fn save_to_file(&self, path: &str) -> std::io::Result<()> {
+        self.save_to_file_with_opts(path, SaveOptions::default())
+    }
+
+    /// Save the database to a file as JSON. The data is written to `<path>.tmp` and then
+    /// renamed into place, so a failed or interrupted save leaves any previous file intact.
+    pub fn save_to_file_with_opts(&self, path: &str, opts: SaveOptions) -> std::io::Result<()> {
         let json = serde_json::to_string_pretty(self).map_err(|e| {
             std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
         })?;
-        
+        write_atomically(path, opts, |file| file.write_all(json.as_bytes()))
+    }
+
+    /// Save the database to a file as gzip-compressed JSON. The JSON is streamed through the
+    /// compressor rather than built in memory first. `load_from_file` recognises these files
+    /// by their gzip header, so either load function can read them back.
+    pub fn save_to_file_gz(&self, path: &str) -> std::io::Result<()> {
+        write_atomically(path, SaveOptions::default(), |file| {
+            let mut encoder = GzEncoder::new(BufWriter::new(file), Compression::default());
+            serde_json::to_writer(&mut encoder, self).map_err(|e| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
+            })?;
+            encoder.finish()?.flush()
+        })
+    }
+
+    /// Save the database to a file in a compact binary format (MessagePack). Much smaller
+    /// and faster than the JSON format, but not human-readable. Written atomically like
+    /// `save_to_file`.
+    pub fn save_to_file_bin(&self, path: &str) -> std::io::Result<()> {
+        let bytes = rmp_serde::to_vec(self).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
+        })?;
+        write_atomically(path, SaveOptions::default(), |file| file.write_all(&bytes))
+    }
+
+    /// Writes a table to a CSV file: a header of column names, then one line per row.
+    /// NULLs are written as empty fields and empty strings as `""`.
+    pub fn export_table_csv(&self, table_name: &str, path: &str) -> std::io::Result<()> {
+        let table = self.get_table(table_name).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Table '{}' does not exist", table_name),
+            )
+        })?;
+        let mut out = csv::format_record(&self.get_table_columns(table_name));
+        for row in &table.rows {
+            out.push_str(&csv::format_values(row.get_values()));
+        }
         let mut file = File::create(path)?;
-        file.write_all(json.as_bytes())?;
+        file.write_all(out.as_bytes())?;
         Ok(())
     }
 
+    /// Exports every table to `<dir>/<table>.csv`, creating the directory if needed.
+    pub fn export_csv_dir(&self, dir: &str) -> std::io::Result<()> {
+        std::fs::create_dir_all(dir)?;
+        for name in self.tables.keys() {
+            let path = std::path::Path::new(dir).join(format!("{}.csv", name));
+            self.export_table_csv(name, &path.to_string_lossy())?;
+        }
+        Ok(())
+    }
+
+    /// Imports rows from a CSV file whose header names columns of the table, in any order.
+    /// Columns missing from the header take their default or NULL, as do empty unquoted
+    /// fields. A quoted empty field (`""`) is an empty string in a String column.
+    /// A malformed row (wrong field count, type mismatch, constraint or foreign key violation)
+    /// is reported with its line number and either skipped (`skip_invalid`) or aborts the
+    /// import, leaving the table unchanged. Returns the number of rows imported.
+    pub fn import_table_csv(
+        &mut self,
+        table_name: &str,
+        path: &str,
+        skip_invalid: bool,
+    ) -> Result<usize, csv::CsvError> {
+        let text = std::fs::read_to_string(path)?;
+        let mut records = csv::parse_records(&text)?.into_iter();
+        let table = self
+            .tables
+            .get(table_name)
+            .ok_or_else(|| csv::CsvError::Schema(SqlError::TableNotFound(table_name.to_string())))?;
+        let Some((_, header)) = records.next() else {
+            return Ok(0);
+        };
+        let columns = table.schema.columns.clone();
+        let mut positions = vec![];
+        for name in &header {
+            let name = name.as_deref().unwrap_or_default().trim();
+            let Some(pos) = columns.iter().position(|c| c.name == name) else {
+                return Err(csv::CsvError::Schema(SqlError::ColumnNotFound(name.to_string())));
+            };
+            if positions.contains(&pos) {
+                return Err(csv::CsvError::Schema(SqlError::Parse(format!(
+                    "Column '{}' appears more than once in the CSV header",
+                    name
+                ))));
+            }
+            positions.push(pos);
+        }
+
+        let backup = table.clone();
+        let mut imported = 0;
+        for (line, fields) in records {
+            let result = if fields.len() != header.len() {
+                Err(SqlError::ColumnCountMismatch {
+                    expected: header.len(),
+                    got: fields.len(),
+                })
+            } else {
+                let mut values: Vec<Value> = columns
+                    .iter()
+                    .map(|c| c.default.clone().unwrap_or(Value::Null))
+                    .collect();
+                fields
+                    .iter()
+                    .zip(&positions)
+                    .filter_map(|(field, &pos)| Some((field.as_deref()?, pos)))
+                    .filter(|(field, pos)| {
+                        !field.is_empty() || columns[*pos].col_type == ColumnType::String
+                    })
+                    .try_for_each(|(field, pos)| {
+                        values[pos] = Value::from_column(&columns[pos], field)?;
+                        Ok(())
+                    })
+                    .and_then(|_| self.insert_checked(table_name, values))
+            };
+            match result {
+                Ok(()) => imported += 1,
+                Err(error) if skip_invalid => log::warn!("Skipping CSV line {}: {}", line, error),
+                Err(error) => {
+                    self.tables.insert(table_name.to_string(), backup);
+                    return Err(csv::CsvError::Row { line, error });
+                }
+            }
+        }
+        Ok(imported)
+    }
+
     /// Load the database from a file (JSON)
     pub fn load_from_file(path: &str) -> std::io::Result<Self> {
         // Check if file exists
@@ -90,13 +849,129 @@ impl Database {
             ));
         }
 
-        let mut file = File::open(path)?;
+        let mut reader = BufReader::new(File::open(path)?);
+        if reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+            return Self::read_gz(reader);
+        }
         let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-        
-        serde_json::from_str(&contents).map_err(|e| {
+        reader.read_to_string(&mut contents)?;
+
+        let mut db: Database = serde_json::from_str(&contents).map_err(|e| {
             std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
+        })?;
+        db.post_load_init()?;
+        Ok(db)
+    }
+
+    /// Load the database from a file written by `save_to_file_gz`.
+    pub fn load_from_file_gz(path: &str) -> std::io::Result<Self> {
+        Self::read_gz(BufReader::new(File::open(path)?))
+    }
+
+    /// Decompresses and parses gzip-compressed JSON as it is read.
+    fn read_gz(reader: impl Read) -> std::io::Result<Self> {
+        let mut db: Database = serde_json::from_reader(GzDecoder::new(reader)).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
+        })?;
+        db.post_load_init()?;
+        Ok(db)
+    }
+
+    /// Load the database from a file written by `save_to_file_bin`.
+    pub fn load_from_file_bin(path: &str) -> std::io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let mut db: Database = rmp_serde::from_slice(&bytes).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
+        })?;
+        db.post_load_init()?;
+        Ok(db)
+    }
+
+    /// Prepares freshly deserialized tables for use: checks that each table is consistent
+    /// with its schema (see `Table::validate`), converts values to their column types,
+    /// rebuilds the primary key and secondary indexes (which are not saved) and raises
+    /// AUTOINCREMENT counters to the highest id present. Called by every `load_from_file*`
+    /// function; an inconsistent table is reported as `InvalidData`.
+    pub fn post_load_init(&mut self) -> std::io::Result<()> {
+        for (name, table) in self.tables.iter_mut() {
+            table.validate().map_err(|msg| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Table '{}': {}", name, msg),
+                )
+            })?;
+            // Files written before values were typed store every value as a string
+            table.coerce_values();
+            table.rebuild_index();
+            table.restore_auto_increment();
+        }
+        Ok(())
+    }
+}
+
+/// The first two bytes of every gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Writes to `<path>.tmp` with `write` and renames it over `path`, removing the temporary
+/// file if anything fails.
+fn write_atomically<F>(path: &str, opts: SaveOptions, write: F) -> std::io::Result<()>
+where
+    F: FnOnce(&mut File) -> std::io::Result<()>,
+{
+    // Validate path and parent directory
+    let tmp = format!("{}.tmp", path);
+    let path = std::path::Path::new(path);
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+        && !parent.exists()
+    {
+        if opts.create_parent_dirs {
+            std::fs::create_dir_all(parent)?;
+        } else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Parent directory does not exist",
+            ));
+        }
+    }
+
+    let mut file = File::create(&tmp)?;
+    let written = write(&mut file).and_then(|_| file.sync_all());
+    drop(file);
+    if let Err(e) = written.and_then(|_| std::fs::rename(&tmp, path)) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Prints rows as a simple text table with a header line, sizing each column to fit.
+pub fn print_table(columns: &[String], rows: &[Vec<String>]) {
+    let col_widths: Vec<_> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let max_val = rows
+                .iter()
+                .map(|row| row.get(i).map(|v| v.len()).unwrap_or(0))
+                .max()
+                .unwrap_or(0);
+            std::cmp::max(name.len(), max_val)
         })
+        .collect();
+    for (h, w) in columns.iter().zip(&col_widths) {
+        print!("{:<width$} ", h, width = w);
+    }
+    println!();
+    for w in &col_widths {
+        print!("{:-<width$}-", "", width = *w);
+    }
+    println!();
+    for row in rows {
+        for (val, w) in row.iter().zip(&col_widths) {
+            print!("{:<width$} ", val, width = w);
+        }
+        println!();
     }
 }
 
@@ -106,23 +981,21 @@ impl DatabaseInterface for Database {
         &mut self,
         table_name: &str,
         columns: Vec<ColumnSchema>,
-        primary_key: Option<String>,
-        unique_columns: Vec<String>,
+        primary_key: Vec<String>,
+        unique_columns: Vec<Vec<String>>,
     ) {
         let schema = Schema { columns };
         let table = Table::new(table_name.to_string(), schema, primary_key, unique_columns);
         self.tables.insert(table_name.to_string(), table);
-        println!("Created table: {}", table_name);
     }
 
     fn create_table(&mut self, table_name: &str, columns: Vec<ColumnSchema>) {
         let schema = Schema { columns };
-        let table = Table::new(table_name.to_string(), schema, None, vec![]);
+        let table = Table::new(table_name.to_string(), schema, vec![], vec![]);
         self.tables.insert(table_name.to_string(), table);
-        println!("Created table: {}", table_name);
     }
 
-    fn list_tables(&self, tables: &Vec<String>) {
+    fn list_tables(&self, tables: &[String]) {
         // format the list of tables as a table
         println!("Tables in the database:");
         println!("{:-<20}-", "");
@@ -133,97 +1006,99 @@ impl DatabaseInterface for Database {
         }
     }
 
-    fn insert(&mut self, table_name: &str, values: Vec<String>) {
-        if let Some(table) = self.tables.get_mut(table_name) {
-            table.add_row(values);
-            println!("Inserted values into table: {}", table_name);
-        } else {
-            println!("Table not found: {}", table_name);
-        }
+    fn insert(&mut self, table_name: &str, values: Vec<String>) -> Result<(), SqlError> {
+        let values = typed_values(&self.table(table_name)?.schema.columns, values)?;
+        self.insert_checked(table_name, values)
+    }
+
+    fn insert_values(&mut self, table_name: &str, values: Vec<Value>) -> Result<(), SqlError> {
+        self.insert_checked(table_name, values)
+    }
+
+    fn update<F>(
+        &mut self,
+        table_name: &str,
+        set_values: Vec<Option<String>>,
+        predicate: F,
+    ) -> Result<usize, SqlError>
+    where
+        F: Fn(&Vec<Value>) -> bool,
+    {
+        let set_values = self.table(table_name)?.positional_assignments(set_values);
+        self.update_checked(table_name, set_values, &predicate)
     }
 
-    fn update<F>(&mut self, table_name: &str, set_values: Vec<String>, predicate: F)
+    fn update_named<F>(
+        &mut self,
+        table_name: &str,
+        assignments: &HashMap<String, Assignment>,
+        predicate: F,
+    ) -> Result<usize, SqlError>
     where
-        F: Fn(&Vec<String>) -> bool,
+        F: Fn(&Vec<Value>) -> bool,
     {
-        if let Some(table) = self.tables.get_mut(table_name) {
-            table.update_rows(set_values, predicate);
-            println!("Updated matching rows in table: {}", table_name);
-        } else {
-            println!("Table not found: {}", table_name);
-        }
+        let set_values = self.table(table_name)?.named_set_values(assignments)?;
+        self.update_checked(table_name, set_values, &predicate)
     }
 
-    fn delete<F>(&mut self, table_name: &str, predicate: F)
+    fn delete<F>(&mut self, table_name: &str, predicate: F) -> Result<usize, SqlError>
     where
-        F: Fn(&Vec<String>) -> bool,
+        F: Fn(&Vec<Value>) -> bool,
     {
-        if let Some(table) = self.tables.get_mut(table_name) {
-            table.delete_rows(predicate);
-            println!("Deleted matching rows in table: {}", table_name);
-        } else {
-            println!("Table not found: {}", table_name);
-        }
+        self.delete_cascading(table_name, &predicate)
     }
 
-    fn select<F>(&self, table_name: &str, columns: Vec<String>, _predicate: F)
+    fn select<F>(&self, table_name: &str, columns: Vec<String>, predicate: F)
     where
-        F: Fn(&Vec<String>) -> bool,
+        F: Fn(&Vec<Value>) -> bool,
     {
-        if let Some(table) = self.tables.get(table_name) {
-            println!("Selecting from table: {}", table_name);
-            // Use named field access
-            let col_names: Vec<_> = if columns == vec!["*"] {
-                table
-                    .schema
-                    .columns
-                    .iter()
-                    .map(|c| c.name.clone())
-                    .collect()
+        if self.tables.contains_key(table_name) {
+            log::info!("Selecting from table: {}", table_name);
+            let col_names = if columns == vec!["*"] {
+                self.get_table_columns(table_name)
             } else {
                 columns.clone()
             };
-            // Print header
-            let col_widths: Vec<_> = col_names
-                .iter()
-                .map(|name| {
-                    let max_val = table
-                        .rows
-                        .iter()
-                        .map(|row| {
-                            row.get_by_name(name, &table.schema)
-                                .map(|v| v.len())
-                                .unwrap_or(0)
-                        })
-                        .max()
-                        .unwrap_or(0);
-                    std::cmp::max(name.len(), max_val)
-                })
-                .collect();
-            for (h, w) in col_names.iter().zip(&col_widths) {
-                print!("{:<width$} ", h, width = w);
-            }
-            println!();
-            for w in &col_widths {
-                print!("{:-<width$}-", "", width = *w);
-            }
-            println!();
-            for row in &table.rows {
-                if _predicate(row.get_values()) {
-                    for (col, w) in col_names.iter().zip(&col_widths) {
-                        let val = row
-                            .get_by_name(col, &table.schema)
-                            .map(|s| s.as_str())
-                            .unwrap_or("");
-                        print!("{:<width$} ", val, width = w);
-                    }
-                    println!();
-                }
-            }
+            let rows = self.select_rows(table_name, columns, predicate);
+            print_table(&col_names, &rows);
         } else {
-            println!("Table not found: {}", table_name);
+            log::warn!("Table not found: {}", table_name);
         }
     }
+
+    fn select_rows<F>(&self, table_name: &str, columns: Vec<String>, predicate: F) -> Vec<Vec<String>>
+    where
+        F: Fn(&Vec<Value>) -> bool,
+    {
+        let Some(table) = self.get_table(table_name) else {
+            return vec![];
+        };
+        let col_names: Vec<_> = if columns == vec!["*"] {
+            table
+                .schema
+                .columns
+                .iter()
+                .map(|c| c.name.clone())
+                .collect()
+        } else {
+            columns
+        };
+        table
+            .rows
+            .iter()
+            .filter(|row| predicate(row.get_values()))
+            .map(|row| {
+                col_names
+                    .iter()
+                    .map(|col| {
+                        row.get_by_name(col, &table.schema)
+                            .map(|v| v.to_string())
+                            .unwrap_or_default()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
 }
 
 // tests moved to integration tests in tests/
@@ -0,0 +1,117 @@
+use crate::query::{ParseError, ParseErrorKind};
+use crate::schema::ColumnType;
+use std::fmt;
+// error.rs
+
+/// Errors produced while parsing or executing a statement.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlError {
+    /// The statement could not be parsed.
+    Parse(String),
+    /// The statement kind is not supported.
+    Unsupported(String),
+    /// The referenced table does not exist.
+    TableNotFound(String),
+    /// The referenced column does not exist in the table.
+    ColumnNotFound(String),
+    /// The referenced index does not exist.
+    IndexNotFound(String),
+    /// The number of supplied values does not match the table's columns.
+    ColumnCountMismatch { expected: usize, got: usize },
+    /// A value could not be interpreted as the column's type.
+    TypeMismatch {
+        column: String,
+        value: String,
+        expected: ColumnType,
+    },
+    /// A primary key value is missing or already present.
+    PrimaryKeyViolation(String),
+    /// A unique column value is already present.
+    UniqueViolation(String),
+    /// A NOT NULL column was given no value.
+    NotNullViolation(String),
+    /// A row does not satisfy a CHECK constraint (the condition is given).
+    CheckViolation(String),
+    /// A foreign key value has no matching row in the referenced table, or a referenced row
+    /// is still in use.
+    ForeignKeyViolation(String),
+    /// An aggregate function was applied to an unsuitable column.
+    InvalidAggregate(String),
+    /// BEGIN / COMMIT / ROLLBACK was used out of order.
+    Transaction(String),
+    /// The statement could not be written to the write-ahead log.
+    Wal(String),
+}
+
+impl SqlError {
+    /// Whether the statement was rejected because of the data it supplied: a value of the
+    /// wrong type or one breaking a constraint.
+    pub fn is_violation(&self) -> bool {
+        matches!(
+            self,
+            SqlError::TypeMismatch { .. }
+                | SqlError::PrimaryKeyViolation(_)
+                | SqlError::UniqueViolation(_)
+                | SqlError::NotNullViolation(_)
+                | SqlError::CheckViolation(_)
+                | SqlError::ForeignKeyViolation(_)
+        )
+    }
+}
+
+impl fmt::Display for SqlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SqlError::Parse(msg) => write!(f, "Parse error: {}", msg),
+            SqlError::Unsupported(msg) => write!(f, "Unsupported SQL operation: {}", msg),
+            SqlError::TableNotFound(table) => write!(f, "Table '{}' does not exist", table),
+            SqlError::ColumnNotFound(col) => write!(f, "Column '{}' does not exist", col),
+            SqlError::IndexNotFound(index) => write!(f, "Index '{}' does not exist", index),
+            SqlError::ColumnCountMismatch { expected, got } => write!(
+                f,
+                "Wrong number of values: expected {}, got {}",
+                expected, got
+            ),
+            SqlError::TypeMismatch {
+                column,
+                value,
+                expected,
+            } => write!(
+                f,
+                "Type error: value '{}' does not match column '{}' type {:?}",
+                value, column, expected
+            ),
+            SqlError::PrimaryKeyViolation(col) => write!(
+                f,
+                "Primary key constraint violation: '{}' must be unique!",
+                col
+            ),
+            SqlError::UniqueViolation(col) => {
+                write!(f, "Unique constraint violation: '{}' must be unique!", col)
+            }
+            SqlError::NotNullViolation(col) => {
+                write!(f, "NOT NULL constraint violation: '{}' cannot be NULL", col)
+            }
+            SqlError::CheckViolation(check) => {
+                write!(f, "CHECK constraint violation: '{}' is not satisfied", check)
+            }
+            SqlError::ForeignKeyViolation(msg) => {
+                write!(f, "Foreign key constraint violation: {}", msg)
+            }
+            SqlError::InvalidAggregate(msg) => write!(f, "Invalid aggregate: {}", msg),
+            SqlError::Transaction(msg) => write!(f, "Transaction error: {}", msg),
+            SqlError::Wal(msg) => write!(f, "Write-ahead log error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for SqlError {}
+
+impl From<ParseError> for SqlError {
+    fn from(err: ParseError) -> Self {
+        match err.kind {
+            ParseErrorKind::UnknownColumn(col) => SqlError::ColumnNotFound(col),
+            _ => SqlError::Parse(format!("WHERE clause: {}", err)),
+        }
+    }
+}
@@ -1,17 +1,26 @@
+pub mod aggregate;
+pub mod arithmetic;
+pub mod csv;
 pub mod database;
+pub mod error;
 pub mod query;
 pub mod row;
 pub mod schema;
 pub mod sql;
 pub mod table;
+pub mod value;
+pub mod wal;
 pub mod server;
 pub mod client;
 pub mod replication;
+pub mod metrics;
 
 // Re-export commonly used types for tests and consumers
 pub use database::*;
+pub use error::*;
 pub use query::*;
 pub use row::*;
 pub use schema::*;
 pub use sql::*;
 pub use table::*;
+pub use value::*;
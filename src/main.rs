@@ -1,46 +1,29 @@
 use std::io::Write;
-
-mod database;
-mod query;
-mod row;
-mod schema;
-mod sql;
-mod table;
+use std::time::Duration;
 
 // use std::io::Stdin;
 
-use database::Database;
-use sql::execute_sql;
+use lab::database::Database;
+use lab::sql::{execute_and_print, execute_sql};
+use lab::{client, server};
 
 fn init_demo_database() -> Database {
     let mut db = Database::new();
     
     // Create Products table and insert sample data
-    execute_sql(
-        &mut db,
+    for stmt in [
         "CREATE TABLE Products (id INT PRIMARY KEY, name STRING, price FLOAT, stock INT)",
-    );
-    execute_sql(
-        &mut db,
         "INSERT INTO Products (id, name, price, stock) VALUES (1, 'Pen', 2.5, 100)",
-    );
-    execute_sql(
-        &mut db,
         "INSERT INTO Products (id, name, price, stock) VALUES (2, 'Pencil', 1.2, 50)",
-    );
-    execute_sql(
-        &mut db,
         "INSERT INTO Products (id, name, price, stock) VALUES (3, 'Eraser', 0.8, 30)",
-    );
-    
+    ] {
+        execute_sql(&mut db, stmt).expect("demo statement should succeed");
+    }
+
     db
 }
 
-mod server;
-mod client;
-mod replication;
-
-use crate::replication::ReplicationConfig;
+use lab::replication::ReplicationConfig;
 
 fn run_cli_mode() {
     let mut db = init_demo_database();
@@ -59,12 +42,26 @@ fn run_cli_mode() {
             break;
         }
         if !input.is_empty() {
-            execute_sql(&mut db, input);
+            execute_and_print(&mut db, input);
         }
     }
 }
 
+/// Shuts the server down on Ctrl-C, so `wait` returns and the final save runs.
+fn close_on_ctrl_c(handle: jsonrpc_http_server::CloseHandle) {
+    let result = ctrlc::set_handler(move || {
+        println!("Shutting down...");
+        handle.clone().close();
+    });
+    if let Err(e) = result {
+        eprintln!("Warning: Ctrl-C will not shut the server down cleanly: {}", e);
+    }
+}
+
 fn main() {
+    // Log at info level unless RUST_LOG says otherwise
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
     // Parse command line arguments
     let args: Vec<String> = std::env::args().collect();
     if args.len() > 1 {
@@ -75,6 +72,15 @@ fn main() {
                 let mut is_replica = false;
                 let mut primary_url = None;
                 let mut replicas_arg: Option<String> = None;
+                let mut tls_cert: Option<String> = None;
+                let mut tls_key: Option<String> = None;
+                let mut auth_token: Option<String> = None;
+                let mut data_file: Option<String> = None;
+                let mut advertise_url: Option<String> = None;
+                let mut sync_interval = ReplicationConfig::default().sync_interval;
+                let mut display = false;
+                let mut query_timeout = None;
+                let mut bind_addr = server::ServerOptions::default().bind_addr;
 
                 while let Some(arg) = arg_iter.next() {
                     match arg.as_str() {
@@ -96,14 +102,91 @@ fn main() {
                                 replicas_arg = Some(list.to_string());
                             }
                         }
+                        "--tls-cert" => {
+                            tls_cert = arg_iter.next().cloned();
+                        }
+                        "--tls-key" => {
+                            tls_key = arg_iter.next().cloned();
+                        }
+                        "--auth-token" => {
+                            auth_token = arg_iter.next().cloned();
+                        }
+                        "--data-file" => {
+                            data_file = arg_iter.next().cloned();
+                        }
+                        "--advertise-url" => {
+                            advertise_url = arg_iter.next().cloned();
+                        }
+                        "--query-timeout" => {
+                            let secs = arg_iter.next().map(String::as_str).unwrap_or("");
+                            query_timeout = match secs.parse::<u64>() {
+                                Ok(secs) if secs > 0 => Some(Duration::from_secs(secs)),
+                                _ => {
+                                    eprintln!("Error: invalid --query-timeout '{}'", secs);
+                                    std::process::exit(1);
+                                }
+                            };
+                        }
+                        "--display" => {
+                            display = true;
+                        }
+                        "--sync-interval" => {
+                            let secs = arg_iter.next().map(String::as_str).unwrap_or("");
+                            sync_interval = match ReplicationConfig::parse_sync_interval(secs) {
+                                Ok(interval) => interval,
+                                Err(e) => {
+                                    eprintln!("Error: {}", e);
+                                    std::process::exit(1);
+                                }
+                            };
+                        }
+                        "--bind" => {
+                            let addr = arg_iter.next().map(String::as_str).unwrap_or("");
+                            bind_addr = match addr.parse() {
+                                Ok(addr) => addr,
+                                Err(_) => {
+                                    eprintln!("Error: invalid --bind address '{}'", addr);
+                                    std::process::exit(1);
+                                }
+                            };
+                        }
                         _ => {}
                     }
                 }
 
+                let tls = match (tls_cert, tls_key) {
+                    (Some(cert_path), Some(key_path)) => Some(server::TlsConfig {
+                        cert_path,
+                        key_path,
+                    }),
+                    (None, None) => None,
+                    _ => {
+                        eprintln!("Error: --tls-cert and --tls-key must be given together");
+                        std::process::exit(1);
+                    }
+                };
+
+                if is_replica && data_file.is_some() {
+                    eprintln!("Error: --data-file is only supported on the primary server");
+                    std::process::exit(1);
+                }
+                let options = server::ServerOptions {
+                    auth_token,
+                    data_file,
+                    bind_addr,
+                    query_timeout,
+                };
+
                 let config = if is_replica {
                     if let Some(primary) = primary_url {
                         println!("Starting RustDB in replica mode...");
-                        Some(ReplicationConfig::new_replica(primary))
+                        let mut cfg = ReplicationConfig::new_replica(primary);
+                        cfg.advertise_url = advertise_url;
+                        cfg.sync_interval = sync_interval;
+                        cfg.display = display;
+                        // A cluster shares one token, used for the primary too
+                        cfg.primary_auth_token = options.auth_token.clone();
+                        Some(cfg)
                     } else {
                         eprintln!("Error: --primary-url is required for replica servers");
                         std::process::exit(1);
@@ -111,6 +194,7 @@ fn main() {
                 } else {
                     println!("Starting RustDB in primary mode...");
                     let mut cfg = ReplicationConfig::new_primary();
+                    cfg.sync_interval = sync_interval;
                     if let Some(list) = replicas_arg {
                         // parse comma-separated list of replica URLs
                         for r in list.split(',') {
@@ -123,12 +207,33 @@ fn main() {
                     Some(cfg)
                 };
 
-                let server = server::start_server(port, config);
-                println!("RustDB RPC Server running on http://127.0.0.1:{}", port);
                 if is_replica {
                     println!("Syncing with primary server...");
                 }
-                server.wait();
+                match tls {
+                    Some(tls) => match server::start_tls_server(port, config, &tls, options) {
+                        Ok(server) => {
+                            close_on_ctrl_c(server.close_handle());
+                            server.wait();
+                        }
+                        Err(e) => {
+                            eprintln!("Error: failed to start TLS server: {}", e);
+                            std::process::exit(1);
+                        }
+                    },
+                    None => match server::start_server_with_options(port, config, options) {
+                        Ok(server) => {
+                            let addr = std::net::SocketAddr::new(bind_addr, port);
+                            println!("RustDB RPC Server running on http://{}", addr);
+                            close_on_ctrl_c(server.close_handle());
+                            server.wait();
+                        }
+                        Err(e) => {
+                            eprintln!("Error: failed to start server: {}", e);
+                            std::process::exit(1);
+                        }
+                    },
+                }
             }
             "--client" => {
                 println!("Starting RustDB in client mode...");
@@ -142,7 +247,15 @@ fn main() {
                 println!("Usage:");
                 println!("  cargo run                                                    # Run in CLI mode");
                 println!("  cargo run -- --server [--port <port>]                       # Run in primary server mode");
+                println!("  cargo run -- --server --bind 0.0.0.0                            # Listen on all interfaces (default 127.0.0.1)");
+                println!("  cargo run -- --server --tls-cert <pem> --tls-key <pem>      # Serve the RPC API over HTTPS");
+                println!("  cargo run -- --server --auth-token <token>                  # Require a bearer token on every RPC");
+                println!("  cargo run -- --server --data-file <path>                    # Load from and save to a JSON file");
+                println!("  cargo run -- --server --query-timeout <secs>                # Fail statements that run longer");
                 println!("  cargo run -- --server --replica --primary-url <url> [--port <port>] # Run in replica mode");
+                println!("  cargo run -- --server --replica --primary-url <url> --advertise-url <url> # Report applied events to the primary");
+                println!("  cargo run -- --server --replica --primary-url <url> --sync-interval <secs> # Poll the primary every <secs> seconds (default 5)");
+                println!("  cargo run -- --server --replica --primary-url <url> --display # Log a snapshot of the tables every interval");
                 println!("  cargo run -- --client                                       # Run in client mode");
             }
         }
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Counters shared by the RPC server and its replication manager.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    queries_executed: AtomicU64,
+    queries_failed: AtomicU64,
+    rows_returned: AtomicU64,
+    events_sent: AtomicU64,
+    events_received: AtomicU64,
+}
+
+/// The counters at one point in time, as returned by the `metrics` RPC.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    /// Statements executed, whether or not they succeeded.
+    pub queries_executed: u64,
+    pub queries_failed: u64,
+    /// Rows returned by SELECT statements.
+    pub rows_returned: u64,
+    /// Events recorded by a primary for its replicas.
+    pub events_sent: u64,
+    /// Events a replica has applied from its primary.
+    pub events_received: u64,
+}
+
+impl Metrics {
+    /// Counts one executed statement and the rows it returned.
+    pub fn record_query(&self, succeeded: bool, rows: usize) {
+        self.queries_executed.fetch_add(1, Ordering::Relaxed);
+        if !succeeded {
+            self.queries_failed.fetch_add(1, Ordering::Relaxed);
+        }
+        self.rows_returned.fetch_add(rows as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_event_sent(&self) {
+        self.events_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_event_received(&self) {
+        self.events_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            queries_executed: self.queries_executed.load(Ordering::Relaxed),
+            queries_failed: self.queries_failed.load(Ordering::Relaxed),
+            rows_returned: self.rows_returned.load(Ordering::Relaxed),
+            events_sent: self.events_sent.load(Ordering::Relaxed),
+            events_received: self.events_received.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -1,134 +1,704 @@
 // query.rs
 
 use crate::schema::{ColumnSchema, ColumnType};
+use crate::sql::{split_top_level, unquote_identifier, unquote_literal};
+use crate::value::{Value, date_key};
+use std::cmp::Ordering;
+use std::fmt;
 
-/// Parses a simple query string (e.g., 'id == 1') into a predicate closure.
-/// Supports ==, !=, >, <, >=, <= for a single column.
+/// A boxed row predicate produced from a WHERE clause.
+pub type Predicate = Box<dyn Fn(&Vec<Value>) -> bool>;
+
+/// A row condition in three-valued logic: `None` is unknown, the result of comparing with
+/// NULL. A WHERE clause keeps only the rows for which it is `Some(true)`.
+type Condition = Box<dyn Fn(&Vec<Value>) -> Option<bool>>;
+
+/// What was wrong with a WHERE condition.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    /// A comparison names a column that is not in the schema.
+    UnknownColumn(String),
+    /// A comparison has no operator (`==`, `<`, LIKE, IN, ...).
+    MissingOperator,
+    /// Anything else, such as unbalanced parentheses or a BETWEEN without AND.
+    InvalidSyntax,
+}
+
+/// A malformed WHERE condition, with the byte offset into the (trimmed) condition at which
+/// the problem was found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub message: String,
+    pub position: usize,
+}
+
+impl ParseError {
+    fn new(kind: ParseErrorKind, message: impl Into<String>, position: usize) -> Self {
+        ParseError {
+            kind,
+            message: message.into(),
+            position,
+        }
+    }
+
+    fn syntax(message: impl Into<String>, position: usize) -> Self {
+        ParseError::new(ParseErrorKind::InvalidSyntax, message, position)
+    }
+
+    /// Shifts the position of an error found in a sub-expression starting at `offset`.
+    fn offset(mut self, offset: usize) -> Self {
+        self.position += offset;
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses a query string (e.g., 'id == 1 AND name != 'Bob'') into a predicate closure.
+/// Conditions may be combined with AND / OR, negated with NOT and grouped with parentheses,
+/// with NOT binding tightest and AND binding tighter than OR. As in SQL, a comparison with
+/// NULL is unknown, and so is its NOT; AND and OR only give unknown when the other operands
+/// do not decide the result. Malformed conditions (e.g. mismatched parentheses) match
+/// nothing; use `try_query_to_predicate` to find out what was wrong.
 /// Uses the provided column schemas to interpret types when comparing.
 pub fn query_to_predicate(
     columns: &[ColumnSchema],
     query: &str,
-) -> Box<dyn Fn(&Vec<String>) -> bool> {
+) -> Predicate {
+    try_query_to_predicate(columns, query).unwrap_or_else(|_| Box::new(|_| false))
+}
+
+/// Like `query_to_predicate`, but reports malformed conditions, unknown columns and
+/// comparisons without an operator as a `ParseError` instead of matching nothing.
+/// Comparisons that are well-formed but can never be true (a literal of the wrong type,
+/// `== NULL`) are unknown, so they match nothing, even under NOT.
+pub fn try_query_to_predicate(
+    columns: &[ColumnSchema],
+    query: &str,
+) -> Result<Predicate, ParseError> {
     let query = query.trim();
-    
+
     // Handle empty query or "true" as always matching
     if query.is_empty() || query == "true" {
-        return Box::new(|_| true);
-    }
-    
-    let ops = ["==", "!=", ">=", "<=", ">", "<"];
-    let mut op_found: Option<(&str, usize)> = None;
-    for op in &ops {
-        if let Some(idx) = query.find(op) {
-            op_found = Some((op, idx));
-            break;
-        }
-    }
-    if let Some((op, idx)) = op_found {
-        let col = query[..idx].trim();
-        let raw_val = query[idx + op.len()..]
-            .trim()
-            .trim_matches('"')
-            .trim_matches('\'');
-        let col_idx = columns.iter().position(|c| c.name == col);
-        if let Some(i) = col_idx {
-            let col_schema = columns[i].clone();
-            match op {
-                "==" => match col_schema.col_type {
-                    ColumnType::Int => {
-                        if let Ok(n) = raw_val.parse::<i64>() {
-                            Box::new(move |row: &Vec<String>| {
-                                row.get(i)
-                                    .and_then(|v| v.parse::<i64>().ok())
-                                    .map_or(false, |v| v == n)
-                            })
-                        } else {
-                            Box::new(|_| false)
-                        }
-                    }
-                    ColumnType::Float => {
-                        if let Ok(n) = raw_val.parse::<f64>() {
-                            Box::new(move |row: &Vec<String>| {
-                                row.get(i)
-                                    .and_then(|v| v.parse::<f64>().ok())
-                                    .map_or(false, |v| v == n)
-                            })
-                        } else {
-                            Box::new(|_| false)
-                        }
-                    }
-                    ColumnType::String => {
-                        let val = raw_val.to_string();
-                        Box::new(move |row: &Vec<String>| row.get(i).map_or(false, |v| v == &val))
-                    }
-                },
-                "!=" => match col_schema.col_type {
-                    ColumnType::Int => {
-                        if let Ok(n) = raw_val.parse::<i64>() {
-                            Box::new(move |row: &Vec<String>| {
-                                row.get(i)
-                                    .and_then(|v| v.parse::<i64>().ok())
-                                    .map_or(false, |v| v != n)
-                            })
-                        } else {
-                            Box::new(|_| false)
-                        }
-                    }
-                    ColumnType::Float => {
-                        if let Ok(n) = raw_val.parse::<f64>() {
-                            Box::new(move |row: &Vec<String>| {
-                                row.get(i)
-                                    .and_then(|v| v.parse::<f64>().ok())
-                                    .map_or(false, |v| v != n)
-                            })
-                        } else {
-                            Box::new(|_| false)
-                        }
-                    }
-                    ColumnType::String => {
-                        let val = raw_val.to_string();
-                        Box::new(move |row: &Vec<String>| row.get(i).map_or(false, |v| v != &val))
-                    }
-                },
-                ">" | "<" | ">=" | "<=" => {
-                    // Numeric comparisons: parse both sides as f64
-                    if let Ok(n) = raw_val.parse::<f64>() {
-                        match op {
-                            ">" => Box::new(move |row: &Vec<String>| {
-                                row.get(i)
-                                    .and_then(|v| v.parse::<f64>().ok())
-                                    .map_or(false, |v| v > n)
-                            }),
-                            "<" => Box::new(move |row: &Vec<String>| {
-                                row.get(i)
-                                    .and_then(|v| v.parse::<f64>().ok())
-                                    .map_or(false, |v| v < n)
-                            }),
-                            ">=" => Box::new(move |row: &Vec<String>| {
-                                row.get(i)
-                                    .and_then(|v| v.parse::<f64>().ok())
-                                    .map_or(false, |v| v >= n)
-                            }),
-                            "<=" => Box::new(move |row: &Vec<String>| {
-                                row.get(i)
-                                    .and_then(|v| v.parse::<f64>().ok())
-                                    .map_or(false, |v| v <= n)
-                            }),
-                            _ => Box::new(|_| false),
-                        }
-                    } else {
-                        Box::new(|_| false)
-                    }
+        return Ok(Box::new(|_| true));
+    }
+
+    let mut parser = ConditionParser {
+        columns,
+        input: query,
+        pos: 0,
+    };
+    let condition = parser.parse()?;
+    Ok(Box::new(move |row: &Vec<Value>| condition(row) == Some(true)))
+}
+
+/// A condition that is unknown for every row.
+fn unknown() -> Condition {
+    Box::new(|_| None)
+}
+
+/// Recursive-descent parser over a WHERE condition:
+///
+/// ```text
+/// or_expr  := and_expr ("OR" and_expr)*
+/// and_expr := not_expr ("AND" not_expr)*
+/// not_expr := "NOT"* primary
+/// primary  := "(" or_expr ")" | comparison
+/// ```
+struct ConditionParser<'a> {
+    columns: &'a [ColumnSchema],
+    input: &'a str,
+    pos: usize,
+}
+
+impl ConditionParser<'_> {
+    /// Parses the whole input, failing if anything (such as a stray ')') is left over.
+    fn parse(&mut self) -> Result<Condition, ParseError> {
+        let pred = self.parse_or()?;
+        self.skip_whitespace();
+        if self.pos < self.input.len() {
+            return Err(ParseError::syntax("Unexpected input", self.pos));
+        }
+        Ok(pred)
+    }
+
+    /// True if any operand is true, otherwise unknown if any is unknown.
+    fn parse_or(&mut self) -> Result<Condition, ParseError> {
+        let mut preds = vec![self.parse_and()?];
+        while self.eat_keyword("OR") {
+            preds.push(self.parse_and()?);
+        }
+        if preds.len() == 1 {
+            return Ok(preds.remove(0));
+        }
+        Ok(Box::new(move |row: &Vec<Value>| {
+            let mut result = Some(false);
+            for pred in &preds {
+                match pred(row) {
+                    Some(true) => return Some(true),
+                    None => result = None,
+                    Some(false) => {}
                 }
-                _ => Box::new(|_| false),
             }
+            result
+        }))
+    }
+
+    /// False if any operand is false, otherwise unknown if any is unknown.
+    fn parse_and(&mut self) -> Result<Condition, ParseError> {
+        let mut preds = vec![self.parse_not()?];
+        while self.eat_keyword("AND") {
+            preds.push(self.parse_not()?);
+        }
+        if preds.len() == 1 {
+            return Ok(preds.remove(0));
+        }
+        Ok(Box::new(move |row: &Vec<Value>| {
+            let mut result = Some(true);
+            for pred in &preds {
+                match pred(row) {
+                    Some(false) => return Some(false),
+                    None => result = None,
+                    Some(true) => {}
+                }
+            }
+            result
+        }))
+    }
+
+    /// A leading NOT inverts the condition that follows, leaving unknown unknown; pairs of
+    /// NOTs cancel out. The NOT of `NOT IN`, `NOT LIKE` and `NOT BETWEEN` follows a column
+    /// name, so it is never seen here.
+    fn parse_not(&mut self) -> Result<Condition, ParseError> {
+        let mut negated = false;
+        while self.eat_keyword("NOT") {
+            negated = !negated;
+        }
+        let pred = self.parse_primary()?;
+        if !negated {
+            return Ok(pred);
+        }
+        Ok(Box::new(move |row: &Vec<Value>| pred(row).map(|b| !b)))
+    }
+
+    fn parse_primary(&mut self) -> Result<Condition, ParseError> {
+        self.skip_whitespace();
+        if self.peek() == Some(b'(') {
+            self.pos += 1;
+            let inner = self.parse_or()?;
+            self.skip_whitespace();
+            if self.peek() != Some(b')') {
+                return Err(ParseError::syntax("Expected ')'", self.pos));
+            }
+            self.pos += 1;
+            return Ok(inner);
+        }
+        let start = self.pos;
+        let end = self.comparison_end();
+        if end == start {
+            return Err(ParseError::syntax("Expected a comparison", self.pos));
+        }
+        self.pos = end;
+        comparison_to_predicate(self.columns, &self.input[start..end])
+            .map_err(|e| e.offset(start))
+    }
+
+    /// Finds where the comparison starting at `pos` ends: at a top-level AND / OR,
+    /// an unmatched closing parenthesis, or the end of input. Quoted literals,
+    /// parenthesized value lists (as in `IN (1, 2)`) and the AND of a BETWEEN are skipped.
+    fn comparison_end(&self) -> usize {
+        let bytes = self.input.as_bytes();
+        let mut quote: Option<u8> = None;
+        let mut depth = 0;
+        let mut in_between = false;
+        for (i, &b) in bytes.iter().enumerate().skip(self.pos) {
+            match quote {
+                Some(q) if b == q => quote = None,
+                Some(_) => {}
+                None if matches!(b, b'\'' | b'"' | b'`') => quote = Some(b),
+                None if b == b'(' => depth += 1,
+                None if b == b')' && depth == 0 => return i,
+                None if b == b')' => depth -= 1,
+                None if depth > 0 => {}
+                None if self.keyword_at(i, "BETWEEN") => in_between = true,
+                None if in_between && self.keyword_at(i, "AND") => in_between = false,
+                None if self.keyword_at(i, "AND") || self.keyword_at(i, "OR") => return i,
+                None => {}
+            }
+        }
+        bytes.len()
+    }
+
+    /// Consumes `keyword` (case-insensitive) if it is next in the input.
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        self.skip_whitespace();
+        if self.keyword_at(self.pos, keyword) {
+            self.pos += keyword.len();
+            true
         } else {
-            // Column not found
-            Box::new(|_| false)
+            false
+        }
+    }
+
+    /// Whether `keyword` appears at byte offset `i` as a standalone word.
+    fn keyword_at(&self, i: usize, keyword: &str) -> bool {
+        keyword_at(self.input, i, keyword)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+}
+
+/// Whether `keyword` (case-insensitive) appears at byte offset `i` of `input` as a
+/// standalone word.
+fn keyword_at(input: &str, i: usize, keyword: &str) -> bool {
+    let bytes = input.as_bytes();
+    let end = i + keyword.len();
+    let is_boundary = |b: u8| b.is_ascii_whitespace() || b == b'(' || b == b')';
+    end <= bytes.len()
+        && bytes[i..end].eq_ignore_ascii_case(keyword.as_bytes())
+        && (i == 0 || is_boundary(bytes[i - 1]))
+        && (end == bytes.len() || is_boundary(bytes[end]))
+}
+
+/// Byte offset of the first standalone `keyword` in `input` outside quoted literals.
+fn find_keyword(input: &str, keyword: &str) -> Option<usize> {
+    let mut quote: Option<u8> = None;
+    for (i, &b) in input.as_bytes().iter().enumerate() {
+        match quote {
+            Some(q) if b == q => quote = None,
+            Some(_) => {}
+            None if matches!(b, b'\'' | b'"' | b'`') => quote = Some(b),
+            None if keyword_at(input, i, keyword) => return Some(i),
+            None => {}
         }
-    } else {
-        // No operator found
-        Box::new(|_| false)
+    }
+    None
+}
+
+/// Splits `column [NOT] KEYWORD rest` into the column name, whether NOT was given and the
+/// text after the keyword. Returns `None` if `keyword` does not appear.
+fn split_keyword_condition<'a>(query: &'a str, keyword: &str) -> Option<(&'a str, bool, &'a str)> {
+    let idx = find_keyword(query, keyword)?;
+    let left = query[..idx].trim_end();
+    let rest = query[idx + keyword.len()..].trim();
+    let negated = left.len() >= 3 && keyword_at(left, left.len() - 3, "NOT");
+    let col = if negated { &left[..left.len() - 3] } else { left };
+    Some((col.trim(), negated, rest))
+}
+
+/// One element of a compiled LIKE pattern.
+#[derive(Clone, Copy, PartialEq)]
+enum LikeToken {
+    /// `%`: any sequence of characters, including none.
+    Any,
+    /// `_`: exactly one character.
+    One,
+    Char(char),
+}
+
+/// Compiles a LIKE pattern; `\%` and `\_` match a literal `%` or `_`.
+fn compile_like(pattern: &str) -> Vec<LikeToken> {
+    let mut tokens = vec![];
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '%' => LikeToken::Any,
+            '_' => LikeToken::One,
+            '\\' => LikeToken::Char(chars.next().unwrap_or('\\')),
+            c => LikeToken::Char(c),
+        });
+    }
+    tokens
+}
+
+/// Whether `text` matches the compiled pattern, backtracking to the last `%` on a mismatch.
+fn like_matches(pattern: &[LikeToken], text: &str) -> bool {
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(LikeToken::Any) => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(LikeToken::One) => {
+                p += 1;
+                t += 1;
+            }
+            Some(LikeToken::Char(c)) if *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the last `%` absorb one more character and retry
+                Some((star_p, star_t)) => {
+                    backtrack = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|token| *token == LikeToken::Any)
+}
+
+/// Parses `column [NOT] LIKE 'pattern'`, or its case-insensitive form `column [NOT] ILIKE
+/// 'pattern'`. Only String columns can match; on other columns, and for NULLs, the result is
+/// unknown.
+fn like_predicate(
+    columns: &[ColumnSchema],
+    query: &str,
+) -> Result<Option<Condition>, ParseError> {
+    let (ignore_case, parts) = match split_keyword_condition(query, "ILIKE") {
+        Some(parts) => (true, parts),
+        None => match split_keyword_condition(query, "LIKE") {
+            Some(parts) => (false, parts),
+            None => return Ok(None),
+        },
+    };
+    let (col, negate, raw_pattern) = parts;
+    let i = column_position(columns, col)?;
+    if columns[i].col_type != ColumnType::String {
+        return Ok(Some(unknown()));
+    }
+    let raw_pattern = unquote_literal(raw_pattern);
+    if ignore_case {
+        let pattern = compile_like(&raw_pattern.to_lowercase());
+        return Ok(Some(Box::new(move |row: &Vec<Value>| match row.get(i) {
+            Some(Value::Str(s)) => Some(like_matches(&pattern, &s.to_lowercase()) != negate),
+            _ => None,
+        })));
+    }
+    let pattern = compile_like(&raw_pattern);
+    Ok(Some(Box::new(move |row: &Vec<Value>| match row.get(i) {
+        Some(Value::Str(s)) => Some(like_matches(&pattern, s) != negate),
+        _ => None,
+    })))
+}
+
+/// Resolves a column, possibly quoted, named in a comparison (which starts at offset 0 of the
+/// comparison).
+fn column_position(columns: &[ColumnSchema], col: &str) -> Result<usize, ParseError> {
+    let col = unquote_identifier(col);
+    columns.iter().position(|c| c.name == col).ok_or_else(|| {
+        ParseError::new(
+            ParseErrorKind::UnknownColumn(col.to_string()),
+            format!("Unknown column '{}'", col),
+            0,
+        )
+    })
+}
+
+/// If `col` is written `LOWER(name)`, returns `name`.
+fn strip_lower(col: &str) -> Option<&str> {
+    let (func, rest) = col.split_once('(')?;
+    if !func.trim().eq_ignore_ascii_case("LOWER") {
+        return None;
+    }
+    Some(rest.strip_suffix(')')?.trim())
+}
+
+/// Parses `column [NOT] IN (v1, v2, ...)`. Values that do not fit the column's type can
+/// never match and are dropped. An empty list matches nothing, or everything for NOT IN.
+/// A NULL is unknown, as is a value missing from a list that holds a NULL.
+fn in_predicate(columns: &[ColumnSchema], query: &str) -> Result<Option<Condition>, ParseError> {
+    let Some((col, negate, list)) = split_keyword_condition(query, "IN") else {
+        return Ok(None);
+    };
+    let i = column_position(columns, col)?;
+    let Some(list) = list.strip_prefix('(').and_then(|l| l.strip_suffix(')')) else {
+        let position = query.len() - list.len();
+        return Err(ParseError::syntax("IN requires a parenthesized list", position));
+    };
+    let items = split_top_level(list);
+    let has_null = items.iter().any(|item| item.eq_ignore_ascii_case("NULL"));
+    let is_date = columns[i].col_type == ColumnType::Date;
+    let values: Vec<Value> = items
+        .iter()
+        .filter(|item| !item.eq_ignore_ascii_case("NULL"))
+        .filter_map(|item| {
+            Value::parse(&unquote_literal(item), &columns[i].col_type)
+        })
+        .filter_map(|v| if is_date { date_value(&v) } else { Some(v) })
+        .collect();
+    if items.is_empty() {
+        return Ok(Some(Box::new(move |_| Some(negate))));
+    }
+    Ok(Some(Box::new(move |row: &Vec<Value>| {
+        let value = row.get(i).filter(|v| !v.is_null())?;
+        let found = if is_date {
+            date_value(value).is_some_and(|key| values.contains(&key))
+        } else {
+            values.contains(value)
+        };
+        if found {
+            Some(!negate)
+        } else if has_null {
+            None
+        } else {
+            Some(negate)
+        }
+    })))
+}
+
+/// A Date value as its seconds since the epoch, so IN matches the same instant written with
+/// a `T` or a space.
+fn date_value(value: &Value) -> Option<Value> {
+    value.as_str().and_then(date_key).map(Value::Int)
+}
+
+/// Parses `column [NOT] BETWEEN low AND high`, inclusive of both bounds. Int and Float
+/// columns compare numerically, Date columns chronologically and String columns
+/// lexicographically; if `low > high` no value is in range.
+fn between_predicate(
+    columns: &[ColumnSchema],
+    query: &str,
+) -> Result<Option<Condition>, ParseError> {
+    let Some((col, negate, range)) = split_keyword_condition(query, "BETWEEN") else {
+        return Ok(None);
+    };
+    let i = column_position(columns, col)?;
+    let Some(and) = find_keyword(range, "AND") else {
+        let position = query.len() - range.len();
+        return Err(ParseError::syntax("BETWEEN requires 'low AND high'", position));
+    };
+    let (low, high) = (unquote_literal(&range[..and]), unquote_literal(&range[and + 3..]));
+    if columns[i].col_type == ColumnType::Date {
+        let (Some(low), Some(high)) = (date_key(&low), date_key(&high)) else {
+            return Ok(Some(unknown()));
+        };
+        return Ok(Some(Box::new(move |row: &Vec<Value>| {
+            row.get(i)
+                .and_then(Value::as_str)
+                .and_then(date_key)
+                .map(|v| (low <= v && v <= high) != negate)
+        })));
+    }
+    if columns[i].col_type == ColumnType::String {
+        return Ok(Some(Box::new(move |row: &Vec<Value>| match row.get(i) {
+            Some(Value::Str(s)) => Some((low <= *s && *s <= high) != negate),
+            _ => None,
+        })));
+    }
+    let (Ok(low), Ok(high)) = (low.parse::<f64>(), high.parse::<f64>()) else {
+        return Ok(Some(unknown()));
+    };
+    Ok(Some(Box::new(move |row: &Vec<Value>| {
+        row.get(i)
+            .and_then(numeric)
+            .map(|v| (low <= v && v <= high) != negate)
+    })))
+}
+
+/// If the WHERE clause is a single `column == literal` comparison, returns the column's
+/// position and the literal converted to its type. Used to answer the query from an index.
+pub fn equality_condition(columns: &[ColumnSchema], query: &str) -> Option<(usize, Value)> {
+    let query = query.trim();
+    let parser = ConditionParser {
+        columns,
+        input: query,
+        pos: 0,
+    };
+    if query.is_empty() || query.starts_with('(') || parser.comparison_end() != query.len() {
+        return None;
+    }
+    let (op, idx) = find_operator(query)?;
+    if op != "==" {
+        return None;
+    }
+    let (col, raw_val) = (&query[..idx], query[idx + op.len()..].trim());
+    if raw_val.eq_ignore_ascii_case("NULL") {
+        return None;
+    }
+    let raw_val = &unquote_literal(raw_val);
+    let i = columns.iter().position(|c| c.name == unquote_identifier(col))?;
+    // Dates written differently may be equal, so they are never looked up by their text
+    if columns[i].col_type == ColumnType::Date {
+        return None;
+    }
+    Some((i, Value::parse(raw_val, &columns[i].col_type)?))
+}
+
+/// Finds the first comparison operator outside quoted literals, so `note == 'a>b'` splits
+/// on the `==` rather than the `>` inside the string. Returns the operator and its offset.
+fn find_operator(query: &str) -> Option<(&'static str, usize)> {
+    const OPS: [&str; 6] = ["==", "!=", ">=", "<=", ">", "<"];
+    let mut quote: Option<u8> = None;
+    for (i, &b) in query.as_bytes().iter().enumerate() {
+        match quote {
+            Some(q) if b == q => quote = None,
+            Some(_) => {}
+            None if matches!(b, b'\'' | b'"' | b'`') => quote = Some(b),
+            None => {
+                let rest = &query.as_bytes()[i..];
+                if let Some(op) = OPS.iter().find(|op| rest.starts_with(op.as_bytes())) {
+                    return Some((op, i));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Parses a single `column OP value` comparison into a predicate closure.
+/// Supports ==, !=, >, <, >=, <=, [NOT] LIKE, [NOT] ILIKE, [NOT] IN and [NOT] BETWEEN for
+/// a single column, and `LOWER(col) == 'text'` for case-insensitive equality. The literal
+/// is converted to the column's type once, and rows are compared by value; dates compare as
+/// instants, whichever separator they are written with. A comparison with NULL, or with a
+/// literal that does not fit the column, is unknown.
+fn comparison_to_predicate(columns: &[ColumnSchema], query: &str) -> Result<Condition, ParseError> {
+    let query = query.trim();
+    if let Some(pred) = like_predicate(columns, query)? {
+        return Ok(pred);
+    }
+    if let Some(pred) = between_predicate(columns, query)? {
+        return Ok(pred);
+    }
+    if let Some(pred) = in_predicate(columns, query)? {
+        return Ok(pred);
+    }
+    let Some((op, idx)) = find_operator(query) else {
+        return Err(ParseError::new(
+            ParseErrorKind::MissingOperator,
+            format!("No comparison operator in '{}'", query),
+            0,
+        ));
+    };
+    let col = query[..idx].trim();
+    let raw_val = query[idx + op.len()..].trim();
+    if let Some(col) = strip_lower(col) {
+        return lower_equality(columns, col, op, raw_val);
+    }
+    let i = column_position(columns, col)?;
+    if raw_val.eq_ignore_ascii_case("NULL") {
+        // Three-valued logic: any comparison with NULL is unknown
+        return Ok(unknown());
+    }
+    let raw_val = &unquote_literal(raw_val);
+    Ok(match op {
+        "==" | "!=" if columns[i].col_type == ColumnType::Date => {
+            // Dates are equal when they name the same instant, whichever separator they use
+            let negate = op == "!=";
+            let Some(d) = date_key(raw_val) else {
+                return Ok(unknown());
+            };
+            Box::new(move |row: &Vec<Value>| {
+                row.get(i)
+                    .and_then(Value::as_str)
+                    .and_then(date_key)
+                    .map(|v| (v == d) != negate)
+            })
+        }
+        "==" | "!=" => {
+            let negate = op == "!=";
+            let Some(lit) = Value::parse(raw_val, &columns[i].col_type) else {
+                // An Int column compared with a float literal such as 1e3 or -2.0
+                let col_type = &columns[i].col_type;
+                let numeric_col = matches!(col_type, ColumnType::Int | ColumnType::Float);
+                let Some(n) = raw_val.parse::<f64>().ok().filter(|_| numeric_col) else {
+                    return Ok(unknown());
+                };
+                return Ok(Box::new(move |row: &Vec<Value>| {
+                    numeric_cmp(row.get(i).and_then(Value::as_float), n)
+                        .map(|o| (o == Ordering::Equal) != negate)
+                }));
+            };
+            Box::new(move |row: &Vec<Value>| {
+                row.get(i)
+                    .filter(|v| !v.is_null())
+                    .map(|v| (*v == lit) != negate)
+            })
+        }
+        ">" | "<" | ">=" | "<=" => {
+            let test: fn(Ordering) -> bool = match op {
+                ">" => |o| o == Ordering::Greater,
+                "<" => |o| o == Ordering::Less,
+                ">=" => |o| o != Ordering::Less,
+                _ => |o| o != Ordering::Greater,
+            };
+            if columns[i].col_type == ColumnType::Date {
+                // Dates compare chronologically by their seconds since the epoch
+                let Some(d) = date_key(raw_val) else {
+                    return Ok(unknown());
+                };
+                return Ok(Box::new(move |row: &Vec<Value>| {
+                    row.get(i)
+                        .and_then(Value::as_str)
+                        .and_then(date_key)
+                        .map(|v| test(v.cmp(&d)))
+                }));
+            }
+            if columns[i].col_type == ColumnType::String {
+                // Strings compare lexicographically, even when they hold numbers, matching
+                // ORDER BY and BETWEEN
+                let lit = raw_val.to_string();
+                return Ok(Box::new(move |row: &Vec<Value>| match row.get(i) {
+                    Some(Value::Str(s)) => Some(test(s.as_str().cmp(lit.as_str()))),
+                    _ => None,
+                }));
+            }
+            // Numeric comparisons: compare both sides as f64
+            let Ok(n) = raw_val.parse::<f64>() else {
+                return Ok(unknown());
+            };
+            Box::new(move |row: &Vec<Value>| numeric_cmp(row.get(i).and_then(numeric), n).map(test))
+        }
+        _ => unknown(),
+    })
+}
+
+/// Case-insensitive equality: `LOWER(col) == 'text'` (or `!=`) lower-cases the stored
+/// String value before comparing it with the literal as written. Other operators,
+/// non-String columns and NULLs are unknown.
+fn lower_equality(
+    columns: &[ColumnSchema],
+    col: &str,
+    op: &str,
+    literal: &str,
+) -> Result<Condition, ParseError> {
+    // LOWER( is 6 bytes, so the column name starts there
+    let i = column_position(columns, col).map_err(|e| e.offset(6))?;
+    let never_matches = columns[i].col_type != ColumnType::String
+        || !matches!(op, "==" | "!=")
+        || literal.eq_ignore_ascii_case("NULL");
+    if never_matches {
+        return Ok(unknown());
+    }
+    let literal = unquote_literal(literal);
+    let negate = op == "!=";
+    Ok(Box::new(move |row: &Vec<Value>| match row.get(i) {
+        Some(Value::Str(s)) => Some((s.to_lowercase() == literal) != negate),
+        _ => None,
+    }))
+}
+
+/// Orders a row's numeric value against the literal; `None` for NULLs, non-numbers and NaN.
+fn numeric_cmp(value: Option<f64>, literal: f64) -> Option<Ordering> {
+    value?.partial_cmp(&literal)
+}
+
+/// The numeric value of a row value; strings holding a number are parsed.
+fn numeric(value: &Value) -> Option<f64> {
+    match value {
+        Value::Str(s) => s.parse().ok(),
+        v => v.as_float(),
     }
 }
 
@@ -1,8 +1,11 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, RwLock};
 use std::time::Duration;
+use crate::database::Database;
+use crate::metrics::Metrics;
 use crate::row::RowInterface;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,8 +14,33 @@ pub struct ReplicationConfig {
     pub primary_url: Option<String>,
     pub replicas: HashSet<String>,
     pub sync_interval: Duration,
+    /// How often a primary with a data file saves the database to it.
+    pub autosave_interval: Duration,
+    /// Number of most recent events kept in memory once the log is compacted (at least one
+    /// is always kept). Replicas that fall further behind catch up from a snapshot instead.
+    pub event_retention: usize,
+    /// Bearer token a replica sends to its primary, when the primary requires one.
+    pub primary_auth_token: Option<String>,
+    /// URL the primary can reach this replica at. A replica with one acknowledges the
+    /// events it has applied under that URL, so the primary can track its lag.
+    pub advertise_url: Option<String>,
+    /// Whether a replica periodically logs a snapshot of its tables (at most
+    /// `DISPLAY_ROW_LIMIT` rows each). Off by default, as it floods the logs.
+    #[serde(default)]
+    pub display: bool,
+    /// Whether the primary skips an event whose query is identical to the previous one.
+    /// Only safe when repeating a statement can never change the data again (e.g. every
+    /// insert names its primary key); off by default.
+    #[serde(default)]
+    pub dedup_consecutive: bool,
 }
 
+/// Default for `ReplicationConfig::event_retention`.
+const DEFAULT_EVENT_RETENTION: usize = 10_000;
+
+/// Rows logged per table by the replica display task; the rest are summarized.
+pub const DISPLAY_ROW_LIMIT: usize = 10;
+
 impl Default for ReplicationConfig {
     fn default() -> Self {
         Self {
@@ -20,20 +48,98 @@ impl Default for ReplicationConfig {
             primary_url: None,
             replicas: HashSet::new(),
             sync_interval: Duration::from_secs(5),
+            autosave_interval: Duration::from_secs(5),
+            event_retention: DEFAULT_EVENT_RETENTION,
+            primary_auth_token: None,
+            advertise_url: None,
+            display: false,
+            dedup_consecutive: false,
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReplicationEvent {
+    /// Sequence number assigned by the primary, starting at 1. Replicas apply an event only
+    /// if its id is greater than the last one they applied, so re-delivery is harmless.
+    #[serde(default)]
+    pub id: u64,
     pub timestamp: u64,
     pub query: String,
+    /// Set on the event recorded by an admin reset: instead of running `query`, replicas
+    /// replace their database with an empty one.
+    #[serde(default)]
+    pub reset: bool,
+}
+
+/// A copy of the primary's database together with the id of the last event it includes, so
+/// a new replica can start from it and then apply only later events.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicationSnapshot {
+    pub database: Database,
+    pub last_event_id: u64,
+}
+
+/// Events recorded on a primary, or applied on a replica, and the id of the newest one.
+/// `last_id` is kept separately because compaction and snapshots leave out older events.
+#[derive(Debug)]
+struct EventLog {
+    events: Vec<ReplicationEvent>,
+    last_id: u64,
+    /// Newest event id seen from the primary, applied or not. Equals `last_id` on a primary.
+    known_id: u64,
+    retention: usize,
+}
+
+impl EventLog {
+    fn new(retention: usize) -> Self {
+        Self {
+            events: Vec::new(),
+            last_id: 0,
+            known_id: 0,
+            // The newest event is what shows a lagging replica that it has missed some
+            retention: retention.max(1),
+        }
+    }
+
+    /// Appends an event, compacting once the log holds twice the retained number of events
+    /// so the cost of compaction is spread over many pushes.
+    fn push(&mut self, event: ReplicationEvent) {
+        self.last_id = event.id;
+        self.known_id = self.known_id.max(event.id);
+        self.events.push(event);
+        if self.events.len() >= self.retention.saturating_mul(2) {
+            self.compact();
+        }
+    }
+
+    /// Drops all but the `retention` most recent events, returning how many were dropped.
+    fn compact(&mut self) -> usize {
+        let excess = self.events.len().saturating_sub(self.retention);
+        self.events.drain(..excess);
+        excess
+    }
+}
+
+/// How far a replica is behind the primary, as reported by `replication_status`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplicaStatus {
+    pub url: String,
+    /// Id of the last event the replica acknowledged, 0 if it never has.
+    pub applied_seq: u64,
+    /// Number of recorded events the replica has not acknowledged yet.
+    pub lag: u64,
 }
 
 pub struct ReplicationManager {
     config: ReplicationConfig,
-    events: Arc<Mutex<Vec<ReplicationEvent>>>,
-    db: Arc<Mutex<crate::database::Database>>,
+    events: Arc<Mutex<EventLog>>,
+    db: Arc<RwLock<Database>>,
+    /// Last event id acknowledged by each replica, on the primary.
+    acks: HashMap<String, u64>,
+    /// Set on promotion to stop the replica's sync and display tasks.
+    replica_tasks_stopped: Arc<AtomicBool>,
+    metrics: Arc<Metrics>,
 }
 
 impl ReplicationConfig {
@@ -43,6 +149,12 @@ impl ReplicationConfig {
             primary_url: None,
             replicas: HashSet::new(),
             sync_interval: Duration::from_secs(5),
+            autosave_interval: Duration::from_secs(5),
+            event_retention: DEFAULT_EVENT_RETENTION,
+            primary_auth_token: None,
+            advertise_url: None,
+            display: false,
+            dedup_consecutive: false,
         }
     }
 
@@ -52,34 +164,164 @@ impl ReplicationConfig {
             primary_url: Some(primary_url),
             replicas: HashSet::new(),
             sync_interval: Duration::from_secs(5),
+            autosave_interval: Duration::from_secs(5),
+            event_retention: DEFAULT_EVENT_RETENTION,
+            primary_auth_token: None,
+            advertise_url: None,
+            display: false,
+            dedup_consecutive: false,
+        }
+    }
+
+    /// Parses a `--sync-interval` value: a whole, positive number of seconds.
+    pub fn parse_sync_interval(secs: &str) -> Result<Duration, String> {
+        match secs.trim().parse::<u64>() {
+            Ok(0) => Err("--sync-interval must be at least 1 second".to_string()),
+            Ok(secs) => Ok(Duration::from_secs(secs)),
+            Err(_) => Err(format!("invalid --sync-interval '{}': expected seconds", secs)),
+        }
+    }
+}
+
+/// Applies, in id order, the events newer than the last one in `applied` and appends them
+/// to it. Events that were already applied, including repeats within `events`, are skipped.
+/// Returns `false` if an event is missing, e.g. because the primary compacted it away; the
+/// events after the gap are not applied and the replica needs a snapshot to continue.
+fn apply_new_events(
+    db: &mut Database,
+    applied: &mut EventLog,
+    mut events: Vec<ReplicationEvent>,
+    metrics: &Metrics,
+) -> bool {
+    events.sort_by_key(|e| e.id);
+    if let Some(newest) = events.last() {
+        applied.known_id = applied.known_id.max(newest.id);
+    }
+    for event in events {
+        if event.id <= applied.last_id {
+            continue;
         }
+        if event.id > applied.last_id + 1 {
+            return false;
+        }
+        if event.reset {
+            *db = Database::new();
+        } else if let Err(e) = crate::sql::execute_sql(db, &event.query) {
+            log::warn!("Replicated event {} failed: {}", event.id, e);
+        }
+        metrics.record_event_received();
+        applied.push(event);
+    }
+    true
+}
+
+/// Replaces the replica's tables with the snapshot's, unless the replica has already
+/// applied everything the snapshot contains.
+fn install_snapshot(
+    db: &RwLock<Database>,
+    applied: &Mutex<EventLog>,
+    mut snapshot: ReplicationSnapshot,
+) {
+    if snapshot.database.post_load_init().is_err() {
+        return;
+    }
+    let mut db = db.write().unwrap_or_else(|p| p.into_inner());
+    let mut applied = applied.lock().unwrap_or_else(|p| p.into_inner());
+    if snapshot.last_event_id <= applied.last_id {
+        return;
+    }
+    db.tables = snapshot.database.tables;
+    applied.events.clear();
+    applied.last_id = snapshot.last_event_id;
+    applied.known_id = applied.known_id.max(snapshot.last_event_id);
+}
+
+/// Builds the client a replica talks to its primary with, sending `auth_token` as a bearer
+/// token on every request if given.
+fn primary_client(auth_token: Option<&str>) -> reqwest::blocking::Client {
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Some(token) = auth_token
+        && let Ok(value) = format!("Bearer {}", token).parse()
+    {
+        headers.insert(reqwest::header::AUTHORIZATION, value);
     }
+    reqwest::blocking::Client::builder()
+        .default_headers(headers)
+        .build()
+        .unwrap_or_default()
+}
+
+/// Calls a JSON-RPC method on the primary and returns its result, if the call succeeded.
+fn call_primary(
+    client: &reqwest::blocking::Client,
+    primary_url: &str,
+    method: &str,
+    params: serde_json::Value,
+) -> Option<serde_json::Value> {
+    let rpc_req = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": params,
+        "id": 1
+    });
+    let response = client.post(primary_url).json(&rpc_req).send().ok()?;
+    let mut rpc_res_val = response.json::<serde_json::Value>().ok()?;
+    rpc_res_val.get_mut("result").map(serde_json::Value::take)
 }
 
 impl ReplicationManager {
-    pub fn new(config: ReplicationConfig, db: Arc<Mutex<crate::database::Database>>) -> Self {
+    pub fn new(config: ReplicationConfig, db: Arc<RwLock<Database>>) -> Self {
         Self {
+            events: Arc::new(Mutex::new(EventLog::new(config.event_retention))),
             config,
-            events: Arc::new(Mutex::new(Vec::new())),
             db,
+            acks: HashMap::new(),
+            replica_tasks_stopped: Arc::new(AtomicBool::new(false)),
+            metrics: Arc::default(),
         }
     }
 
+    /// Records a mutating statement for the replicas. Read-only statements are ignored, as
+    /// replaying them would be pointless, as are repeats of the previous query when
+    /// `dedup_consecutive` is set.
     pub fn record_event(&self, query: String) {
-        if self.config.is_primary {
-            let event = ReplicationEvent {
-                timestamp: std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs(),
-                query,
-            };
+        if crate::sql::is_read_only(&query) {
+            return;
+        }
+        self.record(query, false);
+    }
+
+    /// Records that the primary's database was replaced with an empty one. Earlier events
+    /// no longer matter, so they are dropped from the log.
+    pub fn record_reset(&self) {
+        self.record("RESET".to_string(), true);
+    }
 
-            // Push event into local store, recovering if mutex was poisoned
-            {
+    fn record(&self, query: String, reset: bool) {
+        if self.config.is_primary {
+            // Number and push the event into the local store, recovering if mutex was poisoned
+            let event = {
                 let mut events_lock = self.events.lock().unwrap_or_else(|p| p.into_inner());
+                if reset {
+                    events_lock.events.clear();
+                } else if self.config.dedup_consecutive
+                    && events_lock.events.last().is_some_and(|e| !e.reset && e.query == query)
+                {
+                    return;
+                }
+                let event = ReplicationEvent {
+                    id: events_lock.last_id + 1,
+                    timestamp: std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap()
+                        .as_secs(),
+                    query,
+                    reset,
+                };
                 events_lock.push(event.clone());
-            }
+                event
+            };
+            self.metrics.record_event_sent();
 
             // Spawn a background thread to propagate this event to replicas so we don't
             // create/drop blocking runtimes from within the HTTP worker thread.
@@ -92,7 +334,7 @@ impl ReplicationManager {
                 let events_payload = vec![event];
                 for replica in &replicas {
                     let _ = client
-                        .post(&format!("{}/replicate", replica))
+                        .post(format!("{}/replicate", replica))
                         .json(&events_payload)
                         .send();
                 }
@@ -108,7 +350,7 @@ impl ReplicationManager {
             return;
         }
 
-        let events = self.events.lock().unwrap_or_else(|p| p.into_inner()).clone();
+        let events = self.get_events();
         let client = reqwest::blocking::Client::new();
 
         // Send events as a JSON-RPC call to each replica so we reuse the
@@ -130,21 +372,10 @@ impl ReplicationManager {
             return Err("Cannot apply replication events to primary server".into());
         }
 
-        // Get current event count (recover if mutex was poisoned)
-        let current_count = self.events.lock().unwrap_or_else(|p| p.into_inner()).len();
-
-        // Only apply new events
-        let new_events: Vec<_> = events.into_iter().skip(current_count).collect();
-        
-        if !new_events.is_empty() {
-            let mut db = self.db.lock().unwrap_or_else(|p| p.into_inner());
-            let mut events_lock = self.events.lock().unwrap_or_else(|p| p.into_inner());
-            
-            for event in new_events {
-                // Apply the query to the database
-                crate::sql::execute_sql(&mut db, &event.query);
-                events_lock.push(event);
-            }
+        let mut db = self.db.write().unwrap_or_else(|p| p.into_inner());
+        let mut events_lock = self.events.lock().unwrap_or_else(|p| p.into_inner());
+        if !apply_new_events(&mut db, &mut events_lock, events, &self.metrics) {
+            return Err("Replication events are missing; the replica must resync".into());
         }
 
         Ok(())
@@ -163,82 +394,223 @@ impl ReplicationManager {
         let events = self.events.clone();
         let interval = self.config.sync_interval;
         let db = self.db.clone();
+        let advertise_url = self.config.advertise_url.clone();
+        let stopped = self.replica_tasks_stopped.clone();
+        let metrics = self.metrics.clone();
+        let auth_token = self.config.primary_auth_token.clone();
 
         std::thread::spawn(move || {
-            let client = reqwest::blocking::Client::new();
+            let client = primary_client(auth_token.as_deref());
+            let mut caught_up = false;
             loop {
+                // Start from a snapshot of the primary rather than replaying its whole history
+                if !caught_up
+                    && let Some(result) = call_primary(
+                        &client,
+                        &primary_url,
+                        "replication_snapshot",
+                        serde_json::json!([]),
+                    )
+                    && let Ok(snapshot) = serde_json::from_value::<ReplicationSnapshot>(result)
+                {
+                    install_snapshot(&db, &events, snapshot);
+                    caught_up = true;
+                }
+
                 std::thread::sleep(interval);
+                if stopped.load(Ordering::SeqCst) {
+                    return;
+                }
 
-                // Call primary via JSON-RPC to get events
-                let rpc_req = serde_json::json!({
-                    "jsonrpc": "2.0",
-                    "method": "replication_get_events",
-                    "params": [],
-                    "id": 1
-                });
-
-                if let Ok(response) = client.post(&primary_url).json(&rpc_req).send() {
-                    if let Ok(rpc_res_val) = response.json::<serde_json::Value>() {
-                        if let Some(result) = rpc_res_val.get("result") {
-                            if let Ok(new_events) = serde_json::from_value::<Vec<ReplicationEvent>>(result.clone()) {
-                                let mut db_lock = db.lock().unwrap_or_else(|p| p.into_inner());
-                                let mut events_lock = events.lock().unwrap_or_else(|p| p.into_inner());
-                                let current_count = events_lock.len();
-
-                                // Apply new events to the database
-                                for event in new_events.iter().skip(current_count) {
-                                    crate::sql::execute_sql(&mut db_lock, &event.query);
-                                }
-
-                                events_lock.extend(new_events.into_iter().skip(current_count));
-                            }
-                        }
+                // Call primary via JSON-RPC to get the events after the last one applied
+                let since = events.lock().unwrap_or_else(|p| p.into_inner()).last_id;
+                if let Some(result) = call_primary(
+                    &client,
+                    &primary_url,
+                    "replication_get_events",
+                    serde_json::json!([since]),
+                ) && let Ok(new_events) = serde_json::from_value::<Vec<ReplicationEvent>>(result)
+                {
+                    let mut db_lock = db.write().unwrap_or_else(|p| p.into_inner());
+                    let mut events_lock = events.lock().unwrap_or_else(|p| p.into_inner());
+                    // Promoted while fetching: this node's own writes now take precedence
+                    if stopped.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    // Events the primary has compacted away are recovered from a snapshot
+                    if !apply_new_events(&mut db_lock, &mut events_lock, new_events, &metrics) {
+                        caught_up = false;
                     }
                 }
+
+                if let Some(url) = &advertise_url {
+                    let applied = events.lock().unwrap_or_else(|p| p.into_inner()).last_id;
+                    let params = serde_json::json!([url, applied]);
+                    call_primary(&client, &primary_url, "replication_ack", params);
+                }
             }
         });
     }
 
-    /// Start a background thread that periodically prints the current tables and rows
-    /// on replica nodes. This helps visually verify that replicas have the same content
-    /// as the primary in container logs.
-    pub fn start_display_task(&self) {
-        if self.config.is_primary {
-            return;
-        }
-
-        let db = self.db.clone();
-        let interval = self.config.sync_interval;
+    /// Runs `task` against a snapshot of the database every `interval` on a background
+    /// thread. The thread stops once the database itself has been dropped, or once `stop`
+    /// is set if one is given.
+    fn start_periodic_task<F>(&self, interval: Duration, stop: Option<Arc<AtomicBool>>, task: F)
+    where
+        F: Fn(&Database) + Send + 'static,
+    {
+        let db = Arc::downgrade(&self.db);
 
         std::thread::spawn(move || {
             loop {
                 std::thread::sleep(interval);
 
-                let db_lock = db.lock().unwrap_or_else(|p| p.into_inner());
-                println!("[replica] Current database snapshot:");
-                for (tname, table) in &db_lock.tables {
-                    println!("[replica] Table: {}", tname);
-                    // print schema header
-                    let headers: Vec<_> = table.schema.columns.iter().map(|c| c.name.clone()).collect();
-                    println!("[replica] Columns: {:?}", headers);
-                    // print rows
-                    for (i, row) in table.rows.iter().enumerate() {
-                        let vals = row.get_values().clone();
-                        println!("[replica]   row[{}]: {:?}", i, vals);
-                    }
+                if stop.as_ref().is_some_and(|stop| stop.load(Ordering::SeqCst)) {
+                    return;
                 }
+                let Some(db) = db.upgrade() else {
+                    return;
+                };
+                let db_lock = db.read().unwrap_or_else(|p| p.into_inner());
+                task(&db_lock);
+            }
+        });
+    }
+
+    /// Start a background thread that periodically prints the current tables and rows
+    /// on replica nodes, if `display` is enabled. This helps visually verify that replicas
+    /// have the same content as the primary in container logs. Returns whether the task was
+    /// started.
+    pub fn start_display_task(&self) -> bool {
+        if self.config.is_primary || !self.config.display {
+            return false;
+        }
+
+        let stop = Some(self.replica_tasks_stopped.clone());
+        self.start_periodic_task(self.config.sync_interval, stop, |db_lock| {
+            log::info!("[replica] Current database snapshot:");
+            for (tname, table) in &db_lock.tables {
+                log::info!("[replica] Table: {}", tname);
+                // print schema header
+                let headers: Vec<_> = table.schema.columns.iter().map(|c| c.name.clone()).collect();
+                log::info!("[replica] Columns: {:?}", headers);
+                // print rows
+                for (i, row) in table.rows.iter().take(DISPLAY_ROW_LIMIT).enumerate() {
+                    let vals = row.get_values_as_strings();
+                    log::info!("[replica]   row[{}]: {:?}", i, vals);
+                }
+                if table.rows.len() > DISPLAY_ROW_LIMIT {
+                    log::info!("[replica]   ... {} more", table.rows.len() - DISPLAY_ROW_LIMIT);
+                }
+            }
+        });
+        true
+    }
+
+    /// Start a background thread that saves the primary's database to `path` every autosave
+    /// interval, so a restart loses at most one interval of writes.
+    pub fn start_checkpoint_task(&self, path: String) {
+        if !self.config.is_primary {
+            return;
+        }
+
+        self.start_periodic_task(self.config.autosave_interval, None, move |db_lock| {
+            if let Err(e) = db_lock.save_to_file(&path) {
+                log::error!("Failed to checkpoint database to {}: {}", path, e);
             }
         });
     }
 
     pub fn get_events(&self) -> Vec<ReplicationEvent> {
-        self.events.lock().unwrap_or_else(|p| p.into_inner()).clone()
+        self.events.lock().unwrap_or_else(|p| p.into_inner()).events.clone()
+    }
+
+    /// Drops all but the most recent `event_retention` events from the log, returning how
+    /// many were dropped. This also happens automatically as the log grows. Replicas that
+    /// still need the dropped events catch up from a snapshot, which always reflects every
+    /// event recorded so far.
+    pub fn compact_events(&self) -> usize {
+        self.events.lock().unwrap_or_else(|p| p.into_inner()).compact()
+    }
+
+    /// Counters for the events this node has sent or received.
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
+
+    /// Id of the last event recorded (on a primary) or applied (on a replica).
+    pub fn last_event_id(&self) -> u64 {
+        self.events.lock().unwrap_or_else(|p| p.into_inner()).last_id
+    }
+
+    /// Id of the newest event this node knows of. On a replica this can be ahead of
+    /// `last_event_id` while events are missing and it waits to resync.
+    pub fn latest_known_event_id(&self) -> u64 {
+        self.events.lock().unwrap_or_else(|p| p.into_inner()).known_id
+    }
+
+    /// Returns the recorded events with an id greater than `since`.
+    pub fn get_events_since(&self, since: u64) -> Vec<ReplicationEvent> {
+        let events_lock = self.events.lock().unwrap_or_else(|p| p.into_inner());
+        events_lock.events.iter().filter(|e| e.id > since).cloned().collect()
+    }
+
+    /// Returns a copy of the database and the id of the last event applied to it.
+    pub fn snapshot(&self) -> ReplicationSnapshot {
+        let db = self.db.read().unwrap_or_else(|p| p.into_inner());
+        let events_lock = self.events.lock().unwrap_or_else(|p| p.into_inner());
+        ReplicationSnapshot {
+            database: db.clone(),
+            last_event_id: events_lock.last_id,
+        }
     }
 
     pub fn is_primary(&self) -> bool {
         self.config.is_primary
     }
 
+    /// Records that the replica at `url` has applied every event up to `applied_seq`. Acks
+    /// never move backwards, so a late, out-of-order ack is ignored.
+    pub fn record_ack(&mut self, url: String, applied_seq: u64) {
+        let acked = self.acks.entry(url).or_default();
+        *acked = (*acked).max(applied_seq);
+    }
+
+    /// Returns the lag of every configured or acknowledging replica, sorted by URL.
+    pub fn replica_status(&self) -> Vec<ReplicaStatus> {
+        let last_id = self.events.lock().unwrap_or_else(|p| p.into_inner()).last_id;
+        let mut urls: Vec<&String> = self.config.replicas.iter().chain(self.acks.keys()).collect();
+        urls.sort();
+        urls.dedup();
+        urls.into_iter()
+            .map(|url| {
+                let applied_seq = self.acks.get(url).copied().unwrap_or(0);
+                ReplicaStatus {
+                    url: url.clone(),
+                    applied_seq,
+                    lag: last_id.saturating_sub(applied_seq),
+                }
+            })
+            .collect()
+    }
+
+    /// Turns this replica into a primary: its sync and display tasks stop, it accepts writes,
+    /// and its event log becomes authoritative, so new events continue from the last one it
+    /// applied. Returns `false` if the node already was a primary.
+    ///
+    /// Nothing fences off the old primary: if it is still running, or comes back, both nodes
+    /// accept writes and their data diverges (split brain). Only promote once the old primary
+    /// is known to be down, and point the remaining replicas at the new one.
+    pub fn promote_to_primary(&mut self) -> bool {
+        if self.config.is_primary {
+            return false;
+        }
+        self.replica_tasks_stopped.store(true, Ordering::SeqCst);
+        self.config.is_primary = true;
+        self.config.primary_url = None;
+        true
+    }
+
     /// Add a replica URL to the primary configuration so future events are propagated.
     pub fn add_replica(&mut self, url: String) {
         self.config.replicas.insert(url);
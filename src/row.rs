@@ -1,8 +1,9 @@
 use crate::schema::Schema;
+use crate::value::Value;
 #[allow(dead_code)]
 impl Row {
     /// Get a reference to a value by column name using the schema.
-    pub fn get_by_name<'a>(&'a self, col_name: &str, schema: &Schema) -> Option<&'a String> {
+    pub fn get_by_name<'a>(&'a self, col_name: &str, schema: &Schema) -> Option<&'a Value> {
         schema
             .columns
             .iter()
@@ -11,12 +12,12 @@ impl Row {
     }
 
     /// Set a value by column name using the schema.
-    pub fn set_by_name(&mut self, col_name: &str, value: String, schema: &Schema) -> bool {
-        if let Some(idx) = schema.columns.iter().position(|c| c.name == col_name) {
-            if idx < self.values.len() {
-                self.values[idx] = value;
-                return true;
-            }
+    pub fn set_by_name(&mut self, col_name: &str, value: Value, schema: &Schema) -> bool {
+        if let Some(idx) = schema.columns.iter().position(|c| c.name == col_name)
+            && idx < self.values.len()
+        {
+            self.values[idx] = value;
+            return true;
         }
         false
     }
@@ -28,21 +29,37 @@ use serde::{Deserialize, Serialize};
 /// Provides methods to get and set the values of the row.
 pub trait RowInterface {
     /// Returns a reference to the values stored in the row.
-    fn get_values(&self) -> &Vec<String>;
+    fn get_values(&self) -> &Vec<Value>;
     /// Sets the values of the row.
-    fn set_values(&mut self, values: Vec<String>);
+    fn set_values(&mut self, values: Vec<Value>);
+    /// Returns the values in their displayed form, with `Null` as an empty string.
+    fn get_values_as_strings(&self) -> Vec<String> {
+        self.get_values().iter().map(|v| v.to_string()).collect()
+    }
+    /// Returns the value at `idx` if it is an integer.
+    fn get_int(&self, idx: usize) -> Option<i64> {
+        self.get_values().get(idx).and_then(Value::as_int)
+    }
+    /// Returns the value at `idx` as a float if it is numeric.
+    fn get_float(&self, idx: usize) -> Option<f64> {
+        self.get_values().get(idx).and_then(Value::as_float)
+    }
+    /// Returns the value at `idx` if it is a string.
+    fn get_str(&self, idx: usize) -> Option<&str> {
+        self.get_values().get(idx).and_then(Value::as_str)
+    }
 }
 
 /// Struct representing a single row in a table.
-/// Stores the values for each column as a vector of strings.
+/// Stores the typed value of each column.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Row {
-    values: Vec<String>,
+    values: Vec<Value>,
 }
 
 impl Row {
     /// Creates a new Row with the given values.
-    pub fn new(values: Vec<String>) -> Self {
+    pub fn new(values: Vec<Value>) -> Self {
         Row { values }
     }
 }
@@ -50,12 +67,12 @@ impl Row {
 /// Implements the RowInterface trait for the Row struct.
 impl RowInterface for Row {
     /// Returns a reference to the values stored in the row.
-    fn get_values(&self) -> &Vec<String> {
+    fn get_values(&self) -> &Vec<Value> {
         &self.values
     }
 
     /// Sets the values of the row.
-    fn set_values(&mut self, values: Vec<String>) {
+    fn set_values(&mut self, values: Vec<Value>) {
         self.values = values;
     }
 }
@@ -1,3 +1,4 @@
+use crate::value::Value;
 use serde::{Deserialize, Serialize};
 
 /// Represents the type of a column in a table schema.
@@ -6,13 +7,40 @@ pub enum ColumnType {
     Int,
     String,
     Float,
+    Bool,
+    /// An ISO-8601 date (`YYYY-MM-DD`), optionally with a time (`THH:MM[:SS]`), stored as
+    /// text.
+    Date,
 }
 
-/// Represents a column in a schema (name and type).
+/// Represents a column in a schema (name, type and whether it accepts NULL).
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ColumnSchema {
     pub name: String,
     pub col_type: ColumnType,
+    /// Whether the column accepts NULL. Columns saved before this flag existed are nullable.
+    #[serde(default = "nullable_default")]
+    pub nullable: bool,
+    /// Value used when an INSERT does not provide this column.
+    #[serde(default)]
+    pub default: Option<Value>,
+}
+
+fn nullable_default() -> bool {
+    true
+}
+
+/// A `FOREIGN KEY (column) REFERENCES ref_table(ref_column)` constraint. The referenced
+/// column is the single-column primary key of `ref_table`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ForeignKey {
+    pub column: String,
+    pub ref_table: String,
+    pub ref_column: String,
+    /// Delete referencing rows along with the referenced row (`ON DELETE CASCADE`) instead of
+    /// refusing the delete.
+    #[serde(default)]
+    pub on_delete_cascade: bool,
 }
 
 /// Represents the schema of a table (list of columns).
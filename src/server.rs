@@ -1,11 +1,22 @@
 use jsonrpc_core::{Result, IoHandler};
 use jsonrpc_derive::rpc;
-use jsonrpc_http_server::ServerBuilder;
+use jsonrpc_http_server::{RequestMiddlewareAction, ServerBuilder, hyper};
 use crate::database::Database;
-use crate::replication::{ReplicationConfig, ReplicationManager};
+use crate::metrics::{Metrics, MetricsSnapshot};
+use crate::replication::{
+    ReplicaStatus, ReplicationConfig, ReplicationManager, ReplicationSnapshot,
+};
 use crate::row::RowInterface;
+use crate::error::SqlError;
+use crate::sql::{PreparedStatement, SqlOutcome, TypedColumn};
+use crate::table::TableDescription;
+use crate::value::Value;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::Arc;
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
 use hex;
@@ -18,19 +29,151 @@ pub struct QueryResponse {
     pub rows: Option<Vec<Vec<String>>>,
 }
 
+/// Like `QueryResponse`, but a SELECT's rows keep their types and come with the column
+/// schema. Other statements carry no columns, and any rows they return as strings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TypedQueryResponse {
+    pub success: bool,
+    pub message: String,
+    pub columns: Option<Vec<TypedColumn>>,
+    pub rows: Option<Vec<Vec<Value>>>,
+}
+
+/// Version of the RPC protocol, raised whenever a change would break existing clients.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Build and protocol details reported by the `server_info` RPC.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerInfo {
+    /// The server's crate version.
+    pub version: String,
+    pub protocol_version: u32,
+    /// Seconds since the server started.
+    pub uptime_secs: u64,
+}
+
+impl ServerInfo {
+    /// Whether this client speaks the server's protocol version.
+    pub fn is_compatible(&self) -> bool {
+        self.protocol_version == PROTOCOL_VERSION
+    }
+}
+
+/// Node state reported by the `health` RPC, for load balancers and monitoring.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthStatus {
+    pub is_primary: bool,
+    pub table_count: usize,
+    /// Rows across all tables.
+    pub row_count: usize,
+    /// Id of the newest replication event this node knows of.
+    pub event_seq: u64,
+    /// Id of the last event a replica has applied; `None` on a primary.
+    pub last_synced_seq: Option<u64>,
+    /// Known events a replica has yet to apply; `None` on a primary.
+    pub lag: Option<u64>,
+}
+
+/// A page of rows read from a cursor opened by `select_open`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CursorPage {
+    pub rows: Vec<Vec<String>>,
+    /// Whether the cursor has no rows left; it is closed once this is returned.
+    pub done: bool,
+}
+
+/// How long an unused cursor is kept before it is freed.
+pub const CURSOR_TTL: Duration = Duration::from_secs(300);
+
+/// The remaining rows of a SELECT opened with `select_open`.
+struct Cursor {
+    rows: std::vec::IntoIter<Vec<String>>,
+    expires: Instant,
+}
+
+/// Open cursors by id.
+#[derive(Default)]
+struct Cursors {
+    open: HashMap<u64, Cursor>,
+    next_id: u64,
+}
+
+impl Cursors {
+    /// Frees the cursors that have not been used for `CURSOR_TTL`.
+    fn remove_expired(&mut self) {
+        let now = Instant::now();
+        self.open.retain(|_, cursor| cursor.expires > now);
+    }
+}
+
 #[rpc]
 pub trait Rpc {
     #[rpc(name = "execute")]
     fn execute(&self, query: String) -> Result<QueryResponse>;
 
+    /// Executes a query like `execute`, returning a SELECT's rows as typed values.
+    #[rpc(name = "execute_typed")]
+    fn execute_typed(&self, query: String) -> Result<TypedQueryResponse>;
+
+    /// Executes a statement with `?` placeholders bound to `params`; see
+    /// `PreparedStatement`.
+    #[rpc(name = "execute_prepared")]
+    fn execute_prepared(&self, query: String, params: Vec<Value>) -> Result<QueryResponse>;
+
+    /// Runs a SELECT and keeps its result on the server, returning a cursor id to read it
+    /// with `select_fetch`. Cursors unused for `CURSOR_TTL` are freed.
+    #[rpc(name = "select_open")]
+    fn select_open(&self, query: String) -> Result<u64>;
+
+    /// Returns up to `n` more rows of a cursor.
+    #[rpc(name = "select_fetch")]
+    fn select_fetch(&self, cursor_id: u64, n: usize) -> Result<CursorPage>;
+
+    /// Frees a cursor before it is read to the end, returning whether it was open.
+    #[rpc(name = "select_close")]
+    fn select_close(&self, cursor_id: u64) -> Result<bool>;
+
+    /// Executes statements in order. With `atomic` set they run in a transaction: if one
+    /// fails, the ones before it are rolled back and the rest are not run.
+    #[rpc(name = "execute_batch")]
+    fn execute_batch(
+        &self,
+        queries: Vec<String>,
+        atomic: Option<bool>,
+    ) -> Result<Vec<QueryResponse>>;
+
     #[rpc(name = "ping")]
     fn ping(&self) -> Result<String>;
+
+    /// Crate and protocol versions and uptime, for clients to check compatibility.
+    #[rpc(name = "server_info")]
+    fn server_info(&self) -> Result<ServerInfo>;
+
+    #[rpc(name = "health")]
+    fn health(&self) -> Result<HealthStatus>;
+
+    /// Query and replication counters since the server started.
+    #[rpc(name = "metrics")]
+    fn metrics(&self) -> Result<MetricsSnapshot>;
     
     #[rpc(name = "list_tables")]
     fn list_tables(&self) -> Result<Vec<String>>;
 
+    #[rpc(name = "describe_table")]
+    fn describe_table(&self, name: String) -> Result<TableDescription>;
+
+    #[rpc(name = "table_row_count")]
+    fn table_row_count(&self, name: String) -> Result<usize>;
+
+    /// Returns the recorded events, or only those with an id greater than `since`.
     #[rpc(name = "replication_get_events")]
-    fn replication_get_events(&self) -> Result<Vec<crate::replication::ReplicationEvent>>;
+    fn replication_get_events(
+        &self,
+        since: Option<u64>,
+    ) -> Result<Vec<crate::replication::ReplicationEvent>>;
+
+    #[rpc(name = "replication_snapshot")]
+    fn replication_snapshot(&self) -> Result<ReplicationSnapshot>;
 
     #[rpc(name = "replication_checksum")]
     fn replication_checksum(&self) -> Result<String>;
@@ -40,35 +183,430 @@ pub trait Rpc {
 
     #[rpc(name = "replication_register_replica")]
     fn replication_register_replica(&self, url: String) -> Result<bool>;
+
+    /// Sent by a replica after applying every event up to `applied_seq`.
+    #[rpc(name = "replication_ack")]
+    fn replication_ack(&self, replica_url: String, applied_seq: u64) -> Result<bool>;
+
+    #[rpc(name = "replication_status")]
+    fn replication_status(&self) -> Result<Vec<ReplicaStatus>>;
+
+    /// Promotes a replica to primary; see `ReplicationManager::promote_to_primary`. Like
+    /// `admin_reset`, refused unless the server requires an auth token, so a client of an
+    /// open replica cannot promote it.
+    #[rpc(name = "promote")]
+    fn promote(&self) -> Result<bool>;
+
+    /// Replaces the primary's database with an empty one and clears its event log; replicas
+    /// follow through a reset event. Refused unless the server requires an auth token, so
+    /// an open server cannot be wiped by accident.
+    #[rpc(name = "admin_reset")]
+    fn admin_reset(&self) -> Result<bool>;
+}
+
+/// Converts the outcome of a statement into the response sent to clients.
+fn query_response(result: std::result::Result<SqlOutcome, SqlError>) -> QueryResponse {
+    match result {
+        Ok(SqlOutcome::Selected { rows, .. }) => QueryResponse {
+            success: true,
+            message: "Query executed successfully".to_string(),
+            rows: Some(rows),
+        },
+        Ok(outcome) => QueryResponse {
+            success: true,
+            message: outcome.to_string(),
+            rows: None,
+        },
+        Err(e) => QueryResponse {
+            success: false,
+            message: e.to_string(),
+            rows: None,
+        },
+    }
+}
+
+/// Converts a string-based response into a typed one, keeping any rows as strings.
+fn untyped_response(response: QueryResponse) -> TypedQueryResponse {
+    TypedQueryResponse {
+        success: response.success,
+        message: response.message,
+        columns: None,
+        rows: response.rows.map(|rows| {
+            rows.into_iter()
+                .map(|row| row.into_iter().map(Value::Str).collect())
+                .collect()
+        }),
+    }
+}
+
+/// Runs a batch in a transaction, recording its statements for replication only if all of
+/// them succeed. On the first failure the earlier statements are rolled back and the later
+/// ones skipped, each response saying so.
+fn execute_atomic(
+    db: &mut Database,
+    repl: &ReplicationManager,
+    queries: Vec<String>,
+    deadline: Option<&Deadline>,
+) -> Vec<QueryResponse> {
+    let failed = |message: String| QueryResponse {
+        success: false,
+        message,
+        rows: None,
+    };
+    // The batch is the transaction, so it cannot end it early
+    if controls_transaction(&queries) {
+        let message = "An atomic batch cannot contain BEGIN, COMMIT or ROLLBACK";
+        return queries.iter().map(|_| failed(message.to_string())).collect();
+    }
+    if let Err(e) = crate::sql::execute_sql(db, "BEGIN") {
+        return queries.iter().map(|_| failed(e.to_string())).collect();
+    }
+    let mut responses = Vec::with_capacity(queries.len());
+    for (i, query) in queries.iter().enumerate() {
+        let result = crate::sql::execute_sql(db, query);
+        if result.is_ok() {
+            responses.push(query_response(result));
+            continue;
+        }
+        let _ = crate::sql::execute_sql(db, "ROLLBACK");
+        let n = i + 1;
+        for response in &mut responses {
+            *response = failed(format!("Rolled back because statement {} failed", n));
+        }
+        responses.push(query_response(result));
+        responses.extend(
+            queries[n..]
+                .iter()
+                .map(|_| failed(format!("Not executed because statement {} failed", n))),
+        );
+        return responses;
+    }
+    if deadline.is_some_and(|d| !d.finish()) {
+        // The client has already been told the batch timed out
+        let _ = crate::sql::execute_sql(db, "ROLLBACK");
+        return queries.iter().map(|_| failed("Rolled back after a timeout".to_string())).collect();
+    }
+    if let Err(e) = crate::sql::execute_sql(db, "COMMIT") {
+        return queries.iter().map(|_| failed(e.to_string())).collect();
+    }
+    for query in queries {
+        repl.record_event(query);
+    }
+    responses
+}
+
+/// Whether any statement of `queries` begins or ends a transaction.
+fn controls_transaction(queries: &[String]) -> bool {
+    queries.iter().flat_map(|q| crate::sql::split_statements(q)).any(|s| {
+        matches!(
+            s.to_uppercase().as_str(),
+            "BEGIN" | "BEGIN TRANSACTION" | "START TRANSACTION" | "COMMIT" | "ROLLBACK"
+        )
+    })
+}
+
+/// Runs a mutating script one statement at a time, recording each statement that succeeds
+/// as a replication event. Like `execute_sql`, stops at the first error; statements that
+/// failed, and any after them, are not replicated.
+fn execute_recorded(
+    db: &mut Database,
+    repl: &ReplicationManager,
+    query: &str,
+) -> std::result::Result<SqlOutcome, SqlError> {
+    let mut applied = vec![];
+    let result = execute_applied(db, query, &mut applied);
+    for statement in applied {
+        repl.record_event(statement);
+    }
+    result
+}
+
+/// Runs a mutating script one statement at a time like `execute_sql`, adding each statement
+/// that succeeds to `applied`.
+fn execute_applied(
+    db: &mut Database,
+    query: &str,
+    applied: &mut Vec<String>,
+) -> std::result::Result<SqlOutcome, SqlError> {
+    let statements = crate::sql::split_statements(query);
+    let Some((last, rest)) = statements.split_last() else {
+        return crate::sql::execute_sql(db, query);
+    };
+    for statement in rest {
+        crate::sql::execute_sql(db, statement)?;
+        applied.push(statement.clone());
+    }
+    let outcome = crate::sql::execute_sql(db, last)?;
+    applied.push(last.clone());
+    Ok(outcome)
+}
+
+/// Runs mutating scripts under a query timeout. Unless they control transactions or one is
+/// already open, they run in an internal transaction that is kept, and replicated, only if
+/// they finish before the client is told they timed out. Otherwise they cannot be undone,
+/// so they run only if they have not yet timed out. Returns `None` if they did not run.
+fn execute_within(
+    db: &mut Database,
+    repl: &ReplicationManager,
+    queries: &[String],
+    deadline: &Deadline,
+) -> Option<Vec<std::result::Result<SqlOutcome, SqlError>>> {
+    if db.in_transaction() || controls_transaction(queries) {
+        if !deadline.start_irrevocable() {
+            return None;
+        }
+        return Some(queries.iter().map(|q| execute_recorded(db, repl, q)).collect());
+    }
+    if let Err(e) = crate::sql::execute_sql(db, "BEGIN") {
+        return Some(queries.iter().map(|_| Err(e.clone())).collect());
+    }
+    let mut applied = vec![];
+    let results = queries.iter().map(|q| execute_applied(db, q, &mut applied)).collect();
+    if !deadline.finish() {
+        let _ = crate::sql::execute_sql(db, "ROLLBACK");
+        return None;
+    }
+    if let Err(e) = crate::sql::execute_sql(db, "COMMIT") {
+        return Some(queries.iter().map(|_| Err(e.clone())).collect());
+    }
+    for statement in applied {
+        repl.record_event(statement);
+    }
+    Some(results)
+}
+
+/// Settles, exactly once, whether a statement run under a query timeout finished in time:
+/// the worker running it and the request waiting for it race to decide.
+#[derive(Clone, Default)]
+struct Deadline(Arc<AtomicU8>);
+
+impl Deadline {
+    const RUNNING: u8 = 0;
+    const FINISHED: u8 = 1;
+    const EXPIRED: u8 = 2;
+    const IRREVOCABLE: u8 = 3;
+
+    fn settle(&self, state: u8) -> bool {
+        let (running, order) = (Self::RUNNING, Ordering::SeqCst);
+        self.0.compare_exchange(running, state, order, order).is_ok()
+    }
+
+    /// Called by the worker before it commits. `false` if the client has already been told
+    /// the statement timed out, so it must be rolled back.
+    fn finish(&self) -> bool {
+        self.settle(Self::FINISHED)
+    }
+
+    /// Called by the worker before a write that cannot be rolled back. `false` if it timed
+    /// out while waiting for the database, so it must not run.
+    fn start_irrevocable(&self) -> bool {
+        self.settle(Self::IRREVOCABLE)
+    }
+
+    /// Called by the waiting request when the limit passes. `false` if the worker has
+    /// already finished or started an irrevocable write.
+    fn expire(&self) -> bool {
+        self.settle(Self::EXPIRED)
+    }
+
+    fn is_irrevocable(&self) -> bool {
+        self.0.load(Ordering::SeqCst) == Self::IRREVOCABLE
+    }
+}
+
+/// Runs one `execute_batch` request, holding the write lock for the whole batch so no other
+/// statement interleaves. Replicas answer each read from their own copy.
+fn execute_batch(
+    db: &RwLock<Database>,
+    replication_manager: &Mutex<ReplicationManager>,
+    queries: Vec<String>,
+    atomic: bool,
+    deadline: Option<&Deadline>,
+) -> Vec<QueryResponse> {
+    let repl = replication_manager.lock().unwrap_or_else(|p| p.into_inner());
+    if !repl.is_primary() {
+        return queries.iter().map(|q| execute_on_replica(db, q)).collect();
+    }
+    let mut db = db.write().unwrap_or_else(|p| p.into_inner());
+    if atomic {
+        return execute_atomic(&mut db, &repl, queries, deadline);
+    }
+    if let Some(deadline) = deadline {
+        let results = execute_within(&mut db, &repl, &queries, deadline).unwrap_or_default();
+        return results.into_iter().map(query_response).collect();
+    }
+    queries
+        .iter()
+        // Each applied statement is its own replication event, in order
+        .map(|query| query_response(execute_recorded(&mut db, &repl, query)))
+        .collect()
+}
+
+/// Runs one `execute` request. Only the primary executes writes, recording what was
+/// applied for replication; replicas answer reads from their own copy.
+fn execute_query(
+    db: &RwLock<Database>,
+    replication_manager: &Mutex<ReplicationManager>,
+    query: &str,
+    deadline: Option<&Deadline>,
+) -> QueryResponse {
+    let is_primary = replication_manager.lock().unwrap_or_else(|p| p.into_inner()).is_primary();
+    if !is_primary {
+        return execute_on_replica(db, query);
+    }
+    // Reads share the lock and change nothing, so there is nothing to replicate
+    if crate::sql::is_read_only(query) {
+        let db = db.read().unwrap_or_else(|p| p.into_inner());
+        return query_response(crate::sql::execute_read(&db, query));
+    }
+
+    let repl = replication_manager.lock().unwrap_or_else(|p| p.into_inner());
+    let mut db = db.write().unwrap_or_else(|p| p.into_inner());
+    let Some(deadline) = deadline else {
+        return query_response(execute_recorded(&mut db, &repl, query));
+    };
+    let queries = [query.to_string()];
+    match execute_within(&mut db, &repl, &queries, deadline) {
+        Some(mut results) => query_response(results.remove(0)),
+        // Not seen by the client, which has already been answered
+        None => query_response(Err(SqlError::Transaction("rolled back".to_string()))),
+    }
+}
+
+/// Runs a read-only statement against the replica's local copy, rejecting writes.
+fn execute_on_replica(db: &RwLock<Database>, query: &str) -> QueryResponse {
+    if !crate::sql::is_read_only(query) {
+        return QueryResponse {
+            success: false,
+            message: "This is a replica server. Write operations are only allowed on the primary server.".to_string(),
+            rows: None,
+        };
+    }
+    let db = db.read().unwrap_or_else(|p| p.into_inner());
+    let mut response = query_response(crate::sql::execute_read(&db, query));
+    if response.success {
+        // Replicas sync periodically, so reads may not yet reflect the latest writes
+        response.message =
+            "Query executed on replica; results may lag behind the primary".to_string();
+    }
+    response
 }
 
 pub struct RpcServer {
-    db: Arc<Mutex<Database>>,
+    db: Arc<RwLock<Database>>,
     replication_manager: Arc<Mutex<ReplicationManager>>,
+    data_file: Option<String>,
+    metrics: Arc<Metrics>,
+    /// Whether `promote` and `admin_reset` are allowed, which needs requests to be
+    /// authenticated.
+    admin_enabled: bool,
+    cursors: Mutex<Cursors>,
+    /// How long a statement-running RPC waits; see `ServerOptions::query_timeout`.
+    query_timeout: Option<Duration>,
+    started: Instant,
 }
 
 impl RpcServer {
     pub fn new(config: Option<ReplicationConfig>) -> Self {
-        let db = Arc::new(Mutex::new(Database::new()));
+        Self::with_database(config, Database::new(), None)
+    }
+
+    /// Creates a server whose primary loads its database from `data_file` (if the file
+    /// exists) and checkpoints to it every autosave interval; the `ServerHandle` of a
+    /// started server saves it once more on shutdown. Replicas take their data from the
+    /// primary and ignore the file.
+    pub fn with_data_file(
+        config: Option<ReplicationConfig>,
+        data_file: Option<String>,
+    ) -> std::io::Result<Self> {
+        let config = config.unwrap_or_else(ReplicationConfig::new_primary);
+        let data_file = data_file.filter(|_| config.is_primary);
+        let db = match &data_file {
+            Some(path) if std::path::Path::new(path).exists() => Database::load_from_file(path)?,
+            _ => Database::new(),
+        };
+        Ok(Self::with_database(Some(config), db, data_file))
+    }
+
+    fn with_database(
+        config: Option<ReplicationConfig>,
+        db: Database,
+        data_file: Option<String>,
+    ) -> Self {
+        let db = Arc::new(RwLock::new(db));
         let replication_manager = Arc::new(Mutex::new(ReplicationManager::new(
-            config.unwrap_or_else(|| ReplicationConfig::new_primary()),
+            config.unwrap_or_else(ReplicationConfig::new_primary),
             Arc::clone(&db),
         )));
 
         // If this node is configured as a replica, start its sync and display tasks.
-        {
+        let metrics = {
             let repl_guard = replication_manager.lock().unwrap_or_else(|p| p.into_inner());
             if !repl_guard.is_primary() {
                 // start background sync with primary
                 repl_guard.start_sync_task();
-                // start periodic display of local DB for debugging/visibility
+                // start periodic display of local DB for debugging/visibility, if enabled
                 repl_guard.start_display_task();
             }
-        }
+            if let Some(path) = &data_file {
+                repl_guard.start_checkpoint_task(path.clone());
+            }
+            repl_guard.metrics()
+        };
 
         RpcServer {
             db,
             replication_manager,
+            data_file,
+            metrics,
+            admin_enabled: false,
+            cursors: Mutex::new(Cursors::default()),
+            query_timeout: None,
+            started: Instant::now(),
+        }
+    }
+
+    /// Counts an executed statement in the metrics before it is returned to the client.
+    fn counted(&self, response: QueryResponse) -> QueryResponse {
+        let rows = response.rows.as_ref().map_or(0, Vec::len);
+        self.metrics.record_query(response.success, rows);
+        response
+    }
+
+    /// Runs `work` on a worker thread if a query timeout is set, answering with an error if
+    /// it has not finished in time. `work` is given the `Deadline` it races against, or
+    /// `None` without a timeout. The worker is abandoned, not stopped: it holds on to the
+    /// database lock until it finishes, then rolls back a timed-out write. A write inside a
+    /// transaction the client opened cannot be rolled back alone, so the error says it may
+    /// still be applied.
+    fn within_timeout<T, F>(&self, work: F) -> std::result::Result<T, String>
+    where
+        F: FnOnce(Option<&Deadline>) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let Some(limit) = self.query_timeout else {
+            return Ok(work(None));
+        };
+        let deadline = Deadline::default();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let worker_deadline = deadline.clone();
+        std::thread::spawn(move || {
+            // The receiver is gone if the statement already timed out
+            let _ = sender.send(work(Some(&worker_deadline)));
+        });
+        let timed_out = || format!("Query timed out after {:?}", limit);
+        match receiver.recv_timeout(limit) {
+            Ok(result) => Ok(result),
+            Err(_) if deadline.expire() => {
+                log::warn!("Statement did not finish within {:?}", limit);
+                Err(timed_out())
+            }
+            Err(_) if deadline.is_irrevocable() => {
+                log::warn!("Statement did not finish within {:?} and cannot be undone", limit);
+                Err(format!("{}; it may still be applied", timed_out()))
+            }
+            // The worker finished just in time and is committing
+            Err(_) => receiver.recv().map_err(|_| timed_out()),
         }
     }
 
@@ -82,45 +620,227 @@ impl RpcServer {
 
 impl Rpc for RpcServer {
     fn execute(&self, query: String) -> Result<QueryResponse> {
-        // Only primary can execute write queries
-        let repl = self.replication_manager.lock().unwrap_or_else(|p| p.into_inner());
-        if !repl.is_primary() {
-            return Ok(QueryResponse {
+        let db = Arc::clone(&self.db);
+        let repl = Arc::clone(&self.replication_manager);
+        let response = self
+            .within_timeout(move |deadline| execute_query(&db, &repl, &query, deadline))
+            .unwrap_or_else(|message| QueryResponse {
                 success: false,
-                message: "This is a replica server. Write operations are only allowed on the primary server.".to_string(),
+                message,
                 rows: None,
             });
+        Ok(self.counted(response))
+    }
+
+    fn execute_typed(&self, query: String) -> Result<TypedQueryResponse> {
+        let statements = crate::sql::split_statements(&query);
+        let [statement] = statements.as_slice() else {
+            return self.execute(query).map(untyped_response);
+        };
+        if !statement.to_uppercase().starts_with("SELECT") {
+            return self.execute(query).map(untyped_response);
         }
+        let db = Arc::clone(&self.db);
+        let statement = statement.clone();
+        let result = self.within_timeout(move |_| {
+            let db = db.read().unwrap_or_else(|p| p.into_inner());
+            crate::sql::execute_select_typed(&db, &statement)
+        });
+        let response = match result {
+            Ok(Ok(typed)) => TypedQueryResponse {
+                success: true,
+                message: "Query executed successfully".to_string(),
+                columns: Some(typed.columns),
+                rows: Some(typed.rows),
+            },
+            Ok(Err(e)) => untyped_response(query_response(Err(e))),
+            Err(message) => TypedQueryResponse {
+                success: false,
+                message,
+                columns: None,
+                rows: None,
+            },
+        };
+        let rows = response.rows.as_ref().map_or(0, Vec::len);
+        self.metrics.record_query(response.success, rows);
+        Ok(response)
+    }
 
-        let mut db = self.db.lock().unwrap_or_else(|p| p.into_inner());
-        // Execute the query and record for replication
-        crate::sql::execute_sql(&mut db, &query);
-        repl.record_event(query);
-        
-        Ok(QueryResponse {
-            success: true,
-            message: "Query executed successfully".to_string(),
-            rows: None, // TODO: Implement proper row capture
-        })
+    fn execute_prepared(&self, query: String, params: Vec<Value>) -> Result<QueryResponse> {
+        match PreparedStatement::prepare(&query).and_then(|stmt| stmt.bind(&params)) {
+            Ok(sql) => self.execute(sql),
+            Err(e) => Ok(self.counted(query_response(Err(e)))),
+        }
+    }
+
+    fn select_open(&self, query: String) -> Result<u64> {
+        let invalid = |message: String| jsonrpc_core::Error {
+            code: jsonrpc_core::ErrorCode::InvalidParams,
+            message,
+            data: None,
+        };
+        if !query.trim_start().to_uppercase().starts_with("SELECT") {
+            return Err(invalid("select_open only accepts a SELECT statement".to_string()));
+        }
+        let db = Arc::clone(&self.db);
+        let result = self.within_timeout(move |_| {
+            let db = db.read().unwrap_or_else(|p| p.into_inner());
+            crate::sql::execute_read(&db, &query)
+        });
+        let rows = match result {
+            Ok(Ok(SqlOutcome::Selected { rows, .. })) => rows,
+            Ok(Ok(outcome)) => {
+                return Err(invalid(format!("Query did not select rows: {}", outcome)));
+            }
+            Ok(Err(e)) => {
+                self.metrics.record_query(false, 0);
+                return Err(invalid(e.to_string()));
+            }
+            Err(message) => {
+                self.metrics.record_query(false, 0);
+                return Err(invalid(message));
+            }
+        };
+        self.metrics.record_query(true, rows.len());
+
+        let mut cursors = self.cursors.lock().unwrap_or_else(|p| p.into_inner());
+        cursors.remove_expired();
+        cursors.next_id += 1;
+        let id = cursors.next_id;
+        let cursor = Cursor {
+            rows: rows.into_iter(),
+            expires: Instant::now() + CURSOR_TTL,
+        };
+        cursors.open.insert(id, cursor);
+        Ok(id)
+    }
+
+    fn select_fetch(&self, cursor_id: u64, n: usize) -> Result<CursorPage> {
+        let mut cursors = self.cursors.lock().unwrap_or_else(|p| p.into_inner());
+        cursors.remove_expired();
+        let Some(cursor) = cursors.open.get_mut(&cursor_id) else {
+            return Err(jsonrpc_core::Error {
+                code: jsonrpc_core::ErrorCode::InvalidParams,
+                message: format!("Cursor {} does not exist or has expired", cursor_id),
+                data: None,
+            });
+        };
+        let rows: Vec<Vec<String>> = cursor.rows.by_ref().take(n).collect();
+        let done = cursor.rows.len() == 0;
+        if done {
+            cursors.open.remove(&cursor_id);
+        } else {
+            cursor.expires = Instant::now() + CURSOR_TTL;
+        }
+        Ok(CursorPage { rows, done })
+    }
+
+    fn select_close(&self, cursor_id: u64) -> Result<bool> {
+        let mut cursors = self.cursors.lock().unwrap_or_else(|p| p.into_inner());
+        cursors.remove_expired();
+        Ok(cursors.open.remove(&cursor_id).is_some())
+    }
+
+    fn execute_batch(
+        &self,
+        queries: Vec<String>,
+        atomic: Option<bool>,
+    ) -> Result<Vec<QueryResponse>> {
+        let db = Arc::clone(&self.db);
+        let repl = Arc::clone(&self.replication_manager);
+        let count = queries.len();
+        let atomic = atomic == Some(true);
+        let responses = self
+            .within_timeout(move |deadline| execute_batch(&db, &repl, queries, atomic, deadline))
+            .unwrap_or_else(|message| {
+                let failed = || QueryResponse {
+                    success: false,
+                    message: message.clone(),
+                    rows: None,
+                };
+                (0..count).map(|_| failed()).collect()
+            });
+        Ok(responses.into_iter().map(|r| self.counted(r)).collect())
     }
 
     fn ping(&self) -> Result<String> {
         Ok("pong".to_string())
     }
+
+    fn server_info(&self) -> Result<ServerInfo> {
+        Ok(ServerInfo {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            protocol_version: PROTOCOL_VERSION,
+            uptime_secs: self.started.elapsed().as_secs(),
+        })
+    }
+
+    fn metrics(&self) -> Result<MetricsSnapshot> {
+        Ok(self.metrics.snapshot())
+    }
+
+    fn health(&self) -> Result<HealthStatus> {
+        let repl = self.replication_manager.lock().unwrap_or_else(|p| p.into_inner());
+        let db = self.db.read().unwrap_or_else(|p| p.into_inner());
+        let event_seq = repl.latest_known_event_id();
+        let (last_synced_seq, lag) = if repl.is_primary() {
+            (None, None)
+        } else {
+            let applied = repl.last_event_id();
+            (Some(applied), Some(event_seq.saturating_sub(applied)))
+        };
+        Ok(HealthStatus {
+            is_primary: repl.is_primary(),
+            table_count: db.tables.len(),
+            row_count: db.tables.values().map(|t| t.len()).sum(),
+            event_seq,
+            last_synced_seq,
+            lag,
+        })
+    }
     
     fn list_tables(&self) -> Result<Vec<String>> {
-        let db = self.db.lock().unwrap_or_else(|p| p.into_inner());
+        let db = self.db.read().unwrap_or_else(|p| p.into_inner());
         Ok(db.tables.keys().cloned().collect())
     }
 
-    fn replication_get_events(&self) -> Result<Vec<crate::replication::ReplicationEvent>> {
+    fn describe_table(&self, name: String) -> Result<TableDescription> {
+        let db = self.db.read().unwrap_or_else(|p| p.into_inner());
+        match db.tables.get(&name) {
+            Some(table) => Ok(table.describe()),
+            None => Err(jsonrpc_core::Error {
+                code: jsonrpc_core::ErrorCode::InvalidParams,
+                message: SqlError::TableNotFound(name).to_string(),
+                data: None,
+            }),
+        }
+    }
+
+    fn table_row_count(&self, name: String) -> Result<usize> {
+        let db = self.db.read().unwrap_or_else(|p| p.into_inner());
+        db.row_count(&name).ok_or_else(|| jsonrpc_core::Error {
+            code: jsonrpc_core::ErrorCode::InvalidParams,
+            message: SqlError::TableNotFound(name).to_string(),
+            data: None,
+        })
+    }
+
+    fn replication_get_events(
+        &self,
+        since: Option<u64>,
+    ) -> Result<Vec<crate::replication::ReplicationEvent>> {
         let repl = self.replication_manager.lock().unwrap_or_else(|p| p.into_inner());
-        Ok(repl.get_events())
+        Ok(repl.get_events_since(since.unwrap_or(0)))
+    }
+
+    fn replication_snapshot(&self) -> Result<ReplicationSnapshot> {
+        let repl = self.replication_manager.lock().unwrap_or_else(|p| p.into_inner());
+        Ok(repl.snapshot())
     }
 
     fn replication_checksum(&self) -> Result<String> {
         // Build a deterministic string representation of the DB and SHA256 it
-        let db = self.db.lock().unwrap_or_else(|p| p.into_inner());
+        let db = self.db.read().unwrap_or_else(|p| p.into_inner());
         // Collect table names sorted for deterministic ordering
         let mut table_names: Vec<_> = db.tables.keys().cloned().collect();
         table_names.sort();
@@ -165,23 +885,295 @@ impl Rpc for RpcServer {
         repl.add_replica(url);
         Ok(true)
     }
+
+    fn replication_ack(&self, replica_url: String, applied_seq: u64) -> Result<bool> {
+        let mut repl = self.replication_manager.lock().unwrap_or_else(|p| p.into_inner());
+        if !repl.is_primary() {
+            return Ok(false);
+        }
+        repl.record_ack(replica_url, applied_seq);
+        Ok(true)
+    }
+
+    fn replication_status(&self) -> Result<Vec<ReplicaStatus>> {
+        let repl = self.replication_manager.lock().unwrap_or_else(|p| p.into_inner());
+        Ok(repl.replica_status())
+    }
+
+    fn promote(&self) -> Result<bool> {
+        if !self.admin_enabled {
+            return Err(jsonrpc_core::Error {
+                code: jsonrpc_core::ErrorCode::InvalidRequest,
+                message: "promote requires the server to be started with an auth token".to_string(),
+                data: None,
+            });
+        }
+        let mut repl = self.replication_manager.lock().unwrap_or_else(|p| p.into_inner());
+        Ok(repl.promote_to_primary())
+    }
+
+    fn admin_reset(&self) -> Result<bool> {
+        let refused = |message: &str| jsonrpc_core::Error {
+            code: jsonrpc_core::ErrorCode::InvalidRequest,
+            message: message.to_string(),
+            data: None,
+        };
+        if !self.admin_enabled {
+            return Err(refused("admin_reset requires the server to be started with an auth token"));
+        }
+        let repl = self.replication_manager.lock().unwrap_or_else(|p| p.into_inner());
+        if !repl.is_primary() {
+            return Err(refused("admin_reset must be sent to the primary server"));
+        }
+        let mut db = self.db.write().unwrap_or_else(|p| p.into_inner());
+        *db = Database::new();
+        repl.record_reset();
+        Ok(true)
+    }
+}
+
+/// Compares a presented token with the expected one in constant time, so the time taken
+/// does not reveal how much of a guess was right. Comparing SHA-256 digests also hides the
+/// expected token's length.
+fn tokens_match(given: &str, expected: &str) -> bool {
+    let (given, expected) = (Sha256::digest(given), Sha256::digest(expected));
+    given.iter().zip(expected.iter()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Rejects requests that do not carry `Authorization: Bearer <token>` with a JSON-RPC
+/// error, before any method runs. Without a token every request proceeds.
+fn auth_middleware(
+    auth_token: Option<String>,
+) -> impl Fn(hyper::Request<hyper::Body>) -> RequestMiddlewareAction + Send + Sync + 'static {
+    move |request: hyper::Request<hyper::Body>| {
+        let authorized = match &auth_token {
+            Some(token) => request
+                .headers()
+                .get(hyper::header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "))
+                .is_some_and(|given| tokens_match(given, token)),
+            None => true,
+        };
+        if authorized {
+            return RequestMiddlewareAction::Proceed {
+                should_continue_on_invalid_cors: false,
+                request,
+            };
+        }
+        let error = serde_json::json!({
+            "jsonrpc": "2.0",
+            "error": {"code": -32001, "message": "Unauthorized: missing or invalid auth token"},
+            "id": null
+        });
+        jsonrpc_http_server::Response::ok(error.to_string()).into()
+    }
+}
+
+/// Optional server behaviour shared by the HTTP and HTTPS servers.
+#[derive(Debug, Clone)]
+pub struct ServerOptions {
+    /// Require every request to carry `Authorization: Bearer <token>`.
+    pub auth_token: Option<String>,
+    /// Load the database from this JSON file at startup and save it back periodically and
+    /// on shutdown (primary only).
+    pub data_file: Option<String>,
+    /// Address to listen on. Defaults to loopback; use `0.0.0.0` to accept connections
+    /// from other hosts.
+    pub bind_addr: IpAddr,
+    /// Answer `execute`, `execute_typed`, `execute_prepared`, `execute_batch` and
+    /// `select_open` with an error if a statement or batch takes longer than this. The
+    /// statement itself keeps running to completion; a timed-out write is then rolled back
+    /// and not replicated, unless it ran inside a transaction the client opened.
+    pub query_timeout: Option<Duration>,
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        Self {
+            auth_token: None,
+            data_file: None,
+            bind_addr: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            query_timeout: None,
+        }
+    }
+}
+
+/// A running RPC server. Closing it, through `close_handle` or by dropping it, stops it
+/// accepting connections and then saves the database to its data file, if it has one.
+pub struct ServerHandle {
+    /// `None` once the server has been waited for.
+    http: Option<jsonrpc_http_server::Server>,
+    db: Arc<RwLock<Database>>,
+    data_file: Option<String>,
+}
+
+impl ServerHandle {
+    pub fn address(&self) -> &SocketAddr {
+        self.http.as_ref().expect("server is running").address()
+    }
+
+    /// Returns a handle that shuts the server down from another thread, e.g. a signal
+    /// handler, making `wait` return.
+    pub fn close_handle(&self) -> jsonrpc_http_server::CloseHandle {
+        self.http.as_ref().expect("server is running").close_handle()
+    }
+
+    /// Shuts the server down and saves the database.
+    pub fn close(self) {
+        drop(self);
+    }
+
+    /// Blocks until the server is closed, then saves the database.
+    pub fn wait(mut self) {
+        if let Some(http) = self.http.take() {
+            http.wait();
+        }
+    }
+}
+
+impl Drop for ServerHandle {
+    fn drop(&mut self) {
+        // Dropping the HTTP server closes it and waits for in-flight requests to finish
+        drop(self.http.take());
+        // Final save so writes since the last checkpoint survive a shutdown
+        if let Some(path) = &self.data_file {
+            let db = self.db.read().unwrap_or_else(|p| p.into_inner());
+            if let Err(e) = db.save_to_file(path) {
+                log::error!("Failed to save database to {}: {}", path, e);
+            }
+        }
+    }
 }
 
-pub fn start_server(port: u16, config: Option<ReplicationConfig>) -> jsonrpc_http_server::Server {
-    let rpc = RpcServer::new(config);
+/// Builds the JSON-RPC HTTP server on `addr`.
+fn build_http_server(
+    addr: SocketAddr,
+    config: Option<ReplicationConfig>,
+    options: ServerOptions,
+) -> std::io::Result<ServerHandle> {
+    let mut rpc = RpcServer::with_data_file(config, options.data_file)?;
+    rpc.admin_enabled = options.auth_token.is_some();
+    rpc.query_timeout = options.query_timeout;
+    let db = Arc::clone(&rpc.db);
+    let data_file = rpc.data_file.clone();
     let mut io = IoHandler::new();
     io.extend_with(rpc.to_delegate());
 
-    let server = ServerBuilder::new(io)
+    let http = ServerBuilder::new(io)
         .threads(3)
         .cors(jsonrpc_http_server::DomainsValidation::AllowOnly(vec![
             "http://localhost:3000".into(),
             "http://127.0.0.1:3000".into(),
         ]))
-        // Bind to 0.0.0.0 so the server is reachable from outside the container
-        .start_http(&format!("0.0.0.0:{}", port).parse().unwrap())
-        .expect("Unable to start RPC server");
+        .request_middleware(auth_middleware(options.auth_token))
+        .start_http(&addr)?;
+    Ok(ServerHandle {
+        http: Some(http),
+        db,
+        data_file,
+    })
+}
+
+/// Starts the RPC server on loopback; see `start_server_with_options` to choose the
+/// address. Panics if the server cannot be started.
+pub fn start_server(port: u16, config: Option<ReplicationConfig>) -> ServerHandle {
+    start_server_with_auth(port, config, None)
+}
+
+/// Starts the RPC server, requiring every request to carry `auth_token` if one is given.
+pub fn start_server_with_auth(
+    port: u16,
+    config: Option<ReplicationConfig>,
+    auth_token: Option<String>,
+) -> ServerHandle {
+    let options = ServerOptions {
+        auth_token,
+        ..ServerOptions::default()
+    };
+    start_server_with_options(port, config, options).expect("Unable to start RPC server")
+}
+
+/// Starts the RPC server with the given options. Fails if the data file cannot be loaded
+/// or the address cannot be bound, e.g. because the port is in use.
+pub fn start_server_with_options(
+    port: u16,
+    config: Option<ReplicationConfig>,
+    options: ServerOptions,
+) -> std::io::Result<ServerHandle> {
+    let addr = SocketAddr::new(options.bind_addr, port);
+    let server = build_http_server(addr, config, options)?;
+
+    log::info!("RPC Server running on http://{}", addr);
+    Ok(server)
+}
+
+/// PEM files used to serve the RPC API over HTTPS.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub cert_path: String,
+    pub key_path: String,
+}
+
+/// A running HTTPS server: a TLS listener forwarding decrypted connections to the JSON-RPC
+/// server, which only listens on loopback. Stops when dropped.
+pub struct TlsServer {
+    http: ServerHandle,
+    _runtime: tokio::runtime::Runtime,
+}
+
+impl TlsServer {
+    /// Returns a handle that shuts the server down from another thread.
+    pub fn close_handle(&self) -> jsonrpc_http_server::CloseHandle {
+        self.http.close_handle()
+    }
+
+    /// Blocks until the server shuts down.
+    pub fn wait(self) {
+        let TlsServer { http, _runtime } = self;
+        http.wait();
+    }
+}
+
+/// Starts the RPC server over HTTPS using a PEM certificate chain and PKCS#8 private key.
+pub fn start_tls_server(
+    port: u16,
+    config: Option<ReplicationConfig>,
+    tls: &TlsConfig,
+    options: ServerOptions,
+) -> std::io::Result<TlsServer> {
+    let invalid = |e: native_tls::Error| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+    let cert = std::fs::read(&tls.cert_path)?;
+    let key = std::fs::read(&tls.key_path)?;
+    let identity = native_tls::Identity::from_pkcs8(&cert, &key).map_err(invalid)?;
+    let acceptor = tokio_native_tls::TlsAcceptor::from(
+        native_tls::TlsAcceptor::new(identity).map_err(invalid)?,
+    );
+
+    // The plain HTTP server is only reachable through the TLS listener
+    let addr = SocketAddr::new(options.bind_addr, port);
+    let http = build_http_server((Ipv4Addr::LOCALHOST, 0).into(), config, options)?;
+    let backend = *http.address();
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let listener = runtime.block_on(tokio::net::TcpListener::bind(addr))?;
+    runtime.spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                // Connections that fail the handshake or drop mid-way are simply closed
+                if let Ok(mut tls_stream) = acceptor.accept(stream).await
+                    && let Ok(mut upstream) = tokio::net::TcpStream::connect(backend).await
+                {
+                    let _ = tokio::io::copy_bidirectional(&mut tls_stream, &mut upstream).await;
+                }
+            });
+        }
+    });
 
-    println!("RPC Server running on http://0.0.0.0:{}", port);
-    server
+    log::info!("RPC Server running on https://{}", addr);
+    Ok(TlsServer {
+        http,
+        _runtime: runtime,
+    })
 }
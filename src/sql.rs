@@ -1,207 +1,999 @@
-use crate::schema::{ColumnSchema, ColumnType};
+use crate::schema::{ColumnSchema, ColumnType, ForeignKey};
 
-fn parse_create_table(sql: &str) -> (String, Vec<ColumnSchema>, Option<String>, Vec<String>) {
+/// Table name, columns, primary key, unique constraints, AUTOINCREMENT columns, CHECK
+/// conditions and foreign keys.
+type CreateTable = (
+    String,
+    Vec<ColumnSchema>,
+    Vec<String>,
+    Vec<Vec<String>>,
+    Vec<String>,
+    Vec<String>,
+    Vec<ForeignKey>,
+);
+
+fn parse_create_table(sql: &str) -> Result<CreateTable, SqlError> {
     // Example: CREATE TABLE Users (id PRIMARY KEY, name, email UNIQUE, age)
+    // Composite key: CREATE TABLE Lines (order_id INT, line_no INT, PRIMARY KEY (order_id, line_no))
+    // Composite unique: CREATE TABLE People (first STRING, last STRING, UNIQUE (first, last))
+    // Check: CREATE TABLE Accounts (id INT, balance FLOAT CHECK (balance >= 0))
+    // Foreign key: CREATE TABLE Orders (id INT, uid INT, FOREIGN KEY (uid) REFERENCES Users(id))
     let sql = sql.trim_end_matches(';');
-    let upper = sql.to_uppercase();
+    let upper = sql.to_ascii_uppercase();
     let mut table = String::new();
     let mut columns: Vec<ColumnSchema> = vec![];
-    let mut primary_key = None;
+    let mut primary_key = vec![];
+    let mut table_primary_key = None;
     let mut unique_columns = vec![];
+    let mut auto_increment = vec![];
+    let mut checks = vec![];
+    let mut foreign_keys = vec![];
 
     // Must start with CREATE TABLE and have both parentheses
     if !upper.starts_with("CREATE TABLE") || 
        !sql.contains('(') || 
        !sql.contains(')') {
-        return (table, columns, primary_key, unique_columns);
+        return Ok((
+            table, columns, primary_key, unique_columns, auto_increment, checks, foreign_keys,
+        ));
     }
 
     if let Some(table_idx) = upper.find("TABLE ") {
         let after_table = &sql[table_idx + 6..];
         if let Some(paren_idx) = after_table.find('(') {
-            let table_name = after_table[..paren_idx].trim().to_string();
+            let table_name = unquote_identifier(&after_table[..paren_idx]).to_string();
             if table_name.is_empty() {
-                return (table, columns, primary_key, unique_columns);
+                return Ok((
+                    table, columns, primary_key, unique_columns, auto_increment, checks,
+                    foreign_keys,
+                ));
             }
             table = table_name;
             
-            if let Some(end_paren_idx) = after_table.find(')') {
+            if let Some(end_paren_idx) = after_table.rfind(')') {
                 if paren_idx >= end_paren_idx {
-                    return (table, columns, primary_key, unique_columns);
+                    return Ok((
+                        table, columns, primary_key, unique_columns, auto_increment, checks,
+                        foreign_keys,
+                    ));
                 }
                 let cols_str = &after_table[paren_idx + 1..end_paren_idx];
-                for col_def in cols_str.split(',') {
-                    let col_def = col_def.trim();
-                    let parts: Vec<&str> = col_def.split_whitespace().collect();
-                    if !parts.is_empty() {
-                        let col_name = parts[0].to_string();
+                for col_def in split_top_level(cols_str) {
+                    // Table-level clause naming the key columns
+                    if col_def.to_uppercase().starts_with("PRIMARY KEY") {
+                        table_primary_key = Some(parse_key_columns(&col_def[11..])?);
+                        continue;
+                    }
+                    if col_def.to_uppercase().starts_with("UNIQUE")
+                        && col_def[6..].trim_start().starts_with('(')
+                    {
+                        unique_columns.push(parse_key_columns(&col_def[6..])?);
+                        continue;
+                    }
+                    if col_def.to_uppercase().starts_with("FOREIGN KEY") {
+                        foreign_keys.push(parse_foreign_key(&col_def[11..])?);
+                        continue;
+                    }
+                    if col_def.to_uppercase().starts_with("CHECK") {
+                        checks.push(parse_check(&col_def[5..])?);
+                        continue;
+                    }
+                    let (col_def, check) = split_check(col_def)?;
+                    checks.extend(check);
+                    let (col_def, default) = split_default(&col_def);
+                    let (col_name, rest) = leading_identifier(&col_def);
+                    let parts: Vec<&str> = rest.split_whitespace().collect();
+                    if !col_name.is_empty() {
+                        let col_name = col_name.to_string();
                         // default type
                         let mut col_type = ColumnType::String;
+                        let mut nullable = true;
                         // detect tokens for type and constraints (order may vary)
-                        let mut i = 1;
+                        let mut i = 0;
                         while i < parts.len() {
                             let token = parts[i].to_uppercase();
+                            if let Some(ty) = parse_column_type(&token) {
+                                col_type = ty;
+                                i += 1;
+                                continue;
+                            }
                             match token.as_str() {
-                                "INT" | "INTEGER" => {
-                                    col_type = ColumnType::Int;
-                                }
-                                "FLOAT" | "REAL" | "DOUBLE" => {
-                                    col_type = ColumnType::Float;
-                                }
-                                "STRING" | "TEXT" | "CHAR" => {
-                                    col_type = ColumnType::String;
-                                }
-                                "PRIMARY" => {
-                                    // check next token for KEY
+                                // check next token for KEY
+                                "PRIMARY"
                                     if parts.get(i + 1).map(|s| s.to_uppercase())
-                                        == Some("KEY".to_string())
-                                    {
-                                        primary_key = Some(col_name.clone());
-                                        i += 1; // skip KEY
-                                    }
+                                        == Some("KEY".to_string()) =>
+                                {
+                                    primary_key.push(col_name.clone());
+                                    nullable = false;
+                                    i += 1; // skip KEY
                                 }
                                 "KEY" => { /* handled with PRIMARY before */ }
+                                "NOT"
+                                    if parts.get(i + 1).map(|s| s.to_uppercase())
+                                        == Some("NULL".to_string()) =>
+                                {
+                                    nullable = false;
+                                    i += 1; // skip NULL
+                                }
                                 "UNIQUE" => {
-                                    unique_columns.push(col_name.clone());
+                                    unique_columns.push(vec![col_name.clone()]);
+                                }
+                                "AUTOINCREMENT" | "AUTO_INCREMENT" => {
+                                    auto_increment.push(col_name.clone());
                                 }
                                 _ => { /* unknown token, ignore */ }
                             }
                             i += 1;
                         }
-                        columns.push(ColumnSchema {
+                        let mut column = ColumnSchema {
                             name: col_name.clone(),
                             col_type,
-                        });
+                            nullable,
+                            default: None,
+                        };
+                        // The default must fit the column's type
+                        if let Some(raw) = default.and_then(|d| parse_literal(&d)) {
+                            column.default = Some(Value::from_column(&column, &raw)?);
+                        }
+                        columns.push(column);
                     }
                 }
             }
         }
     }
-    (table, columns, primary_key, unique_columns)
+    if let Some(key_columns) = table_primary_key {
+        if !primary_key.is_empty() {
+            return Err(SqlError::Parse(
+                "PRIMARY KEY declared both on a column and as a table constraint".to_string(),
+            ));
+        }
+        for name in &key_columns {
+            let idx = column_index(&columns, name)?;
+            columns[idx].nullable = false;
+        }
+        primary_key = key_columns;
+    } else if primary_key.len() > 1 {
+        return Err(SqlError::Parse(
+            "Only one column can be marked PRIMARY KEY; use PRIMARY KEY (a, b) for a composite key"
+                .to_string(),
+        ));
+    }
+    for name in unique_columns.iter().flatten() {
+        column_index(&columns, name)?;
+    }
+    for name in &auto_increment {
+        let col = &columns[column_index(&columns, name)?];
+        if col.col_type != ColumnType::Int {
+            return Err(SqlError::Parse(format!(
+                "AUTOINCREMENT column '{}' must be INT",
+                name
+            )));
+        }
+    }
+    // Reject conditions that could never be evaluated, e.g. naming an unknown column
+    for check in &checks {
+        let _ = try_query_to_predicate(&columns, check)?;
+    }
+    for fk in &foreign_keys {
+        column_index(&columns, &fk.column)?;
+    }
+    Ok((table, columns, primary_key, unique_columns, auto_increment, checks, foreign_keys))
+}
+
+/// Parses the `(column) REFERENCES table(column) [ON DELETE CASCADE | RESTRICT]` that
+/// follows FOREIGN KEY in a table constraint.
+fn parse_foreign_key(text: &str) -> Result<ForeignKey, SqlError> {
+    let invalid = || SqlError::Parse(format!("Invalid FOREIGN KEY syntax: '{}'", text.trim()));
+    let single = |names: Vec<String>| match names.as_slice() {
+        [name] => Ok(name.clone()),
+        _ => Err(SqlError::Parse("FOREIGN KEY must name exactly one column".to_string())),
+    };
+    let text = text.trim();
+    let close = text.find(')').ok_or_else(invalid)?;
+    let column = single(parse_key_columns(&text[..=close])?)?;
+    let rest = text[close + 1..].trim_start();
+    let rest = match rest.get(..10) {
+        Some(kw) if kw.eq_ignore_ascii_case("REFERENCES") => &rest[10..],
+        _ => return Err(invalid()),
+    };
+    let (open, close) = rest.find('(').zip(rest.find(')')).ok_or_else(invalid)?;
+    let ref_table = rest[..open].trim();
+    if ref_table.is_empty() || ref_table.contains(char::is_whitespace) || close < open {
+        return Err(invalid());
+    }
+    let ref_column = single(parse_key_columns(&rest[open..=close])?)?;
+    let action: Vec<String> =
+        rest[close + 1..].split_whitespace().map(|w| w.to_uppercase()).collect();
+    let on_delete_cascade = match action.join(" ").as_str() {
+        "" | "ON DELETE RESTRICT" | "ON DELETE NO ACTION" => false,
+        "ON DELETE CASCADE" => true,
+        _ => return Err(invalid()),
+    };
+    Ok(ForeignKey {
+        column,
+        ref_table: ref_table.to_string(),
+        ref_column,
+        on_delete_cascade,
+    })
+}
+
+/// Splits a comma-separated list, ignoring commas inside parentheses or quotes.
+pub(crate) fn split_top_level(text: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '\'' | '"' | '`' if quote == Some(c) => quote = None,
+            '\'' | '"' | '`' if quote.is_none() => quote = Some(c),
+            '(' if quote.is_none() => depth += 1,
+            ')' if quote.is_none() => depth -= 1,
+            ',' if quote.is_none() && depth == 0 => {
+                parts.push(text[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(text[start..].trim());
+    parts.retain(|p| !p.is_empty());
+    parts
+}
+
+/// If `text` is an identifier quoted with double quotes or backticks, such as `"first name"`
+/// or `` `order` ``, returns the name between the quotes.
+pub(crate) fn quoted_identifier(text: &str) -> Option<&str> {
+    let text = text.trim();
+    ['"', '`'].into_iter().find_map(|q| {
+        let inner = text.strip_prefix(q)?.strip_suffix(q)?;
+        (!inner.contains(q)).then_some(inner)
+    })
+}
+
+/// The name an identifier refers to: the text between the quotes of a quoted identifier,
+/// otherwise the identifier as written. Names are case-sensitive either way.
+pub(crate) fn unquote_identifier(text: &str) -> &str {
+    quoted_identifier(text).unwrap_or(text.trim())
+}
+
+/// Splits the identifier at the start of `text` from what follows it. A quoted identifier
+/// may contain spaces and keywords and is returned without its quotes; an unquoted one ends
+/// at the first whitespace.
+fn leading_identifier(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    if let Some(q @ ('"' | '`')) = text.chars().next()
+        && let Some(len) = text[1..].find(q)
+    {
+        return (&text[1..=len], text[len + 2..].trim_start());
+    }
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    (&text[..end], text[end..].trim_start())
+}
+
+/// Parses the `(a, b)` column list of a table-level key constraint.
+fn parse_key_columns(text: &str) -> Result<Vec<String>, SqlError> {
+    let inner = text
+        .trim()
+        .strip_prefix('(')
+        .and_then(|t| t.strip_suffix(')'))
+        .ok_or_else(|| SqlError::Parse("Expected a column list in parentheses".to_string()))?;
+    let names: Vec<String> = split_top_level(inner)
+        .into_iter()
+        .map(|c| unquote_identifier(c).to_string())
+        .collect();
+    if names.is_empty() {
+        return Err(SqlError::Parse("Key column list is empty".to_string()));
+    }
+    Ok(names)
+}
+
+/// Parses the `(condition)` of a CHECK constraint, returning the condition.
+fn parse_check(text: &str) -> Result<String, SqlError> {
+    let condition = text
+        .trim()
+        .strip_prefix('(')
+        .and_then(|t| t.strip_suffix(')'))
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .ok_or_else(|| SqlError::Parse("Expected a condition in parentheses after CHECK".into()))?;
+    Ok(condition.to_string())
+}
+
+/// Removes a `CHECK (condition)` clause from a column definition, returning the remaining
+/// definition and the condition.
+fn split_check(col_def: &str) -> Result<(String, Option<String>), SqlError> {
+    let upper = col_def.to_ascii_uppercase();
+    let Some(idx) = upper.find(" CHECK") else {
+        return Ok((col_def.to_string(), None));
+    };
+    let after = &col_def[idx + 6..];
+    let open = after.len() - after.trim_start().len();
+    if !after[open..].starts_with('(') {
+        return Ok((col_def.to_string(), None));
+    }
+    // Find the parenthesis closing the condition, skipping quoted literals
+    let mut depth = 0;
+    let mut quote = None;
+    let mut end = None;
+    for (i, c) in after.char_indices().skip(open) {
+        match c {
+            '\'' | '"' | '`' if quote == Some(c) => quote = None,
+            '\'' | '"' | '`' if quote.is_none() => quote = Some(c),
+            '(' if quote.is_none() => depth += 1,
+            ')' if quote.is_none() => {
+                depth -= 1;
+                if depth == 0 {
+                    end = Some(i + 1);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let end = end.ok_or_else(|| SqlError::Parse("Unbalanced parentheses in CHECK".into()))?;
+    let check = parse_check(&after[..end])?;
+    Ok((format!("{} {}", &col_def[..idx], &after[end..]), Some(check)))
+}
+
+/// Removes a `DEFAULT literal` clause from a column definition, returning the remaining
+/// definition and the raw literal (quotes included).
+fn split_default(col_def: &str) -> (String, Option<String>) {
+    let upper = col_def.to_ascii_uppercase();
+    let Some(idx) = upper.find(" DEFAULT ") else {
+        return (col_def.to_string(), None);
+    };
+    let after = col_def[idx + 9..].trim_start();
+    let end = match after.chars().next() {
+        Some('\'' | '"') => quoted_len(after).unwrap_or(after.len()),
+        _ => after.find(char::is_whitespace).unwrap_or(after.len()),
+    };
+    let rest = format!("{} {}", &col_def[..idx], &after[end..]);
+    (rest, Some(after[..end].to_string()))
 }
 // sql.rs
 // Minimal SQL-like query parser and dispatcher for CRUD operations
 
-use crate::database::{Database, DatabaseInterface};
-use crate::query::query_to_predicate;
+use crate::aggregate::{Aggregate, AggregateFunc};
+use crate::arithmetic::Arithmetic;
+use crate::database::{Database, DatabaseInterface, print_table};
+use crate::error::SqlError;
+use crate::query::{equality_condition, try_query_to_predicate};
+use crate::row::RowInterface;
+use crate::table::{Assignment, Table, TableInterface};
+use crate::value::{Value, date_key};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// The successful result of executing a statement.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlOutcome {
+    /// CREATE TABLE finished; `false` if the table already existed (only with IF NOT EXISTS).
+    Created(bool),
+    /// A table's schema was changed.
+    Altered,
+    /// An index was created.
+    IndexCreated,
+    /// An index was dropped.
+    IndexDropped,
+    /// DROP TABLE finished; `false` if the table did not exist (only with IF EXISTS).
+    Dropped(bool),
+    /// The given number of rows were inserted.
+    Inserted(usize),
+    /// Rows returned by a SELECT, along with the projected column names.
+    Selected {
+        columns: Vec<String>,
+        rows: Vec<Vec<String>>,
+    },
+    /// The given number of rows were updated.
+    Updated(usize),
+    /// The given number of rows were deleted.
+    Deleted(usize),
+    /// TRUNCATE TABLE removed the given number of rows.
+    Truncated(usize),
+    /// Table names returned by LIST TABLES.
+    Tables(Vec<String>),
+    /// A transaction was started.
+    Begun,
+    /// The current transaction was committed.
+    Committed,
+    /// The current transaction was rolled back.
+    RolledBack,
+    /// VACUUM compacted the tables in memory. Data files are left as they are; a save only
+    /// ever writes the remaining rows anyway.
+    Vacuumed,
+}
+
+impl fmt::Display for SqlOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SqlOutcome::Created(true) => write!(f, "Table created"),
+            SqlOutcome::Created(false) => write!(f, "Table already exists"),
+            SqlOutcome::Altered => write!(f, "Table altered"),
+            SqlOutcome::IndexCreated => write!(f, "Index created"),
+            SqlOutcome::IndexDropped => write!(f, "Index dropped"),
+            SqlOutcome::Dropped(true) => write!(f, "Table dropped"),
+            SqlOutcome::Dropped(false) => write!(f, "Table did not exist"),
+            SqlOutcome::Inserted(n) => write!(f, "Inserted {} row(s)", n),
+            SqlOutcome::Selected { rows, .. } => write!(f, "Selected {} row(s)", rows.len()),
+            SqlOutcome::Updated(n) => write!(f, "Updated {} row(s)", n),
+            SqlOutcome::Deleted(n) => write!(f, "Deleted {} row(s)", n),
+            SqlOutcome::Truncated(n) => write!(f, "Table truncated, {} row(s) removed", n),
+            SqlOutcome::Tables(tables) => write!(f, "{} table(s)", tables.len()),
+            SqlOutcome::Begun => write!(f, "Transaction started"),
+            SqlOutcome::Committed => write!(f, "Transaction committed"),
+            SqlOutcome::RolledBack => write!(f, "Transaction rolled back"),
+            SqlOutcome::Vacuumed => write!(f, "Database vacuumed (memory only)"),
+        }
+    }
+}
+
+/// Executes a statement and prints its outcome (or error) to stdout.
+/// Used by the interactive CLI.
+pub fn execute_and_print(db: &mut Database, sql: &str) {
+    match execute_sql(db, sql) {
+        Ok(SqlOutcome::Selected { columns, rows }) => {
+            print_table(&columns, &rows);
+            println!("({} row(s))", rows.len());
+        }
+        Ok(SqlOutcome::Tables(tables)) => db.list_tables(&tables),
+        Ok(outcome) => println!("{}", outcome),
+        Err(e) => println!("{}", e),
+    }
+}
+
+/// Strips `--` line comments and `/* */` block comments from a script and splits it on
+/// semicolons outside string literals. Empty statements are dropped.
+pub fn split_statements(sql: &str) -> Vec<String> {
+    let mut statements = vec![];
+    let mut current = String::new();
+    let mut quote = None;
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' | '`' if quote == Some(c) => quote = None,
+            '\'' | '"' | '`' if quote.is_none() => quote = Some(c),
+            '-' if quote.is_none() && chars.peek() == Some(&'-') => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+                continue;
+            }
+            '/' if quote.is_none() && chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = None;
+                for c in chars.by_ref() {
+                    if prev == Some('*') && c == '/' {
+                        break;
+                    }
+                    prev = Some(c);
+                }
+                // Keep the tokens on either side of the comment apart
+                current.push(' ');
+                continue;
+            }
+            ';' if quote.is_none() => {
+                statements.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    statements.push(current);
+    statements
+        .into_iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// A single statement with `?` placeholders, parsed once and then bound to typed
+/// parameters for each execution. Parameters are inserted as literals, never as SQL text,
+/// so they cannot add clauses or statements of their own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreparedStatement {
+    sql: String,
+    /// The statement split at its placeholders; one more than the number of parameters.
+    segments: Vec<String>,
+}
+
+impl PreparedStatement {
+    /// Parses `sql`, which must be exactly one statement. `?` inside string literals is not
+    /// a placeholder.
+    pub fn prepare(sql: &str) -> Result<Self, SqlError> {
+        let statement = match split_statements(sql).as_slice() {
+            [statement] => statement.clone(),
+            _ => {
+                return Err(SqlError::Parse(
+                    "A prepared statement must contain exactly one statement".to_string(),
+                ));
+            }
+        };
+        let mut segments = vec![String::new()];
+        let mut quote = None;
+        for c in statement.chars() {
+            match c {
+                '\'' | '"' | '`' if quote == Some(c) => quote = None,
+                '\'' | '"' | '`' if quote.is_none() => quote = Some(c),
+                '?' if quote.is_none() => {
+                    segments.push(String::new());
+                    continue;
+                }
+                _ => {}
+            }
+            segments.last_mut().expect("segments is never empty").push(c);
+        }
+        Ok(PreparedStatement {
+            sql: sql.to_string(),
+            segments,
+        })
+    }
+
+    /// The statement as it was prepared.
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
 
-/// Dispatches a SQL-like query string to the appropriate database operation.
-pub fn execute_sql(db: &mut Database, sql: &str) {
-    let sql = sql.trim();
+    /// The number of `?` placeholders.
+    pub fn param_count(&self) -> usize {
+        self.segments.len() - 1
+    }
+
+    /// Builds the statement with each placeholder replaced by the matching parameter as a
+    /// literal. Fails if the number of parameters is wrong or a parameter cannot be written
+    /// as a literal.
+    pub fn bind(&self, params: &[Value]) -> Result<String, SqlError> {
+        if params.len() != self.param_count() {
+            return Err(SqlError::Parse(format!(
+                "Expected {} parameter(s) but got {}",
+                self.param_count(),
+                params.len()
+            )));
+        }
+        let mut sql = self.segments[0].clone();
+        for (param, segment) in params.iter().zip(&self.segments[1..]) {
+            sql.push_str(&param_literal(param)?);
+            sql.push_str(segment);
+        }
+        Ok(sql)
+    }
+}
+
+/// Writes a parameter as a SQL literal. Strings are single-quoted, with quotes doubled and
+/// backslashes escaped so `unquote_literal` gives back exactly the bound text.
+fn param_literal(value: &Value) -> Result<String, SqlError> {
+    match value {
+        Value::Float(n) if !n.is_finite() => {
+            Err(SqlError::Parse(format!("Parameter {} is not a finite number", n)))
+        }
+        Value::Int(_) | Value::Float(_) | Value::Bool(_) => Ok(value.to_string()),
+        Value::Null => Ok("NULL".to_string()),
+        Value::Str(s) => Ok(format!("'{}'", s.replace('\\', "\\\\").replace('\'', "''"))),
+    }
+}
+
+/// Whether a script only reads data (SELECT, EXPLAIN, LIST TABLES), so it is safe to run
+/// on a replica and needs no logging or replication.
+pub fn is_read_only(sql: &str) -> bool {
+    let statements = split_statements(sql);
+    !statements.is_empty()
+        && statements.iter().all(|statement| {
+            let upper = statement.to_uppercase();
+            ["SELECT", "EXPLAIN", "LIST"].iter().any(|kw| upper.starts_with(kw))
+        })
+}
+
+/// Executes a read-only script (see `is_read_only`), which needs only shared access.
+/// Returns the outcome of the last statement.
+pub fn execute_read(db: &Database, sql: &str) -> Result<SqlOutcome, SqlError> {
+    let statements = split_statements(sql);
+    let Some((last, rest)) = statements.split_last() else {
+        return Err(SqlError::Parse("Empty statement".to_string()));
+    };
+    for statement in rest {
+        read_statement(db, statement)?;
+    }
+    read_statement(db, last)
+}
+
+fn read_statement(db: &Database, sql: &str) -> Result<SqlOutcome, SqlError> {
+    if sql.to_uppercase().starts_with("SELECT") {
+        // Example: SELECT * FROM Users WHERE age > 25 ORDER BY age DESC
+        //     or: SELECT name FROM Users UNION ALL SELECT name FROM Admins
+        execute_union(db, sql)
+    } else if sql.to_uppercase().starts_with("EXPLAIN") {
+        // Example: EXPLAIN SELECT * FROM Users WHERE id == 3
+        let query = sql["EXPLAIN".len()..].trim_start();
+        if !query.to_uppercase().starts_with("SELECT") {
+            return Err(SqlError::Unsupported("EXPLAIN only supports SELECT".to_string()));
+        }
+        explain_select(db, query)
+    } else if sql.to_uppercase().starts_with("LIST") {
+        Ok(SqlOutcome::Tables(parse_tables(db, sql)))
+    } else {
+        Err(SqlError::Unsupported(format!("'{}' is not a read-only statement", sql)))
+    }
+}
+
+/// Executes a script of one or more `;`-separated statements, which may contain comments.
+/// Statements run in order and execution stops at the first error; the outcome of the last
+/// statement is returned.
+pub fn execute_sql(db: &mut Database, sql: &str) -> Result<SqlOutcome, SqlError> {
+    let statements = split_statements(sql);
+    let Some((last, rest)) = statements.split_last() else {
+        return Err(SqlError::Parse("Empty statement".to_string()));
+    };
+    for statement in rest {
+        execute_statement(db, statement).inspect_err(|e| log_rejected(statement, e))?;
+    }
+    execute_statement(db, last).inspect_err(|e| log_rejected(last, e))
+}
+
+/// Logs a failed statement: bad data as a warning, anything else only for debugging.
+fn log_rejected(sql: &str, err: &SqlError) {
+    if err.is_violation() {
+        log::warn!("Rejected '{}': {}", sql, err);
+    } else {
+        log::debug!("Failed '{}': {}", sql, err);
+    }
+}
+
+/// Dispatches a single SQL-like statement to the appropriate database operation.
+fn execute_statement(db: &mut Database, sql: &str) -> Result<SqlOutcome, SqlError> {
+    if is_read_only(sql) {
+        return read_statement(db, sql);
+    }
+    // Mutating statements are logged before they are applied
+    db.log_statement(sql)?;
+    match sql.to_uppercase().as_str() {
+        "BEGIN" | "BEGIN TRANSACTION" | "START TRANSACTION" => {
+            db.begin_transaction()?;
+            return Ok(SqlOutcome::Begun);
+        }
+        "COMMIT" => {
+            db.commit()?;
+            return Ok(SqlOutcome::Committed);
+        }
+        "ROLLBACK" => {
+            db.rollback()?;
+            return Ok(SqlOutcome::RolledBack);
+        }
+        "VACUUM" => {
+            db.vacuum();
+            return Ok(SqlOutcome::Vacuumed);
+        }
+        _ => {}
+    }
     if sql.to_uppercase().starts_with("CREATE TABLE") {
         // Example: CREATE TABLE Users (id PRIMARY KEY, name, email UNIQUE, age)
-        let (table, columns, primary_key, unique_columns) = parse_create_table(sql);
+        let (sql, if_not_exists) = split_if_not_exists(sql);
+        let (table, columns, primary_key, unique_columns, auto_increment, checks, foreign_keys) =
+            parse_create_table(&sql)?;
         if table.is_empty() || columns.is_empty() {
-            println!("Invalid CREATE TABLE syntax - table name and at least one column required");
-            return;
+            return Err(SqlError::Parse(
+                "Invalid CREATE TABLE syntax - table name and at least one column required"
+                    .to_string(),
+            ));
+        }
+        // Creating a table never replaces an existing one and its rows
+        if db.tables.contains_key(&table) {
+            if if_not_exists {
+                return Ok(SqlOutcome::Created(false));
+            }
+            return Err(SqlError::Parse(format!("Table '{}' already exists", table)));
+        }
+        for fk in &foreign_keys {
+            // A table may reference itself, e.g. an employee's manager
+            let parent_key = if fk.ref_table == table {
+                &primary_key
+            } else {
+                &db.table(&fk.ref_table)?.primary_key
+            };
+            if *parent_key != [fk.ref_column.as_str()] {
+                return Err(SqlError::Parse(format!(
+                    "FOREIGN KEY must reference the primary key of '{}'",
+                    fk.ref_table
+                )));
+            }
         }
         db.create_table_with_constraints(&table, columns, primary_key, unique_columns);
-    } else if sql.to_uppercase().starts_with("SELECT") {
-        // Example: SELECT * FROM Users WHERE age > 25
-        let (columns, table, where_clause) = parse_select(sql);
-        
-        // Validate table exists
-        if table.is_empty() || !db.tables.contains_key(&table) {
-            println!("Table '{}' does not exist", table);
-            return;
-        }
-
-        let _table_columns = db.get_table_columns(&table);
-        let selected_columns = if columns == ["*"] {
-            _table_columns.clone()
-        } else if columns.is_empty() {
-            println!("No columns specified in SELECT");
-            return;
-        } else {
-            columns
+        if let Some(table_ref) = db.tables.get_mut(&table) {
+            for column in auto_increment {
+                table_ref.auto_increment.insert(column, 0);
+            }
+            table_ref.checks = checks;
+            table_ref.foreign_keys = foreign_keys;
+        }
+        Ok(SqlOutcome::Created(true))
+    } else if sql.to_uppercase().starts_with("ALTER TABLE") {
+        // Example: ALTER TABLE Users RENAME TO Customers
+        // Example: ALTER TABLE Users RENAME COLUMN name TO full_name
+        match parse_rename(sql)? {
+            Some((table, None, new_name)) => {
+                db.rename_table(&table, &new_name)?;
+                return Ok(SqlOutcome::Altered);
+            }
+            Some((table, Some(column), new_name)) => {
+                db.rename_column(&table, &column, &new_name)?;
+                return Ok(SqlOutcome::Altered);
+            }
+            None => {}
+        }
+        // Example: ALTER TABLE Users ADD COLUMN email STRING
+        let (table, column) = parse_alter_table(sql)?;
+        let Some(table_ref) = db.tables.get_mut(&table) else {
+            return Err(SqlError::TableNotFound(table));
         };
-        
-        let table_schema_cols = db.tables.get(&table).unwrap().schema.columns.clone();
-        let pred = query_to_predicate(&table_schema_cols, &where_clause);
-        db.select(&table, selected_columns, pred);
+        if table_ref.schema.columns.iter().any(|c| c.name == column.name) {
+            return Err(SqlError::Parse(format!(
+                "Column '{}' already exists in table '{}'",
+                column.name, table
+            )));
+        }
+        table_ref.add_column(column, String::new());
+        Ok(SqlOutcome::Altered)
+    } else if sql.to_uppercase().starts_with("DROP TABLE") {
+        // Example: DROP TABLE IF EXISTS Users
+        let (table, if_exists) = parse_drop_table(sql)?;
+        let referencing = db.referencing_tables(&table);
+        if !referencing.is_empty() {
+            return Err(SqlError::ForeignKeyViolation(format!(
+                "table {} is referenced by {}",
+                table,
+                referencing.join(", ")
+            )));
+        }
+        if db.drop_table(&table) {
+            Ok(SqlOutcome::Dropped(true))
+        } else if if_exists {
+            Ok(SqlOutcome::Dropped(false))
+        } else {
+            Err(SqlError::TableNotFound(table))
+        }
+    } else if sql.to_uppercase().starts_with("TRUNCATE") {
+        // Example: TRUNCATE TABLE Users
+        let table = parse_truncate(sql)?;
+        Ok(SqlOutcome::Truncated(db.truncate(&table)?))
+    } else if sql.to_uppercase().starts_with("CREATE INDEX") {
+        // Example: CREATE INDEX users_age ON Users (age)
+        let (name, table, column) = parse_create_index(sql)?;
+        db.create_index(&name, &table, &column)?;
+        Ok(SqlOutcome::IndexCreated)
+    } else if sql.to_uppercase().starts_with("DROP INDEX") {
+        // Example: DROP INDEX users_age
+        let name = sql.trim_end_matches(';')["DROP INDEX".len()..].trim();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(SqlError::Parse(format!("Invalid DROP INDEX syntax: '{}'", sql)));
+        }
+        if db.drop_index(name) {
+            Ok(SqlOutcome::IndexDropped)
+        } else {
+            Err(SqlError::IndexNotFound(name.to_string()))
+        }
     } else if sql.to_uppercase().starts_with("INSERT") {
         // Example: INSERT INTO Users (id, name, age) VALUES (3, 'Carol', 22)
-        let (table, values) = parse_insert(sql);
-        
+        let (table, names, values) = parse_insert(sql);
+
         // Validate table and values
         if table.is_empty() {
-            println!("No table specified in INSERT");
-            return;
-        }
-        if !db.tables.contains_key(&table) {
-            println!("Table '{}' does not exist", table);
-            return;
+            return Err(SqlError::Parse("No table specified in INSERT".to_string()));
         }
+        let Some(table_ref) = db.tables.get(&table) else {
+            return Err(SqlError::TableNotFound(table));
+        };
         if values.is_empty() {
-            println!("No values specified in INSERT");
-            return;
-        }
-        
-        // Validate column count
-        let expected_cols = db.tables.get(&table).unwrap().schema.columns.len();
-        if values.len() != expected_cols {
-            println!(
-                "Wrong number of values: expected {}, got {}",
-                expected_cols,
-                values.len()
-            );
-            return;
-        }
-        
-        db.insert(&table, values);
+            return Err(SqlError::Parse("No values specified in INSERT".to_string()));
+        }
+
+        let columns = &table_ref.schema.columns;
+        let values = if names.is_empty() {
+            // Validate column count
+            if values.len() != columns.len() {
+                return Err(SqlError::ColumnCountMismatch {
+                    expected: columns.len(),
+                    got: values.len(),
+                });
+            }
+            columns
+                .iter()
+                .zip(values)
+                .map(|(col, val)| literal_value(col, val))
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            // Every named column must exist, once, and have a value
+            for (i, name) in names.iter().enumerate() {
+                column_index(columns, name)?;
+                if names[..i].contains(name) {
+                    return Err(SqlError::Parse(format!(
+                        "Column '{}' specified more than once in INSERT",
+                        name
+                    )));
+                }
+            }
+            if values.len() != names.len() {
+                return Err(SqlError::ColumnCountMismatch {
+                    expected: names.len(),
+                    got: values.len(),
+                });
+            }
+            // Values are reordered into schema order; columns left out of the list take
+            // their default, or NULL
+            let mut named: HashMap<&str, Option<String>> =
+                names.iter().map(String::as_str).zip(values).collect();
+            columns
+                .iter()
+                .map(|col| match named.remove(col.name.as_str()) {
+                    Some(val) => literal_value(col, val),
+                    None => Ok(col.default.clone().unwrap_or(Value::Null)),
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        };
+        db.insert_values(&table, values)?;
+        Ok(SqlOutcome::Inserted(1))
     } else if sql.to_uppercase().starts_with("UPDATE") {
         // Example: UPDATE Users SET age = 40 WHERE id == 2
-        let (table, set_values, where_clause) = parse_update(sql, db);
-        
+        let (table, assignments, where_clause) = parse_update(sql)?;
+
         // Validate table
         if table.is_empty() {
-            println!("No table specified in UPDATE");
-            return;
-        }
-        if !db.tables.contains_key(&table) {
-            println!("Table '{}' does not exist", table);
-            return;
+            return Err(SqlError::Parse("No table specified in UPDATE".to_string()));
         }
-        
-        let table_ref = db.tables.get(&table).unwrap();
+        let Some(table_ref) = db.tables.get(&table) else {
+            return Err(SqlError::TableNotFound(table));
+        };
         let table_schema_cols = table_ref.schema.columns.clone();
-        
+
         // Validate set values
-        if set_values.iter().all(|v| v.is_empty()) {
-            println!("No values specified in UPDATE SET clause");
-            return;
-        }
-        
-        if set_values.len() != table_schema_cols.len() {
-            println!(
-                "Wrong number of values in UPDATE: expected {}, got {}",
-                table_schema_cols.len(),
-                set_values.len()
-            );
-            return;
-        }
-        
-        let pred = query_to_predicate(&table_schema_cols, &where_clause);
-        db.update(&table, set_values, pred);
+        if assignments.is_empty() {
+            return Err(SqlError::Parse(
+                "No values specified in UPDATE SET clause".to_string(),
+            ));
+        }
+
+        let pred = try_query_to_predicate(&table_schema_cols, &where_clause)?;
+        let updated = db.update_named(&table, &assignments, pred)?;
+        Ok(SqlOutcome::Updated(updated))
     } else if sql.to_uppercase().starts_with("DELETE") {
         // Example: DELETE FROM Users WHERE id == 2
-        let (table, where_clause) = parse_delete(sql);
-        let _table_columns = db.get_table_columns(&table);
-        let table_schema_cols: Vec<_> = if let Some(t) = db.tables.get(&table) {
-            t.schema.columns.clone()
-        } else {
-            vec![]
+        let (table, where_clause) = parse_delete(sql)?;
+        let Some(table_ref) = db.tables.get(&table) else {
+            return Err(SqlError::TableNotFound(table));
         };
-        let pred = query_to_predicate(&table_schema_cols, &where_clause);
-        db.delete(&table, pred);
-    } else if sql.to_uppercase().starts_with("LIST") {
-        let tables = parse_tables(db, sql);
-        db.list_tables(&tables);
+        let deleted = match where_clause {
+            Some(where_clause) => {
+                let pred = try_query_to_predicate(&table_ref.schema.columns, &where_clause)?;
+                db.delete(&table, pred)?
+            }
+            // No WHERE clause: intentionally delete every row
+            None => db.delete(&table, |_| true)?,
+        };
+        Ok(SqlOutcome::Deleted(deleted))
     } else {
-        println!("Unsupported SQL operation.");
+        Err(SqlError::Unsupported(sql.to_string()))
+    }
+}
+
+/// Maps a SQL type name (already uppercased) to its column type.
+fn parse_column_type(token: &str) -> Option<ColumnType> {
+    match token {
+        "INT" | "INTEGER" => Some(ColumnType::Int),
+        "FLOAT" | "REAL" | "DOUBLE" => Some(ColumnType::Float),
+        "STRING" | "TEXT" | "CHAR" => Some(ColumnType::String),
+        "BOOL" | "BOOLEAN" => Some(ColumnType::Bool),
+        "DATE" | "DATETIME" | "TIMESTAMP" => Some(ColumnType::Date),
+        _ => None,
     }
 }
 
+/// Parses `ALTER TABLE name ADD [COLUMN] col [TYPE]`; the type defaults to STRING.
+fn parse_alter_table(sql: &str) -> Result<(String, ColumnSchema), SqlError> {
+    let invalid = || SqlError::Parse(format!("Invalid ALTER TABLE syntax: '{}'", sql));
+    let parts: Vec<&str> = sql.trim_end_matches(';').split_whitespace().collect();
+    let rest = match parts.get(3) {
+        Some(kw) if kw.eq_ignore_ascii_case("ADD") => &parts[4..],
+        _ => return Err(invalid()),
+    };
+    let rest = match rest.first() {
+        Some(kw) if kw.eq_ignore_ascii_case("COLUMN") => &rest[1..],
+        _ => rest,
+    };
+    let (name, col_type) = match rest {
+        [name] => (name, ColumnType::String),
+        [name, ty] => (name, parse_column_type(&ty.to_uppercase()).ok_or_else(invalid)?),
+        _ => return Err(invalid()),
+    };
+    Ok((
+        parts[2].to_string(),
+        ColumnSchema {
+            name: name.to_string(),
+            col_type,
+            nullable: true,
+            default: None,
+        },
+    ))
+}
+
+/// Parses `ALTER TABLE old RENAME TO new` or `ALTER TABLE t RENAME COLUMN old TO new`,
+/// returning the table, the column if one is renamed, and the new name. Other ALTER TABLE
+/// statements give `None`.
+fn parse_rename(sql: &str) -> Result<Option<(String, Option<String>, String)>, SqlError> {
+    let parts: Vec<&str> = sql.trim_end_matches(';').split_whitespace().collect();
+    match parts.get(3) {
+        Some(kw) if kw.eq_ignore_ascii_case("RENAME") => {}
+        _ => return Ok(None),
+    }
+    match parts[..] {
+        [_, _, old, _, to, new] if to.eq_ignore_ascii_case("TO") => {
+            Ok(Some((old.to_string(), None, new.to_string())))
+        }
+        [_, _, table, _, kw, old, to, new]
+            if kw.eq_ignore_ascii_case("COLUMN") && to.eq_ignore_ascii_case("TO") =>
+        {
+            Ok(Some((table.to_string(), Some(old.to_string()), new.to_string())))
+        }
+        _ => Err(SqlError::Parse(format!("Invalid ALTER TABLE syntax: '{}'", sql))),
+    }
+}
+
+/// Parses `DROP TABLE [IF EXISTS] name`, returning the table name and whether IF EXISTS was given.
+fn parse_drop_table(sql: &str) -> Result<(String, bool), SqlError> {
+    let rest = sql.trim_end_matches(';')["DROP TABLE".len()..].trim();
+    let (name, if_exists) = match rest.get(..9) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("IF EXISTS") => (rest[9..].trim(), true),
+        _ => (rest, false),
+    };
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(SqlError::Parse(format!("Invalid DROP TABLE syntax: '{}'", sql)));
+    }
+    Ok((name.to_string(), if_exists))
+}
+
+/// Removes the IF NOT EXISTS from `CREATE TABLE IF NOT EXISTS ...`, returning the plain
+/// statement and whether the clause was present.
+fn split_if_not_exists(sql: &str) -> (String, bool) {
+    let rest = sql["CREATE TABLE".len()..].trim_start();
+    match rest.get(..13) {
+        Some(clause) if clause.eq_ignore_ascii_case("IF NOT EXISTS") => {
+            (format!("CREATE TABLE {}", rest[13..].trim_start()), true)
+        }
+        _ => (sql.to_string(), false),
+    }
+}
+
+/// Parses `TRUNCATE [TABLE] name`, returning the table name.
+fn parse_truncate(sql: &str) -> Result<String, SqlError> {
+    let rest = sql.trim_end_matches(';')["TRUNCATE".len()..].trim();
+    let name = match rest.split_once(char::is_whitespace) {
+        Some((kw, name)) if kw.eq_ignore_ascii_case("TABLE") => name.trim(),
+        _ => rest,
+    };
+    if name.is_empty() || name.contains(char::is_whitespace) || name.eq_ignore_ascii_case("TABLE")
+    {
+        return Err(SqlError::Parse(format!("Invalid TRUNCATE syntax: '{}'", sql)));
+    }
+    Ok(name.to_string())
+}
+
+/// Parses `CREATE INDEX name ON table (column)`, returning the index, table and column names.
+fn parse_create_index(sql: &str) -> Result<(String, String, String), SqlError> {
+    let invalid = || SqlError::Parse(format!("Invalid CREATE INDEX syntax: '{}'", sql));
+    let rest = sql.trim_end_matches(';')["CREATE INDEX".len()..].trim();
+    let (name, rest) = rest.split_once(char::is_whitespace).ok_or_else(invalid)?;
+    let rest = rest.trim_start();
+    let rest = match rest.get(..3) {
+        Some(kw) if kw.eq_ignore_ascii_case("ON ") => rest[3..].trim(),
+        _ => return Err(invalid()),
+    };
+    let (table, column) = rest.split_once('(').ok_or_else(invalid)?;
+    let column = column.strip_suffix(')').ok_or_else(invalid)?.trim();
+    let table = table.trim();
+    if table.is_empty() || column.is_empty() || column.contains([',', ' ']) {
+        return Err(invalid());
+    }
+    Ok((name.to_string(), table.to_string(), column.to_string()))
+}
+
 fn parse_tables(db: &Database, sql: &str) -> Vec<String> {
     // LIST TABLES
     let sql = sql.trim_end_matches(';');
@@ -215,155 +1007,1185 @@ fn parse_tables(db: &Database, sql: &str) -> Vec<String> {
     tables
 }
 
-// Helper functions for parsing SQL-like queries (very basic, not robust)
-fn parse_select(sql: &str) -> (Vec<String>, String, String) {
-    // SELECT col1, col2 FROM table WHERE condition
-    let mut columns = vec![];
-    let mut table = String::new();
-    let mut where_clause = String::new();
-    let sql = sql.trim_end_matches(';');
-    let upper = sql.to_uppercase();
-    
-    // Must start with SELECT and have FROM
-    if !upper.starts_with("SELECT") || !upper.contains("FROM") {
-        return (columns, table, where_clause);
+/// A column of a `TypedRows` result.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TypedColumn {
+    pub name: String,
+    /// `None` if the type cannot be determined, e.g. for MIN of an unknown column.
+    pub col_type: Option<ColumnType>,
+}
+
+/// The result of a SELECT with each value typed according to its column.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TypedRows {
+    pub columns: Vec<TypedColumn>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+/// Runs a SELECT (or UNION of SELECTs) like `execute_read`, but returns typed values instead
+/// of display strings, so NULL and an empty string stay apart.
+pub fn execute_select_typed(db: &Database, sql: &str) -> Result<TypedRows, SqlError> {
+    if !sql.trim_start().to_uppercase().starts_with("SELECT") {
+        return Err(SqlError::Unsupported(format!("'{}' is not a SELECT", sql)));
     }
-    
-    if let Some(select_idx) = upper.find("SELECT ") {
-        if let Some(from_idx) = upper.find(" FROM ") {
-            // Safely get columns between SELECT and FROM
-            if from_idx > select_idx + 7 {
-                let cols = &sql[select_idx + 7..from_idx];
-                columns = cols.split(',').map(|s| s.trim().to_string()).collect();
+    let output = union_output(db, sql)?;
+    let rows = output.rows;
+    let columns = output
+        .columns
+        .into_iter()
+        .zip(output.types)
+        .map(|(name, col_type)| TypedColumn { name, col_type })
+        .collect();
+    Ok(TypedRows { columns, rows })
+}
+
+fn execute_union(db: &Database, sql: &str) -> Result<SqlOutcome, SqlError> {
+    let output = union_output(db, sql)?;
+    let rows = output.display_rows();
+    Ok(SqlOutcome::Selected { columns: output.columns, rows })
+}
+
+/// Runs `SELECT ... UNION [ALL] SELECT ...`, or a single SELECT. Each SELECT keeps its own
+/// WHERE / ORDER BY / LIMIT clauses. The SELECTs must project the same number of columns,
+/// with compatible types (Int and Float mix, giving Float), and the result takes the first
+/// one's column names. UNION removes duplicate rows from the combined result, keeping the
+/// first occurrence; UNION ALL keeps every row.
+fn union_output(db: &Database, sql: &str) -> Result<SelectOutput, SqlError> {
+    let (first, rest) = split_clauses(sql.trim_end_matches(';'), &["UNION ALL", "UNION"]);
+    let SelectOutput { columns, mut types, mut rows, plain_floats } = select_output(db, first)?;
+    let mut distinct = false;
+    for (keyword, select) in rest {
+        if !select.to_uppercase().starts_with("SELECT") {
+            return Err(SqlError::Parse(format!("Expected a SELECT after {}", keyword)));
+        }
+        let other = select_output(db, select)?;
+        if other.types.len() != types.len() {
+            return Err(SqlError::ColumnCountMismatch {
+                expected: types.len(),
+                got: other.types.len(),
+            });
+        }
+        for (n, (a, b)) in types.iter_mut().zip(other.types).enumerate() {
+            match (&*a, b) {
+                (Some(x), Some(y)) if !union_compatible(x, &y) => {
+                    return Err(SqlError::Parse(format!(
+                        "UNION column {} has type {:?} in one SELECT and {:?} in another",
+                        n + 1,
+                        x,
+                        y
+                    )));
+                }
+                (Some(x), Some(y)) if x != &y => *a = Some(ColumnType::Float),
+                (None, known) => *a = known,
+                _ => {}
             }
-            
-            // Safely get table name after FROM and optional WHERE clause
-            let after_from = &sql[from_idx + 6..];
-            if !after_from.is_empty() {
-                if let Some(where_idx) = after_from.to_uppercase().find(" WHERE ") {
-                    table = after_from[..where_idx].trim().to_string();
-                    where_clause = after_from[where_idx + 7..].trim().to_string();
-                    if where_clause.is_empty() {
-                        where_clause = "true".to_string();
-                    }
+        }
+        rows.extend(other.rows);
+        distinct |= keyword == "UNION";
+    }
+    // Widen values to the merged column types, so Int 1 and Float 1.0 are one value
+    for row in &mut rows {
+        for (value, col_type) in row.iter_mut().zip(&types) {
+            if let Some(col_type) = col_type {
+                *value = std::mem::replace(value, Value::Null).coerce(col_type);
+            }
+        }
+    }
+    if distinct {
+        // Values hold floats and cannot be hashed, so rows are keyed by their debug form
+        let mut seen = HashSet::new();
+        rows.retain(|row| seen.insert(format!("{:?}", row)));
+    }
+    Ok(SelectOutput { columns, types, rows, plain_floats })
+}
+
+/// Whether values of the two types may share a UNION column.
+fn union_compatible(a: &ColumnType, b: &ColumnType) -> bool {
+    let numeric = |t: &ColumnType| matches!(t, ColumnType::Int | ColumnType::Float);
+    a == b || (numeric(a) && numeric(b))
+}
+
+/// The result of a single SELECT.
+struct SelectOutput {
+    columns: Vec<String>,
+    /// The type of each projected column, if known.
+    types: Vec<Option<ColumnType>>,
+    rows: Vec<Vec<Value>>,
+    /// Whether each column displays floats in their shortest form, e.g. `60` rather than
+    /// `60.0`, as SUM and AVG results do.
+    plain_floats: Vec<bool>,
+}
+
+impl SelectOutput {
+    /// The rows as display strings, for `SqlOutcome::Selected`.
+    fn display_rows(&self) -> Vec<Vec<String>> {
+        self.rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(&self.plain_floats)
+                    .map(|(value, plain)| match value {
+                        Value::Float(n) if *plain => n.to_string(),
+                        value => value.to_string(),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Runs a SELECT: filters with the WHERE clause, sorts by ORDER BY, projects columns (or
+/// computes aggregates per GROUP BY bucket), then applies OFFSET / LIMIT.
+fn select_output(db: &Database, sql: &str) -> Result<SelectOutput, SqlError> {
+    let mut query = parse_select(sql)?;
+
+    // Validate table exists
+    if query.table.is_empty() {
+        return Err(SqlError::Parse("No table specified in SELECT".to_string()));
+    }
+    query.resolve_qualified_names()?;
+    let (schema_cols, rows, where_clause) = match &query.join {
+        Some(join) => {
+            let (columns, rows) = join_source(db, &query.table, join)?;
+            let where_clause = query.where_with_aliases(&columns)?;
+            (columns, rows, where_clause)
+        }
+        None => {
+            let Some(table) = db.tables.get(&query.table) else {
+                return Err(SqlError::TableNotFound(query.table));
+            };
+            let where_clause = query.where_with_aliases(&table.schema.columns)?;
+            let rows = Access::choose(table, &where_clause).rows(table);
+            (table.schema.columns.clone(), rows, where_clause)
+        }
+    };
+    let schema_cols = &schema_cols;
+
+    let (items, labels): (Vec<SelectItem>, Vec<String>) = if query.columns.len() == 1
+        && query.columns[0] == ("*".to_string(), None)
+    {
+        schema_cols
+            .iter()
+            .map(|c| (SelectItem::Column(c.name.clone()), c.name.clone()))
+            .unzip()
+    } else if query.columns.is_empty() {
+        return Err(SqlError::Parse("No columns specified in SELECT".to_string()));
+    } else {
+        query
+            .columns
+            .iter()
+            .map(|(expr, alias)| {
+                let item = SelectItem::parse(expr);
+                let label = alias.clone().unwrap_or_else(|| item.label());
+                (item, label)
+            })
+            .unzip()
+    };
+    // HAVING without GROUP BY treats all rows as one group
+    let is_grouped = !query.group_by.is_empty()
+        || !query.having.is_empty()
+        || items.iter().any(|i| matches!(i, SelectItem::Aggregate(_)));
+    let group_indices = query
+        .group_by
+        .iter()
+        .map(|c| column_index(schema_cols, c))
+        .collect::<Result<Vec<_>, _>>()?;
+    for item in &items {
+        match item {
+            SelectItem::Column(name) => {
+                column_index(schema_cols, name)?;
+                if is_grouped && !query.group_by.contains(name) {
+                    return Err(SqlError::Parse(format!(
+                        "Column '{}' must appear in GROUP BY or be used in an aggregate",
+                        name
+                    )));
+                }
+            }
+            SelectItem::Computed(arith, text) => {
+                for name in arith.columns() {
+                    column_index(schema_cols, name)?;
+                }
+                if is_grouped {
+                    return Err(SqlError::Parse(format!(
+                        "Computed column '{}' cannot be used with GROUP BY or aggregates",
+                        text
+                    )));
+                }
+            }
+            SelectItem::Aggregate(_) => {}
+        }
+    }
+
+    let pred = try_query_to_predicate(schema_cols, &where_clause)?;
+    let mut rows: Vec<Vec<Value>> = rows.into_iter().filter(|row| pred(row)).collect();
+
+    if !query.order_by.is_empty() {
+        let keys = query
+            .order_by
+            .iter()
+            .map(|order| Ok((column_index(schema_cols, &order.column)?, order.descending)))
+            .collect::<Result<Vec<_>, SqlError>>()?;
+        // A stable sort, so rows equal on every key keep their order
+        rows.sort_by(|a, b| {
+            keys.iter()
+                .map(|&(idx, descending)| {
+                    let ord = compare_for_sort(&schema_cols[idx].col_type, a.get(idx), b.get(idx));
+                    if descending { ord.reverse() } else { ord }
+                })
+                .find(|ord| ord.is_ne())
+                .unwrap_or(Ordering::Equal)
+        });
+    }
+
+    let types: Vec<Option<ColumnType>> = items
+        .iter()
+        .map(|item| match item {
+            SelectItem::Column(name) => {
+                column_index(schema_cols, name).ok().map(|i| schema_cols[i].col_type.clone())
+            }
+            SelectItem::Computed(arith, _) => Some(arith.result_type(schema_cols)),
+            SelectItem::Aggregate(agg) => agg.result_type(schema_cols),
+        })
+        .collect();
+    let plain_floats: Vec<bool> = items
+        .iter()
+        .map(|item| {
+            matches!(item, SelectItem::Aggregate(agg)
+                if matches!(agg.func, AggregateFunc::Sum | AggregateFunc::Avg))
+        })
+        .collect();
+
+    let output: Vec<Vec<Value>> = if is_grouped {
+        // Aggregates only HAVING uses are computed in hidden columns after the projected ones
+        let (having, having_aggs) = having_aggregates(&query.having);
+        let mut hidden: Vec<Aggregate> = vec![];
+        for agg in having_aggs {
+            if !labels.contains(&agg.label()) && !hidden.contains(&agg) {
+                hidden.push(agg);
+            }
+        }
+        let mut output = group_rows(rows, &group_indices, query.group_by.is_empty())
+            .into_iter()
+            .map(|(_, bucket)| {
+                items
+                    .iter()
+                    .map(|item| match item {
+                        SelectItem::Aggregate(agg) => agg.evaluate(schema_cols, &bucket),
+                        // Every row of the bucket holds the same grouped values
+                        SelectItem::Column(name) => {
+                            let pos = query.group_by.iter().position(|g| g == name);
+                            let value = pos.and_then(|p| bucket.first()?.get(group_indices[p]));
+                            Ok(value.cloned().unwrap_or(Value::Null))
+                        }
+                        // Rejected above for grouped queries
+                        SelectItem::Computed(..) => Ok(Value::Null),
+                    })
+                    .chain(hidden.iter().map(|agg| agg.evaluate(schema_cols, &bucket)))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if !having.is_empty() {
+            let column = |name: String, col_type: Option<ColumnType>| ColumnSchema {
+                name,
+                col_type: col_type.unwrap_or(ColumnType::String),
+                nullable: true,
+                default: None,
+            };
+            let having_cols: Vec<ColumnSchema> = labels
+                .iter()
+                .zip(&types)
+                .map(|(label, col_type)| column(label.clone(), col_type.clone()))
+                .chain(hidden.iter().map(|agg| column(agg.label(), agg.result_type(schema_cols))))
+                .collect();
+            filter_having(&mut output, &having_cols, &having)?;
+        }
+        for row in &mut output {
+            row.truncate(items.len());
+        }
+        output
+    } else {
+        let col_indices: Vec<Option<usize>> = items
+            .iter()
+            .map(|item| match item {
+                SelectItem::Column(name) => column_index(schema_cols, name).ok(),
+                SelectItem::Aggregate(_) | SelectItem::Computed(..) => None,
+            })
+            .collect();
+        rows.into_iter()
+            .map(|row| {
+                items
+                    .iter()
+                    .zip(&col_indices)
+                    .map(|(item, idx)| match (item, idx) {
+                        (SelectItem::Computed(arith, _), _) => arith.evaluate(schema_cols, &row),
+                        (_, Some(i)) => Ok(row.get(*i).cloned().unwrap_or(Value::Null)),
+                        (_, None) => Ok(Value::Null),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    let rows = output
+        .into_iter()
+        .skip(query.offset)
+        .take(query.limit.unwrap_or(usize::MAX))
+        .collect();
+    Ok(SelectOutput {
+        columns: labels,
+        types,
+        rows,
+        plain_floats,
+    })
+}
+
+/// Describes how `EXPLAIN SELECT ...` would run the query, one step per row of a single
+/// `plan` column, without running it.
+fn explain_select(db: &Database, sql: &str) -> Result<SqlOutcome, SqlError> {
+    if !split_clauses(sql, &["UNION"]).1.is_empty() {
+        return Err(SqlError::Unsupported("EXPLAIN does not support UNION".to_string()));
+    }
+    let mut query = parse_select(sql)?;
+    if query.table.is_empty() {
+        return Err(SqlError::Parse("No table specified in SELECT".to_string()));
+    }
+    query.resolve_qualified_names()?;
+    let mut plan = vec![];
+    let where_clause = match &query.join {
+        Some(join) => {
+            let (left, right) = (db.table(&query.table)?, db.table(&join.table)?);
+            plan.push(format!(
+                "{}: {} with {} on {} == {}",
+                if join.left_outer { "Left join" } else { "Join" },
+                query.table,
+                join.table,
+                join.on.0,
+                join.on.1
+            ));
+            plan.push("Access: nested loop over both tables".to_string());
+            plan.push(format!("Estimated rows scanned: {}", left.len() * right.len()));
+            query.where_clause.clone()
+        }
+        None => {
+            let table = db.table(&query.table)?;
+            let where_clause = query.where_with_aliases(&table.schema.columns)?;
+            let access = Access::choose(table, &where_clause);
+            plan.push(format!("Table: {}", query.table));
+            plan.push(format!("Access: {}", access));
+            plan.push(format!("Estimated rows scanned: {}", access.estimate(table)));
+            where_clause
+        }
+    };
+    if !where_clause.is_empty() {
+        plan.push(format!("Filter: {}", where_clause));
+    }
+    if !query.group_by.is_empty() {
+        plan.push(format!("Group by: {}", query.group_by.join(", ")));
+    } else if !query.having.is_empty()
+        || query.columns.iter().any(|(expr, _)| Aggregate::parse(expr).is_some())
+    {
+        plan.push("Aggregate: all rows as one group".to_string());
+    }
+    if !query.having.is_empty() {
+        plan.push(format!("Having: {}", query.having));
+    }
+    if !query.order_by.is_empty() {
+        let keys: Vec<String> = query
+            .order_by
+            .iter()
+            .map(|order| {
+                let direction = if order.descending { "DESC" } else { "ASC" };
+                format!("{} {}", order.column, direction)
+            })
+            .collect();
+        plan.push(format!("Sort: {}", keys.join(", ")));
+    }
+    if query.offset > 0 {
+        plan.push(format!("Offset: {}", query.offset));
+    }
+    if let Some(limit) = query.limit {
+        plan.push(format!("Limit: {}", limit));
+    }
+    Ok(SqlOutcome::Selected {
+        columns: vec!["plan".to_string()],
+        rows: plan.into_iter().map(|step| vec![step]).collect(),
+    })
+}
+
+/// How a SELECT on a single table finds its candidate rows. A WHERE clause that is a
+/// single equality on the primary key or an indexed column only visits the matching rows.
+enum Access {
+    PrimaryKey { column: String, key: String },
+    Index { name: String, column: String, key: String },
+    Scan,
+}
+
+impl Access {
+    fn choose(table: &Table, where_clause: &str) -> Self {
+        let Some((i, literal)) = equality_condition(&table.schema.columns, where_clause) else {
+            return Access::Scan;
+        };
+        let column = table.schema.columns[i].name.clone();
+        let key = literal.to_string();
+        if table.primary_key == [column.as_str()] {
+            return Access::PrimaryKey { column, key };
+        }
+        let index = table.indexes.iter().filter(|(_, c)| **c == column).map(|(n, _)| n).min();
+        match index {
+            Some(name) => Access::Index { name: name.clone(), column, key },
+            None => Access::Scan,
+        }
+    }
+
+    /// The candidate rows, in table order.
+    fn rows(&self, table: &Table) -> Vec<Vec<Value>> {
+        match self {
+            Access::PrimaryKey { key, .. } => {
+                table.find_by_pk(key).map(|r| r.get_values().clone()).into_iter().collect()
+            }
+            Access::Index { column, key, .. } => table
+                .lookup_index(column, key)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|r| r.get_values().clone())
+                .collect(),
+            Access::Scan => table.select_rows(|_| true),
+        }
+    }
+
+    /// The number of rows `rows` would visit.
+    fn estimate(&self, table: &Table) -> usize {
+        match self {
+            Access::PrimaryKey { key, .. } => usize::from(table.find_by_pk(key).is_some()),
+            Access::Index { column, key, .. } => {
+                table.lookup_index(column, key).map_or(0, |rows| rows.len())
+            }
+            Access::Scan => table.len(),
+        }
+    }
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Access::PrimaryKey { column, .. } => write!(f, "primary key lookup on {}", column),
+            Access::Index { name, column, .. } => {
+                write!(f, "index lookup using {} on {}", name, column)
+            }
+            Access::Scan => write!(f, "full table scan"),
+        }
+    }
+}
+
+/// Buckets rows by the string values of the grouping columns, keeping groups in the order
+/// they are first seen. With `single_group` set (aggregates without GROUP BY), all rows form
+/// one group even when there are none.
+fn group_rows(
+    rows: Vec<Vec<Value>>,
+    group_indices: &[usize],
+    single_group: bool,
+) -> Vec<(Vec<String>, Vec<Vec<Value>>)> {
+    if single_group {
+        return vec![(vec![], rows)];
+    }
+    let mut groups: Vec<(Vec<String>, Vec<Vec<Value>>)> = vec![];
+    let mut positions: HashMap<Vec<String>, usize> = HashMap::new();
+    for row in rows {
+        let key: Vec<String> = group_indices
+            .iter()
+            .map(|&i| row.get(i).map(|v| v.to_string()).unwrap_or_default())
+            .collect();
+        match positions.get(&key) {
+            Some(&pos) => groups[pos].1.push(row),
+            None => {
+                positions.insert(key.clone(), groups.len());
+                groups.push((key, vec![row]));
+            }
+        }
+    }
+    groups
+}
+
+/// Rewrites each aggregate call in a HAVING condition to its column label, so `count( * )`
+/// reads `COUNT(*)`, returning the rewritten condition and the aggregates it calls.
+fn having_aggregates(condition: &str) -> (String, Vec<Aggregate>) {
+    let bytes = condition.as_bytes();
+    let mut rewritten = String::with_capacity(condition.len());
+    let mut aggregates = vec![];
+    let mut quote: Option<u8> = None;
+    let (mut i, mut copied) = (0, 0);
+    while i < bytes.len() {
+        let b = bytes[i];
+        match quote {
+            Some(q) if b == q => quote = None,
+            Some(_) => {}
+            None if matches!(b, b'\'' | b'"' | b'`') => quote = Some(b),
+            None if b.is_ascii_alphabetic() => {
+                let is_word = |b: &u8| b.is_ascii_alphanumeric() || *b == b'_' || *b == b'.';
+                let word_end = i + bytes[i..].iter().take_while(|b| is_word(b)).count();
+                let open = word_end + bytes[word_end..].iter().take_while(|b| **b == b' ').count();
+                if bytes.get(open) == Some(&b'(')
+                    && let Some(close) = condition[open..].find(')').map(|c| open + c)
+                    && let Some(agg) = Aggregate::parse(&condition[i..=close])
+                {
+                    rewritten.push_str(&condition[copied..i]);
+                    rewritten.push_str(&agg.label());
+                    aggregates.push(agg);
+                    copied = close + 1;
+                    i = copied;
                 } else {
-                    table = after_from.trim().to_string();
-                    where_clause = "".to_string(); // Empty string will be treated as true
+                    i = word_end;
                 }
+                continue;
             }
+            None => {}
         }
+        i += 1;
     }
-    (columns, table, where_clause)
+    rewritten.push_str(&condition[copied..]);
+    (rewritten, aggregates)
 }
 
-fn parse_insert(sql: &str) -> (String, Vec<String>) {
-    // INSERT INTO table (col1, col2) VALUES (val1, val2)
+/// Keeps the grouped rows that satisfy a HAVING condition. Unlike WHERE, which filters the
+/// rows before grouping, the condition sees one row per group laid out according to
+/// `columns`: grouped columns by name and aggregates by their alias or call, e.g. `COUNT(*)`.
+fn filter_having(
+    rows: &mut Vec<Vec<Value>>,
+    columns: &[ColumnSchema],
+    condition: &str,
+) -> Result<(), SqlError> {
+    let pred = try_query_to_predicate(columns, condition)?;
+    rows.retain(|row| pred(row));
+    Ok(())
+}
+
+/// One entry of a SELECT projection list.
+enum SelectItem {
+    Column(String),
+    Aggregate(Aggregate),
+    /// An arithmetic expression, with its text as written.
+    Computed(Arithmetic, String),
+}
+
+impl SelectItem {
+    fn parse(expr: &str) -> Self {
+        if let Some(name) = quoted_identifier(expr) {
+            return SelectItem::Column(name.to_string());
+        }
+        if let Some(agg) = Aggregate::parse(expr) {
+            return SelectItem::Aggregate(agg);
+        }
+        match Arithmetic::parse(expr) {
+            Some(arith) => SelectItem::Computed(arith, expr.trim().to_string()),
+            None => SelectItem::Column(expr.to_string()),
+        }
+    }
+
+    /// The column header shown for this item.
+    fn label(&self) -> String {
+        match self {
+            SelectItem::Column(name) => name.clone(),
+            SelectItem::Aggregate(agg) => agg.label(),
+            SelectItem::Computed(_, text) => text.clone(),
+        }
+    }
+}
+
+/// Builds the combined columns and rows of `left [LEFT] JOIN join.table ON ...`. Columns of
+/// the result are qualified as `Table.col`.
+fn join_source(
+    db: &Database,
+    left: &str,
+    join: &JoinClause,
+) -> Result<(Vec<ColumnSchema>, Vec<Vec<Value>>), SqlError> {
+    let (a_table, a_col) = split_qualified(&join.on.0)?;
+    let (b_table, b_col) = split_qualified(&join.on.1)?;
+    let join_rows = if join.left_outer { Database::left_join_rows } else { Database::join_rows };
+    if a_table == left && b_table == join.table {
+        join_rows(db, left, a_col, &join.table, b_col)
+    } else if a_table == join.table && b_table == left {
+        join_rows(db, left, b_col, &join.table, a_col)
+    } else {
+        Err(SqlError::Parse(format!(
+            "ON condition must compare a column of '{}' with a column of '{}'",
+            left, join.table
+        )))
+    }
+}
+
+/// Splits a `Table.col` reference into its table and column parts.
+fn split_qualified(name: &str) -> Result<(&str, &str), SqlError> {
+    name.split_once('.')
+        .map(|(t, c)| (t.trim(), c.trim()))
+        .ok_or_else(|| {
+            SqlError::Parse(format!("Expected a qualified column 'Table.col', got '{}'", name))
+        })
+}
+
+/// Resolves a column name to its position in the schema.
+fn column_index(columns: &[ColumnSchema], name: &str) -> Result<usize, SqlError> {
+    columns
+        .iter()
+        .position(|c| c.name == name)
+        .ok_or_else(|| SqlError::ColumnNotFound(name.to_string()))
+}
+
+/// A parsed SELECT statement.
+struct SelectQuery {
+    /// Projected expressions, each with its `AS` alias if one was given.
+    columns: Vec<(String, Option<String>)>,
+    table: String,
+    table_alias: Option<String>,
+    join: Option<JoinClause>,
+    where_clause: String,
+    group_by: Vec<String>,
+    /// Condition on the grouped rows, empty if there is no HAVING clause.
+    having: String,
+    /// ORDER BY keys, most significant first.
+    order_by: Vec<OrderBy>,
+    limit: Option<usize>,
+    offset: usize,
+}
+
+/// The second table of a `[LEFT] JOIN ... ON a == b` clause.
+struct JoinClause {
+    table: String,
+    alias: Option<String>,
+    /// LEFT JOIN: keep left rows without a match, with NULLs for this table's columns.
+    left_outer: bool,
+    /// The two sides of the ON equality, as written.
+    on: (String, String),
+}
+
+impl SelectQuery {
+    /// Rewrites `Table.col` and `alias.col` references to the names columns have in the row
+    /// source: `col` for a single table and `Table.col` for a join. A qualifier that names
+    /// neither a table of the query nor its alias is an error.
+    fn resolve_qualified_names(&mut self) -> Result<(), SqlError> {
+        let mut qualifiers = HashMap::from([(self.table.clone(), self.table.clone())]);
+        if let Some(alias) = &self.table_alias {
+            qualifiers.insert(alias.clone(), self.table.clone());
+        }
+        if let Some(join) = &self.join {
+            qualifiers.insert(join.table.clone(), join.table.clone());
+            if let Some(alias) = &join.alias {
+                qualifiers.insert(alias.clone(), join.table.clone());
+            }
+        }
+        let joined = self.join.is_some();
+        let resolve = |text: &str| {
+            rewrite_identifiers(text, |ident| {
+                let Some((qualifier, column)) = ident.split_once('.') else {
+                    return Ok(None);
+                };
+                match qualifiers.get(qualifier) {
+                    Some(table) if joined => Ok(Some(format!("{}.{}", table, column))),
+                    Some(_) => Ok(Some(column.to_string())),
+                    None => Err(SqlError::Parse(format!(
+                        "Unknown table or alias '{}' in '{}'",
+                        qualifier, ident
+                    ))),
+                }
+            })
+        };
+        for (expr, _) in &mut self.columns {
+            *expr = resolve(expr)?;
+        }
+        self.where_clause = resolve(&self.where_clause)?;
+        self.having = resolve(&self.having)?;
+        for column in &mut self.group_by {
+            *column = resolve(column)?;
+        }
+        for order in &mut self.order_by {
+            order.column = resolve(&order.column)?;
+        }
+        if let Some(join) = &mut self.join {
+            // ON conditions always compare qualified columns of the two tables
+            let on = (resolve(&join.on.0)?, resolve(&join.on.1)?);
+            join.on = on;
+        }
+        Ok(())
+    }
+
+    /// The WHERE clause with column aliases from the projection replaced by the column they
+    /// name. Names of real columns in `columns` take precedence over aliases.
+    fn where_with_aliases(&self, columns: &[ColumnSchema]) -> Result<String, SqlError> {
+        let aliases: HashMap<&str, &str> = self
+            .columns
+            .iter()
+            .filter_map(|(expr, alias)| Some((alias.as_deref()?, expr.as_str())))
+            .filter(|(alias, _)| !columns.iter().any(|c| c.name == *alias))
+            .collect();
+        rewrite_identifiers(&self.where_clause, |ident| {
+            Ok(aliases.get(ident).map(|expr| expr.to_string()))
+        })
+    }
+}
+
+/// Replaces each identifier (letters, digits, `_` and `.`, not starting with a digit)
+/// outside quoted literals with the result of `replace`, keeping it when that is `None`.
+pub(crate) fn rewrite_identifiers(
+    text: &str,
+    replace: impl Fn(&str) -> Result<Option<String>, SqlError>,
+) -> Result<String, SqlError> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '.';
+    let mut out = String::with_capacity(text.len());
+    let mut quote = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if matches!(c, '\'' | '"' | '`') => quote = Some(c),
+            None if is_ident(c) && !c.is_ascii_digit() && c != '.' => {
+                let mut end = start + c.len_utf8();
+                while let Some(&(i, c)) = chars.peek() {
+                    if !is_ident(c) {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                let ident = &text[start..end];
+                out.push_str(&replace(ident)?.unwrap_or_else(|| ident.to_string()));
+                continue;
+            }
+            None if is_ident(c) => {
+                // Numbers such as 1.5 are not identifiers
+                out.push(c);
+                while let Some(&(_, c)) = chars.peek() {
+                    if !is_ident(c) {
+                        break;
+                    }
+                    out.push(c);
+                    chars.next();
+                }
+                continue;
+            }
+            None => {}
+        }
+        out.push(c);
+    }
+    Ok(out)
+}
+
+/// Parses a table reference in FROM or JOIN: `Table`, `Table alias` or `Table AS alias`.
+fn parse_table_ref(text: &str) -> Result<(String, Option<String>), SqlError> {
+    let (table, rest) = leading_identifier(text);
+    let table = table.to_string();
+    match rest.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [] if !table.is_empty() => Ok((table, None)),
+        [alias] if !table.is_empty() => Ok((table, Some(alias.to_string()))),
+        [kw, alias] if !table.is_empty() && kw.eq_ignore_ascii_case("AS") => {
+            Ok((table, Some(alias.to_string())))
+        }
+        _ => Err(SqlError::Parse(format!("Invalid table reference: '{}'", text))),
+    }
+}
+
+/// Splits a projection entry into its expression and optional `AS` alias.
+fn parse_projection(text: &str) -> (String, Option<String>) {
+    match split_clauses(text, &["AS"]) {
+        (expr, clauses) if !expr.is_empty() => match clauses.as_slice() {
+            [("AS", alias)] if !alias.is_empty() => {
+                (expr.to_string(), Some(unquote_identifier(alias).to_string()))
+            }
+            _ => (text.trim().to_string(), None),
+        },
+        _ => (text.trim().to_string(), None),
+    }
+}
+
+/// Orders two values of a column of type `col_type` for ORDER BY. Dates compare
+/// chronologically, other values by `Value::compare`; NULLs sort first.
+fn compare_for_sort(col_type: &ColumnType, a: Option<&Value>, b: Option<&Value>) -> Ordering {
+    let date = |v: Option<&Value>| v.and_then(Value::as_str).and_then(date_key);
+    match (a, b) {
+        (Some(a), Some(b)) if *col_type == ColumnType::Date => {
+            date(Some(a)).cmp(&date(Some(b))).then_with(|| a.compare(b))
+        }
+        (Some(a), Some(b)) => a.compare(b),
+        (a, b) => a.is_some().cmp(&b.is_some()),
+    }
+}
+
+/// One sort key of an ORDER BY clause.
+struct OrderBy {
+    column: String,
+    descending: bool,
+}
+
+/// Splits `text` at top-level clause keywords (see `keyword_positions`). Returns the text
+/// before the first clause and each clause's body.
+fn split_clauses<'a>(
+    text: &'a str,
+    keywords: &[&'static str],
+) -> (&'a str, Vec<(&'static str, &'a str)>) {
+    let found = keyword_positions(text, keywords);
+    let head_end = found.first().map_or(text.len(), |&(_, start, _)| start);
+    let clauses = found
+        .iter()
+        .enumerate()
+        .map(|(n, &(kw, _, end))| {
+            let next = found.get(n + 1).map_or(text.len(), |&(_, start, _)| start);
+            (kw, text[end..next].trim())
+        })
+        .collect();
+    (text[..head_end].trim(), clauses)
+}
+
+/// Splits `text` around the first top-level `keyword`, returning the trimmed text before
+/// and after it, or `None` if the keyword does not appear.
+fn split_keyword<'a>(text: &'a str, keyword: &'static str) -> Option<(&'a str, &'a str)> {
+    let &(_, start, end) = keyword_positions(text, &[keyword]).first()?;
+    Some((text[..start].trim(), text[end..].trim()))
+}
+
+/// Finds the keywords in `text`, with the byte range each occupies. Keywords match
+/// case-insensitively as whole words outside quoted literals, so identifiers and string
+/// contents are never mistaken for them; the words of a keyword such as `ORDER BY` may be
+/// separated by any whitespace.
+fn keyword_positions(text: &str, keywords: &[&'static str]) -> Vec<(&'static str, usize, usize)> {
+    let bytes = text.as_bytes();
+    let mut found: Vec<(&'static str, usize, usize)> = vec![];
+    let mut quote: Option<u8> = None;
+    for (i, &b) in bytes.iter().enumerate() {
+        match quote {
+            Some(q) if b == q => quote = None,
+            Some(_) => {}
+            None if matches!(b, b'\'' | b'"' | b'`') => quote = Some(b),
+            None => {
+                if (i > 0 && !bytes[i - 1].is_ascii_whitespace() && bytes[i - 1] != b')')
+                    || found.last().is_some_and(|&(_, _, end)| i < end)
+                {
+                    continue;
+                }
+                if let Some((kw, end)) =
+                    keywords.iter().find_map(|kw| Some((*kw, keyword_end(bytes, i, kw)?)))
+                {
+                    found.push((kw, i, end));
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Where `keyword` ends if it starts at byte `start` of `bytes` and is followed by
+/// whitespace, `(` or the end of the text.
+fn keyword_end(bytes: &[u8], start: usize, keyword: &str) -> Option<usize> {
+    let mut pos = start;
+    for (n, word) in keyword.split(' ').enumerate() {
+        if n > 0 {
+            let gap = bytes[pos..].iter().take_while(|b| b.is_ascii_whitespace()).count();
+            if gap == 0 {
+                return None;
+            }
+            pos += gap;
+        }
+        let end = pos + word.len();
+        if end > bytes.len() || !bytes[pos..end].eq_ignore_ascii_case(word.as_bytes()) {
+            return None;
+        }
+        pos = end;
+    }
+    match bytes.get(pos) {
+        None | Some(b'(') => Some(pos),
+        Some(b) if b.is_ascii_whitespace() => Some(pos),
+        _ => None,
+    }
+}
+
+// Helper functions for parsing SQL-like queries (very basic, not robust)
+fn parse_select(sql: &str) -> Result<SelectQuery, SqlError> {
+    // SELECT col1, col2 FROM table [JOIN other ON table.a == other.b] WHERE condition
+    //     GROUP BY col1 HAVING condition ORDER BY col [ASC|DESC] LIMIT n OFFSET m
+    let mut query = SelectQuery {
+        columns: vec![],
+        table: String::new(),
+        table_alias: None,
+        join: None,
+        where_clause: String::new(),
+        group_by: vec![],
+        having: String::new(),
+        order_by: vec![],
+        limit: None,
+        offset: 0,
+    };
     let sql = sql.trim_end_matches(';');
-    let upper = sql.to_uppercase();
-    let mut table = String::new();
-    let mut values = vec![];
-    
-    // Must start with INSERT INTO and have VALUES
-    if !upper.starts_with("INSERT INTO") || !upper.contains("VALUES") {
-        return (table, values);
-    }
-    
-    if let Some(into_idx) = upper.find("INTO ") {
-        let after_into = &sql[into_idx + 5..];
-        // Handle both formats:
-        // INSERT INTO table VALUES (...)
-        // INSERT INTO table (col1, col2) VALUES (...)
-        // Find VALUES first to get table name (handle both with/without column list)
-        if let Some(values_idx) = after_into.to_uppercase().find("VALUES") {
-            table = after_into[..values_idx].trim().to_string();
-            // If there's a column list, strip it from table name
-            if let Some(paren_start) = table.find('(') {
-                table = table[..paren_start].trim().to_string();
-            }
-
-            // Now get values from within parentheses after VALUES
-            if let Some(vals_idx) = after_into[values_idx..].find('(') {
-                let vals_start = values_idx + vals_idx + 1;
-                if let Some(vals_end) = after_into[vals_start..].find(')') {
-                    let vals_str = &after_into[vals_start..vals_start + vals_end];
-                    values = vals_str
-                        .split(',')
-                        .map(|s| s.trim().trim_matches('"').trim_matches('\'').to_string())
+
+    // Must start with SELECT and have FROM
+    let Some((select, from)) = split_keyword(sql, "FROM") else {
+        return Ok(query);
+    };
+    if let Some((head, cols)) = split_keyword(select, "SELECT")
+        && head.is_empty()
+    {
+        if !cols.is_empty() {
+            query.columns = split_top_level(cols).into_iter().map(parse_projection).collect();
+        }
+
+        // Table name after FROM, followed by the optional clauses
+        let (table, clauses) = split_clauses(
+            from,
+            &["WHERE", "GROUP BY", "HAVING", "ORDER BY", "LIMIT", "OFFSET"],
+        );
+        let (table, join) = parse_join(table)?;
+        (query.table, query.table_alias) = parse_table_ref(table)?;
+        query.join = join;
+        for (keyword, body) in clauses {
+            match keyword {
+                "WHERE" => query.where_clause = body.to_string(),
+                "GROUP BY" => {
+                    query.group_by = split_top_level(body)
+                        .into_iter()
+                        .map(|s| unquote_identifier(s).to_string())
                         .collect();
                 }
+                "HAVING" => query.having = body.to_string(),
+                "ORDER BY" => query.order_by = parse_order_by(body)?,
+                "LIMIT" => query.limit = Some(parse_count(keyword, body)?),
+                "OFFSET" => query.offset = parse_count(keyword, body)?,
+                _ => {}
             }
         }
     }
-    (table, values)
+    Ok(query)
+}
+
+/// Splits `left [INNER | LEFT [OUTER]] JOIN right ON a == b` into the left table and the
+/// join clause.
+fn parse_join(text: &str) -> Result<(&str, Option<JoinClause>), SqlError> {
+    let (left, clauses) = split_clauses(
+        text,
+        &["INNER JOIN", "LEFT OUTER JOIN", "LEFT JOIN", "JOIN", "ON"],
+    );
+    match clauses.as_slice() {
+        [] => Ok((left, None)),
+        [(kind, right), ("ON", cond)] if *kind != "ON" && !right.is_empty() => {
+            let on = cond
+                .split_once("==")
+                .or_else(|| cond.split_once('='))
+                .map(|(a, b)| (a.trim().to_string(), b.trim().to_string()))
+                .ok_or_else(|| {
+                    SqlError::Parse(format!("JOIN requires an equality ON condition: '{}'", cond))
+                })?;
+            let (table, alias) = parse_table_ref(right)?;
+            let left_outer = kind.starts_with("LEFT");
+            Ok((left, Some(JoinClause { table, alias, left_outer, on })))
+        }
+        _ => Err(SqlError::Parse(format!("Invalid JOIN clause: '{}'", text))),
+    }
+}
+
+/// Parses the non-negative row count following LIMIT or OFFSET.
+fn parse_count(keyword: &str, body: &str) -> Result<usize, SqlError> {
+    body.parse::<usize>()
+        .map_err(|_| SqlError::Parse(format!("Invalid {} value: '{}'", keyword, body)))
+}
+
+fn parse_order_by(body: &str) -> Result<Vec<OrderBy>, SqlError> {
+    // ORDER BY col1 [ASC|DESC], col2 [ASC|DESC], ...
+    let invalid = || SqlError::Parse(format!("Invalid ORDER BY clause: '{}'", body));
+    let keys = split_top_level(body);
+    if keys.is_empty() {
+        return Err(invalid());
+    }
+    keys.into_iter()
+        .map(|key| {
+            let (column, dir) = leading_identifier(key);
+            let descending = match dir {
+                _ if column.is_empty() => return Err(invalid()),
+                "" => false,
+                dir if dir.eq_ignore_ascii_case("ASC") => false,
+                dir if dir.eq_ignore_ascii_case("DESC") => true,
+                _ => return Err(invalid()),
+            };
+            Ok(OrderBy {
+                column: column.to_string(),
+                descending,
+            })
+        })
+        .collect()
 }
 
-fn parse_update(sql: &str, db: &Database) -> (String, Vec<String>, String) {
-    // UPDATE table SET col1 = val1, col2 = val2 WHERE condition
+/// Parses `INSERT INTO table [(columns)] VALUES (...)`, returning the table, the named
+/// columns (empty if no list was given) and the values, each `None` for an unquoted NULL.
+fn parse_insert(sql: &str) -> (String, Vec<String>, Vec<Option<String>>) {
+    // INSERT INTO table (col1, col2) VALUES (val1, val2)
     let sql = sql.trim_end_matches(';');
-    let upper = sql.to_uppercase();
     let mut table = String::new();
-    let mut set_values = vec![];
-    let mut where_clause = String::new();
-    if let Some(update_idx) = upper.find("UPDATE ") {
-        let after_update = &sql[update_idx + 7..];
-        if let Some(set_idx) = after_update.to_uppercase().find(" SET ") {
-            table = after_update[..set_idx].trim().to_string();
-            let after_set = &after_update[set_idx + 5..];
-            let mut col_map = std::collections::HashMap::new();
-
-            // Split the SET clause into column/value pairs
-            let set_part = if let Some(where_idx) = after_set.to_uppercase().find(" WHERE ") {
-                let set_str = &after_set[..where_idx];
-                where_clause = after_set[where_idx + 7..].trim().to_string();
-                set_str
-            } else {
-                after_set
-            };
+    let mut names = vec![];
+    let mut values = vec![];
 
-            // Parse column=value pairs
-            for pair in set_part.split(',') {
-                let parts: Vec<&str> = pair.split('=').map(|s| s.trim()).collect();
-                if parts.len() == 2 {
-                    let col = parts[0].trim().to_string();
-                    let val = parts[1]
-                        .trim()
-                        .trim_matches('"')
-                        .trim_matches('\'')
-                        .to_string();
-                    col_map.insert(col, val);
-                }
-            }
+    // Must start with INSERT INTO and have VALUES
+    let Some((insert, after_into)) = split_keyword(sql, "INTO") else {
+        return (table, names, values);
+    };
+    if !insert.eq_ignore_ascii_case("INSERT") {
+        return (table, names, values);
+    }
 
-            // Get all columns in order for this table
-            if let Some(table_ref) = db.tables.get(&table) {
-                let columns = &table_ref.schema.columns;
-                // Create set_values in correct column order
-                set_values = columns
-                    .iter()
-                    .map(|col| col_map.get(&col.name).cloned().unwrap_or_default())
-                    .collect();
+    // Handle both formats:
+    // INSERT INTO table VALUES (...)
+    // INSERT INTO table (col1, col2) VALUES (...)
+    // Find VALUES first to get table name (handle both with/without column list)
+    if let Some((target, vals)) = split_keyword(after_into, "VALUES") {
+        table = target.to_string();
+        // If there's a column list, strip it from table name
+        if let Some(paren_start) = table.find('(') {
+            names = split_top_level(table[paren_start + 1..].trim_end().trim_end_matches(')'))
+                .into_iter()
+                .map(|s| unquote_identifier(s).to_string())
+                .collect();
+            table = table[..paren_start].to_string();
+        }
+        table = unquote_identifier(&table).to_string();
+
+        // Now get values from within parentheses after VALUES; commas and parentheses
+        // inside quoted values are kept
+        if let Some(vals_str) = vals.strip_prefix('(').and_then(|v| v.strip_suffix(')')) {
+            values = split_top_level(vals_str).into_iter().map(parse_literal).collect();
+        }
+    }
+    (table, names, values)
+}
+
+/// Converts a parsed INSERT literal to the column's type; `None` (unquoted NULL) is NULL.
+fn literal_value(col: &ColumnSchema, literal: Option<String>) -> Result<Value, SqlError> {
+    match literal {
+        Some(raw) => Value::from_column(col, &raw),
+        None => Ok(Value::Null),
+    }
+}
+
+/// Parses a literal value: `None` for an unquoted NULL, otherwise its text (see
+/// `unquote_literal`).
+fn parse_literal(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    if raw.eq_ignore_ascii_case("NULL") {
+        None
+    } else {
+        Some(unquote_literal(raw))
+    }
+}
+
+/// The text of a literal as written in a statement. A string in single (or double) quotes
+/// loses them; inside it a doubled quote stands for one (`'it''s'` is `it's`), and `\n`, `\r`,
+/// `\t` and `\\` stand for a newline, carriage return, tab and backslash. Other backslashes are
+/// kept, so LIKE patterns can still escape `%` and `_`. Unquoted text is returned as is.
+pub(crate) fn unquote_literal(raw: &str) -> String {
+    let raw = raw.trim();
+    let quoted = match raw.chars().next() {
+        Some(q @ ('\'' | '"')) => raw[1..].strip_suffix(q).map(|body| (q, body)),
+        _ => None,
+    };
+    let Some((q, body)) = quoted else {
+        return raw.trim_matches('"').trim_matches('\'').to_string();
+    };
+    let mut text = String::with_capacity(body.len());
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        let escaped = match (c, chars.peek()) {
+            (c, Some(&next)) if c == q && next == q => Some(q),
+            ('\\', Some('n')) => Some('\n'),
+            ('\\', Some('r')) => Some('\r'),
+            ('\\', Some('t')) => Some('\t'),
+            ('\\', Some('\\')) => Some('\\'),
+            _ => None,
+        };
+        match escaped {
+            Some(decoded) => {
+                chars.next();
+                text.push(decoded);
             }
+            None => text.push(c),
+        }
+    }
+    text
+}
+
+/// The byte length of the quoted literal at the start of `text`, quotes included, or `None`
+/// if it is not closed. A doubled quote does not close it.
+fn quoted_len(text: &str) -> Option<usize> {
+    let q = text.chars().next().filter(|c| matches!(c, '\'' | '"'))?;
+    let mut chars = text.char_indices().skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == q && chars.next_if(|&(_, next)| next == q).is_none() {
+            return Some(i + 1);
         }
     }
-    (table, set_values, where_clause)
+    None
 }
 
-fn parse_delete(sql: &str) -> (String, String) {
-    // DELETE FROM table WHERE condition
+/// Parses `UPDATE table SET col1 = val1, col2 = val2 WHERE condition` into the table, the
+/// new values keyed by column name, and the WHERE clause.
+fn parse_update(sql: &str) -> Result<(String, HashMap<String, Assignment>, String), SqlError> {
     let sql = sql.trim_end_matches(';');
-    let upper = sql.to_uppercase();
     let mut table = String::new();
+    let mut col_map = HashMap::new();
     let mut where_clause = String::new();
-    if let Some(from_idx) = upper.find("FROM ") {
-        let after_from = &sql[from_idx + 5..];
-        if let Some(where_idx) = after_from.to_uppercase().find(" WHERE ") {
-            table = after_from[..where_idx].trim().to_string();
-            where_clause = after_from[where_idx + 7..].trim().to_string();
+    if let Some((head, after_update)) = split_keyword(sql, "UPDATE")
+        && head.is_empty()
+        && let Some((target, after_set)) = split_keyword(after_update, "SET")
+    {
+        table = unquote_identifier(target).to_string();
+
+        // Split the SET clause into column/value pairs
+        let set_part = if let Some((set_str, condition)) = split_keyword(after_set, "WHERE") {
+            where_clause = condition.to_string();
+            set_str
         } else {
-            table = after_from.trim().to_string();
+            after_set
+        };
+
+        // Parse column=value pairs; commas and '=' inside quoted values are kept
+        for pair in split_top_level(set_part) {
+            let Some((col, val)) = pair.split_once('=') else {
+                return Err(SqlError::Parse(format!(
+                    "Invalid SET assignment: '{}'",
+                    pair.trim()
+                )));
+            };
+            let val = match val.trim() {
+                quoted if quoted.starts_with(['\'', '"']) => {
+                    Assignment::Literal(Some(unquote_literal(quoted)))
+                }
+                null if null.eq_ignore_ascii_case("NULL") => Assignment::Literal(None),
+                text => Assignment::Expr(text.to_string()),
+            };
+            if col_map.insert(unquote_identifier(col).to_string(), val).is_some() {
+                return Err(SqlError::Parse(format!(
+                    "Column '{}' is assigned more than once in UPDATE",
+                    col.trim()
+                )));
+            }
         }
     }
-    (table, where_clause)
+    Ok((table, col_map, where_clause))
+}
+
+/// Parses `DELETE FROM table [WHERE condition]` into the table and the condition, which is
+/// `None` when there is no WHERE clause (delete every row).
+fn parse_delete(sql: &str) -> Result<(String, Option<String>), SqlError> {
+    let invalid = || SqlError::Parse(format!("Invalid DELETE syntax: '{}'", sql));
+    let sql = sql.trim_end_matches(';').trim();
+    let rest = sql.get(6..).ok_or_else(invalid)?.trim_start();
+    if rest.len() < 5 || !rest[..5].eq_ignore_ascii_case("FROM ") {
+        return Err(invalid());
+    }
+    let after_from = rest[5..].trim();
+    let (table, where_clause) = match split_keyword(after_from, "WHERE") {
+        // A dangling WHERE is a mistake rather than a request to delete everything
+        Some((_, "")) => return Err(invalid()),
+        Some((table, condition)) => (table, Some(condition.to_string())),
+        None => (after_from, None),
+    };
+    let (table, rest) = leading_identifier(table);
+    if table.is_empty() || !rest.is_empty() {
+        return Err(invalid());
+    }
+    Ok((table.to_string(), where_clause))
 }
 
 // tests moved to tests/integration_tests.rs
@@ -2,7 +2,7 @@ use std::collections::HashMap;
 #[allow(dead_code)]
 impl Table {
     /// Get a value from a row by column name.
-    pub fn get_value(&self, row_idx: usize, col_name: &str) -> Option<&String> {
+    pub fn get_value(&self, row_idx: usize, col_name: &str) -> Option<&Value> {
         self.schema
             .columns
             .iter()
@@ -10,15 +10,15 @@ impl Table {
             .and_then(|col_idx| self.rows.get(row_idx)?.get_values().get(col_idx))
     }
 
-    /// Set a value in a row by column name.
+    /// Set a value in a row by column name. Fails if the value does not fit the column's type.
     pub fn set_value(&mut self, row_idx: usize, col_name: &str, value: String) -> bool {
-        if let Some(col_idx) = self.schema.columns.iter().position(|c| c.name == col_name) {
-            if let Some(row) = self.rows.get_mut(row_idx) {
-                if col_idx < row.get_values().len() {
-                    row.set_by_name(col_name, value, &self.schema);
-                    return true;
-                }
-            }
+        if let Some(col_idx) = self.schema.columns.iter().position(|c| c.name == col_name)
+            && let Some(value) = Value::parse(&value, &self.schema.columns[col_idx].col_type)
+            && let Some(row) = self.rows.get_mut(row_idx)
+            && col_idx < row.get_values().len()
+        {
+            row.set_by_name(col_name, value, &self.schema);
+            return true;
         }
         false
     }
@@ -39,7 +39,10 @@ impl Table {
                     .map(|(i, col)| {
                         (
                             col.name.clone(),
-                            row.get_values().get(i).cloned().unwrap_or_default(),
+                            row.get_values()
+                                .get(i)
+                                .map(|v| v.to_string())
+                                .unwrap_or_default(),
                         )
                     })
                     .collect::<HashMap<String, String>>()
@@ -47,26 +50,52 @@ impl Table {
             .collect()
     }
 }
-use crate::schema::{ColumnType, Schema};
-use serde::{Deserialize, Serialize};
+use crate::arithmetic::Arithmetic;
+use crate::database::MergePolicy;
+use crate::error::SqlError;
+use crate::query::{query_to_predicate, try_query_to_predicate};
+use crate::schema::{ColumnSchema, ForeignKey, Schema};
+use crate::sql::{rewrite_identifiers, unquote_identifier};
+use crate::value::Value;
+use serde::{Deserialize, Deserializer, Serialize};
 // table.rs
 /// Trait defining the interface for a table.
 /// Provides methods to add, update, delete, and select rows.
 pub trait TableInterface {
-    /// Adds a new row to the table with the given values.
-    fn add_row(&mut self, values: Vec<String>);
-    /// Updates all rows matching the predicate with new values.
-    fn update_rows<F>(&mut self, set_values: Vec<String>, predicate: F)
+    /// Adds a new row to the table with the given values, one per column.
+    fn add_row(&mut self, values: Vec<String>) -> Result<(), SqlError>;
+    /// Adds a new row of typed values; missing trailing values take the column default or NULL.
+    /// More values than columns are rejected.
+    fn add_values(&mut self, values: Vec<Value>) -> Result<(), SqlError>;
+    /// Updates all rows matching the predicate, returning how many changed. `set_values` must
+    /// have one entry per column; `None` leaves that column unchanged. A value that does not fit
+    /// its column but is an arithmetic expression, such as `stock - 1`, is computed from each
+    /// row's current values.
+    fn update_rows<F>(
+        &mut self,
+        set_values: Vec<Option<String>>,
+        predicate: F,
+    ) -> Result<usize, SqlError>
     where
-        F: Fn(&Vec<String>) -> bool;
-    /// Deletes all rows matching the predicate.
-    fn delete_rows<F>(&mut self, predicate: F)
+        F: Fn(&Vec<Value>) -> bool;
+    /// Like `update_rows`, but takes the new values keyed by column name, with quoted
+    /// literals told apart from expressions (see `Assignment`). Fails with
+    /// `ColumnNotFound`, listing every unknown name, if any name is not in the schema.
+    fn update_rows_named<F>(
+        &mut self,
+        assignments: &HashMap<String, Assignment>,
+        predicate: F,
+    ) -> Result<usize, SqlError>
     where
-        F: Fn(&Vec<String>) -> bool;
+        F: Fn(&Vec<Value>) -> bool;
+    /// Deletes all rows matching the predicate, returning how many were removed.
+    fn delete_rows<F>(&mut self, predicate: F) -> usize
+    where
+        F: Fn(&Vec<Value>) -> bool;
     /// Selects and returns all rows matching the predicate.
-    fn select_rows<F>(&self, predicate: F) -> Vec<Vec<String>>
+    fn select_rows<F>(&self, predicate: F) -> Vec<Vec<Value>>
     where
-        F: Fn(&Vec<String>) -> bool;
+        F: Fn(&Vec<Value>) -> bool;
 }
 
 use crate::row::{Row, RowInterface};
@@ -78,8 +107,132 @@ pub struct Table {
     name: String,                    // Name of the table
     pub schema: Schema,              // Table schema
     pub rows: Vec<Row>,              // Rows in the table (for serialization, use Row directly)
-    pub primary_key: Option<String>, // Single-column primary key
-    pub unique_columns: Vec<String>, // Unique columns
+    #[serde(default, deserialize_with = "deserialize_primary_key")]
+    pub primary_key: Vec<String>,    // Primary key columns; more than one forms a composite key
+    #[serde(deserialize_with = "deserialize_unique_columns")]
+    pub unique_columns: Vec<Vec<String>>, // Unique constraints, each over one or more columns
+    #[serde(default)]
+    pub auto_increment: HashMap<String, i64>, // AUTOINCREMENT columns and the highest id used
+    #[serde(default)]
+    pub indexes: HashMap<String, String>, // Secondary index name to indexed column
+    #[serde(default)]
+    pub checks: Vec<String>, // CHECK conditions every row must satisfy
+    #[serde(default)]
+    pub foreign_keys: Vec<ForeignKey>, // Columns that must match a row in another table
+    #[serde(skip)]
+    pk_index: HashMap<String, usize>, // Primary key value to row position; rebuilt after loading
+    #[serde(skip)]
+    index_data: HashMap<String, HashMap<String, Vec<usize>>>, // Column to value to row positions
+}
+
+/// The value an UPDATE assigns to a column, as written in its SET clause.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Assignment {
+    /// A quoted literal of the column's type, taken as written; `None` is NULL, which NOT
+    /// NULL columns reject.
+    Literal(Option<String>),
+    /// Unquoted text. An arithmetic expression or a column name is evaluated against each
+    /// row; anything else, such as a number, is a literal of the column's type.
+    Expr(String),
+}
+
+/// A value assigned by an update: fixed, or computed from the row being updated.
+enum NewValue {
+    Fixed(Value),
+    Computed(Arithmetic),
+    /// The value of the column at this position.
+    Column(usize),
+}
+
+/// A row that satisfies its table's constraints, ready to be added by `Table::push_checked`.
+pub(crate) struct CheckedRow {
+    values: Vec<Value>,
+    key: Option<String>,
+    counters: HashMap<String, i64>,
+}
+
+impl CheckedRow {
+    /// The row's values, with defaults and AUTOINCREMENT ids filled in.
+    pub(crate) fn values(&self) -> &Vec<Value> {
+        &self.values
+    }
+
+    /// The row's primary key, as used by `Table::find_by_pk`.
+    pub(crate) fn key(&self) -> Option<&String> {
+        self.key.as_ref()
+    }
+}
+
+/// An update that satisfies its table's constraints, ready to be made by
+/// `Table::apply_update`.
+pub(crate) struct CheckedUpdate {
+    /// Positions of the updated rows.
+    positions: Vec<usize>,
+    /// Every row of the table as it will be after the update.
+    rows: Vec<Row>,
+}
+
+impl CheckedUpdate {
+    /// The position and new values of each updated row.
+    pub(crate) fn changed(&self) -> impl Iterator<Item = (usize, &Vec<Value>)> {
+        self.positions.iter().map(|&i| (i, self.rows[i].get_values()))
+    }
+
+    /// The rows of the table as they will be after the update.
+    pub(crate) fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+}
+
+/// A table's schema and constraints, as reported to clients by `describe_table`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TableDescription {
+    pub name: String,
+    pub columns: Vec<ColumnSchema>,
+    pub primary_key: Vec<String>,
+    pub unique_columns: Vec<Vec<String>>,
+}
+
+/// Separates the components of a composite primary key in `Table::find_by_pk` lookups.
+pub const PK_SEPARATOR: char = '\u{1f}';
+
+/// Reads the primary key columns, also accepting files saved when the key was a single
+/// optional column name.
+fn deserialize_primary_key<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum PrimaryKey {
+        Columns(Vec<String>),
+        Column(Option<String>),
+    }
+    Ok(match PrimaryKey::deserialize(deserializer)? {
+        PrimaryKey::Columns(columns) => columns,
+        PrimaryKey::Column(column) => column.into_iter().collect(),
+    })
+}
+
+/// Reads the unique constraints, also accepting files saved when each constraint was a
+/// single column name.
+fn deserialize_unique_columns<'de, D>(deserializer: D) -> Result<Vec<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Unique {
+        Columns(Vec<String>),
+        Column(String),
+    }
+    Ok(Vec::<Unique>::deserialize(deserializer)?
+        .into_iter()
+        .map(|unique| match unique {
+            Unique::Columns(columns) => columns,
+            Unique::Column(column) => vec![column],
+        })
+        .collect())
 }
 
 impl Table {
@@ -87,8 +240,8 @@ impl Table {
     pub fn new(
         name: String,
         schema: Schema,
-        primary_key: Option<String>,
-        unique_columns: Vec<String>,
+        primary_key: Vec<String>,
+        unique_columns: Vec<Vec<String>>,
     ) -> Self {
         Table {
             name,
@@ -96,106 +249,371 @@ impl Table {
             rows: Vec::new(),
             primary_key,
             unique_columns,
+            auto_increment: HashMap::new(),
+            indexes: HashMap::new(),
+            checks: Vec::new(),
+            foreign_keys: Vec::new(),
+            pk_index: HashMap::new(),
+            index_data: HashMap::new(),
         }
     }
-}
 
-/// Implements the TableInterface trait for the Table struct.
-impl TableInterface for Table {
-    /// Adds a new row to the table with the given values, enforcing primary key and unique constraints.
-    fn add_row(&mut self, values: Vec<String>) {
-        // Type checking
-        for (i, val) in values.iter().enumerate() {
-            if let Some(col) = self.schema.columns.get(i) {
-                let valid = match col.col_type {
-                    ColumnType::Int => val.parse::<i64>().is_ok(),
-                    ColumnType::Float => val.parse::<f64>().is_ok(),
-                    ColumnType::String => true,
-                };
-                if !valid {
-                    println!(
-                        "Type error: value '{}' does not match column '{}' type {:?}",
-                        val, col.name, col.col_type
-                    );
-                    return;
-                }
+    /// Changes the table's name; the database keys the table by it.
+    pub(crate) fn rename(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    /// Renames a column along with every constraint, counter, index and CHECK condition
+    /// that names it. Row values are positional, so they stay as they are. Returns false,
+    /// changing nothing, if `old` does not exist or `new` already does.
+    pub fn rename_column(&mut self, old: &str, new: &str) -> bool {
+        let has_column = |name: &str| self.schema.columns.iter().any(|c| c.name == name);
+        if !has_column(old) || has_column(new) {
+            return false;
+        }
+        let rename = |name: &mut String| {
+            if name == old {
+                *name = new.to_string();
             }
+        };
+        self.schema.columns.iter_mut().for_each(|c| rename(&mut c.name));
+        self.primary_key.iter_mut().for_each(rename);
+        self.unique_columns.iter_mut().flatten().for_each(rename);
+        self.indexes.values_mut().for_each(rename);
+        self.foreign_keys.iter_mut().for_each(|fk| rename(&mut fk.column));
+        if let Some(last) = self.auto_increment.remove(old) {
+            self.auto_increment.insert(new.to_string(), last);
         }
-        // Check primary key constraint
-        if let Some(pk_col) = &self.primary_key {
-            if let Some(pk_idx) = self.schema.columns.iter().position(|c| &c.name == pk_col) {
-                let pk_val = values.get(pk_idx);
-                if pk_val.is_none() {
-                    println!(
-                        "Primary key column '{}' missing in inserted values!",
-                        pk_col
-                    );
-                    return;
-                }
-                let pk_val = pk_val.unwrap();
-                for row in &self.rows {
-                    if let Some(existing_val) = row.get_values().get(pk_idx) {
-                        if existing_val == pk_val {
-                            println!(
-                                "Primary key constraint violation: '{}' must be unique!",
-                                pk_col
-                            );
-                            return;
-                        }
+        if let Some(data) = self.index_data.remove(old) {
+            self.index_data.insert(new.to_string(), data);
+        }
+        for check in self.checks.iter_mut() {
+            let renamed = rewrite_identifiers(check, |ident| {
+                Ok((ident == old).then(|| new.to_string()))
+            });
+            if let Ok(renamed) = renamed {
+                *check = renamed;
+            }
+        }
+        true
+    }
+
+    /// Describes the table's columns and constraints.
+    pub fn describe(&self) -> TableDescription {
+        TableDescription {
+            name: self.name.clone(),
+            columns: self.schema.columns.clone(),
+            primary_key: self.primary_key.clone(),
+            unique_columns: self.unique_columns.clone(),
+        }
+    }
+
+    /// Returns the number of rows in the table.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns whether the table has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Builds the index key for a row's primary key, or `None` if the table has no primary
+    /// key or a key column is NULL.
+    pub(crate) fn pk_key(&self, values: &[Value]) -> Option<String> {
+        let pk_indices = self.column_indices(&self.primary_key);
+        if pk_indices.is_empty() {
+            return None;
+        }
+        let mut parts = Vec::with_capacity(pk_indices.len());
+        for i in pk_indices {
+            match values.get(i) {
+                Some(v) if !v.is_null() => parts.push(v.to_string()),
+                _ => return None,
+            }
+        }
+        Some(parts.join(&PK_SEPARATOR.to_string()))
+    }
+
+    /// Recomputes the primary key and secondary indexes from the stored rows.
+    /// Must be called after rows are replaced wholesale, e.g. after loading from a file.
+    pub fn rebuild_index(&mut self) {
+        let index = self
+            .rows
+            .iter()
+            .enumerate()
+            .filter_map(|(i, row)| self.pk_key(row.get_values()).map(|key| (key, i)))
+            .collect();
+        self.pk_index = index;
+        self.index_data.clear();
+        let columns: Vec<String> = self.indexes.values().cloned().collect();
+        for column in columns {
+            self.build_column_index(&column);
+        }
+    }
+
+    /// Releases the memory left over from deleted rows and rebuilds the indexes from the
+    /// remaining rows. The rows themselves are unchanged.
+    pub fn vacuum(&mut self) {
+        self.rows.shrink_to_fit();
+        self.rebuild_index();
+    }
+
+    /// Adds rows taken from another table with the same columns, resolving primary key
+    /// collisions by `on_conflict` (see `Database::merge`). An overwritten row is replaced
+    /// where it stands. Returns how many rows were added or replaced.
+    pub fn merge_rows(
+        &mut self,
+        rows: Vec<Row>,
+        on_conflict: MergePolicy,
+    ) -> Result<usize, SqlError> {
+        let mut added = 0;
+        for row in rows {
+            let values = row.get_values().clone();
+            let existing = self.pk_key(&values).and_then(|key| self.pk_index.get(&key).copied());
+            if let Some(pos) = existing {
+                match on_conflict {
+                    MergePolicy::Skip => continue,
+                    MergePolicy::Error => {
+                        return Err(SqlError::PrimaryKeyViolation(self.primary_key.join(", ")));
+                    }
+                    MergePolicy::Overwrite => {
+                        let row = self.check_row(values, Some(pos))?;
+                        self.replace_checked(pos, row);
                     }
                 }
+            } else {
+                self.add_values(values)?;
             }
+            added += 1;
         }
-        // Check unique constraints
-        for uniq_col in &self.unique_columns {
-            if let Some(uniq_idx) = self.schema.columns.iter().position(|c| &c.name == uniq_col) {
-                let uniq_val = values.get(uniq_idx);
-                if uniq_val.is_none() {
+        Ok(added)
+    }
+
+    /// Indexes every row by its value in `column`. NULLs are left out, as they never
+    /// compare equal.
+    fn build_column_index(&mut self, column: &str) {
+        let Some(idx) = self.schema.columns.iter().position(|c| c.name == column) else {
+            return;
+        };
+        let mut data: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, row) in self.rows.iter().enumerate() {
+            if let Some(v) = row.get_values().get(idx).filter(|v| !v.is_null()) {
+                data.entry(v.to_string()).or_default().push(i);
+            }
+        }
+        self.index_data.insert(column.to_string(), data);
+    }
+
+    /// Creates a secondary index named `name` on `column`.
+    pub fn create_index(&mut self, name: &str, column: &str) -> Result<(), SqlError> {
+        if !self.schema.columns.iter().any(|c| c.name == column) {
+            return Err(SqlError::ColumnNotFound(column.to_string()));
+        }
+        if self.indexes.contains_key(name) {
+            return Err(SqlError::Parse(format!("Index '{}' already exists", name)));
+        }
+        self.indexes.insert(name.to_string(), column.to_string());
+        self.build_column_index(column);
+        Ok(())
+    }
+
+    /// Removes the secondary index named `name`, returning whether it existed.
+    pub fn drop_index(&mut self, name: &str) -> bool {
+        let Some(column) = self.indexes.remove(name) else {
+            return false;
+        };
+        // Another index may cover the same column
+        if !self.indexes.values().any(|c| *c == column) {
+            self.index_data.remove(&column);
+        }
+        true
+    }
+
+    /// Returns the rows whose `column` displays as `value`, in table order, or `None` if
+    /// the column has no secondary index.
+    pub fn lookup_index(&self, column: &str, value: &str) -> Option<Vec<&Row>> {
+        let data = self.index_data.get(column)?;
+        let positions = data.get(value).map(Vec::as_slice).unwrap_or_default();
+        Some(positions.iter().filter_map(|&i| self.rows.get(i)).collect())
+    }
+
+    /// Looks up a row by its primary key value in constant time.
+    /// For a composite key, pass the components joined with `PK_SEPARATOR`.
+    pub fn find_by_pk(&self, pk: &str) -> Option<&Row> {
+        self.pk_index.get(pk).and_then(|&i| self.rows.get(i))
+    }
+
+    /// Fills NULL AUTOINCREMENT values with the next id and returns the updated counters.
+    /// Explicit ids advance the counter, so later generated ids never collide with them.
+    fn assign_auto_increment(&self, values: &mut [Value]) -> HashMap<String, i64> {
+        let mut counters = self.auto_increment.clone();
+        for (col, last) in counters.iter_mut() {
+            let Some(idx) = self.schema.columns.iter().position(|c| &c.name == col) else {
+                continue;
+            };
+            match values[idx].as_int() {
+                Some(id) => *last = (*last).max(id),
+                None if values[idx].is_null() => {
+                    *last += 1;
+                    values[idx] = Value::Int(*last);
+                }
+                None => {}
+            }
+        }
+        counters
+    }
+
+    /// Removes every row, keeping the schema, constraints and index definitions, and resets
+    /// AUTOINCREMENT counters so the next generated id is 1 again. Returns the number of rows
+    /// removed.
+    pub fn truncate(&mut self) -> usize {
+        let removed = self.rows.len();
+        self.rows.clear();
+        for last in self.auto_increment.values_mut() {
+            *last = 0;
+        }
+        self.rebuild_index();
+        removed
+    }
+
+    /// Fills in defaults and AUTOINCREMENT ids for a new row of typed values and checks it
+    /// against the NOT NULL, primary key, unique and CHECK constraints, without adding it.
+    pub(crate) fn check_new_row(&self, values: Vec<Value>) -> Result<CheckedRow, SqlError> {
+        self.check_row(values, None)
+    }
+
+    /// Like `check_new_row`, but for a row taking the place of the one at `replacing`, whose
+    /// primary key and unique values it may repeat.
+    fn check_row(
+        &self,
+        mut values: Vec<Value>,
+        replacing: Option<usize>,
+    ) -> Result<CheckedRow, SqlError> {
+        if values.len() > self.schema.columns.len() {
+            return Err(SqlError::ColumnCountMismatch {
+                expected: self.schema.columns.len(),
+                got: values.len(),
+            });
+        }
+        // Absent values take the column default, or NULL
+        for col in self.schema.columns.iter().skip(values.len()) {
+            values.push(col.default.clone().unwrap_or(Value::Null));
+        }
+        let counters = self.assign_auto_increment(&mut values);
+        // Check NOT NULL constraints
+        for (col, val) in self.schema.columns.iter().zip(&values) {
+            if !col.nullable && val.is_null() {
+                return Err(SqlError::NotNullViolation(col.name.clone()));
+            }
+        }
+        // Check primary key constraint; a composite key compares the whole tuple
+        let pk_key = self.pk_key(&values);
+        if !self.column_indices(&self.primary_key).is_empty() {
+            match &pk_key {
+                Some(key) if self.pk_index.get(key).is_none_or(|&pos| Some(pos) == replacing) => {}
+                _ => return Err(SqlError::PrimaryKeyViolation(self.primary_key.join(", "))),
+            }
+        }
+        // Check unique constraints; a multi-column constraint compares the whole tuple
+        for uniq_cols in &self.unique_columns {
+            let uniq_indices = self.column_indices(uniq_cols);
+            // NULLs never conflict with each other
+            if uniq_indices.is_empty() || uniq_indices.iter().any(|&i| values[i].is_null()) {
+                continue;
+            }
+            for (pos, row) in self.rows.iter().enumerate() {
+                if Some(pos) == replacing {
                     continue;
                 }
-                let uniq_val = uniq_val.unwrap();
-                for row in &self.rows {
-                    if let Some(existing_val) = row.get_values().get(uniq_idx) {
-                        if existing_val == uniq_val {
-                            println!(
-                                "Unique constraint violation: '{}' must be unique!",
-                                uniq_col
-                            );
-                            return;
-                        }
-                    }
+                let existing = row.get_values();
+                if uniq_indices.iter().all(|&i| existing.get(i) == Some(&values[i])) {
+                    return Err(SqlError::UniqueViolation(uniq_cols.join(", ")));
                 }
             }
         }
-        let row = Row::new(values);
-        self.rows.push(row);
+        if let Some(check) = self.failed_check(&values) {
+            return Err(SqlError::CheckViolation(check.clone()));
+        }
+        Ok(CheckedRow {
+            values,
+            key: pk_key,
+            counters,
+        })
     }
 
-    /// Updates all rows matching the predicate with new values, enforcing primary key and unique constraints.
-    fn update_rows<F>(&mut self, set_values: Vec<String>, predicate: F)
-    where
-        F: Fn(&Vec<String>) -> bool,
-    {
-        // Type checking for non-empty update values
-        for (i, val) in set_values.iter().enumerate() {
-            if !val.is_empty() {
-                // Only check non-empty values
-                if let Some(col) = self.schema.columns.get(i) {
-                    let valid = match col.col_type {
-                        ColumnType::Int => val.parse::<i64>().is_ok(),
-                        ColumnType::Float => val.parse::<f64>().is_ok(),
-                        ColumnType::String => true,
-                    };
-                    if !valid {
-                        println!(
-                            "Type error: value '{}' does not match column '{}' type {:?}",
-                            val, col.name, col.col_type
-                        );
-                        return;
-                    }
+    /// Adds a row returned by `check_new_row`, updating the indexes.
+    pub(crate) fn push_checked(&mut self, row: CheckedRow) {
+        self.rows.push(Row::new(row.values));
+        let pos = self.rows.len() - 1;
+        if let Some(key) = row.key {
+            self.pk_index.insert(key, pos);
+        }
+        for (column, data) in self.index_data.iter_mut() {
+            if let Some(idx) = self.schema.columns.iter().position(|c| &c.name == column)
+                && let Some(v) = self.rows[pos].get_values().get(idx).filter(|v| !v.is_null())
+            {
+                data.entry(v.to_string()).or_default().push(pos);
+            }
+        }
+        self.auto_increment = row.counters;
+    }
+
+    /// Replaces the row at `pos` with one returned by `check_row` for that position, which
+    /// has the same primary key, moving only that row's secondary index entries.
+    fn replace_checked(&mut self, pos: usize, row: CheckedRow) {
+        let old = std::mem::replace(&mut self.rows[pos], Row::new(row.values));
+        for (column, data) in self.index_data.iter_mut() {
+            let Some(idx) = self.schema.columns.iter().position(|c| &c.name == column) else {
+                continue;
+            };
+            let key = |row: &Row| {
+                row.get_values().get(idx).filter(|v| !v.is_null()).map(|v| v.to_string())
+            };
+            if let Some(key) = key(&old)
+                && let Some(positions) = data.get_mut(&key)
+            {
+                positions.retain(|&i| i != pos);
+                if positions.is_empty() {
+                    data.remove(&key);
                 }
             }
+            if let Some(key) = key(&self.rows[pos]) {
+                // Positions stay in table order
+                let positions = data.entry(key).or_default();
+                let at = positions.partition_point(|&i| i < pos);
+                positions.insert(at, pos);
+            }
         }
+        self.auto_increment = row.counters;
+    }
+
+    /// Computes the rows an update would change and checks the result against the primary
+    /// key, unique and CHECK constraints, without changing the table. `set_values` has one
+    /// entry per column: `None` leaves it unchanged.
+    pub(crate) fn check_update<F>(
+        &self,
+        set_values: Vec<Option<Assignment>>,
+        predicate: F,
+    ) -> Result<CheckedUpdate, SqlError>
+    where
+        F: Fn(&Vec<Value>) -> bool,
+    {
+        if set_values.len() != self.schema.columns.len() {
+            return Err(SqlError::ColumnCountMismatch {
+                expected: self.schema.columns.len(),
+                got: set_values.len(),
+            });
+        }
+        // Type check and convert the assigned values; `None` means "leave unchanged"
+        let set_values: Vec<Option<NewValue>> = set_values
+            .iter()
+            .enumerate()
+            .map(|(i, val)| match (self.schema.columns.get(i), val) {
+                (Some(col), Some(val)) => self.new_value(col, val).map(Some),
+                _ => Ok(None),
+            })
+            .collect::<Result<_, _>>()?;
 
         // Collect rows to update and create simulated state
         let to_update: Vec<usize> = self
@@ -213,81 +631,373 @@ impl TableInterface for Table {
 
         let mut simulated = self.rows.clone();
 
-        // Create updated rows by merging existing values with set_values
+        // Create updated rows by merging existing values with set_values. Expressions see
+        // the row as it was before the update.
         for &row_idx in &to_update {
-            let mut new_values = simulated[row_idx].get_values().clone();
+            let old_values = self.rows[row_idx].get_values();
+            let mut new_values = old_values.clone();
             for (i, val) in set_values.iter().enumerate() {
-                if i < new_values.len() && !val.is_empty() {
-                    new_values[i] = val.clone();
+                if i < new_values.len()
+                    && let Some(val) = val
+                {
+                    new_values[i] = self.computed_value(&self.schema.columns[i], val, old_values)?;
                 }
             }
             simulated[row_idx].set_values(new_values);
         }
 
-        // Check constraints on simulated state
-        let pk_idx = self
-            .primary_key
-            .as_ref()
-            .and_then(|pk| self.schema.columns.iter().position(|c| &c.name == pk));
-
-        // Check primary key constraint
-        if let Some(idx) = pk_idx {
+        // Check primary key constraint on simulated state
+        let pk_indices = self.column_indices(&self.primary_key);
+        if !pk_indices.is_empty() {
             let mut seen = std::collections::HashSet::new();
             for row in &simulated {
-                if let Some(val) = row.get_values().get(idx) {
-                    if !seen.insert(val) {
-                        println!(
-                            "Primary key constraint violation on update: '{}' must be unique!",
-                            self.schema.columns[idx].name
-                        );
-                        return;
-                    }
+                let key: Vec<String> = pk_indices
+                    .iter()
+                    .filter_map(|&i| row.get_values().get(i).map(|v| v.to_string()))
+                    .collect();
+                if !seen.insert(key) {
+                    return Err(SqlError::PrimaryKeyViolation(self.primary_key.join(", ")));
                 }
             }
         }
 
         // Check unique constraints
-        for uniq_col in &self.unique_columns {
-            if let Some(uniq_idx) = self.schema.columns.iter().position(|c| &c.name == uniq_col) {
-                let mut seen = std::collections::HashSet::new();
-                for row in &simulated {
-                    if let Some(val) = row.get_values().get(uniq_idx) {
-                        if !seen.insert(val) {
-                            println!(
-                                "Unique constraint violation on update: '{}' must be unique!",
-                                uniq_col
-                            );
-                            return;
-                        }
-                    }
+        for uniq_cols in &self.unique_columns {
+            let uniq_indices = self.column_indices(uniq_cols);
+            if uniq_indices.is_empty() {
+                continue;
+            }
+            let mut seen = std::collections::HashSet::new();
+            for row in &simulated {
+                let values = row.get_values();
+                // Tuples containing a NULL never conflict
+                if uniq_indices.iter().any(|&i| values.get(i).is_none_or(|v| v.is_null())) {
+                    continue;
+                }
+                let key: Vec<String> =
+                    uniq_indices.iter().map(|&i| values[i].to_string()).collect();
+                if !seen.insert(key) {
+                    return Err(SqlError::UniqueViolation(uniq_cols.join(", ")));
                 }
             }
         }
 
-        // All checks passed, apply updates
+        // Check CHECK constraints on the updated rows
         for &row_idx in &to_update {
-            let mut new_values = self.rows[row_idx].get_values().clone();
-            for (i, val) in set_values.iter().enumerate() {
-                if i < new_values.len() && !val.is_empty() {
-                    new_values[i] = val.clone();
+            if let Some(check) = self.failed_check(simulated[row_idx].get_values()) {
+                return Err(SqlError::CheckViolation(check.clone()));
+            }
+        }
+
+        Ok(CheckedUpdate {
+            positions: to_update,
+            rows: simulated,
+        })
+    }
+
+    /// Makes an update returned by `check_update`, returning the number of rows changed.
+    pub(crate) fn apply_update(&mut self, update: CheckedUpdate) -> usize {
+        let CheckedUpdate { positions: to_update, rows } = update;
+        self.rows = rows;
+        self.rebuild_index();
+        // Ids set by hand must not be handed out again
+        for (col, last) in self.auto_increment.iter_mut() {
+            if let Some(idx) = self.schema.columns.iter().position(|c| &c.name == col) {
+                for &row_idx in &to_update {
+                    if let Some(id) = self.rows[row_idx].get_values()[idx].as_int() {
+                        *last = (*last).max(id);
+                    }
                 }
             }
-            self.rows[row_idx].set_values(new_values);
+        }
+        to_update.len()
+    }
+
+    /// Turns `column = value` assignments into the per-column form `check_update` takes.
+    pub(crate) fn named_set_values(
+        &self,
+        assignments: &HashMap<String, Assignment>,
+    ) -> Result<Vec<Option<Assignment>>, SqlError> {
+        let mut unknown: Vec<&str> = assignments
+            .keys()
+            .filter(|name| !self.schema.columns.iter().any(|c| &c.name == *name))
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            unknown.sort_unstable();
+            return Err(SqlError::ColumnNotFound(unknown.join(", ")));
+        }
+        Ok(self
+            .schema
+            .columns
+            .iter()
+            .map(|col| assignments.get(&col.name).cloned())
+            .collect())
+    }
+
+    /// Checks that the table is consistent with its schema: every row has one value per
+    /// column, and the primary key, unique constraints, AUTOINCREMENT counters and indexes
+    /// only name existing columns. Used to reject hand-edited or corrupt files on load.
+    pub fn validate(&self) -> Result<(), String> {
+        let expected = self.schema.columns.len();
+        for (i, row) in self.rows.iter().enumerate() {
+            let got = row.get_values().len();
+            if got != expected {
+                return Err(format!("row {} has {} values but {} columns", i, got, expected));
+            }
+        }
+        for check in &self.checks {
+            let _ = try_query_to_predicate(&self.schema.columns, check)
+                .map_err(|e| format!("CHECK ({}): {}", check, e))?;
+        }
+        let has_column = |name: &String| self.schema.columns.iter().any(|c| &c.name == name);
+        let constrained = self
+            .primary_key
+            .iter()
+            .map(|col| ("primary key", col))
+            .chain(self.unique_columns.iter().flatten().map(|col| ("unique", col)))
+            .chain(self.auto_increment.keys().map(|col| ("AUTOINCREMENT", col)))
+            .chain(self.indexes.values().map(|col| ("index", col)))
+            .chain(self.foreign_keys.iter().map(|fk| ("foreign key", &fk.column)));
+        for (kind, col) in constrained {
+            if !has_column(col) {
+                return Err(format!("{} column '{}' does not exist", kind, col));
+            }
+        }
+        Ok(())
+    }
+
+    /// Raises each AUTOINCREMENT counter to at least the highest id in the table, so a file
+    /// whose counters are missing or stale never hands out an id that is already used.
+    pub fn restore_auto_increment(&mut self) {
+        for (col, last) in self.auto_increment.iter_mut() {
+            if let Some(idx) = self.schema.columns.iter().position(|c| &c.name == col) {
+                let highest = self.rows.iter().filter_map(|r| r.get_values()[idx].as_int()).max();
+                *last = (*last).max(highest.unwrap_or(0));
+            }
         }
     }
 
-    /// Deletes all rows matching the predicate.
-    fn delete_rows<F>(&mut self, predicate: F)
+    /// Returns the first CHECK condition the values do not satisfy. As in SQL, a condition
+    /// that mentions a NULL column is unknown rather than false, so it does not fail.
+    fn failed_check(&self, values: &Vec<Value>) -> Option<&String> {
+        self.checks.iter().find(|check| {
+            let mentions_null = check_columns(&self.schema.columns, check)
+                .iter()
+                .any(|&i| values.get(i).is_none_or(|v| v.is_null()));
+            !mentions_null && !query_to_predicate(&self.schema.columns, check)(values)
+        })
+    }
+
+    /// Interprets a value assigned to `col` by an update (see `Assignment`).
+    fn new_value(&self, col: &ColumnSchema, assignment: &Assignment) -> Result<NewValue, SqlError> {
+        let text = match assignment {
+            Assignment::Literal(None) if !col.nullable => {
+                return Err(SqlError::NotNullViolation(col.name.clone()));
+            }
+            Assignment::Literal(None) => return Ok(NewValue::Fixed(Value::Null)),
+            Assignment::Literal(Some(text)) => {
+                return Value::from_column(col, text).map(NewValue::Fixed);
+            }
+            Assignment::Expr(text) => text,
+        };
+        if let Some(expr) = Arithmetic::parse(text) {
+            if let Some(unknown) =
+                expr.columns().find(|name| !self.schema.columns.iter().any(|c| &c.name == *name))
+            {
+                return Err(SqlError::ColumnNotFound(unknown.clone()));
+            }
+            return Ok(NewValue::Computed(expr));
+        }
+        let name = unquote_identifier(text);
+        match self.schema.columns.iter().position(|c| c.name == name) {
+            Some(idx) => Ok(NewValue::Column(idx)),
+            None => Value::from_column(col, text).map(NewValue::Fixed),
+        }
+    }
+
+    /// Reads the per-column text `update_rows` takes: a literal of the column's type, or
+    /// else an expression.
+    pub(crate) fn positional_assignments(
+        &self,
+        set_values: Vec<Option<String>>,
+    ) -> Vec<Option<Assignment>> {
+        set_values
+            .into_iter()
+            .zip(self.schema.columns.iter().map(Some).chain(std::iter::repeat(None)))
+            .map(|(text, col)| {
+                let text = text?;
+                Some(match col.map(|col| Value::from_column(col, &text)) {
+                    Some(Ok(_)) => Assignment::Literal(Some(text)),
+                    _ => Assignment::Expr(text),
+                })
+            })
+            .collect()
+    }
+
+    /// Computes a value assigned to `col` for a row, type checking a computed or copied
+    /// result against the column.
+    fn computed_value(
+        &self,
+        col: &ColumnSchema,
+        new_value: &NewValue,
+        row: &[Value],
+    ) -> Result<Value, SqlError> {
+        let value = match new_value {
+            NewValue::Fixed(value) => return Ok(value.clone()),
+            NewValue::Computed(expr) => expr.evaluate(&self.schema.columns, row)?,
+            NewValue::Column(idx) => row.get(*idx).cloned().unwrap_or(Value::Null),
+        };
+        if value.is_null() {
+            if !col.nullable {
+                return Err(SqlError::NotNullViolation(col.name.clone()));
+            }
+            return Ok(Value::Null);
+        }
+        Value::from_column(col, &value.to_string())
+    }
+
+    /// Positions of the named columns in the schema, in the given order.
+    fn column_indices(&self, names: &[String]) -> Vec<usize> {
+        names
+            .iter()
+            .filter_map(|name| self.schema.columns.iter().position(|c| &c.name == name))
+            .collect()
+    }
+
+    /// Appends a column to the schema, filling it with `default` in every existing row.
+    /// An empty default, or one that does not fit the column's type, is stored as NULL.
+    pub fn add_column(&mut self, col: ColumnSchema, default: String) {
+        let default = match default.as_str() {
+            "" => Value::Null,
+            raw => Value::parse(raw, &col.col_type).unwrap_or(Value::Null),
+        };
+        self.schema.columns.push(col);
+        for row in &mut self.rows {
+            let mut values = row.get_values().clone();
+            values.push(default.clone());
+            row.set_values(values);
+        }
+    }
+
+    /// Converts every stored value to its column's type. Databases saved before values
+    /// were typed hold plain strings, which this migrates after loading.
+    pub fn coerce_values(&mut self) {
+        for row in &mut self.rows {
+            let values = row
+                .get_values()
+                .iter()
+                .enumerate()
+                .map(|(i, v)| match self.schema.columns.get(i) {
+                    Some(col) => v.clone().coerce(&col.col_type),
+                    None => v.clone(),
+                })
+                .collect();
+            row.set_values(values);
+        }
+    }
+}
+
+/// Positions of the columns named in a CHECK condition, ignoring quoted literals.
+fn check_columns(columns: &[ColumnSchema], check: &str) -> Vec<usize> {
+    let mut unquoted = String::new();
+    let mut quote = None;
+    for c in check.chars() {
+        match c {
+            '\'' | '"' if quote == Some(c) => quote = None,
+            '\'' | '"' if quote.is_none() => quote = Some(c),
+            _ if quote.is_none() => unquoted.push(c),
+            _ => {}
+        }
+    }
+    let words: Vec<&str> = unquoted
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+        .collect();
+    (0..columns.len())
+        .filter(|&i| words.contains(&columns[i].name.as_str()))
+        .collect()
+}
+
+/// Converts raw values to the types of the corresponding columns, which must be one value
+/// per column.
+pub(crate) fn typed_values(
+    columns: &[ColumnSchema],
+    values: Vec<String>,
+) -> Result<Vec<Value>, SqlError> {
+    if values.len() != columns.len() {
+        return Err(SqlError::ColumnCountMismatch {
+            expected: columns.len(),
+            got: values.len(),
+        });
+    }
+    columns
+        .iter()
+        .zip(values)
+        .map(|(col, val)| Value::from_column(col, &val))
+        .collect()
+}
+
+/// Implements the TableInterface trait for the Table struct.
+impl TableInterface for Table {
+    /// Adds a new row to the table with the given values, enforcing primary key and unique constraints.
+    fn add_row(&mut self, values: Vec<String>) -> Result<(), SqlError> {
+        // Type checking happens while converting to typed values
+        let values = typed_values(&self.schema.columns, values)?;
+        self.add_values(values)
+    }
+
+    /// Adds a new row of typed values, enforcing NOT NULL, primary key and unique constraints.
+    fn add_values(&mut self, values: Vec<Value>) -> Result<(), SqlError> {
+        let row = self.check_new_row(values)?;
+        self.push_checked(row);
+        Ok(())
+    }
+
+    /// Updates all rows matching the predicate with new values, enforcing primary key and unique constraints.
+    fn update_rows<F>(
+        &mut self,
+        set_values: Vec<Option<String>>,
+        predicate: F,
+    ) -> Result<usize, SqlError>
     where
-        F: Fn(&Vec<String>) -> bool,
+        F: Fn(&Vec<Value>) -> bool,
     {
+        let set_values = self.positional_assignments(set_values);
+        let update = self.check_update(set_values, predicate)?;
+        Ok(self.apply_update(update))
+    }
+
+    /// Updates all rows matching the predicate with new values keyed by column name.
+    fn update_rows_named<F>(
+        &mut self,
+        assignments: &HashMap<String, Assignment>,
+        predicate: F,
+    ) -> Result<usize, SqlError>
+    where
+        F: Fn(&Vec<Value>) -> bool,
+    {
+        let set_values = self.named_set_values(assignments)?;
+        let update = self.check_update(set_values, predicate)?;
+        Ok(self.apply_update(update))
+    }
+
+    fn delete_rows<F>(&mut self, predicate: F) -> usize
+    where
+        F: Fn(&Vec<Value>) -> bool,
+    {
+        let before = self.rows.len();
         self.rows.retain(|row| !predicate(row.get_values()));
+        // Removing rows shifts the positions of the ones after them
+        if self.rows.len() != before {
+            self.rebuild_index();
+        }
+        before - self.rows.len()
     }
 
     /// Selects and returns all rows matching the predicate.
-    fn select_rows<F>(&self, predicate: F) -> Vec<Vec<String>>
+    fn select_rows<F>(&self, predicate: F) -> Vec<Vec<Value>>
     where
-        F: Fn(&Vec<String>) -> bool,
+        F: Fn(&Vec<Value>) -> bool,
     {
         self.rows
             .iter()
@@ -0,0 +1,238 @@
+use crate::error::SqlError;
+use crate::schema::{ColumnSchema, ColumnType};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+// value.rs
+
+/// A single typed value stored in a row.
+/// Serialized as a plain JSON number, string or null.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bool(bool),
+    Null,
+}
+
+impl Value {
+    /// Interprets raw text as a value of the given column type, or `None` if it does not fit.
+    pub fn parse(raw: &str, col_type: &ColumnType) -> Option<Value> {
+        match col_type {
+            ColumnType::Int => raw.parse().ok().map(Value::Int),
+            ColumnType::Float => raw.parse().ok().map(Value::Float),
+            ColumnType::String => Some(Value::Str(raw.to_string())),
+            ColumnType::Bool => match raw.to_ascii_lowercase().as_str() {
+                "true" | "1" => Some(Value::Bool(true)),
+                "false" | "0" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            ColumnType::Date => date_key(raw).map(|_| Value::Str(raw.to_string())),
+        }
+    }
+
+    /// Builds the value for a column from raw text, reporting a type error if it does not fit.
+    pub fn from_column(col: &ColumnSchema, raw: &str) -> Result<Value, SqlError> {
+        Value::parse(raw, &col.col_type).ok_or_else(|| SqlError::TypeMismatch {
+            column: col.name.clone(),
+            value: raw.to_string(),
+            expected: col.col_type.clone(),
+        })
+    }
+
+    /// Converts a stored value to the column's type. Used to migrate rows saved as plain
+    /// strings; text that does not fit a numeric column becomes `Null`.
+    pub fn coerce(self, col_type: &ColumnType) -> Value {
+        match (self, col_type) {
+            (
+                Value::Str(s),
+                ColumnType::Int | ColumnType::Float | ColumnType::Bool | ColumnType::Date,
+            ) => {
+                Value::parse(&s, col_type).unwrap_or(Value::Null)
+            }
+            (Value::Int(n @ (0 | 1)), ColumnType::Bool) => Value::Bool(n == 1),
+            (Value::Int(n), ColumnType::Float) => Value::Float(n as f64),
+            (Value::Float(n), ColumnType::Int) if n.fract() == 0.0 => Value::Int(n as i64),
+            (v @ (Value::Int(_) | Value::Float(_) | Value::Bool(_)), ColumnType::String) => {
+                Value::Str(v.to_string())
+            }
+            (v, _) => v,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// Returns the integer if this is an `Int`.
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Returns the number as `f64` if this is an `Int` or `Float`.
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Int(n) => Some(*n as f64),
+            Value::Float(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Returns the text if this is a `Str`.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Total ordering used for sorting: `Null` first, then booleans (false before true),
+    /// then numbers (compared numerically), then strings (compared lexicographically).
+    pub fn compare(&self, other: &Value) -> Ordering {
+        fn rank(v: &Value) -> u8 {
+            match v {
+                Value::Null => 0,
+                Value::Bool(_) => 1,
+                Value::Int(_) | Value::Float(_) => 2,
+                Value::Str(_) => 3,
+            }
+        }
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => a.cmp(b),
+            (Value::Str(a), Value::Str(b)) => a.cmp(b),
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (a, b) if rank(a) == 2 && rank(b) == 2 => a
+                .as_float()
+                .partial_cmp(&b.as_float())
+                .unwrap_or(Ordering::Equal),
+            (a, b) => rank(a).cmp(&rank(b)),
+        }
+    }
+}
+
+/// Seconds since 1970-01-01T00:00:00 for an ISO-8601 date (`YYYY-MM-DD`) with an optional
+/// time (`THH:MM` or `THH:MM:SS`, a space may replace the `T`, and a trailing `Z` is
+/// allowed). Returns `None` if the text is not a valid date, so dates compare
+/// chronologically through their keys.
+pub fn date_key(raw: &str) -> Option<i64> {
+    let raw = raw.trim();
+    let (date, time) = match raw.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time.strip_suffix('Z').unwrap_or(time))),
+        None => (raw, None),
+    };
+    let number = |part: &str, len: usize| -> Option<i64> {
+        if part.len() == len && part.bytes().all(|b| b.is_ascii_digit()) {
+            part.parse().ok()
+        } else {
+            None
+        }
+    };
+    let mut fields = date.split('-');
+    let year = number(fields.next()?, 4)?;
+    let month = number(fields.next()?, 2)?;
+    let day = number(fields.next()?, 2)?;
+    if fields.next().is_some() || !(1..=12).contains(&month) {
+        return None;
+    }
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    if !(1..=days_in_month).contains(&day) {
+        return None;
+    }
+    let mut seconds = 0;
+    if let Some(time) = time {
+        let mut fields = time.split(':');
+        let hour = number(fields.next()?, 2)?;
+        let minute = number(fields.next()?, 2)?;
+        let second = fields.next().map_or(Some(0), |s| number(s, 2))?;
+        if fields.next().is_some() || hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+        seconds = hour * 3600 + minute * 60 + second;
+    }
+    // Days from the civil calendar (Howard Hinnant's algorithm), March-based years
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    Some(days * 86400 + seconds)
+}
+
+/// Formats the value as it is shown to users; `Null` is empty.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(n) => write!(f, "{}", n),
+            // Debug keeps the fractional part, so 2.0 is shown as "2.0" rather than "2"
+            Value::Float(n) => write!(f, "{:?}", n),
+            Value::Str(s) => write!(f, "{}", s),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Null => Ok(()),
+        }
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Value::Int(n)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Float(n)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::Str(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::Str(s)
+    }
+}
+
+/// Compares against the displayed form, so `Value::Int(1) == "1"`.
+impl PartialEq<str> for Value {
+    fn eq(&self, other: &str) -> bool {
+        match self {
+            Value::Str(s) => s == other,
+            v => v.to_string().as_str() == other,
+        }
+    }
+}
+
+impl PartialEq<&str> for Value {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<String> for Value {
+    fn eq(&self, other: &String) -> bool {
+        self == other.as_str()
+    }
+}
@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+// wal.rs
+// Append-only write-ahead log of mutating statements, one JSON entry per line.
+
+/// A logged statement, recorded before it is applied.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WalEntry {
+    pub timestamp: u64,
+    pub query: String,
+}
+
+impl WalEntry {
+    pub fn new(query: &str) -> Self {
+        Self {
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            query: query.to_string(),
+        }
+    }
+}
+
+/// Appends an entry to the log at `path` and flushes it to disk.
+pub fn append(path: &str, entry: &WalEntry) -> std::io::Result<()> {
+    let line = serde_json::to_string(entry)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(format!("{}\n", line).as_bytes())?;
+    file.sync_data()
+}
+
+/// Reads all entries from the log at `path`; a missing log is empty.
+/// A final line cut short by a crash is ignored.
+pub fn read_entries(path: &str) -> std::io::Result<Vec<WalEntry>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    let lines: Vec<String> = BufReader::new(file).lines().collect::<Result<_, _>>()?;
+    let mut entries = vec![];
+    for (i, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(entry) => entries.push(entry),
+            Err(_) if i + 1 == lines.len() => break,
+            Err(e) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Corrupt WAL entry on line {}: {}", i + 1, e),
+                ));
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Empties the log at `path`.
+pub fn truncate(path: &str) -> std::io::Result<()> {
+    File::create(path)?.sync_all()
+}
@@ -4,47 +4,62 @@ use lab::*;
 fn sql_parser_edge_cases() {
     let mut db = Database::new();
     
-    // Invalid SQL operations should be ignored
-    execute_sql(&mut db, "INVALID SQL");
+    // Invalid SQL operations should be rejected
+    assert!(matches!(
+        execute_sql(&mut db, "INVALID SQL"),
+        Err(SqlError::Unsupported(_))
+    ));
     assert_eq!(db.tables.len(), 0, "Invalid SQL should not create tables");
     
-    // CREATE without proper syntax should be ignored
-    execute_sql(&mut db, "CREATE TABLE");
+    // CREATE without proper syntax should be rejected
+    assert!(matches!(
+        execute_sql(&mut db, "CREATE TABLE"),
+        Err(SqlError::Parse(_))
+    ));
     assert_eq!(db.tables.len(), 0, "CREATE TABLE without name/columns should not create a table");
     
     // Valid table for testing malformed queries
-    execute_sql(&mut db, "CREATE TABLE Users(id INT PRIMARY KEY, name STRING);");
+    execute_sql(&mut db, "CREATE TABLE Users(id INT PRIMARY KEY, name STRING);").unwrap();
     assert!(db.tables.contains_key("Users"), "Valid CREATE TABLE should work");
     
-    // Malformed SELECT - these should not panic
-    execute_sql(&mut db, "SELECT"); // Missing everything
-    execute_sql(&mut db, "SELECT FROM"); // Missing columns and table
-    execute_sql(&mut db, "SELECT * FROM"); // Missing table
-    execute_sql(&mut db, "SELECT * FROM NonexistentTable"); // Invalid table
+    // Malformed SELECT - these should error, not panic
+    assert!(execute_sql(&mut db, "SELECT").is_err()); // Missing everything
+    assert!(execute_sql(&mut db, "SELECT FROM").is_err()); // Missing columns and table
+    assert!(execute_sql(&mut db, "SELECT * FROM").is_err()); // Missing table
+    assert!(execute_sql(&mut db, "SELECT * FROM NonexistentTable").is_err()); // Invalid table
     
     // Malformed INSERT - these should not add rows
-    execute_sql(&mut db, "INSERT INTO;"); // Missing everything
-    execute_sql(&mut db, "INSERT INTO Users;"); // Missing VALUES
-    execute_sql(&mut db, "INSERT INTO Users VALUES;"); // Missing values
-    execute_sql(&mut db, "INSERT INTO NonexistentTable VALUES (1);"); // Invalid table
-    assert_eq!(db.tables.get("Users").unwrap().rows.len(), 0);
+    assert!(execute_sql(&mut db, "INSERT INTO;").is_err()); // Missing everything
+    assert!(execute_sql(&mut db, "INSERT INTO Users;").is_err()); // Missing VALUES
+    assert!(execute_sql(&mut db, "INSERT INTO Users VALUES;").is_err()); // Missing values
+    assert_eq!(
+        execute_sql(&mut db, "INSERT INTO NonexistentTable VALUES (1);"),
+        Err(SqlError::TableNotFound("NonexistentTable".to_string()))
+    ); // Invalid table
+    assert_eq!(db.row_count("Users").unwrap(), 0);
     
     // Type mismatches in INSERT
-    execute_sql(&mut db, "INSERT INTO Users VALUES (notanint, 'name');"); // Bad int
-    assert_eq!(db.tables.get("Users").unwrap().rows.len(), 0);
+    assert!(matches!(
+        execute_sql(&mut db, "INSERT INTO Users VALUES (notanint, 'name');"),
+        Err(SqlError::TypeMismatch { .. })
+    )); // Bad int
+    assert_eq!(db.row_count("Users").unwrap(), 0);
     
     // Valid insert for update/delete tests
-    execute_sql(&mut db, "INSERT INTO Users VALUES (1, 'Alice');");
-    assert_eq!(db.tables.get("Users").unwrap().rows.len(), 1);
+    execute_sql(&mut db, "INSERT INTO Users VALUES (1, 'Alice');").unwrap();
+    assert_eq!(db.row_count("Users").unwrap(), 1);
     
     // Malformed UPDATE - these should not modify rows
-    execute_sql(&mut db, "UPDATE;"); // Missing everything
-    execute_sql(&mut db, "UPDATE Users;"); // Missing SET
-    execute_sql(&mut db, "UPDATE Users SET;"); // Missing assignments
-    execute_sql(&mut db, "UPDATE NonexistentTable SET id = 1;"); // Invalid table
+    assert!(execute_sql(&mut db, "UPDATE;").is_err()); // Missing everything
+    assert!(execute_sql(&mut db, "UPDATE Users;").is_err()); // Missing SET
+    assert!(execute_sql(&mut db, "UPDATE Users SET;").is_err()); // Missing assignments
+    assert!(execute_sql(&mut db, "UPDATE NonexistentTable SET id = 1;").is_err()); // Invalid table
     
     // Type mismatches in UPDATE
-    execute_sql(&mut db, "UPDATE Users SET id = notanint WHERE id == 1;"); // Bad int
+    assert!(matches!(
+        execute_sql(&mut db, "UPDATE Users SET id = notanint WHERE id == 1;"),
+        Err(SqlError::TypeMismatch { .. })
+    )); // Bad int
     let first_row = &db.tables.get("Users").unwrap().rows[0];
     assert_eq!(first_row.get_values()[0], "1"); // Value unchanged
 }
@@ -54,23 +69,29 @@ fn constraint_violations() {
     let mut db = Database::new();
     
     // Test UNIQUE constraint on non-PK column
-    execute_sql(&mut db, "CREATE TABLE Emails(id INT PRIMARY KEY, email STRING UNIQUE);");
+    execute_sql(&mut db, "CREATE TABLE Emails(id INT PRIMARY KEY, email STRING UNIQUE);").unwrap();
     
     // First insert succeeds
-    execute_sql(&mut db, "INSERT INTO Emails VALUES (1, 'a@b.com');");
-    let rows = db.tables.get("Emails").unwrap().rows.len();
+    execute_sql(&mut db, "INSERT INTO Emails VALUES (1, 'a@b.com');").unwrap();
+    let rows = db.row_count("Emails").unwrap();
     assert_eq!(rows, 1, "First insert should succeed");
     
     // Duplicate email fails
-    execute_sql(&mut db, "INSERT INTO Emails (id, email) VALUES (2, 'a@b.com');");
-    assert_eq!(db.tables.get("Emails").unwrap().rows.len(), 1, "Duplicate email should be rejected");
+    assert_eq!(
+        execute_sql(&mut db, "INSERT INTO Emails (id, email) VALUES (2, 'a@b.com');"),
+        Err(SqlError::UniqueViolation("email".to_string()))
+    );
+    assert_eq!(db.row_count("Emails").unwrap(), 1, "Duplicate email should be rejected");
     
     // Different email succeeds
-    execute_sql(&mut db, "INSERT INTO Emails VALUES (2, 'c@d.com');");
-    assert_eq!(db.tables.get("Emails").unwrap().rows.len(), 2);
+    execute_sql(&mut db, "INSERT INTO Emails VALUES (2, 'c@d.com');").unwrap();
+    assert_eq!(db.row_count("Emails").unwrap(), 2);
     
     // UPDATE violating UNIQUE constraint
-    execute_sql(&mut db, "UPDATE Emails SET email = 'a@b.com' WHERE id == 2;");
+    assert_eq!(
+        execute_sql(&mut db, "UPDATE Emails SET email = 'a@b.com' WHERE id == 2;"),
+        Err(SqlError::UniqueViolation("email".to_string()))
+    );
     // Should remain unchanged
     assert_eq!(
         db.tables.get("Emails").unwrap().rows[1].get_values()[1],
@@ -81,29 +102,30 @@ fn constraint_violations() {
     execute_sql(
         &mut db,
         "CREATE TABLE Users(id INT PRIMARY KEY, email STRING UNIQUE, username STRING UNIQUE);"
-    );
+    )
+    .unwrap();
     
     // First insert succeeds
-    execute_sql(&mut db, "INSERT INTO Users VALUES (1, 'a@b.com', 'alice')");
-    assert_eq!(db.tables.get("Users").unwrap().rows.len(), 1);
+    execute_sql(&mut db, "INSERT INTO Users VALUES (1, 'a@b.com', 'alice')").unwrap();
+    assert_eq!(db.row_count("Users").unwrap(), 1);
     
     // Duplicate email fails
-    execute_sql(&mut db, "INSERT INTO Users VALUES (2, 'a@b.com', 'bob')");
-    assert_eq!(db.tables.get("Users").unwrap().rows.len(), 1);
+    assert!(execute_sql(&mut db, "INSERT INTO Users VALUES (2, 'a@b.com', 'bob')").is_err());
+    assert_eq!(db.row_count("Users").unwrap(), 1);
     
     // Duplicate username fails
-    execute_sql(&mut db, "INSERT INTO Users VALUES (2, 'c@d.com', 'alice')");
-    assert_eq!(db.tables.get("Users").unwrap().rows.len(), 1);
+    assert!(execute_sql(&mut db, "INSERT INTO Users VALUES (2, 'c@d.com', 'alice')").is_err());
+    assert_eq!(db.row_count("Users").unwrap(), 1);
     
     // Unique values succeed
-    execute_sql(&mut db, "INSERT INTO Users VALUES (2, 'c@d.com', 'bob')");
-    assert_eq!(db.tables.get("Users").unwrap().rows.len(), 2);
+    execute_sql(&mut db, "INSERT INTO Users VALUES (2, 'c@d.com', 'bob')").unwrap();
+    assert_eq!(db.row_count("Users").unwrap(), 2);
 }
 
 #[test]
 fn save_load_edge_cases() {
     let mut db = Database::new();
-    execute_sql(&mut db, "CREATE TABLE Test(id INT PRIMARY KEY, name STRING);");
+    execute_sql(&mut db, "CREATE TABLE Test(id INT PRIMARY KEY, name STRING);").unwrap();
     
     // Save to invalid path should fail gracefully
     assert!(db.save_to_file("/invalid/path/db.json").is_err());
@@ -116,9 +138,10 @@ fn save_load_edge_cases() {
         db.insert(
             "Test",
             vec![i.to_string(), format!("user{}", i)],
-        );
+        )
+        .unwrap();
     }
-    assert_eq!(db.tables.get("Test").unwrap().rows.len(), 1000);
+    assert_eq!(db.row_count("Test").unwrap(), 1000);
     
     // Save and load large dataset
     let tmp = tempfile::NamedTempFile::new().unwrap();
@@ -130,7 +153,7 @@ fn save_load_edge_cases() {
     // Load should restore all data
     let loaded = Database::load_from_file(&path).unwrap();
     assert_eq!(
-        loaded.tables.get("Test").unwrap().rows.len(),
+        loaded.row_count("Test").unwrap(),
         1000
     );
     
@@ -144,4 +167,102 @@ fn save_load_edge_cases() {
     
     // Clean up
     drop(tmp);
-}
\ No newline at end of file
+}
+#[test]
+fn failed_save_keeps_previous_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("db.json").to_string_lossy().to_string();
+    let mut db = Database::new();
+    execute_sql(&mut db, "CREATE TABLE Test (id INT PRIMARY KEY)").unwrap();
+    execute_sql(&mut db, "INSERT INTO Test VALUES (1)").unwrap();
+    db.save_to_file(&path).unwrap();
+    assert!(!std::path::Path::new(&format!("{}.tmp", path)).exists());
+
+    // Block the temporary file so the next save fails before reaching the real file
+    execute_sql(&mut db, "INSERT INTO Test VALUES (2)").unwrap();
+    std::fs::create_dir(format!("{}.tmp", path)).unwrap();
+    assert!(db.save_to_file(&path).is_err());
+    assert_eq!(Database::load_from_file(&path).unwrap().row_count("Test"), Some(1));
+
+    // Missing parent directories are only created on request
+    let nested = dir.path().join("a/b/db.json").to_string_lossy().to_string();
+    assert!(db.save_to_file(&nested).is_err());
+    let opts = SaveOptions {
+        create_parent_dirs: true,
+    };
+    db.save_to_file_with_opts(&nested, opts).unwrap();
+    assert_eq!(Database::load_from_file(&nested).unwrap().row_count("Test"), Some(2));
+}
+
+#[test]
+fn malformed_files_are_rejected_on_load() {
+    let mut db = Database::new();
+    execute_sql(&mut db, "CREATE TABLE Users (id INT PRIMARY KEY, email STRING UNIQUE)").unwrap();
+    execute_sql(&mut db, "INSERT INTO Users VALUES (1, 'a@b.com')").unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("db.json").to_string_lossy().to_string();
+    db.save_to_file(&path).unwrap();
+    let original: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+
+    let corruptions = [
+        ("rows/0/values", serde_json::json!([1]), "row 0 has 1 values but 2 columns"),
+        (
+            "rows/0/values",
+            serde_json::json!([1, "a@b.com", "extra"]),
+            "row 0 has 3 values but 2 columns",
+        ),
+        ("primary_key", serde_json::json!(["uid"]), "primary key column 'uid' does not exist"),
+        ("unique_columns", serde_json::json!([["mail"]]), "unique column 'mail' does not exist"),
+    ];
+    for (field, value, message) in corruptions {
+        let mut json = original.clone();
+        *json.pointer_mut(&format!("/tables/Users/{}", field)).unwrap() = value;
+        std::fs::write(&path, json.to_string()).unwrap();
+        let err = Database::load_from_file(&path).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), format!("Table 'Users': {}", message));
+    }
+}
+
+/// Keeps every log record so tests can assert on what was logged.
+struct CapturingLogger {
+    records: std::sync::Mutex<Vec<(log::Level, String)>>,
+}
+
+impl log::Log for CapturingLogger {
+    fn enabled(&self, _: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        let mut records = self.records.lock().unwrap_or_else(|p| p.into_inner());
+        records.push((record.level(), record.args().to_string()));
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: CapturingLogger = CapturingLogger {
+    records: std::sync::Mutex::new(Vec::new()),
+};
+
+#[test]
+fn rejected_values_are_logged_as_warnings() {
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(log::LevelFilter::Trace);
+    let logged = |needle: &str| {
+        let records = LOGGER.records.lock().unwrap();
+        records.iter().filter(|(_, msg)| msg.contains(needle)).map(|(l, _)| *l).collect::<Vec<_>>()
+    };
+
+    let mut db = Database::new();
+    execute_sql(&mut db, "CREATE TABLE Logged (id INT PRIMARY KEY, age INT)").unwrap();
+    let err = execute_sql(&mut db, "INSERT INTO Logged VALUES (1, 'old')").unwrap_err();
+    assert!(matches!(err, SqlError::TypeMismatch { .. }));
+    assert_eq!(logged("INSERT INTO Logged VALUES (1, 'old')"), vec![log::Level::Warn]);
+
+    // Errors that are not about the data are only logged for debugging
+    execute_sql(&mut db, "SELECT * FROM NotLogged").unwrap_err();
+    assert_eq!(logged("NotLogged"), vec![log::Level::Debug]);
+}
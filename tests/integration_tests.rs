@@ -1,5 +1,13 @@
 use lab::*;
 
+/// Builds a typed row from raw values according to the columns.
+fn typed_row(cols: &[ColumnSchema], raw: &[&str]) -> Vec<Value> {
+    cols.iter()
+        .zip(raw)
+        .map(|(col, v)| Value::from_column(col, v).unwrap())
+        .collect()
+}
+
 #[test]
 fn integration_predicate_and_row_and_table_and_db() {
     // Query predicate
@@ -7,20 +15,26 @@ fn integration_predicate_and_row_and_table_and_db() {
         ColumnSchema {
             name: "id".to_string(),
             col_type: ColumnType::Int,
+            nullable: true,
+            default: None,
         },
         ColumnSchema {
             name: "price".to_string(),
             col_type: ColumnType::Float,
+            nullable: true,
+            default: None,
         },
         ColumnSchema {
             name: "name".to_string(),
             col_type: ColumnType::String,
+            nullable: true,
+            default: None,
         },
     ];
 
     let pred_price = query_to_predicate(&cols, "price > 1.5");
-    let row1 = vec!["1".to_string(), "2.5".to_string(), "Pen".to_string()];
-    let row2 = vec!["2".to_string(), "1.0".to_string(), "Pencil".to_string()];
+    let row1 = typed_row(&cols, &["1", "2.5", "Pen"]);
+    let row2 = typed_row(&cols, &["2", "1.0", "Pencil"]);
     assert!(pred_price(&row1));
     assert!(!pred_price(&row2));
 
@@ -34,35 +48,41 @@ fn integration_predicate_and_row_and_table_and_db() {
             ColumnSchema {
                 name: "id".to_string(),
                 col_type: ColumnType::Int,
+                nullable: true,
+                default: None,
             },
             ColumnSchema {
                 name: "name".to_string(),
                 col_type: ColumnType::String,
+                nullable: true,
+                default: None,
             },
         ],
     };
-    let mut r = Row::new(vec!["1".to_string(), "Alice".to_string()]);
-    assert_eq!(r.get_by_name("id", &schema), Some(&"1".to_string()));
-    assert!(r.set_by_name("name", "Bob".to_string(), &schema));
-    assert_eq!(r.get_by_name("name", &schema), Some(&"Bob".to_string()));
+    let mut r = Row::new(vec![Value::Int(1), Value::from("Alice")]);
+    assert_eq!(r.get_by_name("id", &schema), Some(&Value::Int(1)));
+    assert!(r.set_by_name("name", Value::from("Bob"), &schema));
+    assert_eq!(r.get_by_name("name", &schema), Some(&Value::from("Bob")));
 
     // Table CRUD
     let mut t = Table::new(
         "Test".to_string(),
         schema.clone(),
-        Some("id".to_string()),
+        vec!["id".to_string()],
         vec![],
     );
-    t.add_row(vec!["1".to_string(), "Alice".to_string()]);
-    t.add_row(vec!["2".to_string(), "Bob".to_string()]);
+    t.add_row(vec!["1".to_string(), "Alice".to_string()]).unwrap();
+    t.add_row(vec!["2".to_string(), "Bob".to_string()]).unwrap();
     // duplicate pk should be rejected
-    t.add_row(vec!["1".to_string(), "Carol".to_string()]);
+    assert!(t.add_row(vec!["1".to_string(), "Carol".to_string()]).is_err());
     assert_eq!(t.rows.len(), 2);
-    t.update_rows(vec!["".to_string(), "Bobby".to_string()], |r| {
-        r.get(0).map(|v| v == "2").unwrap_or(false)
+    let updated = t.update_rows(vec![None, Some("Bobby".to_string())], |r| {
+        r.first().map(|v| v == "2").unwrap_or(false)
     });
+    assert_eq!(updated, Ok(1));
     assert_eq!(t.rows[1].get_values()[1], "Bobby");
-    t.delete_rows(|r| r.get(0).map(|v| v == "1").unwrap_or(false));
+    let deleted = t.delete_rows(|r| r.first().map(|v| v == "1").unwrap_or(false));
+    assert_eq!(deleted, 1);
     assert_eq!(t.rows.len(), 1);
 
     // Database level
@@ -71,32 +91,44 @@ fn integration_predicate_and_row_and_table_and_db() {
         ColumnSchema {
             name: "id".to_string(),
             col_type: ColumnType::Int,
+            nullable: true,
+            default: None,
         },
         ColumnSchema {
             name: "name".to_string(),
             col_type: ColumnType::String,
+            nullable: true,
+            default: None,
         },
     ];
-    db.create_table_with_constraints("People", cols_db, Some("id".to_string()), vec![]);
-    db.insert("People", vec!["1".to_string(), "Alice".to_string()]);
-    db.insert("People", vec!["2".to_string(), "Bob".to_string()]);
-    assert!(db.tables.get("People").map(|t| t.rows.len()).unwrap_or(0) == 2);
-    db.update("People", vec!["".to_string(), "Bobby".to_string()], |r| {
-        r.get(0).map(|v| v == "2").unwrap_or(false)
-    });
+    db.create_table_with_constraints("People", cols_db, vec!["id".to_string()], vec![]);
+    db.insert("People", vec!["1".to_string(), "Alice".to_string()]).unwrap();
+    db.insert("People", vec!["2".to_string(), "Bob".to_string()]).unwrap();
+    assert_eq!(db.row_count("People"), Some(2));
+    db.update("People", vec![None, Some("Bobby".to_string())], |r| {
+        r.first().map(|v| v == "2").unwrap_or(false)
+    })
+    .unwrap();
     assert_eq!(
         db.tables.get("People").unwrap().rows[1].get_values()[1],
         "Bobby"
     );
-    db.delete("People", |r| r.get(0).map(|v| v == "1").unwrap_or(false));
-    assert_eq!(db.tables.get("People").unwrap().rows.len(), 1);
+    assert_eq!(
+        db.delete("People", |r| r.first().map(|v| v == "1").unwrap_or(false)),
+        Ok(1)
+    );
+    assert_eq!(db.row_count("People").unwrap(), 1);
+    assert_eq!(
+        db.delete("Missing", |_| true),
+        Err(SqlError::TableNotFound("Missing".to_string()))
+    );
 
     // Use a temp file for save/load
     let tmp = tempfile::NamedTempFile::new().expect("tempfile");
     let fname = tmp.path().to_str().unwrap().to_string();
     let _ = db.save_to_file(&fname);
     let loaded = Database::load_from_file(&fname).unwrap();
-    assert!(loaded.tables.get("People").is_some());
+    assert!(loaded.tables.contains_key("People"));
     drop(tmp);
 }
 
@@ -107,7 +139,8 @@ fn parse_create_and_predicate_tests() {
     execute_sql(
         &mut db,
         "CREATE TABLE Users(id INT PRIMARY KEY, name STRING, score FLOAT, email UNIQUE)",
-    );
+    )
+    .unwrap();
     let table_ref = db.tables.get("Users").expect("Users table created");
     assert_eq!(table_ref.schema.columns.len(), 4);
     assert_eq!(table_ref.schema.columns[0].name, "id");
@@ -122,19 +155,25 @@ fn parse_create_and_predicate_tests() {
         ColumnSchema {
             name: "id".to_string(),
             col_type: ColumnType::Int,
+            nullable: true,
+            default: None,
         },
         ColumnSchema {
             name: "price".to_string(),
             col_type: ColumnType::Float,
+            nullable: true,
+            default: None,
         },
         ColumnSchema {
             name: "name".to_string(),
             col_type: ColumnType::String,
+            nullable: true,
+            default: None,
         },
     ];
     let pred_price = query_to_predicate(&cols, "price > 1.5");
-    let row1 = vec!["1".to_string(), "2.5".to_string(), "Pen".to_string()];
-    let row2 = vec!["2".to_string(), "1.0".to_string(), "Pencil".to_string()];
+    let row1 = typed_row(&cols, &["1", "2.5", "Pen"]);
+    let row2 = typed_row(&cols, &["2", "1.0", "Pencil"]);
     assert!(pred_price(&row1));
     assert!(!pred_price(&row2));
 }
@@ -146,15 +185,19 @@ fn create_table_type_synonyms_and_list_tables() {
     execute_sql(
         &mut db,
         "CREATE TABLE TypesTest(a INTEGER, b REAL, c TEXT);",
-    );
+    )
+    .unwrap();
     let t = db.tables.get("TypesTest").expect("TypesTest created");
     assert_eq!(t.schema.columns.len(), 3);
     assert_eq!(t.schema.columns[0].col_type, ColumnType::Int);
     assert_eq!(t.schema.columns[1].col_type, ColumnType::Float);
     assert_eq!(t.schema.columns[2].col_type, ColumnType::String);
 
-    // LIST TABLES should not panic and should include our tables
-    execute_sql(&mut db, "LIST TABLES");
+    // LIST TABLES should include our tables
+    assert_eq!(
+        execute_sql(&mut db, "LIST TABLES"),
+        Ok(SqlOutcome::Tables(vec!["TypesTest".to_string()]))
+    );
     assert!(db.tables.contains_key("TypesTest"));
 }
 
@@ -168,28 +211,34 @@ fn insert_and_update_type_mismatch_handling() {
             ColumnSchema {
                 name: "id".to_string(),
                 col_type: ColumnType::Int,
+                nullable: true,
+                default: None,
             },
             ColumnSchema {
                 name: "val".to_string(),
                 col_type: ColumnType::Float,
+                nullable: true,
+                default: None,
             },
         ],
-        Some("id".to_string()),
+        vec!["id".to_string()],
         vec![],
     );
 
     // inserting wrong type into id should be rejected
-    db.insert("Nums", vec!["notanint".to_string(), "1.23".to_string()]);
-    assert_eq!(db.tables.get("Nums").unwrap().rows.len(), 0);
+    let err = db.insert("Nums", vec!["notanint".to_string(), "1.23".to_string()]);
+    assert!(matches!(err, Err(SqlError::TypeMismatch { .. })));
+    assert_eq!(db.row_count("Nums").unwrap(), 0);
 
     // insert a correct row
-    db.insert("Nums", vec!["1".to_string(), "2.5".to_string()]);
-    assert_eq!(db.tables.get("Nums").unwrap().rows.len(), 1);
+    db.insert("Nums", vec!["1".to_string(), "2.5".to_string()]).unwrap();
+    assert_eq!(db.row_count("Nums").unwrap(), 1);
 
     // attempt an update that provides an invalid float for 'val' should be rejected
-    db.update("Nums", vec!["".to_string(), "notafloat".to_string()], |r| {
-        r.get(0).map(|v| v == "1").unwrap_or(false)
+    let err = db.update("Nums", vec![None, Some("notafloat".to_string())], |r| {
+        r.first().map(|v| v == "1").unwrap_or(false)
     });
+    assert!(matches!(err, Err(SqlError::TypeMismatch { .. })));
     // value should remain unchanged
     let val = db.tables.get("Nums").unwrap().rows[0].get_values()[1].clone();
     assert_eq!(val, "2.5");
@@ -200,14 +249,367 @@ fn predicate_comparison_edge_cases() {
     let cols = vec![ColumnSchema {
         name: "n".to_string(),
         col_type: ColumnType::Float,
+        nullable: true,
+        default: None,
     }];
     let p_ge = query_to_predicate(&cols, "n >= 2.5");
-    assert!(p_ge(&vec!["2.5".to_string()]));
-    assert!(p_ge(&vec!["3.0".to_string()]));
-    assert!(!p_ge(&vec!["2.499".to_string()]));
+    assert!(p_ge(&typed_row(&cols, &["2.5"])));
+    assert!(p_ge(&typed_row(&cols, &["3.0"])));
+    assert!(!p_ge(&typed_row(&cols, &["2.499"])));
 
     let p_le = query_to_predicate(&cols, "n <= 1.0");
-    assert!(p_le(&vec!["1.0".to_string()]));
-    assert!(p_le(&vec!["0.5".to_string()]));
-    assert!(!p_le(&vec!["1.0001".to_string()]));
+    assert!(p_le(&typed_row(&cols, &["1.0"])));
+    assert!(p_le(&typed_row(&cols, &["0.5"])));
+    assert!(!p_le(&typed_row(&cols, &["1.0001"])));
+}
+
+#[test]
+fn like_predicate_wildcards() {
+    let cols = vec![
+        ColumnSchema {
+            name: "id".to_string(),
+            col_type: ColumnType::Int,
+            nullable: true,
+            default: None,
+        },
+        ColumnSchema {
+            name: "name".to_string(),
+            col_type: ColumnType::String,
+            nullable: true,
+            default: None,
+        },
+    ];
+    let pen = typed_row(&cols, &["1", "Pen"]);
+    let pencil = typed_row(&cols, &["2", "Pencil"]);
+    let open = typed_row(&cols, &["3", "Open"]);
+    let null = vec![Value::Int(4), Value::Null];
+    let matching = |query: &str| {
+        let pred = query_to_predicate(&cols, query);
+        [&pen, &pencil, &open, &null]
+            .iter()
+            .filter(|r| pred(r))
+            .map(|r| r[0].clone())
+            .collect::<Vec<_>>()
+    };
+
+    // prefix, suffix and contains
+    assert_eq!(matching("name LIKE 'Pen%'"), vec![Value::Int(1), Value::Int(2)]);
+    assert_eq!(matching("name LIKE '%en'"), vec![Value::Int(1), Value::Int(3)]);
+    assert_eq!(matching("name like '%nci%'"), vec![Value::Int(2)]);
+    assert_eq!(matching("name LIKE '_pen'"), vec![Value::Int(3)]);
+    assert_eq!(matching("name LIKE 'Pen'"), vec![Value::Int(1)]);
+    assert_eq!(matching("name NOT LIKE 'Pen%'"), vec![Value::Int(3)]);
+    assert_eq!(matching("name LIKE 'Pen%' AND id > 1"), vec![Value::Int(2)]);
+
+    // Non-string columns never match, and `\%` is a literal percent sign
+    assert!(matching("id LIKE '1%'").is_empty());
+    let pct = typed_row(&cols, &["5", "50% off"]);
+    assert!(query_to_predicate(&cols, r"name LIKE '%\% off'")(&pct));
+    assert!(!query_to_predicate(&cols, r"name LIKE '%\% off'")(&open));
+}
+
+#[test]
+fn in_list_predicates() {
+    let cols = vec![
+        ColumnSchema {
+            name: "id".to_string(),
+            col_type: ColumnType::Int,
+            nullable: true,
+            default: None,
+        },
+        ColumnSchema {
+            name: "name".to_string(),
+            col_type: ColumnType::String,
+            nullable: true,
+            default: None,
+        },
+    ];
+    let rows = [
+        typed_row(&cols, &["1", "Alice"]),
+        typed_row(&cols, &["2", "Bob"]),
+        typed_row(&cols, &["3", "Carol"]),
+        vec![Value::Int(4), Value::Null],
+    ];
+    let matching = |query: &str| {
+        let pred = query_to_predicate(&cols, query);
+        rows.iter()
+            .filter(|r| pred(r))
+            .map(|r| r[0].clone())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(matching("id IN (1, 3)"), vec![Value::Int(1), Value::Int(3)]);
+    // Literals that do not fit an Int column are ignored
+    assert_eq!(matching("id in (2, 'two', 9)"), vec![Value::Int(2)]);
+    assert_eq!(matching("name NOT IN ('Bob', 'Carol')"), vec![Value::Int(1)]);
+    assert_eq!(
+        matching("(id IN (1, 2) OR name IN ('Carol')) AND id != 2"),
+        vec![Value::Int(1), Value::Int(3)]
+    );
+
+    // An empty list matches nothing, NOT IN an empty list matches everything
+    assert!(matching("id IN ()").is_empty());
+    assert_eq!(matching("id NOT IN ()").len(), 4);
+
+    let mut db = Database::new();
+    execute_sql(&mut db, "CREATE TABLE T (id INT, name STRING)").unwrap();
+    execute_sql(&mut db, "INSERT INTO T VALUES (1, 'a'); INSERT INTO T VALUES (2, 'b')").unwrap();
+    assert_eq!(
+        execute_sql(&mut db, "DELETE FROM T WHERE id IN (2, 5)"),
+        Ok(SqlOutcome::Deleted(1))
+    );
+}
+
+#[test]
+fn between_predicates() {
+    let cols = vec![
+        ColumnSchema {
+            name: "price".to_string(),
+            col_type: ColumnType::Float,
+            nullable: true,
+            default: None,
+        },
+        ColumnSchema {
+            name: "name".to_string(),
+            col_type: ColumnType::String,
+            nullable: true,
+            default: None,
+        },
+    ];
+    let rows = [
+        typed_row(&cols, &["0.5", "Eraser"]),
+        typed_row(&cols, &["1.0", "Pen"]),
+        typed_row(&cols, &["2.5", "Pencil"]),
+        typed_row(&cols, &["3.0", "Ruler"]),
+    ];
+    let matching = |query: &str| {
+        let pred = query_to_predicate(&cols, query);
+        rows.iter()
+            .filter(|r| pred(r))
+            .map(|r| r[1].clone())
+            .collect::<Vec<_>>()
+    };
+
+    // Both endpoints are inclusive
+    assert_eq!(
+        matching("price BETWEEN 1.0 AND 2.5"),
+        vec![Value::from("Pen"), Value::from("Pencil")]
+    );
+    assert_eq!(
+        matching("price NOT BETWEEN 1.0 AND 2.5"),
+        vec![Value::from("Eraser"), Value::from("Ruler")]
+    );
+    // The BETWEEN's AND is not a boolean connector
+    assert_eq!(
+        matching("price between 1.0 and 3.0 AND name != 'Pen' OR price < 0.6"),
+        vec![Value::from("Eraser"), Value::from("Pencil"), Value::from("Ruler")]
+    );
+    assert_eq!(
+        matching("name BETWEEN 'P' AND 'Q'"),
+        vec![Value::from("Pen"), Value::from("Pencil")]
+    );
+    // Reversed bounds match nothing
+    assert!(matching("price BETWEEN 2.5 AND 1.0").is_empty());
+}
+
+#[test]
+fn string_ordering_predicates() {
+    let cols = vec![ColumnSchema {
+        name: "name".to_string(),
+        col_type: ColumnType::String,
+        nullable: true,
+        default: None,
+    }];
+    let rows = [
+        typed_row(&cols, &["Eraser"]),
+        typed_row(&cols, &["Pen"]),
+        typed_row(&cols, &["Pencil"]),
+        typed_row(&cols, &["Ruler"]),
+        vec![Value::Null],
+    ];
+    let matching = |query: &str| {
+        let pred = query_to_predicate(&cols, query);
+        rows.iter()
+            .filter(|r| pred(r))
+            .map(|r| r[0].to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(matching("name > 'P'"), ["Pen", "Pencil", "Ruler"]);
+    assert_eq!(matching("name >= 'Pencil'"), ["Pencil", "Ruler"]);
+    assert_eq!(matching("name < 'Pen'"), ["Eraser"]);
+    assert_eq!(matching("name <= 'Pen'"), ["Eraser", "Pen"]);
+
+    // Text holding numbers still compares lexicographically: '10' sorts before '9'
+    let row = typed_row(&cols, &["10"]);
+    assert!(!query_to_predicate(&cols, "name > 9")(&row));
+    assert!(query_to_predicate(&cols, "name < '9'")(&row));
+    assert!(query_to_predicate(&cols, "name >= '10'")(&row));
+    let row = typed_row(&cols, &["9"]);
+    assert!(query_to_predicate(&cols, "name > '10'")(&row));
+    assert!(!query_to_predicate(&cols, "name <= '10'")(&row));
+}
+
+#[test]
+fn case_insensitive_string_matching() {
+    let cols = vec![
+        ColumnSchema {
+            name: "id".to_string(),
+            col_type: ColumnType::Int,
+            nullable: true,
+            default: None,
+        },
+        ColumnSchema {
+            name: "name".to_string(),
+            col_type: ColumnType::String,
+            nullable: true,
+            default: None,
+        },
+    ];
+    let pen = typed_row(&cols, &["1", "Pen"]);
+    let pencil = typed_row(&cols, &["2", "PENCIL"]);
+
+    assert!(!query_to_predicate(&cols, "name == 'pen'")(&pen));
+    assert!(query_to_predicate(&cols, "LOWER(name) == 'pen'")(&pen));
+    assert!(query_to_predicate(&cols, "lower(name) != 'pen'")(&pencil));
+    assert!(query_to_predicate(&cols, "name ILIKE 'pen'")(&pen));
+    assert!(query_to_predicate(&cols, "name ILIKE 'pen%'")(&pencil));
+    assert!(!query_to_predicate(&cols, "name LIKE 'pen%'")(&pencil));
+    assert!(!query_to_predicate(&cols, "name NOT ILIKE 'PEN%'")(&pen));
+
+    // Only String columns are lower-cased
+    assert!(!query_to_predicate(&cols, "LOWER(id) == '1'")(&pen));
+    assert!(!query_to_predicate(&cols, "id ILIKE '1'")(&pen));
+}
+
+#[test]
+fn operators_inside_quoted_literals() {
+    let cols = vec![
+        ColumnSchema {
+            name: "id".to_string(),
+            col_type: ColumnType::Int,
+            nullable: true,
+            default: None,
+        },
+        ColumnSchema {
+            name: "note".to_string(),
+            col_type: ColumnType::String,
+            nullable: true,
+            default: None,
+        },
+    ];
+    let arrow = typed_row(&cols, &["1", "a>b"]);
+    let eq = typed_row(&cols, &["2", "x==y"]);
+
+    assert!(query_to_predicate(&cols, "note == 'a>b'")(&arrow));
+    assert!(!query_to_predicate(&cols, "note == 'a>b'")(&eq));
+    assert!(query_to_predicate(&cols, "note != 'x==y'")(&arrow));
+    assert!(!query_to_predicate(&cols, "note != 'x==y'")(&eq));
+    assert!(query_to_predicate(&cols, "note == \"x==y\" OR note == '<='")(&eq));
+
+    let mut db = Database::new();
+    execute_sql(&mut db, "CREATE TABLE Notes (id INT, note STRING)").unwrap();
+    execute_sql(&mut db, "INSERT INTO Notes VALUES (1, 'a>b')").unwrap();
+    execute_sql(&mut db, "INSERT INTO Notes VALUES (2, 'a<b AND c')").unwrap();
+    assert_eq!(
+        execute_sql(&mut db, "DELETE FROM Notes WHERE note == 'a<b AND c'"),
+        Ok(SqlOutcome::Deleted(1))
+    );
+}
+
+#[test]
+fn signed_and_scientific_literals() {
+    let cols = vec![
+        ColumnSchema {
+            name: "balance".to_string(),
+            col_type: ColumnType::Int,
+            nullable: true,
+            default: None,
+        },
+        ColumnSchema {
+            name: "mass".to_string(),
+            col_type: ColumnType::Float,
+            nullable: true,
+            default: None,
+        },
+    ];
+    let debtor = typed_row(&cols, &["-5", "1499.5"]);
+    let saver = typed_row(&cols, &["1000", "-0.25"]);
+
+    assert!(query_to_predicate(&cols, "balance == -5")(&debtor));
+    assert!(query_to_predicate(&cols, "balance==-5")(&debtor));
+    assert!(!query_to_predicate(&cols, "balance != -5")(&debtor));
+    assert!(query_to_predicate(&cols, "balance > -6 AND balance < -4")(&debtor));
+    assert!(!query_to_predicate(&cols, "balance > -5")(&debtor));
+    assert!(query_to_predicate(&cols, "balance == +1000")(&saver));
+    assert!(query_to_predicate(&cols, "balance == 1e3")(&saver));
+    assert!(!query_to_predicate(&cols, "balance == 1.5e3")(&saver));
+
+    assert!(query_to_predicate(&cols, "mass < 1.5e3")(&debtor));
+    assert!(!query_to_predicate(&cols, "mass < 1.4995E3")(&debtor));
+    assert!(query_to_predicate(&cols, "mass == -2.5e-1")(&saver));
+    assert!(query_to_predicate(&cols, "mass BETWEEN -1e0 AND 0")(&saver));
+
+    let mut db = Database::new();
+    execute_sql(&mut db, "CREATE TABLE Accounts (id INT, balance INT)").unwrap();
+    execute_sql(&mut db, "INSERT INTO Accounts VALUES (1, -5); INSERT INTO Accounts VALUES (2, 7)")
+        .unwrap();
+    assert_eq!(
+        execute_sql(&mut db, "DELETE FROM Accounts WHERE balance < -1"),
+        Ok(SqlOutcome::Deleted(1))
+    );
+}
+
+#[test]
+fn where_parse_errors_report_kind_and_position() {
+    let cols = vec![
+        ColumnSchema {
+            name: "id".to_string(),
+            col_type: ColumnType::Int,
+            nullable: true,
+            default: None,
+        },
+        ColumnSchema {
+            name: "name".to_string(),
+            col_type: ColumnType::String,
+            nullable: true,
+            default: None,
+        },
+    ];
+    let err = |query: &str| match try_query_to_predicate(&cols, query) {
+        Ok(_) => panic!("expected {:?} to fail", query),
+        Err(e) => e,
+    };
+
+    let e = err("id == 1 AND age > 3");
+    assert_eq!(e.kind, ParseErrorKind::UnknownColumn("age".to_string()));
+    assert_eq!(e.position, 12);
+    let e = err("id == 1 OR (name LIKE 'a%' AND nope IN (1))");
+    assert_eq!(e.kind, ParseErrorKind::UnknownColumn("nope".to_string()));
+    assert_eq!(e.position, 31);
+
+    let e = err("id == 1 AND name");
+    assert_eq!(e.kind, ParseErrorKind::MissingOperator);
+    assert_eq!(e.position, 12);
+    assert_eq!(err("(id == 1").kind, ParseErrorKind::InvalidSyntax);
+    assert_eq!(err("id BETWEEN 1").kind, ParseErrorKind::InvalidSyntax);
+
+    // Well-formed conditions still parse, and the infallible form matches nothing on error
+    let row = typed_row(&cols, &["1", "Alice"]);
+    assert!(try_query_to_predicate(&cols, "id == 1 AND name != 'Bob'").unwrap()(&row));
+    assert!(try_query_to_predicate(&cols, "id == 'abc'").is_ok());
+    assert!(!query_to_predicate(&cols, "age > 3")(&row));
+
+    let mut db = Database::new();
+    execute_sql(&mut db, "CREATE TABLE Users (id INT, name TEXT)").unwrap();
+    assert_eq!(
+        execute_sql(&mut db, "UPDATE Users SET name = 'x' WHERE age > 3"),
+        Err(SqlError::ColumnNotFound("age".to_string()))
+    );
+    assert_eq!(
+        execute_sql(&mut db, "SELECT * FROM Users WHERE id == 1 AND name"),
+        Err(SqlError::Parse(
+            "WHERE clause: No comparison operator in 'name' at position 12".to_string()
+        ))
+    );
 }
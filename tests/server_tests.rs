@@ -0,0 +1,1080 @@
+use lab::client::{AsyncRustDBClient, ClientConfig, RustDBClient, verify_replica};
+use lab::database::Database;
+use lab::metrics::MetricsSnapshot;
+use lab::replication::ReplicationConfig;
+use lab::schema::ColumnType;
+use lab::server::{
+    HealthStatus, PROTOCOL_VERSION, ServerInfo, ServerOptions, TlsConfig, start_server,
+    start_server_with_auth, start_server_with_options, start_tls_server,
+};
+use lab::sql::{PreparedStatement, execute_sql};
+use lab::value::Value;
+use std::time::{Duration, Instant};
+
+/// Reserve an ephemeral port on localhost for a test server.
+fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .expect("bind ephemeral port")
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// Calls a JSON-RPC method directly and returns the whole response object.
+fn rpc_call(port: u16, method: &str, params: serde_json::Value) -> serde_json::Value {
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": params,
+        "id": 1
+    });
+    reqwest::blocking::Client::new()
+        .post(format!("http://127.0.0.1:{}", port))
+        .json(&request)
+        .send()
+        .unwrap()
+        .json()
+        .unwrap()
+}
+
+/// Like `rpc_call` without params, sending `token` as a bearer token.
+fn rpc_call_with_token(port: u16, method: &str, token: &str) -> serde_json::Value {
+    let request = serde_json::json!({"jsonrpc": "2.0", "method": method, "params": [], "id": 1});
+    reqwest::blocking::Client::new()
+        .post(format!("http://127.0.0.1:{}", port))
+        .bearer_auth(token)
+        .json(&request)
+        .send()
+        .unwrap()
+        .json()
+        .unwrap()
+}
+
+#[test]
+fn execute_select_returns_rows() {
+    let port = free_port();
+    let _server = start_server(port, None);
+    let client = RustDBClient::new("127.0.0.1", port);
+
+    client
+        .execute("CREATE TABLE Users (id INT PRIMARY KEY, name STRING)")
+        .unwrap();
+    client.execute("INSERT INTO Users VALUES (1, 'Alice')").unwrap();
+    let insert = client.execute("INSERT INTO Users VALUES (2, 'Bob')").unwrap();
+    // Non-SELECT statements carry no rows
+    assert!(insert.rows.is_none());
+
+    let response = client.execute("SELECT * FROM Users").unwrap();
+    assert!(response.success);
+    assert_eq!(
+        response.rows,
+        Some(vec![
+            vec!["1".to_string(), "Alice".to_string()],
+            vec!["2".to_string(), "Bob".to_string()],
+        ])
+    );
+
+    // Projection and WHERE are applied to the returned rows
+    let response = client.execute("SELECT name FROM Users WHERE id == 2").unwrap();
+    assert_eq!(response.rows, Some(vec![vec!["Bob".to_string()]]));
+
+    // Only the three mutating statements became replication events
+    assert_eq!(client.health().unwrap().event_seq, 3);
+}
+
+#[test]
+fn execute_reports_errors() {
+    let port = free_port();
+    let _server = start_server(port, None);
+    let client = RustDBClient::new("127.0.0.1", port);
+
+    let response = client.execute("INSERT INTO Missing VALUES (1)").unwrap();
+    assert!(!response.success);
+    assert_eq!(response.message, "Table 'Missing' does not exist");
+    assert!(response.rows.is_none());
+}
+
+#[tokio::test]
+async fn async_client_talks_to_server() {
+    let port = free_port();
+    // The server owns a runtime, which must not be created or dropped on an async thread
+    let server = tokio::task::spawn_blocking(move || start_server(port, None))
+        .await
+        .unwrap();
+    let client = AsyncRustDBClient::new("127.0.0.1", port);
+
+    assert_eq!(client.ping().await.unwrap(), "pong");
+    client
+        .execute("CREATE TABLE Users (id INT PRIMARY KEY, name STRING)")
+        .await
+        .unwrap();
+    client.execute("INSERT INTO Users VALUES (1, 'Alice')").await.unwrap();
+    let response = client.execute("SELECT name FROM Users").await.unwrap();
+    assert!(response.success);
+    assert_eq!(response.rows, Some(vec![vec!["Alice".to_string()]]));
+    assert_eq!(client.list_tables().await.unwrap(), vec!["Users".to_string()]);
+
+    tokio::task::spawn_blocking(move || drop(server)).await.unwrap();
+}
+
+#[tokio::test]
+async fn async_client_sends_auth_token() {
+    let port = free_port();
+    let token = Some("secret".to_string());
+    let server = tokio::task::spawn_blocking(move || start_server_with_auth(port, None, token))
+        .await
+        .unwrap();
+
+    assert!(AsyncRustDBClient::new("127.0.0.1", port).ping().await.is_err());
+    let wrong = AsyncRustDBClient::new("127.0.0.1", port).with_token("secreT");
+    assert!(wrong.ping().await.is_err());
+    let client = AsyncRustDBClient::new("127.0.0.1", port).with_token("secret");
+    assert_eq!(client.ping().await.unwrap(), "pong");
+
+    tokio::task::spawn_blocking(move || drop(server)).await.unwrap();
+}
+
+#[test]
+fn client_retries_reads_but_not_writes() {
+    // Nothing listens on the port, so every attempt fails to connect
+    let port = free_port();
+    let config = ClientConfig {
+        timeout: Duration::from_secs(2),
+        retries: 2,
+        backoff: Duration::from_millis(20),
+        ..ClientConfig::default()
+    };
+    let client = RustDBClient::with_config("127.0.0.1", port, config);
+
+    let start = Instant::now();
+    let err = client.ping().unwrap_err().to_string();
+    assert!(err.starts_with("Request failed after 3 attempt(s)"), "{}", err);
+    // Backoff of 20ms then 40ms between the three attempts
+    assert!(start.elapsed() >= Duration::from_millis(60));
+
+    let err = client.execute("SELECT * FROM Users").unwrap_err().to_string();
+    assert!(err.starts_with("Request failed after 3 attempt(s)"), "{}", err);
+    let err = client
+        .execute("INSERT INTO Users VALUES (1, 'Alice')")
+        .unwrap_err()
+        .to_string();
+    assert!(err.starts_with("Request failed after 1 attempt(s)"), "{}", err);
+}
+
+#[test]
+fn execute_batch_runs_statements_in_order() {
+    let port = free_port();
+    let _server = start_server(port, None);
+    let client = RustDBClient::new("127.0.0.1", port);
+
+    let responses = client
+        .execute_batch(&[
+            "CREATE TABLE Users (id INT PRIMARY KEY, name STRING)",
+            "INSERT INTO Users VALUES (1, 'Alice')",
+            "INSERT INTO Users VALUES (2, 'Bob')",
+            "INSERT INTO Users VALUES (3, 'Carol')",
+        ])
+        .unwrap();
+    assert_eq!(responses.len(), 4);
+    assert!(responses.iter().all(|r| r.success));
+    assert_eq!(responses[1].message, "Inserted 1 row(s)");
+
+    let rows = client.execute("SELECT * FROM Users").unwrap().rows.unwrap();
+    assert_eq!(rows.len(), 3);
+
+    // A failing statement is reported without stopping the rest
+    let responses = client
+        .execute_batch(&[
+            "INSERT INTO Users VALUES (1, 'Dup')",
+            "SELECT name FROM Users WHERE id == 3",
+        ])
+        .unwrap();
+    assert!(!responses[0].success);
+    assert_eq!(responses[1].rows, Some(vec![vec!["Carol".to_string()]]));
+}
+
+#[test]
+fn replica_serves_reads_but_rejects_writes() {
+    let port = free_port();
+    // The primary is never contacted here; the replica is seeded directly
+    let primary_url = format!("http://127.0.0.1:{}", free_port());
+    let _server = start_server(port, Some(ReplicationConfig::new_replica(primary_url)));
+    let seed = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "replication_apply_events",
+        "params": [[
+            {
+                "id": 1,
+                "timestamp": 0,
+                "query": "CREATE TABLE Users (id INT PRIMARY KEY, name STRING)"
+            },
+            {"id": 2, "timestamp": 0, "query": "INSERT INTO Users VALUES (1, 'Alice')"}
+        ]],
+        "id": 1
+    });
+    reqwest::blocking::Client::new()
+        .post(format!("http://127.0.0.1:{}", port))
+        .json(&seed)
+        .send()
+        .unwrap();
+    let client = RustDBClient::new("127.0.0.1", port);
+
+    let response = client.execute("SELECT name FROM Users").unwrap();
+    assert!(response.success);
+    assert!(response.message.contains("may lag behind the primary"));
+    assert_eq!(response.rows, Some(vec![vec!["Alice".to_string()]]));
+
+    let response = client.execute("INSERT INTO Users VALUES (2, 'Bob')").unwrap();
+    assert!(!response.success);
+    assert!(response.message.contains("replica"));
+    let rows = client.execute("SELECT * FROM Users").unwrap().rows.unwrap();
+    assert_eq!(rows.len(), 1);
+}
+
+#[test]
+fn new_replica_catches_up_from_snapshot() {
+    let primary_port = free_port();
+    let _primary = start_server(primary_port, None);
+    let primary = RustDBClient::new("127.0.0.1", primary_port);
+    primary.execute("CREATE TABLE Users (id INT PRIMARY KEY, name STRING)").unwrap();
+    for id in 1..=20 {
+        primary.execute(&format!("INSERT INTO Users VALUES ({}, 'user{}')", id, id)).unwrap();
+    }
+
+    let replica_port = free_port();
+    let mut config = ReplicationConfig::new_replica(format!("http://127.0.0.1:{}", primary_port));
+    config.sync_interval = Duration::from_millis(100);
+    let _replica = start_server(replica_port, Some(config));
+    let replica = RustDBClient::new("127.0.0.1", replica_port);
+    let converged = || {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if verify_replica(&primary, &replica).unwrap() {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        false
+    };
+    assert!(converged());
+    assert_eq!(replica.table_row_count("Users").unwrap(), 20);
+
+    // Writes after the snapshot are streamed as events
+    primary.execute("DELETE FROM Users WHERE id > 10").unwrap();
+    assert!(converged());
+    assert_eq!(replica.table_row_count("Users").unwrap(), 10);
+    assert_eq!(replica.metrics().unwrap().events_received, 1);
+
+    // Only the event after the snapshot was replayed
+    let response = rpc_call(replica_port, "replication_get_events", serde_json::json!([]));
+    let replayed = response["result"].as_array().unwrap();
+    assert_eq!(replayed.len(), 1);
+    assert_eq!(replayed[0]["id"], 22);
+}
+
+#[test]
+fn replica_converges_after_primary_compacts_events() {
+    let primary_port = free_port();
+    let mut config = ReplicationConfig::new_primary();
+    config.event_retention = 3;
+    let _primary = start_server(primary_port, Some(config));
+    let primary = RustDBClient::new("127.0.0.1", primary_port);
+    primary.execute("CREATE TABLE Users (id INT PRIMARY KEY)").unwrap();
+    for id in 1..=20 {
+        primary.execute(&format!("INSERT INTO Users VALUES ({})", id)).unwrap();
+    }
+    let response = rpc_call(primary_port, "replication_get_events", serde_json::json!([]));
+    let retained = response["result"].as_array().unwrap();
+    assert!(retained.len() < 6);
+    assert_eq!(retained.last().unwrap()["id"], 21);
+
+    // The create statement is gone from the log, yet a fresh replica still gets everything
+    let replica_port = free_port();
+    let mut config = ReplicationConfig::new_replica(format!("http://127.0.0.1:{}", primary_port));
+    config.sync_interval = Duration::from_millis(100);
+    let _replica = start_server(replica_port, Some(config));
+    let replica = RustDBClient::new("127.0.0.1", replica_port);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !verify_replica(&primary, &replica).unwrap() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    assert!(verify_replica(&primary, &replica).unwrap());
+    assert_eq!(replica.table_row_count("Users").unwrap(), 20);
+}
+
+#[test]
+fn replica_acks_update_tracked_lag() {
+    let primary_port = free_port();
+    let mut config = ReplicationConfig::new_primary();
+    config.replicas.insert("http://replica-a".to_string());
+    let _primary = start_server(primary_port, Some(config));
+    let primary = RustDBClient::new("127.0.0.1", primary_port);
+    primary.execute("CREATE TABLE Users (id INT PRIMARY KEY)").unwrap();
+    for id in 1..=4 {
+        primary.execute(&format!("INSERT INTO Users VALUES ({})", id)).unwrap();
+    }
+    let status = || {
+        rpc_call(primary_port, "replication_status", serde_json::json!([]))["result"].clone()
+    };
+    assert_eq!(
+        status(),
+        serde_json::json!([{"url": "http://replica-a", "applied_seq": 0, "lag": 5}])
+    );
+
+    let ack = rpc_call(primary_port, "replication_ack", serde_json::json!(["http://replica-a", 3]));
+    assert_eq!(ack["result"], true);
+    assert_eq!(status()[0]["lag"], 2);
+    // A stale ack does not move the replica backwards
+    rpc_call(primary_port, "replication_ack", serde_json::json!(["http://replica-a", 1]));
+    assert_eq!(status()[0]["applied_seq"], 3);
+
+    // A replica with an advertised URL acknowledges what it applies by itself
+    let replica_port = free_port();
+    let mut config = ReplicationConfig::new_replica(format!("http://127.0.0.1:{}", primary_port));
+    config.sync_interval = Duration::from_millis(100);
+    config.advertise_url = Some(format!("http://127.0.0.1:{}", replica_port));
+    let _replica = start_server(replica_port, Some(config));
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while status().as_array().unwrap().len() < 2 && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    assert_eq!(status()[0]["url"], format!("http://127.0.0.1:{}", replica_port));
+    assert_eq!(status()[0]["lag"], 0);
+}
+
+#[test]
+fn promoted_replica_accepts_writes() {
+    let primary_port = free_port();
+    let primary_server = start_server(primary_port, None);
+    let primary = RustDBClient::new("127.0.0.1", primary_port);
+    primary.execute("CREATE TABLE Users (id INT PRIMARY KEY)").unwrap();
+    primary.execute("INSERT INTO Users VALUES (1)").unwrap();
+
+    let replica_port = free_port();
+    let mut config = ReplicationConfig::new_replica(format!("http://127.0.0.1:{}", primary_port));
+    config.sync_interval = Duration::from_millis(100);
+    let _replica = start_server_with_auth(replica_port, Some(config), Some("secret".to_string()));
+    let replica = RustDBClient::new("127.0.0.1", replica_port).with_token("secret");
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !verify_replica(&primary, &replica).unwrap() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    assert!(!replica.execute("INSERT INTO Users VALUES (2)").unwrap().success);
+
+    // The primary goes away; promotion needs the token like every other call
+    primary_server.close();
+    assert!(RustDBClient::new("127.0.0.1", replica_port).promote().is_err());
+    assert!(replica.promote().unwrap());
+    assert!(!replica.promote().unwrap());
+
+    let response = replica.execute("INSERT INTO Users VALUES (2)").unwrap();
+    assert!(response.success, "{}", response.message);
+    assert_eq!(replica.table_row_count("Users").unwrap(), 2);
+    let events = rpc_call_with_token(replica_port, "replication_get_events", "secret");
+    assert_eq!(events["result"].as_array().unwrap().last().unwrap()["id"], 3);
+}
+
+#[test]
+fn promote_refused_without_auth_token() {
+    let primary_port = free_port();
+    let _primary = start_server(primary_port, None);
+    let replica_port = free_port();
+    let config = ReplicationConfig::new_replica(format!("http://127.0.0.1:{}", primary_port));
+    let _replica = start_server(replica_port, Some(config));
+    let replica = RustDBClient::new("127.0.0.1", replica_port);
+
+    // An open replica cannot be promoted, and stays read-only
+    assert!(replica.promote().is_err());
+    assert!(!replica.execute("CREATE TABLE Users (id INT PRIMARY KEY)").unwrap().success);
+}
+
+#[test]
+fn health_reports_primary_state() {
+    let port = free_port();
+    let _server = start_server(port, None);
+    let client = RustDBClient::new("127.0.0.1", port);
+    assert_eq!(
+        client.health().unwrap(),
+        HealthStatus {
+            is_primary: true,
+            table_count: 0,
+            row_count: 0,
+            event_seq: 0,
+            last_synced_seq: None,
+            lag: None,
+        }
+    );
+
+    client.execute("CREATE TABLE Users (id INT PRIMARY KEY)").unwrap();
+    client.execute("CREATE TABLE Posts (id INT PRIMARY KEY)").unwrap();
+    client.execute("INSERT INTO Users VALUES (1)").unwrap();
+    client.execute("INSERT INTO Posts VALUES (1)").unwrap();
+    client.execute("INSERT INTO Posts VALUES (2)").unwrap();
+    let health = client.health().unwrap();
+    assert_eq!((health.table_count, health.row_count, health.event_seq), (2, 3, 5));
+}
+
+#[test]
+fn metrics_count_queries_and_events() {
+    let port = free_port();
+    let _server = start_server(port, None);
+    let client = RustDBClient::new("127.0.0.1", port);
+    assert_eq!(client.metrics().unwrap(), MetricsSnapshot::default());
+
+    client.execute("CREATE TABLE Users (id INT PRIMARY KEY)").unwrap();
+    for id in 1..=5 {
+        client.execute(&format!("INSERT INTO Users VALUES ({})", id)).unwrap();
+    }
+    client.execute("INSERT INTO Users VALUES (1)").unwrap();
+    client.execute_batch(&["SELECT * FROM Users", "SELECT * FROM Missing"]).unwrap();
+
+    let metrics = client.metrics().unwrap();
+    assert_eq!(metrics.queries_executed, 9);
+    assert_eq!(metrics.queries_failed, 2);
+    assert_eq!(metrics.rows_returned, 5);
+    // Every mutating statement the primary applied is recorded for replicas; the duplicate
+    // insert and the batch's SELECTs are not
+    assert_eq!(metrics.events_sent, 6);
+    assert_eq!(metrics.events_received, 0);
+}
+
+#[test]
+fn server_binds_to_configured_address() {
+    let port = free_port();
+    let options = ServerOptions {
+        bind_addr: "127.0.0.1".parse().unwrap(),
+        ..ServerOptions::default()
+    };
+    let server = start_server_with_options(port, None, options).unwrap();
+    assert_eq!(server.address().to_string(), format!("127.0.0.1:{}", port));
+    assert_eq!(RustDBClient::new("127.0.0.1", port).ping().unwrap(), "pong");
+}
+
+#[test]
+fn admin_reset_wipes_primary_and_replicas() {
+    // Without an auth token the reset is refused
+    let open_port = free_port();
+    let _open = start_server(open_port, None);
+    let open = RustDBClient::new("127.0.0.1", open_port);
+    open.execute("CREATE TABLE Users (id INT PRIMARY KEY)").unwrap();
+    assert!(open.admin_reset().is_err());
+    assert_eq!(open.list_tables().unwrap(), vec!["Users".to_string()]);
+
+    let primary_port = free_port();
+    let _primary = start_server_with_auth(primary_port, None, Some("secret".to_string()));
+    let primary = RustDBClient::new("127.0.0.1", primary_port).with_token("secret");
+    primary.execute("CREATE TABLE Users (id INT PRIMARY KEY)").unwrap();
+    primary.execute("INSERT INTO Users VALUES (1)").unwrap();
+
+    let replica_port = free_port();
+    let mut config = ReplicationConfig::new_replica(format!("http://127.0.0.1:{}", primary_port));
+    config.sync_interval = Duration::from_millis(100);
+    config.primary_auth_token = Some("secret".to_string());
+    let _replica = start_server(replica_port, Some(config));
+    let replica = RustDBClient::new("127.0.0.1", replica_port);
+    let wait_for_sync = || {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !verify_replica(&primary, &replica).unwrap() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    };
+    wait_for_sync();
+    assert_eq!(replica.list_tables().unwrap(), vec!["Users".to_string()]);
+    assert!(replica.admin_reset().is_err());
+
+    primary.admin_reset().unwrap();
+    assert!(primary.list_tables().unwrap().is_empty());
+    wait_for_sync();
+    assert!(replica.list_tables().unwrap().is_empty());
+
+    // The cluster keeps replicating after the reset
+    primary.execute("CREATE TABLE Posts (id INT PRIMARY KEY)").unwrap();
+    wait_for_sync();
+    assert_eq!(replica.list_tables().unwrap(), vec!["Posts".to_string()]);
+}
+
+#[test]
+fn describe_table_reports_schema() {
+    let port = free_port();
+    let _server = start_server(port, None);
+    let client = RustDBClient::new("127.0.0.1", port);
+    client
+        .execute("CREATE TABLE Products (id INT PRIMARY KEY, name STRING UNIQUE, price FLOAT, stock INT)")
+        .unwrap();
+
+    let description = client.describe_table("Products").unwrap();
+    assert_eq!(description.name, "Products");
+    let columns: Vec<(&str, ColumnType)> = description
+        .columns
+        .iter()
+        .map(|c| (c.name.as_str(), c.col_type.clone()))
+        .collect();
+    assert_eq!(
+        columns,
+        vec![
+            ("id", ColumnType::Int),
+            ("name", ColumnType::String),
+            ("price", ColumnType::Float),
+            ("stock", ColumnType::Int),
+        ]
+    );
+    assert_eq!(description.primary_key, vec!["id"]);
+    assert_eq!(description.unique_columns, vec![vec!["name".to_string()]]);
+    assert!(!description.columns[0].nullable);
+
+    let err = client.describe_table("Missing").unwrap_err();
+    assert_eq!(err.to_string(), "Table 'Missing' does not exist");
+}
+
+#[test]
+fn concurrent_reads_are_served() {
+    let port = free_port();
+    let _server = start_server(port, None);
+    let client = RustDBClient::new("127.0.0.1", port);
+    client
+        .execute("CREATE TABLE Users (id INT PRIMARY KEY, name STRING)")
+        .unwrap();
+    client.execute("INSERT INTO Users VALUES (1, 'Alice')").unwrap();
+
+    let handles: Vec<_> = (0..8)
+        .map(|i| {
+            std::thread::spawn(move || {
+                let client = RustDBClient::new("127.0.0.1", port);
+                for _ in 0..5 {
+                    if i % 2 == 0 {
+                        assert_eq!(client.ping().unwrap(), "pong");
+                    } else {
+                        let response = client.execute("SELECT name FROM Users").unwrap();
+                        assert_eq!(response.rows, Some(vec![vec!["Alice".to_string()]]));
+                    }
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+#[test]
+fn tls_client_pings_tls_server() {
+    let port = free_port();
+    let tls = TlsConfig {
+        cert_path: "tests/fixtures/tls_cert.pem".to_string(),
+        key_path: "tests/fixtures/tls_key.pem".to_string(),
+    };
+    let _server = start_tls_server(port, None, &tls, ServerOptions::default()).unwrap();
+
+    let config = ClientConfig {
+        ca_cert: Some(tls.cert_path.clone()),
+        ..ClientConfig::default()
+    };
+    let client = RustDBClient::connect(&format!("https://localhost:{}", port), config).unwrap();
+    assert_eq!(client.ping().unwrap(), "pong");
+    client.execute("CREATE TABLE Users (id INT PRIMARY KEY)").unwrap();
+    assert_eq!(client.list_tables().unwrap(), vec!["Users".to_string()]);
+
+    // Without trusting the self-signed certificate the handshake fails
+    let untrusted = RustDBClient::connect(
+        &format!("https://localhost:{}", port),
+        ClientConfig {
+            retries: 0,
+            ..ClientConfig::default()
+        },
+    )
+    .unwrap();
+    assert!(untrusted.ping().is_err());
+    // Plain HTTP is not served on the TLS port
+    let plain = RustDBClient::with_config(
+        "127.0.0.1",
+        port,
+        ClientConfig {
+            retries: 0,
+            ..ClientConfig::default()
+        },
+    );
+    assert!(plain.ping().is_err());
+}
+
+#[test]
+fn auth_token_is_required_when_configured() {
+    let port = free_port();
+    let _server = start_server_with_auth(port, None, Some("s3cret".to_string()));
+
+    let client = RustDBClient::new("127.0.0.1", port).with_token("s3cret");
+    assert_eq!(client.ping().unwrap(), "pong");
+    client.execute("CREATE TABLE Users (id INT PRIMARY KEY)").unwrap();
+    assert_eq!(client.list_tables().unwrap(), vec!["Users".to_string()]);
+
+    // Missing and wrong tokens are rejected before the statement runs
+    let err = RustDBClient::new("127.0.0.1", port).ping().unwrap_err();
+    assert!(err.to_string().contains("Unauthorized"), "{}", err);
+    let wrong = RustDBClient::new("127.0.0.1", port).with_token("guess");
+    assert!(wrong.execute("DROP TABLE Users").is_err());
+    assert_eq!(client.list_tables().unwrap(), vec!["Users".to_string()]);
+}
+
+#[test]
+fn server_without_auth_token_accepts_any_client() {
+    let port = free_port();
+    let _server = start_server(port, None);
+
+    let client = RustDBClient::new("127.0.0.1", port).with_token("unused");
+    assert_eq!(client.ping().unwrap(), "pong");
+    assert_eq!(RustDBClient::new("127.0.0.1", port).ping().unwrap(), "pong");
+}
+
+#[test]
+fn table_row_count_over_rpc() {
+    let port = free_port();
+    let _server = start_server(port, None);
+    let client = RustDBClient::new("127.0.0.1", port);
+    client.execute("CREATE TABLE Users (id INT PRIMARY KEY)").unwrap();
+    assert_eq!(client.table_row_count("Users").unwrap(), 0);
+    client
+        .execute_batch(&["INSERT INTO Users VALUES (1)", "INSERT INTO Users VALUES (2)"])
+        .unwrap();
+    assert_eq!(client.table_row_count("Users").unwrap(), 2);
+
+    let err = client.table_row_count("Missing").unwrap_err();
+    assert_eq!(err.to_string(), "Table 'Missing' does not exist");
+}
+
+#[test]
+fn in_sync_servers_report_equal_checksums() {
+    let (primary_port, replica_port) = (free_port(), free_port());
+    let _primary = start_server(primary_port, None);
+    let _replica = start_server(replica_port, None);
+    let primary = RustDBClient::new("127.0.0.1", primary_port);
+    let replica = RustDBClient::new("127.0.0.1", replica_port);
+    let statements = ["CREATE TABLE Users (id INT PRIMARY KEY)", "INSERT INTO Users VALUES (1)"];
+    primary.execute_batch(&statements).unwrap();
+    replica.execute_batch(&statements).unwrap();
+
+    let checksum = primary.replication_checksum().unwrap();
+    assert_eq!(checksum.len(), 64);
+    assert_eq!(replica.replication_checksum().unwrap(), checksum);
+    assert!(verify_replica(&primary, &replica).unwrap());
+
+    // Drift is detected
+    replica.execute("INSERT INTO Users VALUES (2)").unwrap();
+    assert!(!verify_replica(&primary, &replica).unwrap());
+}
+
+#[test]
+fn server_restores_and_saves_data_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("db.json").to_string_lossy().to_string();
+    let mut db = Database::new();
+    execute_sql(&mut db, "CREATE TABLE Users (id INT PRIMARY KEY, name STRING)").unwrap();
+    execute_sql(&mut db, "INSERT INTO Users VALUES (1, 'Alice')").unwrap();
+    db.save_to_file(&path).unwrap();
+
+    let port = free_port();
+    let options = ServerOptions {
+        data_file: Some(path.clone()),
+        ..ServerOptions::default()
+    };
+    let server = start_server_with_options(port, None, options).unwrap();
+    let client = RustDBClient::new("127.0.0.1", port);
+    assert_eq!(client.list_tables().unwrap(), vec!["Users".to_string()]);
+    assert_eq!(client.table_row_count("Users").unwrap(), 1);
+
+    // Shutting the server down saves writes made since startup
+    client.execute("INSERT INTO Users VALUES (2, 'Bob')").unwrap();
+    drop(server);
+    assert_eq!(Database::load_from_file(&path).unwrap().row_count("Users"), Some(2));
+}
+
+#[test]
+fn shutdown_ends_wait_and_saves() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("db.json").to_string_lossy().to_string();
+    let port = free_port();
+    let options = ServerOptions {
+        data_file: Some(path.clone()),
+        ..ServerOptions::default()
+    };
+    let server = start_server_with_options(port, None, options).unwrap();
+    let client = RustDBClient::new("127.0.0.1", port);
+    client.execute("CREATE TABLE Users (id INT PRIMARY KEY)").unwrap();
+    client.execute("INSERT INTO Users VALUES (1)").unwrap();
+
+    let handle = server.close_handle();
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(100));
+        handle.close();
+    });
+    server.wait();
+    // The save has happened by the time wait returns, and the server is gone
+    assert_eq!(Database::load_from_file(&path).unwrap().row_count("Users"), Some(1));
+    assert!(client.ping().is_err());
+}
+
+#[test]
+fn primary_autosaves_to_data_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("db.json").to_string_lossy().to_string();
+    let mut config = ReplicationConfig::new_primary();
+    config.autosave_interval = Duration::from_millis(100);
+    let options = ServerOptions {
+        data_file: Some(path.clone()),
+        ..ServerOptions::default()
+    };
+
+    let port = free_port();
+    let _server = start_server_with_options(port, Some(config), options).unwrap();
+    let client = RustDBClient::new("127.0.0.1", port);
+    client.execute("CREATE TABLE Users (id INT PRIMARY KEY)").unwrap();
+    client.execute("INSERT INTO Users VALUES (7)").unwrap();
+
+    // The server is still running, so only the periodic checkpoint can have written this
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let saved = Database::load_from_file(&path).ok().and_then(|db| db.row_count("Users"));
+        if saved == Some(1) {
+            break;
+        }
+        assert!(Instant::now() < deadline, "autosave did not happen in time");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn prepared_statements_bind_typed_params() {
+    let port = free_port();
+    let _server = start_server(port, None);
+    let client = RustDBClient::new("127.0.0.1", port);
+    client.execute("CREATE TABLE Users (id INT PRIMARY KEY, name STRING)").unwrap();
+    let insert = PreparedStatement::prepare("INSERT INTO Users VALUES (?, ?)").unwrap();
+    for (id, name) in [(1, "Alice"), (2, "Bob"), (3, "O'Brien")] {
+        let response = client.execute_prepared(&insert, vec![id.into(), name.into()]).unwrap();
+        assert!(response.success, "{}", response.message);
+    }
+
+    let by_id = PreparedStatement::prepare("SELECT name FROM Users WHERE id == ?").unwrap();
+    let response = client.execute_prepared(&by_id, vec![Value::Int(2)]).unwrap();
+    assert_eq!(response.rows, Some(vec![vec!["Bob".to_string()]]));
+
+    let by_name = PreparedStatement::prepare("SELECT id FROM Users WHERE name == ?").unwrap();
+    let response = client.execute_prepared(&by_name, vec!["O'Brien".into()]).unwrap();
+    assert_eq!(response.rows, Some(vec![vec!["3".to_string()]]));
+    // A parameter is only ever a value, never more SQL
+    let response = client
+        .execute_prepared(&by_name, vec!["x'; DROP TABLE Users; --".into()])
+        .unwrap();
+    assert_eq!(response.rows, Some(vec![]));
+    assert_eq!(client.table_row_count("Users").unwrap(), 3);
+
+    // The wrong number of parameters is rejected before anything runs
+    assert!(client.execute_prepared(&by_id, vec![]).is_err());
+    let raw = rpc_call(port, "execute_prepared", serde_json::json!([by_id.sql(), [1, 2]]));
+    assert_eq!(raw["result"]["success"], false);
+}
+
+#[test]
+fn cursors_page_through_large_results() {
+    let port = free_port();
+    let _server = start_server(port, None);
+    let client = RustDBClient::new("127.0.0.1", port);
+    client.execute("CREATE TABLE Items (id INT PRIMARY KEY, label STRING)").unwrap();
+    let inserts: Vec<String> =
+        (1..=250).map(|id| format!("INSERT INTO Items VALUES ({}, 'item{}')", id, id)).collect();
+    client.execute_batch(&inserts.iter().map(String::as_str).collect::<Vec<_>>()).unwrap();
+
+    let pages: Vec<Vec<Vec<String>>> =
+        client.select_pages("SELECT * FROM Items", 100).unwrap().map(Result::unwrap).collect();
+    assert_eq!(pages.iter().map(Vec::len).collect::<Vec<_>>(), vec![100, 100, 50]);
+    let all = client.execute("SELECT * FROM Items").unwrap().rows.unwrap();
+    assert_eq!(pages.concat(), all);
+
+    // A cursor read to the end is freed
+    let id = client.select_open("SELECT id FROM Items WHERE id > 240").unwrap();
+    let page = client.select_fetch(id, 100).unwrap();
+    assert_eq!((page.rows.len(), page.done), (10, true));
+    assert!(client.select_fetch(id, 100).is_err());
+
+    // So is one closed early
+    let id = client.select_open("SELECT * FROM Items").unwrap();
+    assert!(!client.select_fetch(id, 10).unwrap().done);
+    assert!(client.select_close(id).unwrap());
+    assert!(client.select_fetch(id, 10).is_err());
+
+    assert!(client.select_open("DELETE FROM Items").is_err());
+    assert!(client.select_open("SELECT * FROM Missing").is_err());
+}
+
+#[test]
+fn client_counts_rows() {
+    let port = free_port();
+    let _server = start_server(port, None);
+    let client = RustDBClient::new("127.0.0.1", port);
+    client.execute("CREATE TABLE Users (id INT PRIMARY KEY, age INT)").unwrap();
+    assert_eq!(client.count("Users", None).unwrap(), 0);
+    for (id, age) in [(1, 20), (2, 35), (3, 41)] {
+        client.execute(&format!("INSERT INTO Users VALUES ({}, {})", id, age)).unwrap();
+    }
+
+    assert_eq!(client.count("Users", None).unwrap(), 3);
+    assert_eq!(client.count("Users", Some("age > 30")).unwrap(), 2);
+    assert_eq!(client.count("Users", Some("age > 50")).unwrap(), 0);
+    let err = client.count("Missing", None).unwrap_err();
+    assert!(err.to_string().contains("Missing"), "{}", err);
+}
+
+#[test]
+fn atomic_batch_applies_all_or_nothing() {
+    let port = free_port();
+    let _server = start_server(port, None);
+    let client = RustDBClient::new("127.0.0.1", port);
+    client
+        .execute_batch(&[
+            "CREATE TABLE Users (id INT PRIMARY KEY, email STRING UNIQUE)",
+            "CREATE TABLE Orders (id INT PRIMARY KEY, user_id INT)",
+        ])
+        .unwrap();
+    let events_before = client.health().unwrap().event_seq;
+
+    let responses = client
+        .execute_batch_atomic(&[
+            "INSERT INTO Users VALUES (1, 'a@example.com')",
+            "INSERT INTO Orders VALUES (10, 1)",
+            "INSERT INTO Users VALUES (2, 'a@example.com')",
+            "INSERT INTO Orders VALUES (11, 2)",
+        ])
+        .unwrap();
+    assert_eq!(
+        responses.iter().map(|r| r.success).collect::<Vec<_>>(),
+        vec![false, false, false, false]
+    );
+    assert!(responses[0].message.contains("Rolled back"), "{}", responses[0].message);
+    assert!(responses[3].message.contains("Not executed"), "{}", responses[3].message);
+    assert_eq!(client.count("Users", None).unwrap(), 0);
+    assert_eq!(client.count("Orders", None).unwrap(), 0);
+    // Nothing was recorded for replicas either
+    assert_eq!(client.health().unwrap().event_seq, events_before);
+
+    let responses = client
+        .execute_batch_atomic(&[
+            "INSERT INTO Users VALUES (1, 'a@example.com')",
+            "INSERT INTO Orders VALUES (10, 1)",
+        ])
+        .unwrap();
+    assert!(responses.iter().all(|r| r.success));
+    assert_eq!(client.count("Orders", None).unwrap(), 1);
+    assert_eq!(client.health().unwrap().event_seq, events_before + 2);
+}
+
+#[test]
+fn sync_interval_comes_from_the_command_line_value() {
+    assert_eq!(ReplicationConfig::parse_sync_interval("2"), Ok(Duration::from_secs(2)));
+    assert!(ReplicationConfig::parse_sync_interval("0").unwrap_err().contains("at least 1"));
+    assert!(ReplicationConfig::parse_sync_interval("soon").is_err());
+    assert!(ReplicationConfig::parse_sync_interval("").is_err());
+
+    let primary_port = free_port();
+    let _primary = start_server(primary_port, None);
+    let primary = RustDBClient::new("127.0.0.1", primary_port);
+    primary.execute("CREATE TABLE Users (id INT PRIMARY KEY)").unwrap();
+
+    // A replica configured with a one second interval picks up new writes within a poll
+    let replica_port = free_port();
+    let mut config = ReplicationConfig::new_replica(format!("http://127.0.0.1:{}", primary_port));
+    config.sync_interval = ReplicationConfig::parse_sync_interval("1").unwrap();
+    let _replica = start_server(replica_port, Some(config));
+    let replica = RustDBClient::new("127.0.0.1", replica_port);
+    primary.execute("INSERT INTO Users VALUES (1)").unwrap();
+    let deadline = Instant::now() + Duration::from_secs(3);
+    while !verify_replica(&primary, &replica).unwrap() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    assert_eq!(replica.table_row_count("Users").unwrap(), 1);
+}
+
+#[test]
+fn rejected_statements_are_not_replicated() {
+    let port = free_port();
+    let _server = start_server(port, None);
+    let client = RustDBClient::new("127.0.0.1", port);
+    client.execute("CREATE TABLE Users (id INT PRIMARY KEY, name STRING)").unwrap();
+    client.execute("INSERT INTO Users VALUES (1, 'Alice')").unwrap();
+    assert_eq!(client.health().unwrap().event_seq, 2);
+
+    let response = client.execute("INSERT INTO Users VALUES (1, 'Again')").unwrap();
+    assert!(!response.success);
+    assert_eq!(client.health().unwrap().event_seq, 2);
+
+    // In a script, the statements before the failing one were applied and are replicated
+    let response = client
+        .execute("INSERT INTO Users VALUES (2, 'Bob'); INSERT INTO Users VALUES (2, 'Bobby')")
+        .unwrap();
+    assert!(!response.success);
+    assert_eq!(client.table_row_count("Users").unwrap(), 2);
+    let response = rpc_call(port, "replication_get_events", serde_json::json!([]));
+    let queries: Vec<&str> = response["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["query"].as_str().unwrap())
+        .collect();
+    assert_eq!(queries.len(), 3);
+    assert_eq!(queries[2], "INSERT INTO Users VALUES (2, 'Bob')");
+}
+
+#[test]
+fn slow_statements_time_out() {
+    let port = free_port();
+    let options = ServerOptions {
+        query_timeout: Some(Duration::from_millis(200)),
+        ..ServerOptions::default()
+    };
+    let _server = start_server_with_options(port, None, options).unwrap();
+    let client = RustDBClient::new("127.0.0.1", port);
+    let mut script = vec!["CREATE TABLE A (id INT, k INT)".to_string()];
+    script.push("CREATE TABLE B (id INT, k INT)".to_string());
+    for id in 0..1000 {
+        script.push(format!("INSERT INTO A VALUES ({}, 0); INSERT INTO B VALUES ({}, 0)", id, id));
+    }
+    assert!(client.execute(&script.join("; ")).unwrap().success);
+
+    // Every row of A matches every row of B: a million joined rows
+    let started = Instant::now();
+    let response = client.execute("SELECT * FROM A JOIN B ON A.k == B.k").unwrap();
+    assert!(!response.success);
+    assert_eq!(response.message, "Query timed out after 200ms");
+    assert!(started.elapsed() < Duration::from_secs(2));
+
+    // Quick statements still answer while the abandoned one finishes
+    let response = client.execute("SELECT id FROM A WHERE id == 7").unwrap();
+    assert_eq!(response.rows, Some(vec![vec!["7".to_string()]]));
+
+    // Batches and cursors are bounded by the same limit
+    let started = Instant::now();
+    let join = "SELECT * FROM A JOIN B ON A.k == B.k";
+    let responses = client.execute_batch(&["SELECT id FROM A WHERE id == 1", join]).unwrap();
+    assert_eq!(responses.len(), 2);
+    for response in responses {
+        assert!(!response.success);
+        assert_eq!(response.message, "Query timed out after 200ms");
+    }
+    let response = rpc_call(port, "select_open", serde_json::json!([join]));
+    assert_eq!(response["error"]["message"], "Query timed out after 200ms");
+    assert!(started.elapsed() < Duration::from_secs(4));
+}
+
+#[test]
+fn timed_out_writes_are_rolled_back() {
+    let port = free_port();
+    let options = ServerOptions {
+        query_timeout: Some(Duration::from_millis(200)),
+        ..ServerOptions::default()
+    };
+    let _server = start_server_with_options(port, None, options).unwrap();
+    let client = RustDBClient::new("127.0.0.1", port);
+    let mut script = vec!["CREATE TABLE A (id INT, k INT)".to_string()];
+    script.push("CREATE TABLE B (id INT, k INT); CREATE TABLE C (id INT)".to_string());
+    for id in 0..1000 {
+        script.push(format!("INSERT INTO A VALUES ({}, 0); INSERT INTO B VALUES ({}, 0)", id, id));
+    }
+    assert!(client.execute(&script.join("; ")).unwrap().success);
+
+    // The insert is undone once the slow join finishes, and never replicated
+    let slow = "INSERT INTO C VALUES (1); SELECT * FROM A JOIN B ON A.k == B.k";
+    let response = client.execute(slow).unwrap();
+    assert_eq!(response.message, "Query timed out after 200ms");
+    assert_eq!(client.table_row_count("C").unwrap(), 0);
+    let events = rpc_call(port, "replication_get_events", serde_json::json!([]));
+    let events = events["result"].as_array().unwrap();
+    assert!(events.iter().all(|e| !e["query"].as_str().unwrap().contains("INTO C")));
+
+    // Inside the client's own transaction it cannot be undone alone, and the client is told
+    assert!(client.execute("BEGIN").unwrap().success);
+    let response = client.execute(slow).unwrap();
+    assert_eq!(response.message, "Query timed out after 200ms; it may still be applied");
+    assert_eq!(client.table_row_count("C").unwrap(), 1);
+    assert!(client.execute("ROLLBACK").unwrap().success);
+    assert_eq!(client.table_row_count("C").unwrap(), 0);
+}
+
+#[test]
+fn execute_typed_preserves_column_types() {
+    let port = free_port();
+    let _server = start_server(port, None);
+    let client = RustDBClient::new("127.0.0.1", port);
+    client
+        .execute_batch(&[
+            "CREATE TABLE Products (id INT PRIMARY KEY, name STRING, price FLOAT, stock INT)",
+            "INSERT INTO Products VALUES (1, 'Pen', 2.5, 100)",
+            "INSERT INTO Products VALUES (2, 'Pencil', 1.0, NULL)",
+        ])
+        .unwrap();
+
+    let response = client.execute_typed("SELECT id, name, price, stock FROM Products").unwrap();
+    assert!(response.success);
+    let columns = response.columns.unwrap();
+    let types: Vec<_> = columns.iter().map(|c| c.col_type.clone()).collect();
+    assert_eq!(
+        types,
+        vec![
+            Some(ColumnType::Int),
+            Some(ColumnType::String),
+            Some(ColumnType::Float),
+            Some(ColumnType::Int)
+        ]
+    );
+    assert_eq!(
+        response.rows.unwrap(),
+        vec![
+            vec![Value::Int(1), Value::from("Pen"), Value::Float(2.5), Value::Int(100)],
+            vec![Value::Int(2), Value::from("Pencil"), Value::Float(1.0), Value::Null],
+        ]
+    );
+
+    // On the wire an Int column is a JSON number, not a string
+    let raw = rpc_call(port, "execute_typed", serde_json::json!(["SELECT id FROM Products"]));
+    assert_eq!(raw["result"]["rows"][0][0], serde_json::json!(1));
+    assert_eq!(raw["result"]["columns"][0]["col_type"], "Int");
+
+    // Aggregates are typed too, and other statements carry no columns
+    let response = client.execute_typed("SELECT COUNT(*), AVG(price) FROM Products").unwrap();
+    assert_eq!(response.rows.unwrap(), vec![vec![Value::Int(2), Value::Float(1.75)]]);
+    let response = client.execute_typed("DELETE FROM Products WHERE id == 2").unwrap();
+    assert!(response.success && response.columns.is_none() && response.rows.is_none());
+
+    // A NULL String cell stays NULL instead of reading back as an empty string
+    client
+        .execute_batch(&[
+            "INSERT INTO Products (id, price) VALUES (3, 0.5)",
+            "INSERT INTO Products VALUES (4, '', 0.5, 1)",
+        ])
+        .unwrap();
+    let response = client.execute_typed("SELECT name FROM Products WHERE id >= 3").unwrap();
+    assert_eq!(response.rows.unwrap(), vec![vec![Value::Null], vec![Value::from("")]]);
+    let response = client.execute_typed("SELECT * FROM Missing").unwrap();
+    assert!(!response.success);
+
+    // The string-based execute is unchanged
+    let response = client.execute("SELECT id FROM Products WHERE id == 1").unwrap();
+    assert_eq!(response.rows, Some(vec![vec!["1".to_string()]]));
+}
+
+#[test]
+fn server_info_reports_versions() {
+    let port = free_port();
+    let _server = start_server(port, None);
+    let client = RustDBClient::new("127.0.0.1", port);
+
+    let info = client.server_info().unwrap();
+    assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(info.protocol_version, PROTOCOL_VERSION);
+    assert!(info.is_compatible());
+    assert!(info.uptime_secs < 60);
+    // The plain liveness check is unchanged
+    assert_eq!(client.ping().unwrap(), "pong");
+
+    let newer = ServerInfo {
+        protocol_version: PROTOCOL_VERSION + 1,
+        ..info
+    };
+    assert!(!newer.is_compatible());
+}
@@ -1,4 +1,22 @@
 use lab::*;
+use lab::replication::{ReplicationConfig, ReplicationEvent, ReplicationManager};
+use std::sync::{Arc, RwLock};
+
+/// Runs a SELECT and returns its rows, panicking if it fails.
+fn select(db: &mut Database, sql: &str) -> Vec<Vec<String>> {
+    match execute_sql(db, sql) {
+        Ok(SqlOutcome::Selected { rows, .. }) => rows,
+        other => panic!("expected rows from {:?}, got {:?}", sql, other),
+    }
+}
+
+/// Builds a typed row from raw values according to the columns.
+fn typed_row(cols: &[ColumnSchema], raw: &[&str]) -> Vec<Value> {
+    cols.iter()
+        .zip(raw)
+        .map(|(col, v)| Value::from_column(col, v).unwrap())
+        .collect()
+}
 
 #[test]
 fn query_predicates_unit() {
@@ -6,52 +24,34 @@ fn query_predicates_unit() {
         ColumnSchema {
             name: "id".to_string(),
             col_type: ColumnType::Int,
+            nullable: true,
+            default: None,
         },
         ColumnSchema {
             name: "price".to_string(),
             col_type: ColumnType::Float,
+            nullable: true,
+            default: None,
         },
         ColumnSchema {
             name: "name".to_string(),
             col_type: ColumnType::String,
+            nullable: true,
+            default: None,
         },
     ];
 
     let eq = query_to_predicate(&cols, "id == 10");
-    assert!(eq(&vec![
-        "10".to_string(),
-        "1.0".to_string(),
-        "x".to_string()
-    ]));
-    assert!(!eq(&vec![
-        "11".to_string(),
-        "1.0".to_string(),
-        "x".to_string()
-    ]));
+    assert!(eq(&typed_row(&cols, &["10", "1.0", "x"])));
+    assert!(!eq(&typed_row(&cols, &["11", "1.0", "x"])));
 
     let gt = query_to_predicate(&cols, "price > 2.5");
-    assert!(gt(&vec![
-        "1".to_string(),
-        "3.0".to_string(),
-        "x".to_string()
-    ]));
-    assert!(!gt(&vec![
-        "1".to_string(),
-        "2.0".to_string(),
-        "x".to_string()
-    ]));
+    assert!(gt(&typed_row(&cols, &["1", "3.0", "x"])));
+    assert!(!gt(&typed_row(&cols, &["1", "2.0", "x"])));
 
     let neq = query_to_predicate(&cols, "name != 'Bob'");
-    assert!(neq(&vec![
-        "1".to_string(),
-        "1.0".to_string(),
-        "Alice".to_string()
-    ]));
-    assert!(!neq(&vec![
-        "1".to_string(),
-        "1.0".to_string(),
-        "Bob".to_string()
-    ]));
+    assert!(neq(&typed_row(&cols, &["1", "1.0", "Alice"])));
+    assert!(!neq(&typed_row(&cols, &["1", "1.0", "Bob"])));
 }
 
 #[test]
@@ -62,16 +62,24 @@ fn sql_parser_and_select_insert_unit() {
     execute_sql(
         &mut db,
         "CREATE TABLE T(a INT PRIMARY KEY, b STRING UNIQUE, c FLOAT);",
-    );
+    )
+    .unwrap();
     // ensure created
     let t = db.tables.get("T").expect("table T exists");
     assert_eq!(t.schema.columns.len(), 3);
 
     // parse a select statement using execute_sql's internal select path (indirect test)
-    execute_sql(&mut db, "SELECT a, b FROM T WHERE a == 1");
+    assert!(select(&mut db, "SELECT a, b FROM T WHERE a == 1").is_empty());
 
-    // insert parsing path (indirect) -- ensure no panic
-    execute_sql(&mut db, "INSERT INTO T (a, b, c) VALUES (1, 'x', 2.0);");
+    // insert parsing path (indirect)
+    assert_eq!(
+        execute_sql(&mut db, "INSERT INTO T (a, b, c) VALUES (1, 'x', 2.0);"),
+        Ok(SqlOutcome::Inserted(1))
+    );
+    assert_eq!(
+        select(&mut db, "SELECT a, b FROM T WHERE a == 1"),
+        vec![vec!["1".to_string(), "x".to_string()]]
+    );
 }
 
 #[test]
@@ -83,21 +91,28 @@ fn table_constraints_unit() {
             ColumnSchema {
                 name: "id".to_string(),
                 col_type: ColumnType::Int,
+                nullable: true,
+                default: None,
             },
             ColumnSchema {
                 name: "name".to_string(),
                 col_type: ColumnType::String,
+                nullable: true,
+                default: None,
             },
         ],
-        Some("id".to_string()),
+        vec!["id".to_string()],
         vec![],
     );
 
-    db.insert("Utest", vec!["1".to_string(), "A".to_string()]);
-    db.insert("Utest", vec!["2".to_string(), "B".to_string()]);
+    db.insert("Utest", vec!["1".to_string(), "A".to_string()]).unwrap();
+    db.insert("Utest", vec!["2".to_string(), "B".to_string()]).unwrap();
     // duplicate primary key rejected
-    db.insert("Utest", vec!["1".to_string(), "C".to_string()]);
-    assert_eq!(db.tables.get("Utest").unwrap().rows.len(), 2);
+    assert_eq!(
+        db.insert("Utest", vec!["1".to_string(), "C".to_string()]),
+        Err(SqlError::PrimaryKeyViolation("id".to_string()))
+    );
+    assert_eq!(db.row_count("Utest").unwrap(), 2);
 }
 
 #[test]
@@ -108,46 +123,2702 @@ fn select_operations_unit() {
     execute_sql(
         &mut db,
         "CREATE TABLE Products (id INT PRIMARY KEY, name STRING, price FLOAT, stock INT);",
-    );
+    )
+    .unwrap();
     
     // Insert test data
-    execute_sql(&mut db, "INSERT INTO Products VALUES (1, 'Pen', 2.5, 100);");
-    execute_sql(&mut db, "INSERT INTO Products VALUES (2, 'Pencil', 1.2, 50);");
-    execute_sql(&mut db, "INSERT INTO Products VALUES (3, 'Eraser', 0.8, 30);");
+    execute_sql(&mut db, "INSERT INTO Products VALUES (1, 'Pen', 2.5, 100);").unwrap();
+    execute_sql(&mut db, "INSERT INTO Products VALUES (2, 'Pencil', 1.2, 50);").unwrap();
+    execute_sql(&mut db, "INSERT INTO Products VALUES (3, 'Eraser', 0.8, 30);").unwrap();
     
     // Test 1: Basic SELECT * without WHERE clause
-    execute_sql(&mut db, "SELECT * FROM Products");
+    assert_eq!(select(&mut db, "SELECT * FROM Products").len(), 3);
     let products = db.tables.get("Products").unwrap();
     assert_eq!(products.rows.len(), 3);
     
     // Test 2: SELECT with specific columns
-    execute_sql(&mut db, "SELECT id, name FROM Products");
+    let rows = select(&mut db, "SELECT id, name FROM Products");
+    assert_eq!(rows[0], vec!["1".to_string(), "Pen".to_string()]);
     
     // Test 3: SELECT with WHERE clause on different data types
     // Integer comparison
-    execute_sql(&mut db, "SELECT * FROM Products WHERE id > 1");
-    execute_sql(&mut db, "SELECT * FROM Products WHERE stock <= 50");
+    assert_eq!(select(&mut db, "SELECT * FROM Products WHERE id > 1").len(), 2);
+    assert_eq!(select(&mut db, "SELECT * FROM Products WHERE stock <= 50").len(), 2);
     
     // Float comparison
-    execute_sql(&mut db, "SELECT * FROM Products WHERE price > 2.0");
+    assert_eq!(select(&mut db, "SELECT * FROM Products WHERE price > 2.0").len(), 1);
     
     // String comparison
-    execute_sql(&mut db, "SELECT * FROM Products WHERE name == 'Pen'");
+    assert_eq!(select(&mut db, "SELECT * FROM Products WHERE name == 'Pen'").len(), 1);
     
     // Test 4: SELECT with invalid table (should not panic)
-    execute_sql(&mut db, "SELECT * FROM NonExistentTable");
+    assert_eq!(
+        execute_sql(&mut db, "SELECT * FROM NonExistentTable"),
+        Err(SqlError::TableNotFound("NonExistentTable".to_string()))
+    );
     
     // Test 5: SELECT with complex WHERE conditions
-    execute_sql(&mut db, "SELECT * FROM Products WHERE price > 1.0");
-    let products = db.tables.get("Products").unwrap();
-    let expensive_products = products.rows.iter()
-        .filter(|row| row.get_values()[2].parse::<f64>().unwrap() > 1.0)
-        .count();
+    let expensive_products = select(&mut db, "SELECT * FROM Products WHERE price > 1.0").len();
     assert_eq!(expensive_products, 2); // Pen and Pencil are > 1.0
     
     // Test 6: SELECT with no matching rows
-    execute_sql(&mut db, "SELECT * FROM Products WHERE price > 10.0");
+    assert!(select(&mut db, "SELECT * FROM Products WHERE price > 10.0").is_empty());
     
-    // Test 7: SELECT with invalid column in WHERE clause (should not panic)
-    execute_sql(&mut db, "SELECT * FROM Products WHERE invalid_column > 10");
+    // Test 7: SELECT with invalid column in WHERE clause is an error (and does not panic)
+    assert_eq!(
+        execute_sql(&mut db, "SELECT * FROM Products WHERE invalid_column > 10"),
+        Err(SqlError::ColumnNotFound("invalid_column".to_string()))
+    );
+
+    // Test 8: SELECT of an unknown projected column is an error
+    assert_eq!(
+        execute_sql(&mut db, "SELECT nope FROM Products"),
+        Err(SqlError::ColumnNotFound("nope".to_string()))
+    );
+}
+
+#[test]
+fn compound_predicates_unit() {
+    let cols = vec![
+        ColumnSchema {
+            name: "id".to_string(),
+            col_type: ColumnType::Int,
+            nullable: true,
+            default: None,
+        },
+        ColumnSchema {
+            name: "price".to_string(),
+            col_type: ColumnType::Float,
+            nullable: true,
+            default: None,
+        },
+        ColumnSchema {
+            name: "name".to_string(),
+            col_type: ColumnType::String,
+            nullable: true,
+            default: None,
+        },
+    ];
+    let row = |id: &str, price: &str, name: &str| typed_row(&cols, &[id, price, name]);
+
+    let and = query_to_predicate(&cols, "price > 1.0 AND id < 3");
+    assert!(and(&row("2", "1.5", "Pen")));
+    assert!(!and(&row("3", "1.5", "Pen")));
+    assert!(!and(&row("2", "0.5", "Pen")));
+
+    let or = query_to_predicate(&cols, "name == 'Pen'  or  id == 7");
+    assert!(or(&row("1", "1.0", "Pen")));
+    assert!(or(&row("7", "1.0", "Eraser")));
+    assert!(!or(&row("1", "1.0", "Eraser")));
+
+    // AND binds tighter than OR: id == 1 OR (price > 2.0 AND name != 'Bob')
+    let mixed = query_to_predicate(&cols, "id == 1 OR price > 2.0 AND name != 'Bob'");
+    assert!(mixed(&row("1", "0.1", "Bob")));
+    assert!(mixed(&row("5", "3.0", "Alice")));
+    assert!(!mixed(&row("5", "3.0", "Bob")));
+
+    // Keywords inside string literals are not connectors
+    let quoted = query_to_predicate(&cols, "name == 'Salt AND Pepper'");
+    assert!(quoted(&row("1", "1.0", "Salt AND Pepper")));
+
+    // A dangling connector matches nothing
+    let dangling = query_to_predicate(&cols, "id == 1 AND");
+    assert!(!dangling(&row("1", "1.0", "Pen")));
+}
+
+#[test]
+fn grouped_predicates_unit() {
+    let cols = vec![
+        ColumnSchema {
+            name: "a".to_string(),
+            col_type: ColumnType::Int,
+            nullable: true,
+            default: None,
+        },
+        ColumnSchema {
+            name: "name".to_string(),
+            col_type: ColumnType::String,
+            nullable: true,
+            default: None,
+        },
+    ];
+    let row = |a: &str, name: &str| typed_row(&cols, &[a, name]);
+
+    let grouped = query_to_predicate(&cols, "(a == 1 OR a == 2) AND name != 'Bob'");
+    assert!(grouped(&row("1", "Alice")));
+    assert!(grouped(&row("2", "Carol")));
+    assert!(!grouped(&row("2", "Bob")));
+    assert!(!grouped(&row("3", "Alice")));
+
+    // Without the parentheses AND binds first, so a == 1 matches even for Bob
+    let ungrouped = query_to_predicate(&cols, "a == 1 OR a == 2 AND name != 'Bob'");
+    assert!(ungrouped(&row("1", "Bob")));
+    assert!(!grouped(&row("1", "Bob")));
+
+    // Nested and redundant parentheses
+    let nested = query_to_predicate(&cols, "((a == 1) OR (a == 3 AND (name == 'Eve')))");
+    assert!(nested(&row("1", "Bob")));
+    assert!(nested(&row("3", "Eve")));
+    assert!(!nested(&row("3", "Bob")));
+    let redundant = query_to_predicate(&cols, "(((a == 2)))");
+    assert!(redundant(&row("2", "x")));
+
+    // Parentheses inside string literals are not grouping
+    let literal = query_to_predicate(&cols, "name == 'f(x)' AND (a == 1)");
+    assert!(literal(&row("1", "f(x)")));
+
+    // Mismatched parentheses match nothing
+    for bad in ["(a == 1", "a == 1)", "(a == 1 OR (a == 2)", "()"] {
+        let pred = query_to_predicate(&cols, bad);
+        assert!(!pred(&row("1", "x")), "{} should match nothing", bad);
+    }
+}
+
+/// Creates the demo Products table used by several SELECT tests.
+fn products_db() -> Database {
+    let mut db = Database::new();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Products (id INT PRIMARY KEY, name STRING, price FLOAT, stock INT);",
+    )
+    .unwrap();
+    execute_sql(&mut db, "INSERT INTO Products VALUES (1, 'Pen', 2.5, 100);").unwrap();
+    execute_sql(&mut db, "INSERT INTO Products VALUES (2, 'Pencil', 1.2, 50);").unwrap();
+    execute_sql(&mut db, "INSERT INTO Products VALUES (3, 'Eraser', 0.8, 30);").unwrap();
+    db
+}
+
+#[test]
+fn order_by_unit() {
+    let mut db = products_db();
+
+    // Float column sorted descending
+    let rows = select(&mut db, "SELECT name, price FROM Products ORDER BY price DESC");
+    let prices: Vec<&str> = rows.iter().map(|r| r[1].as_str()).collect();
+    assert_eq!(prices, vec!["2.5", "1.2", "0.8"]);
+
+    // Default direction is ASC, and the sort column need not be projected
+    let rows = select(&mut db, "SELECT name FROM Products ORDER BY stock");
+    assert_eq!(
+        rows,
+        vec![
+            vec!["Eraser".to_string()],
+            vec!["Pencil".to_string()],
+            vec!["Pen".to_string()]
+        ]
+    );
+
+    // String columns sort lexicographically, combined with WHERE
+    let rows = select(
+        &mut db,
+        "SELECT name FROM Products WHERE price > 1.0 order by name desc",
+    );
+    assert_eq!(rows, vec![vec!["Pencil".to_string()], vec!["Pen".to_string()]]);
+
+    // Unknown sort column or direction is an error
+    assert_eq!(
+        execute_sql(&mut db, "SELECT * FROM Products ORDER BY weight"),
+        Err(SqlError::ColumnNotFound("weight".to_string()))
+    );
+    assert!(matches!(
+        execute_sql(&mut db, "SELECT * FROM Products ORDER BY price SIDEWAYS"),
+        Err(SqlError::Parse(_))
+    ));
+}
+
+#[test]
+fn limit_offset_unit() {
+    let mut db = products_db();
+
+    let rows = select(&mut db, "SELECT id FROM Products LIMIT 2 OFFSET 1");
+    assert_eq!(rows, vec![vec!["2".to_string()], vec!["3".to_string()]]);
+
+    // LIMIT applies after ORDER BY
+    let rows = select(&mut db, "SELECT id FROM Products ORDER BY price DESC LIMIT 1");
+    assert_eq!(rows, vec![vec!["1".to_string()]]);
+
+    // ...and after WHERE
+    let rows = select(&mut db, "SELECT id FROM Products WHERE stock < 100 LIMIT 5");
+    assert_eq!(rows.len(), 2);
+
+    assert!(select(&mut db, "SELECT * FROM Products LIMIT 0").is_empty());
+    assert!(select(&mut db, "SELECT * FROM Products LIMIT 10 OFFSET 3").is_empty());
+    assert_eq!(select(&mut db, "SELECT * FROM Products OFFSET 2").len(), 1);
+
+    assert!(matches!(
+        execute_sql(&mut db, "SELECT * FROM Products LIMIT -1"),
+        Err(SqlError::Parse(_))
+    ));
+}
+
+#[test]
+fn aggregate_functions_unit() {
+    let mut db = products_db();
+
+    let rows = select(
+        &mut db,
+        "SELECT COUNT(*), SUM(stock), AVG(stock), MIN(price), MAX(price) FROM Products",
+    );
+    assert_eq!(rows, vec![vec!["3", "180", "60", "0.8", "2.5"]]);
+
+    // Aggregates see only the rows matching WHERE
+    let rows = select(&mut db, "SELECT count(*), min(name) FROM Products WHERE stock < 100");
+    assert_eq!(rows, vec![vec!["2", "Eraser"]]);
+
+    // Aggregates over no rows: COUNT is 0, the others are empty
+    let rows = select(
+        &mut db,
+        "SELECT COUNT(id), SUM(stock), MAX(name) FROM Products WHERE id > 10",
+    );
+    assert_eq!(rows, vec![vec!["0", "", ""]]);
+
+    // An integer SUM that overflows is NULL instead of wrapping or panicking
+    execute_sql(&mut db, "UPDATE Products SET stock = 9223372036854775807 WHERE id == 1").unwrap();
+    let rows = select(&mut db, "SELECT SUM(stock) FROM Products");
+    assert_eq!(rows, vec![vec![""]]);
+    let rows = select(&mut db, "SELECT SUM(stock) FROM Products WHERE id == 1");
+    assert_eq!(rows, vec![vec!["9223372036854775807"]]);
+
+    // Column headers are the aggregate expressions
+    match execute_sql(&mut db, "SELECT COUNT(*), AVG(price) FROM Products") {
+        Ok(SqlOutcome::Selected { columns, .. }) => {
+            assert_eq!(columns, vec!["COUNT(*)", "AVG(price)"])
+        }
+        other => panic!("expected rows, got {:?}", other),
+    }
+
+    assert!(matches!(
+        execute_sql(&mut db, "SELECT SUM(name) FROM Products"),
+        Err(SqlError::InvalidAggregate(_))
+    ));
+    assert_eq!(
+        execute_sql(&mut db, "SELECT MAX(weight) FROM Products"),
+        Err(SqlError::ColumnNotFound("weight".to_string()))
+    );
+    // Plain columns cannot be mixed with aggregates without GROUP BY
+    assert!(matches!(
+        execute_sql(&mut db, "SELECT name, COUNT(*) FROM Products"),
+        Err(SqlError::Parse(_))
+    ));
+}
+
+#[test]
+fn group_by_unit() {
+    let mut db = Database::new();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Orders (id INT PRIMARY KEY, name STRING, region STRING, amount INT)",
+    )
+    .unwrap();
+    for values in [
+        "1, 'Alice', 'north', 10",
+        "2, 'Bob', 'south', 5",
+        "3, 'Alice', 'north', 7",
+        "4, 'Carol', 'north', 3",
+        "5, 'Bob', 'north', 20",
+    ] {
+        execute_sql(&mut db, &format!("INSERT INTO Orders VALUES ({})", values)).unwrap();
+    }
+
+    // One row per group, in order of first appearance
+    let rows = select(&mut db, "SELECT name, COUNT(*), SUM(amount) FROM Orders GROUP BY name");
+    assert_eq!(
+        rows,
+        vec![
+            vec!["Alice", "2", "17"],
+            vec!["Bob", "2", "25"],
+            vec!["Carol", "1", "3"],
+        ]
+    );
+
+    // Multiple grouping columns, combined with WHERE and ORDER BY
+    let rows = select(
+        &mut db,
+        "SELECT region, name, MAX(amount) FROM Orders WHERE amount > 4 \
+         GROUP BY region, name ORDER BY name DESC",
+    );
+    assert_eq!(
+        rows,
+        vec![
+            vec!["south", "Bob", "5"],
+            vec!["north", "Bob", "20"],
+            vec!["north", "Alice", "10"],
+        ]
+    );
+
+    // GROUP BY without aggregates yields the distinct keys
+    let rows = select(&mut db, "SELECT region FROM Orders GROUP BY region");
+    assert_eq!(rows, vec![vec!["north"], vec!["south"]]);
+
+    // No matching rows means no groups
+    let rows = select(
+        &mut db,
+        "SELECT name, COUNT(*) FROM Orders WHERE id > 9 GROUP BY name",
+    );
+    assert!(rows.is_empty());
+
+    // Selected columns must be grouped or aggregated
+    assert!(matches!(
+        execute_sql(&mut db, "SELECT region, COUNT(*) FROM Orders GROUP BY name"),
+        Err(SqlError::Parse(_))
+    ));
+    assert_eq!(
+        execute_sql(&mut db, "SELECT COUNT(*) FROM Orders GROUP BY city"),
+        Err(SqlError::ColumnNotFound("city".to_string()))
+    );
+}
+
+#[test]
+fn inner_join_unit() {
+    let mut db = Database::new();
+    execute_sql(&mut db, "CREATE TABLE Users (id INT PRIMARY KEY, name STRING)").unwrap();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Orders (id INT PRIMARY KEY, user_id INT, total FLOAT)",
+    )
+    .unwrap();
+    execute_sql(&mut db, "INSERT INTO Users VALUES (1, 'Alice')").unwrap();
+    execute_sql(&mut db, "INSERT INTO Users VALUES (2, 'Bob')").unwrap();
+    execute_sql(&mut db, "INSERT INTO Users VALUES (3, 'Carol')").unwrap();
+    execute_sql(&mut db, "INSERT INTO Orders VALUES (10, 1, 9.5)").unwrap();
+    execute_sql(&mut db, "INSERT INTO Orders VALUES (11, 2, 20.0)").unwrap();
+    execute_sql(&mut db, "INSERT INTO Orders VALUES (12, 1, 3.25)").unwrap();
+    execute_sql(&mut db, "INSERT INTO Orders VALUES (13, 7, 1.0)").unwrap();
+
+    // Only orders with a matching user are returned; Carol has no orders
+    let rows = select(
+        &mut db,
+        "SELECT Orders.id, Users.name FROM Orders JOIN Users ON Orders.user_id == Users.id",
+    );
+    assert_eq!(
+        rows,
+        vec![vec!["10", "Alice"], vec!["11", "Bob"], vec!["12", "Alice"]]
+    );
+
+    // ON sides may be written in either order; WHERE and ORDER BY see qualified columns
+    let rows = select(
+        &mut db,
+        "SELECT Users.name, Orders.total FROM Orders INNER JOIN Users ON Users.id = Orders.user_id \
+         WHERE Orders.total > 5 ORDER BY Orders.total DESC",
+    );
+    assert_eq!(rows, vec![vec!["Bob", "20.0"], vec!["Alice", "9.5"]]);
+
+    // SELECT * yields all columns of both tables, qualified
+    match execute_sql(&mut db, "SELECT * FROM Users JOIN Orders ON Users.id == Orders.user_id") {
+        Ok(SqlOutcome::Selected { columns, rows }) => {
+            assert_eq!(
+                columns,
+                vec!["Users.id", "Users.name", "Orders.id", "Orders.user_id", "Orders.total"]
+            );
+            assert_eq!(rows.len(), 3);
+        }
+        other => panic!("expected rows, got {:?}", other),
+    }
+
+    assert_eq!(
+        execute_sql(&mut db, "SELECT * FROM Orders JOIN Users ON Orders.buyer == Users.id"),
+        Err(SqlError::ColumnNotFound("Orders.buyer".to_string()))
+    );
+    assert_eq!(
+        execute_sql(&mut db, "SELECT * FROM Orders JOIN Items ON Orders.id == Items.id"),
+        Err(SqlError::TableNotFound("Items".to_string()))
+    );
+    assert!(matches!(
+        execute_sql(&mut db, "SELECT * FROM Orders JOIN Users ON user_id == id"),
+        Err(SqlError::Parse(_))
+    ));
+}
+
+#[test]
+fn left_join_unit() {
+    let mut db = Database::new();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Users (id INT PRIMARY KEY, name STRING);
+         CREATE TABLE Orders (id INT PRIMARY KEY, user_id INT, total FLOAT);
+         INSERT INTO Users VALUES (1, 'Alice');
+         INSERT INTO Users VALUES (2, 'Bob');
+         INSERT INTO Users VALUES (3, 'Carol');
+         INSERT INTO Orders VALUES (10, 1, 9.5);
+         INSERT INTO Orders VALUES (11, 2, 20.0);
+         INSERT INTO Orders VALUES (12, 1, 3.25);",
+    )
+    .unwrap();
+
+    // Carol has no orders but still appears, with empty Orders columns
+    let rows = select(
+        &mut db,
+        "SELECT Users.name, Orders.id FROM Users LEFT JOIN Orders ON Users.id == Orders.user_id",
+    );
+    assert_eq!(
+        rows,
+        vec![vec!["Alice", "10"], vec!["Alice", "12"], vec!["Bob", "11"], vec!["Carol", ""]]
+    );
+
+    // LEFT OUTER JOIN is a synonym and works with aliases and WHERE
+    let rows = select(
+        &mut db,
+        "SELECT u.name, o.total FROM Users u LEFT OUTER JOIN Orders o ON o.user_id = u.id \
+         WHERE u.id > 1",
+    );
+    assert_eq!(rows, vec![vec!["Bob", "20.0"], vec!["Carol", ""]]);
+    match execute_sql(&mut db, "SELECT * FROM Users LEFT JOIN Orders ON Users.id == Orders.user_id")
+    {
+        Ok(SqlOutcome::Selected { columns, rows }) => {
+            assert_eq!(columns.len(), 5);
+            assert_eq!(rows[3], vec!["3", "Carol", "", "", ""]);
+        }
+        other => panic!("expected rows, got {:?}", other),
+    }
+
+    // An INNER JOIN over the same data drops Carol
+    let rows = select(
+        &mut db,
+        "SELECT Users.name FROM Users JOIN Orders ON Users.id == Orders.user_id",
+    );
+    assert_eq!(rows.len(), 3);
+}
+
+#[test]
+fn drop_table_unit() {
+    let mut db = products_db();
+    assert_eq!(
+        execute_sql(&mut db, "DROP TABLE Products;"),
+        Ok(SqlOutcome::Dropped(true))
+    );
+    assert!(!db.tables.contains_key("Products"));
+    assert_eq!(
+        execute_sql(&mut db, "SELECT * FROM Products"),
+        Err(SqlError::TableNotFound("Products".to_string()))
+    );
+
+    // Dropping a missing table is an error unless IF EXISTS is given
+    assert_eq!(
+        execute_sql(&mut db, "DROP TABLE Products"),
+        Err(SqlError::TableNotFound("Products".to_string()))
+    );
+    assert_eq!(
+        execute_sql(&mut db, "drop table if exists Products"),
+        Ok(SqlOutcome::Dropped(false))
+    );
+
+    // The name can be reused afterwards
+    execute_sql(&mut db, "CREATE TABLE Products (id INT PRIMARY KEY)").unwrap();
+    assert!(db.drop_table("Products"));
+    assert!(!db.drop_table("Products"));
+}
+
+#[test]
+fn alter_table_add_column_unit() {
+    let mut db = products_db();
+    assert_eq!(
+        execute_sql(&mut db, "ALTER TABLE Products ADD COLUMN weight FLOAT;"),
+        Ok(SqlOutcome::Altered)
+    );
+
+    // Existing rows get an empty value for the new column
+    let table = &db.tables["Products"];
+    assert_eq!(table.schema.columns.len(), 5);
+    assert_eq!(table.rows[0].get_values().len(), 5);
+    assert_eq!(
+        table.rows[0].get_by_name("weight", &table.schema),
+        Some(&Value::Null)
+    );
+
+    // New rows must supply the column, and it can be updated and queried
+    execute_sql(&mut db, "INSERT INTO Products VALUES (4, 'Ruler', 3.0, 10, 0.2)").unwrap();
+    execute_sql(&mut db, "UPDATE Products SET weight = 0.5 WHERE id == 1").unwrap();
+    let rows = select(&mut db, "SELECT id, weight FROM Products WHERE weight > 0.1");
+    assert_eq!(rows, vec![vec!["1", "0.5"], vec!["4", "0.2"]]);
+
+    // COLUMN keyword and type are optional
+    execute_sql(&mut db, "alter table Products add note").unwrap();
+    assert_eq!(
+        db.get_table_columns("Products").last(),
+        Some(&"note".to_string())
+    );
+
+    assert!(matches!(
+        execute_sql(&mut db, "ALTER TABLE Products ADD COLUMN name STRING"),
+        Err(SqlError::Parse(_))
+    ));
+    assert!(matches!(
+        execute_sql(&mut db, "ALTER TABLE Products ADD COLUMN size BLOB"),
+        Err(SqlError::Parse(_))
+    ));
+    assert_eq!(
+        execute_sql(&mut db, "ALTER TABLE Missing ADD COLUMN x INT"),
+        Err(SqlError::TableNotFound("Missing".to_string()))
+    );
+}
+
+#[test]
+fn typed_values_unit() {
+    let mut db = products_db();
+
+    // Values are stored with their column's type
+    let table = &db.tables["Products"];
+    assert_eq!(
+        table.rows[0].get_values(),
+        &vec![
+            Value::Int(1),
+            Value::from("Pen"),
+            Value::Float(2.5),
+            Value::Int(100)
+        ]
+    );
+    assert_eq!(table.rows[1].get_int(3), Some(50));
+    assert_eq!(table.rows[1].get_float(2), Some(1.2));
+    assert_eq!(table.rows[1].get_str(1), Some("Pencil"));
+    assert_eq!(table.rows[1].get_str(0), None);
+    assert_eq!(
+        table.rows[1].get_values_as_strings(),
+        vec!["2", "Pencil", "1.2", "50"]
+    );
+
+    // Typed comparison: 007 is the same integer as 7
+    execute_sql(&mut db, "INSERT INTO Products VALUES (007, 'Ruler', 3, 5)").unwrap();
+    let rows = select(&mut db, "SELECT name, price FROM Products WHERE id == 7");
+    assert_eq!(rows, vec![vec!["Ruler", "3.0"]]);
+    assert_eq!(
+        execute_sql(&mut db, "INSERT INTO Products VALUES (7, 'Tape', 1.0, 1)"),
+        Err(SqlError::PrimaryKeyViolation("id".to_string()))
+    );
+
+    // Values round-trip through JSON with their types
+    let tmp = tempfile::NamedTempFile::new().unwrap();
+    let path = tmp.path().to_str().unwrap().to_string();
+    db.save_to_file(&path).unwrap();
+    let loaded = Database::load_from_file(&path).unwrap();
+    assert_eq!(
+        loaded.tables["Products"].rows[3].get_values(),
+        &vec![Value::Int(7), Value::from("Ruler"), Value::Float(3.0), Value::Int(5)]
+    );
+
+    // Files written with string values are migrated on load
+    let legacy = r#"{"tables":{"T":{"name":"T","schema":{"columns":[
+        {"name":"id","col_type":"Int"},{"name":"score","col_type":"Float"},
+        {"name":"tag","col_type":"String"}]},
+        "rows":[{"values":["1","2.5","x"]},{"values":["2","","10"]}],
+        "primary_key":"id","unique_columns":[]}}}"#;
+    std::fs::write(&path, legacy).unwrap();
+    let loaded = Database::load_from_file(&path).unwrap();
+    let rows = &loaded.tables["T"].rows;
+    assert_eq!(
+        rows[0].get_values(),
+        &vec![Value::Int(1), Value::Float(2.5), Value::from("x")]
+    );
+    assert_eq!(
+        rows[1].get_values(),
+        &vec![Value::Int(2), Value::Null, Value::from("10")]
+    );
+}
+
+#[test]
+fn null_values_unit() {
+    let mut db = Database::new();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE People (id INT PRIMARY KEY, name STRING NOT NULL, age INT, \
+         email STRING UNIQUE)",
+    )
+    .unwrap();
+    let columns = &db.tables["People"].schema.columns;
+    assert!(!columns[0].nullable && !columns[1].nullable);
+    assert!(columns[2].nullable && columns[3].nullable);
+
+    execute_sql(&mut db, "INSERT INTO People VALUES (1, 'Alice', 30, 'a@x')").unwrap();
+    execute_sql(&mut db, "INSERT INTO People VALUES (2, 'Bob', NULL, null)").unwrap();
+    // A quoted 'NULL' is an ordinary string; NULLs do not clash under UNIQUE
+    execute_sql(&mut db, "INSERT INTO People VALUES (3, 'NULL', 25, NULL)").unwrap();
+    let bob = &db.tables["People"].rows[1];
+    assert_eq!(bob.get_values()[2], Value::Null);
+    assert_eq!(bob.get_values()[3], Value::Null);
+
+    // NOT NULL and PRIMARY KEY columns reject NULL
+    assert_eq!(
+        execute_sql(&mut db, "INSERT INTO People VALUES (4, NULL, 1, 'd@x')"),
+        Err(SqlError::NotNullViolation("name".to_string()))
+    );
+    assert_eq!(
+        execute_sql(&mut db, "INSERT INTO People VALUES (NULL, 'Dan', 1, 'd@x')"),
+        Err(SqlError::NotNullViolation("id".to_string()))
+    );
+
+    // Comparisons with NULL are never true, on either side
+    assert!(select(&mut db, "SELECT id FROM People WHERE age != NULL").is_empty());
+    assert!(select(&mut db, "SELECT id FROM People WHERE age == NULL").is_empty());
+    let rows = select(&mut db, "SELECT id FROM People WHERE age != 30");
+    assert_eq!(rows, vec![vec!["3"]]);
+    let rows = select(&mut db, "SELECT id FROM People WHERE age < 100 OR name == 'Bob'");
+    assert_eq!(rows.len(), 3);
+
+    // Absent values are NULL
+    let table = db.tables.get_mut("People").unwrap();
+    table.add_values(vec![Value::Int(5), Value::from("Eve")]).unwrap();
+    assert_eq!(table.rows[3].get_values()[3], Value::Null);
+    assert_eq!(
+        table.add_values(vec![Value::Int(6)]),
+        Err(SqlError::NotNullViolation("name".to_string()))
+    );
+}
+
+#[test]
+fn column_defaults_unit() {
+    let mut db = Database::new();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Events (id INT PRIMARY KEY, created STRING DEFAULT 'unknown', \
+         priority INT NOT NULL DEFAULT 3, note STRING DEFAULT 'to do', score FLOAT)",
+    )
+    .unwrap();
+    let columns = &db.tables["Events"].schema.columns;
+    assert_eq!(columns[1].default, Some(Value::from("unknown")));
+    assert_eq!(columns[2].default, Some(Value::Int(3)));
+    assert!(!columns[2].nullable);
+    assert_eq!(columns[3].default, Some(Value::from("to do")));
+    assert_eq!(columns[4].default, None);
+
+    // Omitted columns take their default, or NULL when there is none
+    execute_sql(&mut db, "INSERT INTO Events (id) VALUES (1)").unwrap();
+    execute_sql(&mut db, "INSERT INTO Events (id, priority) VALUES (2, 9)").unwrap();
+    let rows = &db.tables["Events"].rows;
+    assert_eq!(
+        rows[0].get_values(),
+        &vec![
+            Value::Int(1),
+            Value::from("unknown"),
+            Value::Int(3),
+            Value::from("to do"),
+            Value::Null
+        ]
+    );
+    assert_eq!(rows[1].get_values()[2], Value::Int(9));
+
+    // Full positional inserts are unaffected
+    execute_sql(&mut db, "INSERT INTO Events VALUES (3, 'today', 1, 'x', 0.5)").unwrap();
+    let rows = select(&mut db, "SELECT created FROM Events WHERE id == 3");
+    assert_eq!(rows, vec![vec!["today"]]);
+
+    // Defaults must fit the column type
+    assert!(matches!(
+        execute_sql(&mut db, "CREATE TABLE Bad (id INT DEFAULT 'abc')"),
+        Err(SqlError::TypeMismatch { .. })
+    ));
+    assert!(!db.tables.contains_key("Bad"));
+}
+
+#[test]
+fn insert_column_list_unit() {
+    let mut db = Database::new();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Users (id INT PRIMARY KEY, name STRING, age INT, email STRING)",
+    )
+    .unwrap();
+
+    // Values land in the named columns regardless of schema order
+    execute_sql(
+        &mut db,
+        "INSERT INTO Users (email, age, id, name) VALUES ('c@x', 22, 3, 'Carol')",
+    )
+    .unwrap();
+    execute_sql(&mut db, "INSERT INTO Users (name, id) VALUES ('Dan', 4)").unwrap();
+    let rows = &db.tables["Users"].rows;
+    assert_eq!(
+        rows[0].get_values(),
+        &vec![Value::Int(3), Value::from("Carol"), Value::Int(22), Value::from("c@x")]
+    );
+    assert_eq!(
+        rows[1].get_values(),
+        &vec![Value::Int(4), Value::from("Dan"), Value::Null, Value::Null]
+    );
+
+    // Types are checked against the named column, not the position
+    assert!(matches!(
+        execute_sql(&mut db, "INSERT INTO Users (age, id) VALUES ('old', 5)"),
+        Err(SqlError::TypeMismatch { .. })
+    ));
+
+    assert_eq!(
+        execute_sql(&mut db, "INSERT INTO Users (id, nickname) VALUES (5, 'E')"),
+        Err(SqlError::ColumnNotFound("nickname".to_string()))
+    );
+    assert_eq!(
+        execute_sql(&mut db, "INSERT INTO Users (id, name) VALUES (5)"),
+        Err(SqlError::ColumnCountMismatch {
+            expected: 2,
+            got: 1
+        })
+    );
+    assert!(matches!(
+        execute_sql(&mut db, "INSERT INTO Users (id, id) VALUES (5, 6)"),
+        Err(SqlError::Parse(_))
+    ));
+    assert_eq!(db.row_count("Users").unwrap(), 2);
+}
+
+#[test]
+fn composite_primary_key_unit() {
+    let mut db = Database::new();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Lines (order_id INT, line_no INT, item STRING, PRIMARY KEY (order_id, line_no))",
+    )
+    .unwrap();
+    let table = &db.tables["Lines"];
+    assert_eq!(table.primary_key, vec!["order_id", "line_no"]);
+    assert!(!table.schema.columns[0].nullable && !table.schema.columns[1].nullable);
+
+    // Rows sharing one key component are distinct keys
+    execute_sql(&mut db, "INSERT INTO Lines VALUES (1, 1, 'Pen')").unwrap();
+    execute_sql(&mut db, "INSERT INTO Lines VALUES (1, 2, 'Pencil')").unwrap();
+    execute_sql(&mut db, "INSERT INTO Lines VALUES (2, 1, 'Eraser')").unwrap();
+    assert_eq!(
+        execute_sql(&mut db, "INSERT INTO Lines VALUES (1, 2, 'Ruler')"),
+        Err(SqlError::PrimaryKeyViolation("order_id, line_no".to_string()))
+    );
+    assert_eq!(db.row_count("Lines").unwrap(), 3);
+
+    // Updates that would collide on the full tuple are rejected as a whole
+    assert!(matches!(
+        execute_sql(&mut db, "UPDATE Lines SET line_no = 1 WHERE order_id == 1"),
+        Err(SqlError::PrimaryKeyViolation(_))
+    ));
+    assert_eq!(
+        execute_sql(&mut db, "UPDATE Lines SET order_id = 3 WHERE item == 'Pencil'"),
+        Ok(SqlOutcome::Updated(1))
+    );
+
+    // The key columns must exist, and only one form of PRIMARY KEY is allowed
+    assert_eq!(
+        execute_sql(&mut db, "CREATE TABLE Bad (a INT, PRIMARY KEY (a, b))"),
+        Err(SqlError::ColumnNotFound("b".to_string()))
+    );
+    assert!(matches!(
+        execute_sql(&mut db, "CREATE TABLE Bad (a INT PRIMARY KEY, b INT, PRIMARY KEY (a, b))"),
+        Err(SqlError::Parse(_))
+    ));
+}
+
+#[test]
+fn composite_unique_unit() {
+    let mut db = Database::new();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE People (id INT PRIMARY KEY, first STRING, last STRING, email STRING UNIQUE, UNIQUE (first, last))",
+    )
+    .unwrap();
+    assert_eq!(
+        db.tables["People"].unique_columns,
+        vec![vec!["email".to_string()], vec!["first".to_string(), "last".to_string()]]
+    );
+
+    execute_sql(&mut db, "INSERT INTO People VALUES (1, 'Alice', 'Smith', 'a@s')").unwrap();
+    execute_sql(&mut db, "INSERT INTO People VALUES (2, 'Alice', 'Jones', 'a@j')").unwrap();
+    assert_eq!(
+        execute_sql(&mut db, "INSERT INTO People VALUES (3, 'Alice', 'Smith', 'a@x')"),
+        Err(SqlError::UniqueViolation("first, last".to_string()))
+    );
+    // Single-column uniques still apply on their own
+    assert_eq!(
+        execute_sql(&mut db, "INSERT INTO People VALUES (3, 'Bob', 'Smith', 'a@s')"),
+        Err(SqlError::UniqueViolation("email".to_string()))
+    );
+    // A tuple containing NULL never conflicts
+    execute_sql(&mut db, "INSERT INTO People (id, first) VALUES (4, 'Alice')").unwrap();
+    execute_sql(&mut db, "INSERT INTO People (id, first) VALUES (5, 'Alice')").unwrap();
+    assert_eq!(db.row_count("People").unwrap(), 4);
+
+    assert_eq!(
+        execute_sql(&mut db, "UPDATE People SET last = 'Smith' WHERE id == 2"),
+        Err(SqlError::UniqueViolation("first, last".to_string()))
+    );
+    assert_eq!(
+        execute_sql(&mut db, "UPDATE People SET first = 'Bob' WHERE id == 2"),
+        Ok(SqlOutcome::Updated(1))
+    );
+
+    assert_eq!(
+        execute_sql(&mut db, "CREATE TABLE Bad (a INT, UNIQUE (a, b))"),
+        Err(SqlError::ColumnNotFound("b".to_string()))
+    );
+}
+
+#[test]
+fn auto_increment_unit() {
+    let mut db = Database::new();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Notes (id INT PRIMARY KEY AUTOINCREMENT, body STRING)",
+    )
+    .unwrap();
+    execute_sql(&mut db, "INSERT INTO Notes (body) VALUES ('a')").unwrap();
+    execute_sql(&mut db, "INSERT INTO Notes (body) VALUES ('b')").unwrap();
+    execute_sql(&mut db, "INSERT INTO Notes VALUES (NULL, 'c')").unwrap();
+    let ids = |db: &Database| -> Vec<Value> {
+        db.tables["Notes"]
+            .rows
+            .iter()
+            .map(|r| r.get_values()[0].clone())
+            .collect()
+    };
+    assert_eq!(ids(&db), vec![Value::Int(1), Value::Int(2), Value::Int(3)]);
+
+    // Manual ids advance the counter; a rejected insert does not consume an id
+    execute_sql(&mut db, "INSERT INTO Notes VALUES (10, 'manual')").unwrap();
+    assert!(execute_sql(&mut db, "INSERT INTO Notes VALUES (2, 'dup')").is_err());
+    execute_sql(&mut db, "INSERT INTO Notes (body) VALUES ('d')").unwrap();
+    execute_sql(&mut db, "UPDATE Notes SET id = 20 WHERE body == 'a'").unwrap();
+    execute_sql(&mut db, "INSERT INTO Notes (body) VALUES ('e')").unwrap();
+    assert_eq!(
+        ids(&db),
+        vec![
+            Value::Int(20),
+            Value::Int(2),
+            Value::Int(3),
+            Value::Int(10),
+            Value::Int(11),
+            Value::Int(21)
+        ]
+    );
+
+    // The counter survives a save/load round trip
+    let tmp = tempfile::NamedTempFile::new().unwrap();
+    let path = tmp.path().to_str().unwrap().to_string();
+    db.save_to_file(&path).unwrap();
+    let mut loaded = Database::load_from_file(&path).unwrap();
+    execute_sql(&mut loaded, "INSERT INTO Notes (body) VALUES ('f')").unwrap();
+    assert_eq!(ids(&loaded)[6], Value::Int(22));
+
+    assert!(matches!(
+        execute_sql(&mut db, "CREATE TABLE Bad (code STRING AUTOINCREMENT)"),
+        Err(SqlError::Parse(_))
+    ));
+}
+
+#[test]
+fn primary_key_index_unit() {
+    let mut db = products_db();
+    let table = &db.tables["Products"];
+    assert_eq!(table.find_by_pk("2").unwrap().get_values()[1], "Pencil");
+    assert!(table.find_by_pk("9").is_none());
+
+    // The index follows updates and deletes, including rows shifting position
+    execute_sql(&mut db, "UPDATE Products SET id = 9 WHERE name == 'Pencil'").unwrap();
+    execute_sql(&mut db, "DELETE FROM Products WHERE id == 1").unwrap();
+    let table = &db.tables["Products"];
+    assert!(table.find_by_pk("2").is_none());
+    assert_eq!(table.find_by_pk("9").unwrap().get_values()[1], "Pencil");
+    assert_eq!(table.find_by_pk("3").unwrap().get_values()[1], "Eraser");
+    assert!(execute_sql(&mut db, "INSERT INTO Products VALUES (3, 'Ruler', 1.0, 5)").is_err());
+    execute_sql(&mut db, "INSERT INTO Products VALUES (2, 'Ruler', 1.0, 5)").unwrap();
+
+    // Composite keys join their components with PK_SEPARATOR
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Lines (order_id INT, line_no INT, item STRING, PRIMARY KEY (order_id, line_no))",
+    )
+    .unwrap();
+    execute_sql(&mut db, "INSERT INTO Lines VALUES (1, 2, 'Pen')").unwrap();
+    let key = format!("1{}2", PK_SEPARATOR);
+    assert_eq!(db.tables["Lines"].find_by_pk(&key).unwrap().get_values()[2], "Pen");
+
+    // The index is rebuilt after loading
+    let tmp = tempfile::NamedTempFile::new().unwrap();
+    let path = tmp.path().to_str().unwrap().to_string();
+    db.save_to_file(&path).unwrap();
+    let loaded = Database::load_from_file(&path).unwrap();
+    assert_eq!(loaded.tables["Products"].find_by_pk("9").unwrap().get_values()[1], "Pencil");
+}
+
+#[test]
+fn primary_key_lookup_scales_unit() {
+    // Time a fixed number of PK lookups and inserts against a small and a large table;
+    // with the hash index the large table must not be dramatically slower.
+    fn time_lookups(rows: usize) -> std::time::Duration {
+        let mut db = Database::new();
+        execute_sql(&mut db, "CREATE TABLE T (id INT PRIMARY KEY, name STRING)").unwrap();
+        for i in 0..rows {
+            db.insert("T", vec![i.to_string(), format!("user{}", i)]).unwrap();
+        }
+        let table = &db.tables["T"];
+        let start = std::time::Instant::now();
+        for i in 0..20_000 {
+            let key = (i % rows).to_string();
+            assert!(table.find_by_pk(&key).is_some());
+        }
+        start.elapsed()
+    }
+    let small = time_lookups(100);
+    let large = time_lookups(20_000);
+    assert!(
+        large < small * 20 + std::time::Duration::from_millis(50),
+        "lookups degraded with table size: {:?} vs {:?}",
+        small,
+        large
+    );
+}
+
+#[test]
+fn secondary_index_unit() {
+    let mut db = products_db();
+    execute_sql(&mut db, "INSERT INTO Products VALUES (4, 'Pen', 3.0, 10)").unwrap();
+    let queries = [
+        "SELECT * FROM Products WHERE name == 'Pen'",
+        "SELECT id FROM Products WHERE name == 'Marker'",
+        "SELECT * FROM Products WHERE price == 2.5",
+        "SELECT * FROM Products WHERE name == 'Pen' AND stock > 50",
+    ];
+    let before: Vec<_> = queries.iter().map(|q| select(&mut db, q)).collect();
+
+    assert_eq!(
+        execute_sql(&mut db, "CREATE INDEX products_name ON Products (name)"),
+        Ok(SqlOutcome::IndexCreated)
+    );
+    execute_sql(&mut db, "CREATE INDEX products_price ON Products (price)").unwrap();
+    let table = &db.tables["Products"];
+    assert_eq!(table.lookup_index("name", "Pen").unwrap().len(), 2);
+    assert!(table.lookup_index("stock", "100").is_none());
+    let after: Vec<_> = queries.iter().map(|q| select(&mut db, q)).collect();
+    assert_eq!(before, after);
+
+    // The index is kept up to date by inserts, updates and deletes
+    execute_sql(&mut db, "INSERT INTO Products VALUES (5, 'Marker', 1.5, 7)").unwrap();
+    execute_sql(&mut db, "UPDATE Products SET name = 'Pen' WHERE id == 3").unwrap();
+    execute_sql(&mut db, "DELETE FROM Products WHERE id == 1").unwrap();
+    let ids = |db: &mut Database, q: &str| -> Vec<String> {
+        select(db, q).into_iter().map(|r| r[0].clone()).collect()
+    };
+    assert_eq!(ids(&mut db, "SELECT id FROM Products WHERE name == 'Pen'"), vec!["3", "4"]);
+    assert_eq!(ids(&mut db, "SELECT id FROM Products WHERE name == 'Marker'"), vec!["5"]);
+
+    // Indexes survive a save/load round trip
+    let tmp = tempfile::NamedTempFile::new().unwrap();
+    let path = tmp.path().to_str().unwrap().to_string();
+    db.save_to_file(&path).unwrap();
+    let mut loaded = Database::load_from_file(&path).unwrap();
+    assert_eq!(loaded.tables["Products"].lookup_index("name", "Pen").unwrap().len(), 2);
+    assert_eq!(ids(&mut loaded, "SELECT id FROM Products WHERE name == 'Pen'"), vec!["3", "4"]);
+
+    // Dropping the index falls back to a scan with the same result
+    assert_eq!(
+        execute_sql(&mut db, "DROP INDEX products_name"),
+        Ok(SqlOutcome::IndexDropped)
+    );
+    assert!(db.tables["Products"].lookup_index("name", "Pen").is_none());
+    assert_eq!(ids(&mut db, "SELECT id FROM Products WHERE name == 'Pen'"), vec!["3", "4"]);
+
+    assert_eq!(
+        execute_sql(&mut db, "DROP INDEX products_name"),
+        Err(SqlError::IndexNotFound("products_name".to_string()))
+    );
+    assert!(matches!(
+        execute_sql(&mut db, "CREATE INDEX products_price ON Products (stock)"),
+        Err(SqlError::Parse(_))
+    ));
+    assert_eq!(
+        execute_sql(&mut db, "CREATE INDEX idx ON Products (colour)"),
+        Err(SqlError::ColumnNotFound("colour".to_string()))
+    );
+    assert_eq!(
+        execute_sql(&mut db, "CREATE INDEX idx ON Missing (id)"),
+        Err(SqlError::TableNotFound("Missing".to_string()))
+    );
+}
+
+#[test]
+fn csv_export_unit() {
+    let mut db = products_db();
+    let values = vec![4.into(), "Pens, \"fine\"".into(), 3.0.into(), Value::Null];
+    db.insert_values("Products", values).unwrap();
+    execute_sql(&mut db, "CREATE TABLE Empty (id INT, note STRING)").unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("products.csv");
+    db.export_table_csv("Products", path.to_str().unwrap()).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "id,name,price,stock\r\n\
+         1,Pen,2.5,100\r\n\
+         2,Pencil,1.2,50\r\n\
+         3,Eraser,0.8,30\r\n\
+         4,\"Pens, \"\"fine\"\"\",3.0,\r\n"
+    );
+    assert!(db.export_table_csv("Missing", path.to_str().unwrap()).is_err());
+
+    // One file per table; an empty table has just the header
+    let out = dir.path().join("export");
+    db.export_csv_dir(out.to_str().unwrap()).unwrap();
+    assert!(out.join("Products.csv").exists());
+    assert_eq!(
+        std::fs::read_to_string(out.join("Empty.csv")).unwrap(),
+        "id,note\r\n"
+    );
+}
+
+#[test]
+fn csv_import_unit() {
+    use lab::csv::CsvError;
+
+    // Round trip through export, including a quoted field, NULLs and an empty string
+    let mut db = products_db();
+    let values = vec![4.into(), "Pens, \"fine\"".into(), 3.0.into(), Value::Null];
+    db.insert_values("Products", values).unwrap();
+    db.insert_values("Products", vec![5.into(), "".into(), 1.0.into(), 1.into()]).unwrap();
+    db.insert_values("Products", vec![6.into(), Value::Null, 1.0.into(), 1.into()]).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("products.csv");
+    let path = path.to_str().unwrap();
+    db.export_table_csv("Products", path).unwrap();
+    let mut copy = products_db();
+    execute_sql(&mut copy, "DELETE FROM Products WHERE id > 0").unwrap();
+    assert!(std::fs::read_to_string(path).unwrap().ends_with("5,\"\",1.0,1\r\n6,,1.0,1\r\n"));
+    assert_eq!(copy.import_table_csv("Products", path, false).unwrap(), 6);
+    let rows = |db: &Database| -> Vec<Vec<Value>> {
+        db.tables["Products"]
+            .rows
+            .iter()
+            .map(|r| r.get_values().clone())
+            .collect()
+    };
+    assert_eq!(rows(&copy), rows(&db));
+
+    // Header columns may come in any order; missing columns are NULL
+    let csv = "name,id\n\"Ruler, 30cm\",7\nTape,8\n";
+    std::fs::write(path, csv).unwrap();
+    assert_eq!(db.import_table_csv("Products", path, false).unwrap(), 2);
+    assert_eq!(
+        rows(&db)[6],
+        vec![Value::Int(7), Value::from("Ruler, 30cm"), Value::Null, Value::Null]
+    );
+
+    // A bad row aborts the whole import and reports its line...
+    let csv = "id,name,price\n9,Glue,1.5\n10,Clip\n11,Pin,cheap\n";
+    std::fs::write(path, csv).unwrap();
+    match db.import_table_csv("Products", path, false) {
+        Err(CsvError::Row { line: 3, error }) => assert_eq!(
+            error,
+            SqlError::ColumnCountMismatch {
+                expected: 3,
+                got: 2
+            }
+        ),
+        other => panic!("expected an error on line 3, got {:?}", other),
+    }
+    assert_eq!(db.row_count("Products").unwrap(), 8);
+    // ...or is skipped when asked
+    assert_eq!(db.import_table_csv("Products", path, true).unwrap(), 1);
+    assert_eq!(db.row_count("Products").unwrap(), 9);
+
+    std::fs::write(path, "id,colour\n1,red\n").unwrap();
+    assert!(matches!(
+        db.import_table_csv("Products", path, false),
+        Err(CsvError::Schema(SqlError::ColumnNotFound(_)))
+    ));
+    assert!(matches!(
+        db.import_table_csv("Missing", path, false),
+        Err(CsvError::Schema(SqlError::TableNotFound(_)))
+    ));
+    std::fs::write(path, "id,name\n1,\"open\n").unwrap();
+    assert!(matches!(
+        db.import_table_csv("Products", path, false),
+        Err(CsvError::Row { line: 2, .. })
+    ));
+
+    // Imported rows must reference existing parent rows
+    execute_sql(&mut db, "CREATE TABLE Users (id INT PRIMARY KEY)").unwrap();
+    execute_sql(&mut db, "INSERT INTO Users VALUES (1)").unwrap();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Orders (id INT PRIMARY KEY, uid INT, FOREIGN KEY (uid) REFERENCES Users(id))",
+    )
+    .unwrap();
+    std::fs::write(path, "id,uid\n1,1\n2,99\n").unwrap();
+    assert!(matches!(
+        db.import_table_csv("Orders", path, false),
+        Err(CsvError::Row { line: 3, error: SqlError::ForeignKeyViolation(_) })
+    ));
+    assert_eq!(db.row_count("Orders").unwrap(), 0);
+    assert_eq!(db.import_table_csv("Orders", path, true).unwrap(), 1);
+    assert_eq!(select(&mut db, "SELECT * FROM Orders"), vec![vec!["1", "1"]]);
+}
+
+#[test]
+fn transactions_unit() {
+    let mut db = products_db();
+    let count = |db: &Database| db.row_count("Products").unwrap();
+
+    // A rolled-back INSERT leaves the table unchanged
+    assert_eq!(execute_sql(&mut db, "BEGIN"), Ok(SqlOutcome::Begun));
+    assert!(db.in_transaction());
+    execute_sql(&mut db, "INSERT INTO Products VALUES (4, 'Ruler', 1.0, 5)").unwrap();
+    execute_sql(&mut db, "UPDATE Products SET stock = 0 WHERE id == 1").unwrap();
+    execute_sql(&mut db, "CREATE TABLE Scratch (id INT)").unwrap();
+    assert_eq!(count(&db), 4);
+    assert_eq!(execute_sql(&mut db, "ROLLBACK;"), Ok(SqlOutcome::RolledBack));
+    assert!(!db.in_transaction());
+    assert_eq!(count(&db), 3);
+    assert_eq!(db.tables["Products"].rows[0].get_values()[3], Value::Int(100));
+    assert!(!db.tables.contains_key("Scratch"));
+    // The primary key index is restored along with the rows
+    assert!(db.tables["Products"].find_by_pk("4").is_none());
+
+    // A committed one persists
+    execute_sql(&mut db, "BEGIN TRANSACTION").unwrap();
+    execute_sql(&mut db, "INSERT INTO Products VALUES (4, 'Ruler', 1.0, 5)").unwrap();
+    assert!(execute_sql(&mut db, "INSERT INTO Products VALUES (4, 'Tape', 1.0, 5)").is_err());
+    assert_eq!(execute_sql(&mut db, "COMMIT"), Ok(SqlOutcome::Committed));
+    assert_eq!(count(&db), 4);
+    assert!(matches!(execute_sql(&mut db, "ROLLBACK"), Err(SqlError::Transaction(_))));
+    assert_eq!(count(&db), 4);
+
+    // Nested transactions and stray COMMITs are rejected
+    execute_sql(&mut db, "BEGIN").unwrap();
+    assert!(matches!(execute_sql(&mut db, "BEGIN"), Err(SqlError::Transaction(_))));
+    execute_sql(&mut db, "COMMIT").unwrap();
+    assert!(matches!(execute_sql(&mut db, "COMMIT"), Err(SqlError::Transaction(_))));
+}
+
+#[test]
+fn write_ahead_log_unit() {
+    let dir = tempfile::tempdir().unwrap();
+    let wal_path = dir.path().join("db.wal");
+    let wal_path = wal_path.to_str().unwrap();
+    let snapshot = dir.path().join("db.json");
+    let snapshot = snapshot.to_str().unwrap();
+
+    let mut db = products_db();
+    db.enable_wal(wal_path);
+    db.checkpoint(snapshot).unwrap();
+    for stmt in [
+        "INSERT INTO Products VALUES (4, 'Ruler', 1.0, 5)",
+        "UPDATE Products SET stock = 0 WHERE id == 1",
+        "SELECT * FROM Products",
+        "INSERT INTO Products VALUES (4, 'Duplicate', 1.0, 5)",
+        "DELETE FROM Products WHERE id == 2",
+        "BEGIN",
+        "INSERT INTO Products VALUES (5, 'Uncommitted', 1.0, 5)",
+    ] {
+        let _ = execute_sql(&mut db, stmt);
+    }
+    // Reads are not logged; failed writes are, and replay ignores them again
+    let entries = lab::wal::read_entries(wal_path).unwrap();
+    assert_eq!(entries.len(), 6);
+    assert_eq!(entries[0].query, "INSERT INTO Products VALUES (4, 'Ruler', 1.0, 5)");
+
+    // Simulate a crash: replaying onto the snapshot reconstructs the committed state
+    let mut recovered = Database::load_from_file(snapshot).unwrap();
+    assert_eq!(recovered.recover_from_wal(wal_path).unwrap(), 6);
+    execute_sql(&mut db, "ROLLBACK").unwrap();
+    let contents = |db: &mut Database| select(db, "SELECT * FROM Products ORDER BY id");
+    assert_eq!(contents(&mut recovered), contents(&mut db));
+    assert!(!recovered.in_transaction());
+    assert_eq!(lab::wal::read_entries(wal_path).unwrap().len(), 7);
+
+    // A checkpoint folds the log into the snapshot
+    db.checkpoint(snapshot).unwrap();
+    assert!(lab::wal::read_entries(wal_path).unwrap().is_empty());
+    execute_sql(&mut db, "INSERT INTO Products VALUES (6, 'Tape', 1.0, 5)").unwrap();
+    let mut recovered = Database::load_from_file(snapshot).unwrap();
+    recovered.recover_from_wal(wal_path).unwrap();
+    assert_eq!(contents(&mut recovered), contents(&mut db));
+
+    // A torn final entry is ignored
+    let mut log = std::fs::read_to_string(wal_path).unwrap();
+    log.push_str("{\"timestamp\":1,\"query\":\"DELETE FR");
+    std::fs::write(wal_path, log).unwrap();
+    let mut recovered = Database::load_from_file(snapshot).unwrap();
+    assert_eq!(recovered.recover_from_wal(wal_path).unwrap(), 1);
+}
+
+#[test]
+fn multi_statement_script_unit() {
+    let mut db = Database::new();
+    let script = "
+        -- Users of the demo app
+        CREATE TABLE Users (id INT PRIMARY KEY, name STRING); /* two seed rows */
+        INSERT INTO Users VALUES (1, 'Ann; -- not a comment');
+        INSERT INTO Users VALUES (2, /* inline */ 'Bob');;
+    ";
+    assert_eq!(execute_sql(&mut db, script), Ok(SqlOutcome::Inserted(1)));
+    assert_eq!(
+        select(&mut db, "SELECT name FROM Users; -- trailing comment"),
+        vec![
+            vec!["Ann; -- not a comment".to_string()],
+            vec!["Bob".to_string()],
+        ]
+    );
+    assert_eq!(
+        split_statements("SELECT 1; ; -- only a comment\n/* block */"),
+        vec!["SELECT 1".to_string()]
+    );
+
+    // Execution stops at the first failing statement
+    let err = execute_sql(
+        &mut db,
+        "INSERT INTO Users VALUES (3, 'Cy'); INSERT INTO Missing VALUES (1); \
+         INSERT INTO Users VALUES (4, 'Di')",
+    );
+    assert_eq!(err, Err(SqlError::TableNotFound("Missing".to_string())));
+    assert_eq!(db.row_count("Users").unwrap(), 3);
+    assert!(matches!(execute_sql(&mut db, "-- nothing"), Err(SqlError::Parse(_))));
+}
+
+#[test]
+fn bool_column_unit() {
+    let mut db = Database::new();
+    execute_sql(&mut db, "CREATE TABLE Users (id INT, active BOOLEAN, admin BOOL)").unwrap();
+    let cols = &db.tables.get("Users").unwrap().schema.columns;
+    assert_eq!(cols[1].col_type, ColumnType::Bool);
+    assert_eq!(cols[2].col_type, ColumnType::Bool);
+
+    execute_sql(&mut db, "INSERT INTO Users VALUES (1, true, 0)").unwrap();
+    execute_sql(&mut db, "INSERT INTO Users VALUES (2, FALSE, 1)").unwrap();
+    execute_sql(&mut db, "INSERT INTO Users VALUES (3, 1, False)").unwrap();
+    assert!(matches!(
+        execute_sql(&mut db, "INSERT INTO Users VALUES (4, maybe, 0)"),
+        Err(SqlError::TypeMismatch { expected: ColumnType::Bool, .. })
+    ));
+    assert_eq!(db.tables.get("Users").unwrap().rows[1].get_values()[1], Value::Bool(false));
+
+    assert_eq!(
+        select(&mut db, "SELECT id FROM Users WHERE active == true"),
+        vec![vec!["1".to_string()], vec!["3".to_string()]]
+    );
+    assert_eq!(
+        select(&mut db, "SELECT id, admin FROM Users WHERE active != TRUE"),
+        vec![vec!["2".to_string(), "true".to_string()]]
+    );
+
+    // Booleans round-trip through save/load as JSON true/false
+    let tmp = tempfile::NamedTempFile::new().unwrap();
+    let path = tmp.path().to_str().unwrap();
+    db.save_to_file(path).unwrap();
+    assert!(std::fs::read_to_string(path).unwrap().contains("true"));
+    let loaded = Database::load_from_file(path).unwrap();
+    assert_eq!(loaded.tables.get("Users").unwrap().rows[2].get_values()[2], Value::Bool(false));
+}
+
+#[test]
+fn date_column_unit() {
+    let mut db = Database::new();
+    execute_sql(&mut db, "CREATE TABLE Events (id INT, at DATE)").unwrap();
+    assert_eq!(db.tables.get("Events").unwrap().schema.columns[1].col_type, ColumnType::Date);
+
+    for (id, at) in [
+        (1, "2023-12-31"),
+        (2, "2024-02-29"),
+        (3, "2024-03-01T09:30"),
+        (4, "2024-03-01 18:00:05"),
+        (5, "2025-01-01T00:00:00Z"),
+    ] {
+        execute_sql(&mut db, &format!("INSERT INTO Events VALUES ({}, '{}')", id, at)).unwrap();
+    }
+    for bad in ["2023-02-29", "2024-13-01", "2024-1-05", "yesterday", "2024-03-01T25:00"] {
+        assert!(
+            matches!(
+                execute_sql(&mut db, &format!("INSERT INTO Events VALUES (9, '{}')", bad)),
+                Err(SqlError::TypeMismatch { expected: ColumnType::Date, .. })
+            ),
+            "{} should be rejected",
+            bad
+        );
+    }
+    assert_eq!(db.row_count("Events").unwrap(), 5);
+
+    let ids = |db: &mut Database, filter: &str| {
+        select(db, &format!("SELECT id FROM Events WHERE {}", filter))
+            .into_iter()
+            .map(|row| row[0].clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(ids(&mut db, "at BETWEEN '2024-01-01' AND '2024-03-01T12:00'"), ["2", "3"]);
+    assert_eq!(ids(&mut db, "at >= '2024-03-01'"), ["3", "4", "5"]);
+    // Chronological rather than lexicographic: "2024-03-01 18:00:05" sorts before "...T09:30"
+    assert_eq!(ids(&mut db, "at > '2024-03-01T12:00:00'"), ["4", "5"]);
+    assert_eq!(ids(&mut db, "at < '2024-01-01'"), ["1"]);
+    assert!(ids(&mut db, "at < 'not a date'").is_empty());
+
+    // Equality and IN compare instants, so a space matches a stored `T`, even through an index
+    execute_sql(&mut db, "CREATE INDEX idx_at ON Events (at)").unwrap();
+    assert_eq!(ids(&mut db, "at == '2024-03-01T18:00:05'"), ["4"]);
+    assert_eq!(ids(&mut db, "at == '2024-03-01 09:30:00'"), ["3"]);
+    assert_eq!(ids(&mut db, "at != '2025-01-01 00:00'"), ["1", "2", "3", "4"]);
+    assert_eq!(ids(&mut db, "at IN ('2023-12-31T00:00', '2024-03-01 09:30')"), ["1", "3"]);
+    assert_eq!(ids(&mut db, "at NOT IN ('2024-02-29 00:00:00')"), ["1", "3", "4", "5"]);
+
+    // MIN and MAX are chronological across the two separators
+    let day = "FROM Events WHERE at BETWEEN '2024-03-01' AND '2024-03-02'";
+    let rows = select(&mut db, &format!("SELECT MIN(at), MAX(at) {}", day));
+    assert_eq!(rows, vec![vec!["2024-03-01T09:30", "2024-03-01 18:00:05"]]);
+
+    assert_eq!(date_key("1970-01-01"), Some(0));
+    assert_eq!(date_key("2000-03-01T00:00:01"), Some(951_868_801));
+}
+
+#[test]
+fn update_to_empty_string_unit() {
+    let mut db = Database::new();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Notes (id INT PRIMARY KEY, note STRING, tag STRING)",
+    )
+    .unwrap();
+    execute_sql(&mut db, "INSERT INTO Notes VALUES (1, 'remember milk', 'todo')").unwrap();
+    execute_sql(&mut db, "INSERT INTO Notes VALUES (2, 'call Bob', 'todo')").unwrap();
+
+    assert_eq!(
+        execute_sql(&mut db, "UPDATE Notes SET note = '' WHERE id == 1"),
+        Ok(SqlOutcome::Updated(1))
+    );
+    assert_eq!(
+        select(&mut db, "SELECT * FROM Notes ORDER BY id"),
+        vec![
+            vec!["1".to_string(), "".to_string(), "todo".to_string()],
+            vec!["2".to_string(), "call Bob".to_string(), "todo".to_string()],
+        ]
+    );
+    let row = &db.tables.get("Notes").unwrap().rows[0];
+    assert_eq!(row.get_values()[1], Value::Str(String::new()));
+}
+
+#[test]
+fn update_named_columns_unit() {
+    let mut db = products_db();
+    assert_eq!(
+        execute_sql(&mut db, "UPDATE Products SET stock = 7, name = 'Pen, blue' WHERE id == 1"),
+        Ok(SqlOutcome::Updated(1))
+    );
+    assert_eq!(
+        select(&mut db, "SELECT * FROM Products WHERE id == 1"),
+        vec![vec![
+            "1".to_string(),
+            "Pen, blue".to_string(),
+            "2.5".to_string(),
+            "7".to_string(),
+        ]]
+    );
+
+    // Columns are resolved by name, so adding a column does not shift assignments
+    execute_sql(&mut db, "ALTER TABLE Products ADD COLUMN note STRING").unwrap();
+    let assignments = std::collections::HashMap::from([
+        ("note".to_string(), Assignment::Literal(Some("sale".to_string()))),
+        ("price".to_string(), Assignment::Expr("0.99".to_string())),
+    ]);
+    let table = db.tables.get_mut("Products").unwrap();
+    assert_eq!(table.update_rows_named(&assignments, |r| r[0] == Value::Int(2)), Ok(1));
+    assert_eq!(
+        select(&mut db, "SELECT name, price, note FROM Products WHERE id == 2"),
+        vec![vec!["Pencil".to_string(), "0.99".to_string(), "sale".to_string()]]
+    );
+
+    assert_eq!(
+        execute_sql(&mut db, "UPDATE Products SET colour = 'red' WHERE id == 1"),
+        Err(SqlError::ColumnNotFound("colour".to_string()))
+    );
+}
+
+#[test]
+fn update_set_null_unit() {
+    let mut db = Database::new();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Notes (id INT PRIMARY KEY, name STRING, n INT, tag STRING NOT NULL)",
+    )
+    .unwrap();
+    execute_sql(&mut db, "INSERT INTO Notes VALUES (1, 'first', 5, 'a')").unwrap();
+
+    // An unquoted NULL clears the column, whatever its type
+    assert_eq!(
+        execute_sql(&mut db, "UPDATE Notes SET name = NULL, n = null WHERE id == 1"),
+        Ok(SqlOutcome::Updated(1))
+    );
+    let values = db.tables["Notes"].rows[0].get_values().clone();
+    assert_eq!(values[1..3], [Value::Null, Value::Null]);
+    // A NULL is not the text 'NULL' and equals nothing, not even NULL
+    assert!(select(&mut db, "SELECT id FROM Notes WHERE name == 'NULL'").is_empty());
+    assert!(select(&mut db, "SELECT id FROM Notes WHERE name == NULL").is_empty());
+    assert!(select(&mut db, "SELECT id FROM Notes WHERE n == NULL").is_empty());
+    assert!(select(&mut db, "SELECT id FROM Notes WHERE name != 'first'").is_empty());
+
+    // Quoted, it is just text
+    execute_sql(&mut db, "UPDATE Notes SET name = 'NULL' WHERE id == 1").unwrap();
+    assert_eq!(select(&mut db, "SELECT id FROM Notes WHERE name == 'NULL'"), vec![vec!["1"]]);
+
+    assert_eq!(
+        execute_sql(&mut db, "UPDATE Notes SET tag = NULL WHERE id == 1"),
+        Err(SqlError::NotNullViolation("tag".to_string()))
+    );
+    assert_eq!(db.tables["Notes"].rows[0].get_values()[3], Value::from("a"));
+}
+
+#[test]
+fn delete_all_unit() {
+    let mut db = products_db();
+    assert_eq!(
+        execute_sql(&mut db, "DELETE FROM Products WHERE price < 2.0"),
+        Ok(SqlOutcome::Deleted(2))
+    );
+    assert_eq!(
+        execute_sql(&mut db, "DELETE FROM Products WHERE name == 'Nothing'"),
+        Ok(SqlOutcome::Deleted(0))
+    );
+
+    // Malformed statements are errors and leave the rows alone
+    for bad in [
+        "DELETE Products",
+        "DELETE FROM Products WHERE",
+        "DELETE FROM Products extra",
+        "DELETE FROM",
+    ] {
+        assert!(matches!(execute_sql(&mut db, bad), Err(SqlError::Parse(_))), "{}", bad);
+    }
+    // An unparseable condition is an error rather than deleting nothing
+    assert!(matches!(
+        execute_sql(&mut db, "DELETE FROM Products WHERE ((id == 1"),
+        Err(SqlError::Parse(_))
+    ));
+    assert_eq!(db.row_count("Products").unwrap(), 1);
+
+    let mut db = products_db();
+    assert_eq!(execute_sql(&mut db, "DELETE FROM Products;"), Ok(SqlOutcome::Deleted(3)));
+    assert!(db.tables.get("Products").unwrap().rows.is_empty());
+    assert!(db.tables.get("Products").unwrap().find_by_pk("1").is_none());
+}
+
+#[test]
+fn select_aliases_unit() {
+    let mut db = products_db();
+    match execute_sql(
+        &mut db,
+        "SELECT p.name AS product, price AS cost FROM Products p WHERE p.stock > 40 ORDER BY p.id",
+    ) {
+        Ok(SqlOutcome::Selected { columns, rows }) => {
+            assert_eq!(columns, vec!["product".to_string(), "cost".to_string()]);
+            assert_eq!(
+                rows,
+                vec![
+                    vec!["Pen".to_string(), "2.5".to_string()],
+                    vec!["Pencil".to_string(), "1.2".to_string()],
+                ]
+            );
+        }
+        other => panic!("expected rows, got {:?}", other),
+    }
+
+    // Column aliases and the table name itself work in WHERE
+    assert_eq!(
+        select(&mut db, "SELECT name AS product FROM Products AS p WHERE product == 'Eraser'"),
+        vec![vec!["Eraser".to_string()]]
+    );
+    assert_eq!(
+        select(&mut db, "SELECT Products.id FROM Products WHERE Products.name == 'Pencil'"),
+        vec![vec!["2".to_string()]]
+    );
+
+    // Join tables may be aliased too; results keep the table-qualified names
+    execute_sql(&mut db, "CREATE TABLE Sales (id INT, product_id INT, qty INT)").unwrap();
+    execute_sql(&mut db, "INSERT INTO Sales VALUES (1, 2, 5)").unwrap();
+    match execute_sql(
+        &mut db,
+        "SELECT p.name, s.qty AS sold FROM Sales s JOIN Products p ON s.product_id == p.id",
+    ) {
+        Ok(SqlOutcome::Selected { columns, rows }) => {
+            assert_eq!(columns, vec!["Products.name".to_string(), "sold".to_string()]);
+            assert_eq!(rows, vec![vec!["Pencil".to_string(), "5".to_string()]]);
+        }
+        other => panic!("expected rows, got {:?}", other),
+    }
+
+    // Unknown qualifiers are rejected instead of matching nothing
+    assert!(matches!(
+        execute_sql(&mut db, "SELECT q.name FROM Products p"),
+        Err(SqlError::Parse(_))
+    ));
+    assert!(matches!(
+        execute_sql(&mut db, "SELECT name FROM Products p WHERE x.id == 1"),
+        Err(SqlError::Parse(_))
+    ));
+}
+
+#[test]
+fn row_count_unit() {
+    let mut db = products_db();
+    assert_eq!(db.row_count("Products"), Some(3));
+    assert_eq!(db.row_count("Missing"), None);
+
+    let products = db.tables.get("Products").unwrap();
+    assert_eq!(products.len(), 3);
+    assert!(!products.is_empty());
+
+    execute_sql(&mut db, "DELETE FROM Products").unwrap();
+    assert_eq!(db.row_count("Products"), Some(0));
+    assert!(db.tables.get("Products").unwrap().is_empty());
+}
+
+#[test]
+fn table_accessors_unit() {
+    let mut db = products_db();
+    assert_eq!(db.get_table("Products").map(|t| t.describe().name), Some("Products".into()));
+    assert!(db.get_table("Missing").is_none());
+    assert_eq!(db.table("Products").unwrap().len(), 3);
+    assert_eq!(
+        db.table("Missing").err(),
+        Some(SqlError::TableNotFound("Missing".to_string()))
+    );
+
+    db.get_table_mut("Products").unwrap().delete_rows(|_| true);
+    assert!(db.table("Products").unwrap().is_empty());
+    assert!(db.get_table_mut("Missing").is_none());
+    assert!(matches!(db.table_mut("Missing"), Err(SqlError::TableNotFound(_))));
+}
+
+#[test]
+fn binary_persistence_unit() {
+    let mut db = Database::new();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Test (id INT PRIMARY KEY, name STRING UNIQUE, score FLOAT, ok BOOL)",
+    )
+    .unwrap();
+    for i in 0..1000 {
+        let score = if i % 7 == 0 { "NULL".to_string() } else { format!("{}.5", i) };
+        let sql = format!("INSERT INTO Test VALUES ({}, 'user{}', {}, {})", i, i, score, i % 2);
+        execute_sql(&mut db, &sql).unwrap();
+    }
+    execute_sql(&mut db, "CREATE INDEX test_ok ON Test (ok)").unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let json_path = dir.path().join("db.json").to_string_lossy().to_string();
+    let bin_path = dir.path().join("db.bin").to_string_lossy().to_string();
+    db.save_to_file(&json_path).unwrap();
+    db.save_to_file_bin(&bin_path).unwrap();
+    let json_len = std::fs::metadata(&json_path).unwrap().len();
+    let bin_len = std::fs::metadata(&bin_path).unwrap().len();
+    assert!(bin_len < json_len, "binary {} >= json {}", bin_len, json_len);
+
+    let mut loaded = Database::load_from_file_bin(&bin_path).unwrap();
+    let all = "SELECT * FROM Test";
+    assert_eq!(select(&mut loaded, all), select(&mut db, all));
+    assert_eq!(
+        format!("{:?}", loaded.table("Test").unwrap().describe()),
+        format!("{:?}", db.table("Test").unwrap().describe())
+    );
+    let table = loaded.table("Test").unwrap();
+    assert_eq!(table.find_by_pk("42").unwrap().get_values()[1], "user42");
+    assert_eq!(table.lookup_index("ok", "true").unwrap().len(), 500);
+
+    // A JSON file is not a valid binary file
+    let err = Database::load_from_file_bin(&json_path).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn gzip_persistence_unit() {
+    let mut db = products_db();
+    execute_sql(&mut db, "CREATE TABLE Notes (id INT PRIMARY KEY, body STRING)").unwrap();
+    for i in 0..200 {
+        let sql = format!("INSERT INTO Notes VALUES ({}, 'the same text over and over')", i);
+        execute_sql(&mut db, &sql).unwrap();
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let json_path = dir.path().join("db.json").to_string_lossy().to_string();
+    let gz_path = dir.path().join("db.json.gz").to_string_lossy().to_string();
+    db.save_to_file(&json_path).unwrap();
+    db.save_to_file_gz(&gz_path).unwrap();
+    assert!(std::fs::read(&gz_path).unwrap().starts_with(&[0x1f, 0x8b]));
+    let json_len = std::fs::metadata(&json_path).unwrap().len();
+    assert!(std::fs::metadata(&gz_path).unwrap().len() * 5 < json_len);
+
+    // Compressed files load through either function and match the original
+    let queries = ["SELECT * FROM Products", "SELECT * FROM Notes"];
+    for mut loaded in [
+        Database::load_from_file_gz(&gz_path).unwrap(),
+        Database::load_from_file(&gz_path).unwrap(),
+    ] {
+        for q in queries {
+            assert_eq!(select(&mut loaded, q), select(&mut db, q));
+        }
+        assert!(loaded.table("Notes").unwrap().find_by_pk("199").is_some());
+    }
+    assert!(Database::load_from_file_gz(&json_path).is_err());
+}
+
+#[test]
+fn post_load_init_unit() {
+    let mut db = Database::new();
+    execute_sql(&mut db, "CREATE TABLE Users (id INT PRIMARY KEY AUTOINCREMENT, name STRING)")
+        .unwrap();
+    execute_sql(&mut db, "INSERT INTO Users (name) VALUES ('Alice')").unwrap();
+    execute_sql(&mut db, "INSERT INTO Users (name) VALUES ('Bob')").unwrap();
+    let tmp = tempfile::NamedTempFile::new().unwrap();
+    let path = tmp.path().to_str().unwrap().to_string();
+    db.save_to_file(&path).unwrap();
+
+    // A hand-edited file: an extra row and a counter that was never bumped for it
+    let mut json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    let users = &mut json["tables"]["Users"];
+    users["rows"].as_array_mut().unwrap().push(serde_json::json!({"values": [9, "Carol"]}));
+    users["auto_increment"]["id"] = serde_json::json!(2);
+    std::fs::write(&path, json.to_string()).unwrap();
+
+    let mut loaded = Database::load_from_file(&path).unwrap();
+    let users = loaded.table("Users").unwrap();
+    assert_eq!(users.find_by_pk("9").unwrap().get_values()[1], "Carol");
+    assert_eq!(users.auto_increment["id"], 9);
+    execute_sql(&mut loaded, "INSERT INTO Users (name) VALUES ('Dan')").unwrap();
+    assert_eq!(select(&mut loaded, "SELECT name FROM Users WHERE id == 10"), vec![vec!["Dan"]]);
+
+    // post_load_init can also be run by hand on a database built some other way
+    loaded.post_load_init().unwrap();
+    assert_eq!(loaded.table("Users").unwrap().find_by_pk("10").unwrap().get_values()[1], "Dan");
+}
+
+#[test]
+fn truncate_table_unit() {
+    let mut db = Database::new();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Users (id INT PRIMARY KEY AUTOINCREMENT, email STRING UNIQUE NOT NULL)",
+    )
+    .unwrap();
+    execute_sql(&mut db, "CREATE INDEX users_email ON Users (email)").unwrap();
+    for email in ["a@x.com", "b@x.com", "c@x.com"] {
+        execute_sql(&mut db, &format!("INSERT INTO Users (email) VALUES ('{}')", email)).unwrap();
+    }
+    let schema_before = format!("{:?}", db.table("Users").unwrap().describe());
+
+    assert!(!is_read_only("TRUNCATE TABLE Users"));
+    assert_eq!(execute_sql(&mut db, "TRUNCATE TABLE Users"), Ok(SqlOutcome::Truncated(3)));
+    let users = db.table("Users").unwrap();
+    assert!(users.is_empty());
+    assert!(users.find_by_pk("1").is_none());
+    assert_eq!(users.lookup_index("email", "a@x.com").unwrap().len(), 0);
+    assert_eq!(format!("{:?}", users.describe()), schema_before);
+
+    // Constraints survive and the id sequence starts over, unlike DELETE
+    execute_sql(&mut db, "INSERT INTO Users (email) VALUES ('a@x.com')").unwrap();
+    assert_eq!(select(&mut db, "SELECT id FROM Users"), vec![vec!["1"]]);
+    assert!(matches!(
+        execute_sql(&mut db, "INSERT INTO Users (email) VALUES ('a@x.com')"),
+        Err(SqlError::UniqueViolation(_))
+    ));
+    assert!(matches!(
+        execute_sql(&mut db, "INSERT INTO Users (id, email) VALUES (1, 'd@x.com')"),
+        Err(SqlError::PrimaryKeyViolation(_))
+    ));
+    assert_eq!(db.table("Users").unwrap().lookup_index("email", "a@x.com").unwrap().len(), 1);
+    execute_sql(&mut db, "DELETE FROM Users").unwrap();
+    execute_sql(&mut db, "INSERT INTO Users (email) VALUES ('e@x.com')").unwrap();
+    assert_eq!(select(&mut db, "SELECT id FROM Users"), vec![vec!["2"]]);
+
+    // TABLE is optional; anything else is an error
+    assert_eq!(execute_sql(&mut db, "truncate Users;"), Ok(SqlOutcome::Truncated(1)));
+    assert_eq!(
+        execute_sql(&mut db, "TRUNCATE TABLE Missing"),
+        Err(SqlError::TableNotFound("Missing".to_string()))
+    );
+    for bad in ["TRUNCATE", "TRUNCATE TABLE", "TRUNCATE TABLE Users extra"] {
+        assert!(matches!(execute_sql(&mut db, bad), Err(SqlError::Parse(_))), "{}", bad);
+    }
+}
+
+#[test]
+fn check_constraint_unit() {
+    let mut db = Database::new();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Accounts (id INT PRIMARY KEY, balance FLOAT CHECK (balance >= 0), \
+         lo INT, hi INT, CHECK (lo >= 0 AND (hi < 10 OR hi == 100)))",
+    )
+    .unwrap();
+    assert_eq!(
+        db.table("Accounts").unwrap().checks,
+        vec!["balance >= 0".to_string(), "lo >= 0 AND (hi < 10 OR hi == 100)".to_string()]
+    );
+
+    execute_sql(&mut db, "INSERT INTO Accounts VALUES (1, 10.0, 1, 2)").unwrap();
+    execute_sql(&mut db, "INSERT INTO Accounts VALUES (2, 0, 3, 100)").unwrap();
+    assert_eq!(
+        execute_sql(&mut db, "INSERT INTO Accounts VALUES (3, -0.5, 1, 2)"),
+        Err(SqlError::CheckViolation("balance >= 0".to_string()))
+    );
+    assert_eq!(
+        execute_sql(&mut db, "INSERT INTO Accounts VALUES (3, 1.0, 5, 50)"),
+        Err(SqlError::CheckViolation("lo >= 0 AND (hi < 10 OR hi == 100)".to_string()))
+    );
+    // NULL makes a condition unknown, which does not violate it
+    execute_sql(&mut db, "INSERT INTO Accounts (id, lo, hi) VALUES (3, 1, 2)").unwrap();
+    assert_eq!(db.row_count("Accounts"), Some(3));
+
+    // Updates are checked against the new values and rejected as a whole
+    assert_eq!(
+        execute_sql(&mut db, "UPDATE Accounts SET balance = -1 WHERE id == 1"),
+        Err(SqlError::CheckViolation("balance >= 0".to_string()))
+    );
+    assert_eq!(
+        execute_sql(&mut db, "UPDATE Accounts SET hi = 99 WHERE id <= 2"),
+        Err(SqlError::CheckViolation("lo >= 0 AND (hi < 10 OR hi == 100)".to_string()))
+    );
+    assert_eq!(select(&mut db, "SELECT balance FROM Accounts WHERE id == 1"), vec![vec!["10.0"]]);
+    assert_eq!(
+        execute_sql(&mut db, "UPDATE Accounts SET balance = 5 WHERE id == 1"),
+        Ok(SqlOutcome::Updated(1))
+    );
+
+    // A check naming an unknown column is rejected up front
+    assert_eq!(
+        execute_sql(&mut db, "CREATE TABLE Bad (id INT CHECK (ident > 0))"),
+        Err(SqlError::ColumnNotFound("ident".to_string()))
+    );
+    assert!(matches!(
+        execute_sql(&mut db, "CREATE TABLE Bad (id INT, CHECK ())"),
+        Err(SqlError::Parse(_))
+    ));
+}
+
+#[test]
+fn foreign_key_unit() {
+    let fk_error =
+        |r: Result<SqlOutcome, SqlError>| matches!(r, Err(SqlError::ForeignKeyViolation(_)));
+    let mut db = Database::new();
+    execute_sql(&mut db, "CREATE TABLE Users (id INT PRIMARY KEY, name STRING)").unwrap();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Orders (id INT PRIMARY KEY, user_id INT, \
+         FOREIGN KEY (user_id) REFERENCES Users(id))",
+    )
+    .unwrap();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Notes (id INT PRIMARY KEY, user_id INT, \
+         FOREIGN KEY (user_id) REFERENCES Users(id) ON DELETE CASCADE)",
+    )
+    .unwrap();
+    assert_eq!(
+        db.table("Orders").unwrap().foreign_keys,
+        vec![ForeignKey {
+            column: "user_id".to_string(),
+            ref_table: "Users".to_string(),
+            ref_column: "id".to_string(),
+            on_delete_cascade: false,
+        }]
+    );
+    assert_eq!(db.referencing_tables("Users"), vec!["Notes", "Orders"]);
+
+    execute_sql(&mut db, "INSERT INTO Users VALUES (1, 'ann')").unwrap();
+    execute_sql(&mut db, "INSERT INTO Users VALUES (2, 'bob')").unwrap();
+    execute_sql(&mut db, "INSERT INTO Orders VALUES (10, 1)").unwrap();
+    // NULL references nothing and is allowed
+    execute_sql(&mut db, "INSERT INTO Orders (id) VALUES (11)").unwrap();
+    assert!(fk_error(execute_sql(&mut db, "INSERT INTO Orders VALUES (12, 3)")));
+    assert!(fk_error(execute_sql(&mut db, "UPDATE Orders SET user_id = 3 WHERE id == 10")));
+    assert_eq!(select(&mut db, "SELECT user_id FROM Orders WHERE id == 10"), vec![vec!["1"]]);
+    assert_eq!(db.row_count("Orders"), Some(2));
+
+    // Without CASCADE a referenced parent cannot be deleted; unreferenced ones can
+    execute_sql(&mut db, "INSERT INTO Notes VALUES (20, 2)").unwrap();
+    assert!(fk_error(execute_sql(&mut db, "DELETE FROM Users WHERE id == 1")));
+    assert_eq!(db.row_count("Users"), Some(2));
+    // CASCADE removes the referencing rows along with the parent
+    assert_eq!(
+        execute_sql(&mut db, "DELETE FROM Users WHERE id == 2"),
+        Ok(SqlOutcome::Deleted(1))
+    );
+    assert_eq!(db.row_count("Notes"), Some(0));
+
+    // Changing a parent key is refused while it is referenced, through an index on the
+    // child's column or a scan, and allowed once it is not
+    execute_sql(&mut db, "CREATE INDEX orders_user ON Orders (user_id)").unwrap();
+    assert!(fk_error(execute_sql(&mut db, "UPDATE Users SET id = 5 WHERE id == 1")));
+    execute_sql(&mut db, "INSERT INTO Users VALUES (3, 'cy')").unwrap();
+    execute_sql(&mut db, "INSERT INTO Notes VALUES (21, 3)").unwrap();
+    assert!(fk_error(execute_sql(&mut db, "UPDATE Users SET id = 6 WHERE id == 3")));
+    execute_sql(&mut db, "DELETE FROM Notes WHERE id == 21").unwrap();
+    execute_sql(&mut db, "UPDATE Users SET id = 6 WHERE id == 3").unwrap();
+    execute_sql(&mut db, "DELETE FROM Users WHERE id == 6").unwrap();
+    assert_eq!(select(&mut db, "SELECT id FROM Users"), vec![vec!["1"]]);
+
+    // Referenced tables cannot be dropped or truncated
+    assert!(fk_error(execute_sql(&mut db, "DROP TABLE Users")));
+    assert!(fk_error(execute_sql(&mut db, "TRUNCATE TABLE Users")));
+    assert_eq!(db.row_count("Users"), Some(1));
+
+    // Foreign keys must point at an existing table's single-column primary key
+    assert!(matches!(
+        execute_sql(
+            &mut db,
+            "CREATE TABLE Bad (id INT, uid INT, FOREIGN KEY (uid) REFERENCES Users(name))"
+        ),
+        Err(SqlError::Parse(_))
+    ));
+    assert_eq!(
+        execute_sql(
+            &mut db,
+            "CREATE TABLE Bad (id INT, uid INT, FOREIGN KEY (uid) REFERENCES Nobody(id))"
+        ),
+        Err(SqlError::TableNotFound("Nobody".to_string()))
+    );
+    assert!(matches!(
+        execute_sql(&mut db, "CREATE TABLE Bad (id INT, FOREIGN KEY (uid) REFERENCES Users(id))"),
+        Err(SqlError::ColumnNotFound(_))
+    ));
+}
+
+#[test]
+fn replication_events_apply_once() {
+    let primary_db = Arc::new(RwLock::new(Database::new()));
+    let primary = ReplicationManager::new(ReplicationConfig::new_primary(), primary_db);
+    primary.record_event("CREATE TABLE Users (id INT PRIMARY KEY AUTOINCREMENT)".to_string());
+    primary.record_event("INSERT INTO Users VALUES (NULL)".to_string());
+    let events = primary.get_events();
+    assert_eq!(events.iter().map(|e| e.id).collect::<Vec<_>>(), vec![1, 2]);
+
+    let db = Arc::new(RwLock::new(Database::new()));
+    let replica = ReplicationManager::new(
+        ReplicationConfig::new_replica("http://127.0.0.1:1".to_string()),
+        db.clone(),
+    );
+    replica.apply_events(events.clone()).unwrap();
+    replica.apply_events(events.clone()).unwrap();
+    assert_eq!(db.read().unwrap().row_count("Users"), Some(1));
+
+    // Out-of-order and repeated deliveries are applied once each, in id order
+    let third = ReplicationEvent {
+        id: 3,
+        timestamp: 0,
+        query: "INSERT INTO Users VALUES (NULL)".to_string(),
+        reset: false,
+    };
+    replica.apply_events(vec![third.clone(), events[1].clone(), third]).unwrap();
+    assert_eq!(db.read().unwrap().row_count("Users"), Some(2));
+    assert_eq!(replica.get_events().len(), 3);
+}
+
+#[test]
+fn record_event_skips_reads_and_repeats_unit() {
+    let primary = ReplicationManager::new(
+        ReplicationConfig::new_primary(),
+        Arc::new(RwLock::new(Database::new())),
+    );
+    primary.record_event("CREATE TABLE Users (id INT PRIMARY KEY)".to_string());
+    primary.record_event("SELECT * FROM Users".to_string());
+    primary.record_event("EXPLAIN SELECT * FROM Users WHERE id == 1".to_string());
+    primary.record_event("INSERT INTO Users VALUES (1)".to_string());
+    primary.record_event("INSERT INTO Users VALUES (1)".to_string());
+    let queries: Vec<String> = primary.get_events().into_iter().map(|e| e.query).collect();
+    assert_eq!(
+        queries,
+        vec![
+            "CREATE TABLE Users (id INT PRIMARY KEY)",
+            "INSERT INTO Users VALUES (1)",
+            "INSERT INTO Users VALUES (1)",
+        ]
+    );
+
+    // With deduplication, only consecutive repeats are dropped
+    let mut config = ReplicationConfig::new_primary();
+    config.dedup_consecutive = true;
+    let primary = ReplicationManager::new(config, Arc::new(RwLock::new(Database::new())));
+    for query in ["INSERT INTO T VALUES (1)", "INSERT INTO T VALUES (1)", "DELETE FROM T"] {
+        primary.record_event(query.to_string());
+    }
+    primary.record_event("INSERT INTO T VALUES (1)".to_string());
+    let ids: Vec<u64> = primary.get_events().iter().map(|e| e.id).collect();
+    assert_eq!(ids, vec![1, 2, 3]);
+}
+
+#[test]
+fn replica_display_task_is_opt_in_unit() {
+    let db = Arc::new(RwLock::new(Database::new()));
+    let config = ReplicationConfig::new_replica("http://127.0.0.1:1".to_string());
+    assert!(!config.display);
+    let replica = ReplicationManager::new(config.clone(), db.clone());
+    assert!(!replica.start_display_task());
+
+    let mut config = config;
+    config.display = true;
+    assert!(ReplicationManager::new(config, db.clone()).start_display_task());
+
+    // Primaries never display, even when asked to
+    let mut config = ReplicationConfig::new_primary();
+    config.display = true;
+    assert!(!ReplicationManager::new(config, db).start_display_task());
+}
+
+#[test]
+fn event_log_compaction_unit() {
+    let mut config = ReplicationConfig::new_primary();
+    config.event_retention = 5;
+    let primary = ReplicationManager::new(config, Arc::new(RwLock::new(Database::new())));
+    primary.record_event("CREATE TABLE Users (id INT PRIMARY KEY)".to_string());
+    for id in 1..=7 {
+        primary.record_event(format!("INSERT INTO Users VALUES ({})", id));
+    }
+    assert_eq!(primary.get_events().len(), 8);
+    assert_eq!(primary.compact_events(), 3);
+    let ids: Vec<u64> = primary.get_events().iter().map(|e| e.id).collect();
+    assert_eq!(ids, vec![4, 5, 6, 7, 8]);
+    assert_eq!(primary.compact_events(), 0);
+
+    // Ids keep counting past compacted events, and the log compacts itself as it grows
+    for id in 8..=20 {
+        primary.record_event(format!("INSERT INTO Users VALUES ({})", id));
+    }
+    let events = primary.get_events();
+    assert!(events.len() < 10);
+    assert_eq!(events.last().unwrap().id, 21);
+
+    // A replica missing compacted events refuses to apply past the gap
+    let db = Arc::new(RwLock::new(Database::new()));
+    let replica = ReplicationManager::new(
+        ReplicationConfig::new_replica("http://127.0.0.1:1".to_string()),
+        db.clone(),
+    );
+    assert!(replica.apply_events(events).is_err());
+    assert!(db.read().unwrap().tables.is_empty());
+}
+
+#[test]
+fn create_table_if_not_exists_unit() {
+    let mut db = Database::new();
+    assert_eq!(
+        execute_sql(&mut db, "CREATE TABLE IF NOT EXISTS Users (id INT PRIMARY KEY)"),
+        Ok(SqlOutcome::Created(true))
+    );
+    execute_sql(&mut db, "INSERT INTO Users VALUES (1)").unwrap();
+
+    // Re-running the setup keeps the existing table and its rows
+    assert_eq!(
+        execute_sql(&mut db, "create table if not exists Users (id INT, name STRING)"),
+        Ok(SqlOutcome::Created(false))
+    );
+    // Without IF NOT EXISTS the collision is an error rather than a silent overwrite
+    assert_eq!(
+        execute_sql(&mut db, "CREATE TABLE Users (id INT PRIMARY KEY, name STRING)"),
+        Err(SqlError::Parse("Table 'Users' already exists".to_string()))
+    );
+    assert_eq!(db.row_count("Users"), Some(1));
+    assert_eq!(db.get_table_columns("Users"), vec!["id"]);
+}
+
+#[test]
+fn rename_table_unit() {
+    let mut db = products_db();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Orders (id INT PRIMARY KEY, product INT, \
+         FOREIGN KEY (product) REFERENCES Products(id))",
+    )
+    .unwrap();
+    execute_sql(&mut db, "INSERT INTO Orders VALUES (1, 2)").unwrap();
+
+    assert_eq!(
+        execute_sql(&mut db, "ALTER TABLE Products RENAME TO Items;"),
+        Ok(SqlOutcome::Altered)
+    );
+    assert!(db.get_table("Products").is_none());
+    assert_eq!(db.row_count("Items"), Some(3));
+    assert_eq!(db.get_table("Items").map(|t| t.describe().name), Some("Items".into()));
+    assert_eq!(select(&mut db, "SELECT name FROM Items WHERE id == 2"), vec![vec!["Pencil"]]);
+    // The foreign key now points at the renamed table
+    assert_eq!(db.tables["Orders"].foreign_keys[0].ref_table, "Items");
+    assert!(execute_sql(&mut db, "INSERT INTO Orders VALUES (2, 9)").is_err());
+
+    assert_eq!(
+        db.rename_table("Products", "Goods"),
+        Err(SqlError::TableNotFound("Products".to_string()))
+    );
+    assert_eq!(
+        db.rename_table("Items", "Orders"),
+        Err(SqlError::Parse("Table 'Orders' already exists".to_string()))
+    );
+}
+
+#[test]
+fn rename_column_unit() {
+    let mut db = products_db();
+    execute_sql(&mut db, "CREATE INDEX idx_name ON Products (name)").unwrap();
+    assert_eq!(
+        execute_sql(&mut db, "ALTER TABLE Products RENAME COLUMN id TO product_id"),
+        Ok(SqlOutcome::Altered)
+    );
+    execute_sql(&mut db, "ALTER TABLE Products RENAME COLUMN name TO title").unwrap();
+
+    let table = &db.tables["Products"];
+    assert_eq!(table.primary_key, vec!["product_id"]);
+    assert_eq!(table.indexes["idx_name"], "title");
+    assert_eq!(table.find_by_pk("2").map(|r| r.get_values()[1].to_string()), Some("Pencil".into()));
+    assert_eq!(table.lookup_index("title", "Eraser").map(|rows| rows.len()), Some(1));
+    assert_eq!(
+        select(&mut db, "SELECT title FROM Products WHERE product_id == 1"),
+        vec![vec!["Pen"]]
+    );
+    // The primary key is still enforced under its new name
+    assert!(matches!(
+        execute_sql(&mut db, "INSERT INTO Products VALUES (1, 'Pen', 2.5, 100)"),
+        Err(SqlError::PrimaryKeyViolation(_))
+    ));
+
+    assert_eq!(
+        db.rename_column("Products", "price", "stock"),
+        Err(SqlError::Parse("Column 'stock' already exists in table 'Products'".into()))
+    );
+    assert_eq!(
+        db.rename_column("Products", "id", "key"),
+        Err(SqlError::ColumnNotFound("id".into()))
+    );
+}
+
+#[test]
+fn computed_columns_unit() {
+    let mut db = products_db();
+    match execute_sql(&mut db, "SELECT name, price * stock AS total FROM Products") {
+        Ok(SqlOutcome::Selected { columns, rows }) => {
+            assert_eq!(columns, vec!["name", "total"]);
+            assert_eq!(
+                rows,
+                vec![vec!["Pen", "250.0"], vec!["Pencil", "60.0"], vec!["Eraser", "24.0"]]
+            );
+        }
+        other => panic!("unexpected outcome: {:?}", other),
+    }
+    // Integer arithmetic stays integral; the expression is the header without an alias
+    match execute_sql(&mut db, "SELECT stock - 10 FROM Products WHERE id == 1") {
+        Ok(SqlOutcome::Selected { columns, rows }) => {
+            assert_eq!(columns, vec!["stock - 10"]);
+            assert_eq!(rows, vec![vec!["90"]]);
+        }
+        other => panic!("unexpected outcome: {:?}", other),
+    }
+    assert_eq!(
+        select(&mut db, "SELECT id, stock / 0 AS x, price / 0 AS y FROM Products WHERE id == 2"),
+        vec![vec!["2", "", ""]]
+    );
+    assert_eq!(select(&mut db, "SELECT stock*-2 FROM Products WHERE id == 3"), vec![vec!["-60"]]);
+
+    assert_eq!(
+        execute_sql(&mut db, "SELECT price * weight FROM Products"),
+        Err(SqlError::ColumnNotFound("weight".into()))
+    );
+    assert!(execute_sql(&mut db, "SELECT name + 1 FROM Products").is_err());
+}
+
+#[test]
+fn update_with_arithmetic_unit() {
+    let mut db = products_db();
+    assert_eq!(
+        execute_sql(&mut db, "UPDATE Products SET stock = stock - 1 WHERE id == 1"),
+        Ok(SqlOutcome::Updated(1))
+    );
+    assert_eq!(select(&mut db, "SELECT stock FROM Products WHERE id == 1"), vec![vec!["99"]]);
+
+    // Every assignment sees the row as it was before the update
+    execute_sql(&mut db, "UPDATE Products SET price = price * 2, stock = stock + id").unwrap();
+    assert_eq!(
+        select(&mut db, "SELECT price, stock FROM Products"),
+        vec![vec!["5.0", "100"], vec!["2.4", "52"], vec!["1.6", "33"]]
+    );
+
+    // The result must fit the target column
+    assert!(matches!(
+        execute_sql(&mut db, "UPDATE Products SET stock = price * 2 WHERE id == 2"),
+        Err(SqlError::TypeMismatch { .. })
+    ));
+    assert_eq!(
+        execute_sql(&mut db, "UPDATE Products SET stock = stock - missing"),
+        Err(SqlError::ColumnNotFound("missing".into()))
+    );
+    // Quoted text is a literal and never evaluated
+    execute_sql(&mut db, "UPDATE Products SET name = 'Pen-2' WHERE id == 1").unwrap();
+    assert_eq!(select(&mut db, "SELECT name FROM Products WHERE id == 1"), vec![vec!["Pen-2"]]);
+    assert!(matches!(
+        execute_sql(&mut db, "UPDATE Products SET stock = 'stock + 1' WHERE id == 1"),
+        Err(SqlError::TypeMismatch { .. })
+    ));
+
+    // Unquoted text is evaluated against the row, even for a String column
+    execute_sql(&mut db, "ALTER TABLE Products ADD COLUMN label STRING").unwrap();
+    execute_sql(&mut db, "UPDATE Products SET label = name WHERE id == 1").unwrap();
+    execute_sql(&mut db, "UPDATE Products SET label = stock + 1 WHERE id == 2").unwrap();
+    execute_sql(&mut db, "UPDATE Products SET label = 'name' WHERE id == 3").unwrap();
+    assert_eq!(
+        select(&mut db, "SELECT label FROM Products"),
+        vec![vec!["Pen-2"], vec!["53"], vec!["name"]]
+    );
+    assert!(execute_sql(&mut db, "UPDATE Products SET label = name + 1").is_err());
+}
+
+#[test]
+fn prepared_statement_unit() {
+    let stmt = PreparedStatement::prepare("SELECT * FROM T WHERE a == ? AND b == '?'").unwrap();
+    assert_eq!(stmt.param_count(), 1);
+    assert_eq!(
+        stmt.bind(&[Value::Int(5)]),
+        Ok("SELECT * FROM T WHERE a == 5 AND b == '?'".to_string())
+    );
+    assert_eq!(
+        stmt.bind(&["it's".into()]),
+        Ok("SELECT * FROM T WHERE a == 'it''s' AND b == '?'".to_string())
+    );
+    assert_eq!(
+        stmt.bind(&["both ' and \" \\n".into()]),
+        Ok("SELECT * FROM T WHERE a == 'both '' and \" \\\\n' AND b == '?'".to_string())
+    );
+    assert!(stmt.bind(&[Value::Float(f64::NAN)]).is_err());
+    assert!(stmt.bind(&[]).is_err());
+
+    // Only a single statement can be prepared
+    assert!(PreparedStatement::prepare("DELETE FROM T; DROP TABLE T").is_err());
+}
+
+#[test]
+fn insert_arity_unit() {
+    let mut db = Database::new();
+    execute_sql(&mut db, "CREATE TABLE T (id INT PRIMARY KEY, name STRING, age INT)").unwrap();
+    let mismatch = |expected, got| SqlError::ColumnCountMismatch { expected, got };
+
+    let insert = |db: &mut Database, sql| execute_sql(db, sql).unwrap_err();
+    assert_eq!(insert(&mut db, "INSERT INTO T VALUES (1, 'A')"), mismatch(3, 2));
+    assert_eq!(insert(&mut db, "INSERT INTO T VALUES (1, 'A', 30, 'x')"), mismatch(3, 4));
+
+    let table = db.tables.get_mut("T").unwrap();
+    let raw = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+    assert_eq!(table.add_row(raw(&["1", "A"])), Err(mismatch(3, 2)));
+    assert_eq!(table.add_row(raw(&["1", "A", "30", "extra"])), Err(mismatch(3, 4)));
+    assert_eq!(
+        table.add_values(vec![Value::Int(1), "A".into(), Value::Int(30), Value::Null]),
+        Err(mismatch(3, 4))
+    );
+    assert!(table.is_empty());
+
+    // Leaving out trailing columns of a typed row is intentional and fills in NULL
+    table.add_values(vec![Value::Int(1), "A".into()]).unwrap();
+    table.add_row(raw(&["2", "B", "41"])).unwrap();
+    assert_eq!(table.rows[0].get_values()[2], Value::Null);
+    assert_eq!(table.len(), 2);
+}
+
+#[test]
+fn update_rejects_unknown_columns_unit() {
+    let mut db = products_db();
+    // Nothing is written when any assigned column is unknown
+    assert_eq!(
+        execute_sql(&mut db, "UPDATE Products SET stok = 1, name = 'X', prise = 2 WHERE id == 1"),
+        Err(SqlError::ColumnNotFound("prise, stok".to_string()))
+    );
+    assert_eq!(select(&mut db, "SELECT name FROM Products WHERE id == 1"), vec![vec!["Pen"]]);
+
+    assert!(matches!(
+        execute_sql(&mut db, "UPDATE Products SET stock = 'many' WHERE id == 1"),
+        Err(SqlError::TypeMismatch { .. })
+    ));
+    assert!(matches!(
+        execute_sql(&mut db, "UPDATE Products SET stock = 1, stock = 2"),
+        Err(SqlError::Parse(_))
+    ));
+    assert!(matches!(
+        execute_sql(&mut db, "UPDATE Products SET stock = 1, name 'X'"),
+        Err(SqlError::Parse(_))
+    ));
+
+    // Positional updates need exactly one entry per column
+    let table = db.tables.get_mut("Products").unwrap();
+    assert_eq!(
+        table.update_rows(vec![None, Some("X".to_string())], |_| true),
+        Err(SqlError::ColumnCountMismatch { expected: 4, got: 2 })
+    );
+    assert_eq!(select(&mut db, "SELECT stock FROM Products WHERE id == 1"), vec![vec!["100"]]);
+}
+
+#[test]
+fn explain_select_unit() {
+    let mut db = products_db();
+    let plan = |db: &mut Database, sql| -> Vec<String> {
+        select(db, sql).into_iter().map(|mut row| row.remove(0)).collect()
+    };
+
+    assert_eq!(
+        plan(&mut db, "EXPLAIN SELECT name FROM Products WHERE id == 2"),
+        vec![
+            "Table: Products",
+            "Access: primary key lookup on id",
+            "Estimated rows scanned: 1",
+            "Filter: id == 2",
+        ]
+    );
+    assert_eq!(
+        plan(
+            &mut db,
+            "EXPLAIN SELECT * FROM Products WHERE stock > 40 ORDER BY price DESC LIMIT 1"
+        ),
+        vec![
+            "Table: Products",
+            "Access: full table scan",
+            "Estimated rows scanned: 3",
+            "Filter: stock > 40",
+            "Sort: price DESC",
+            "Limit: 1",
+        ]
+    );
+    execute_sql(&mut db, "CREATE INDEX idx_name ON Products (name)").unwrap();
+    let steps = plan(&mut db, "explain SELECT * FROM Products WHERE name == 'Pen'");
+    assert_eq!(steps[1], "Access: index lookup using idx_name on name");
+
+    // EXPLAIN does not run the statement
+    assert!(execute_sql(&mut db, "EXPLAIN DELETE FROM Products").is_err());
+    assert_eq!(db.row_count("Products"), Some(3));
+    assert_eq!(
+        execute_sql(&mut db, "EXPLAIN SELECT * FROM Missing"),
+        Err(SqlError::TableNotFound("Missing".into()))
+    );
+    // The primary key path returns the same rows as a scan would
+    assert_eq!(select(&mut db, "SELECT name FROM Products WHERE id == 3"), vec![vec!["Eraser"]]);
+}
+
+#[test]
+fn vacuum_unit() {
+    let mut db = products_db();
+    execute_sql(&mut db, "CREATE INDEX idx_name ON Products (name)").unwrap();
+    for round in 0..50 {
+        let id = 100 + round;
+        execute_sql(&mut db, &format!("INSERT INTO Products VALUES ({}, 'Tmp', 1.0, 1)", id))
+            .unwrap();
+        if round % 5 != 0 {
+            execute_sql(&mut db, &format!("DELETE FROM Products WHERE id == {}", id)).unwrap();
+        }
+    }
+    let before = select(&mut db, "SELECT * FROM Products");
+
+    assert_eq!(execute_sql(&mut db, "VACUUM;"), Ok(SqlOutcome::Vacuumed));
+    // The outcome says no file was rewritten
+    assert_eq!(SqlOutcome::Vacuumed.to_string(), "Database vacuumed (memory only)");
+    assert_eq!(select(&mut db, "SELECT * FROM Products"), before);
+    let table = &db.tables["Products"];
+    assert_eq!(table.len(), 13);
+    assert_eq!(table.rows.capacity(), table.len());
+    // Both indexes point at the compacted rows
+    assert_eq!(table.find_by_pk("145").map(|r| r.get_values()[0].clone()), Some(Value::Int(145)));
+    assert!(table.find_by_pk("146").is_none());
+    assert_eq!(table.lookup_index("name", "Tmp").map(|rows| rows.len()), Some(10));
+    assert_eq!(table.lookup_index("name", "Eraser").map(|rows| rows.len()), Some(1));
+}
+
+#[test]
+fn merge_databases_unit() {
+    let shard = |rows: &[&str]| {
+        let mut db = Database::new();
+        execute_sql(&mut db, "CREATE TABLE Users (id INT PRIMARY KEY, name STRING)").unwrap();
+        for row in rows {
+            execute_sql(&mut db, &format!("INSERT INTO Users VALUES ({})", row)).unwrap();
+        }
+        db
+    };
+    let names = |db: &mut Database| select(db, "SELECT * FROM Users ORDER BY id");
+    let mut other = shard(&["2, 'Bobby'", "3, 'Carol'"]);
+    execute_sql(&mut other, "CREATE TABLE Logs (msg STRING)").unwrap();
+    execute_sql(&mut other, "INSERT INTO Logs VALUES ('hello')").unwrap();
+
+    // Skip keeps the existing row on a primary key collision
+    let mut db = shard(&["1, 'Alice'", "2, 'Bob'"]);
+    assert_eq!(db.merge(other.clone(), MergePolicy::Skip), Ok(2));
+    assert_eq!(
+        names(&mut db),
+        vec![vec!["1", "Alice"], vec!["2", "Bob"], vec!["3", "Carol"]]
+    );
+    // Tables only in the other database are added whole
+    assert_eq!(select(&mut db, "SELECT * FROM Logs"), vec![vec!["hello"]]);
+
+    // Overwrite replaces it where it stands, moving only its index entry
+    let mut db = shard(&["1, 'Alice'", "2, 'Bob'"]);
+    execute_sql(&mut db, "CREATE INDEX users_name ON Users (name)").unwrap();
+    assert_eq!(db.merge(other.clone(), MergePolicy::Overwrite), Ok(3));
+    assert_eq!(
+        select(&mut db, "SELECT * FROM Users"),
+        vec![vec!["1", "Alice"], vec!["2", "Bobby"], vec!["3", "Carol"]]
+    );
+    assert_eq!(db.tables["Users"].find_by_pk("2").unwrap().get_values()[1], "Bobby");
+    assert_eq!(db.tables["Users"].lookup_index("name", "Bob").map(|rows| rows.len()), Some(0));
+    assert_eq!(db.tables["Users"].lookup_index("name", "Bobby").map(|rows| rows.len()), Some(1));
+
+    // Error fails the whole merge and changes nothing
+    let mut db = shard(&["1, 'Alice'", "2, 'Bob'"]);
+    assert_eq!(
+        db.merge(other.clone(), MergePolicy::Error),
+        Err(SqlError::PrimaryKeyViolation("id".to_string()))
+    );
+    assert_eq!(names(&mut db), vec![vec!["1", "Alice"], vec!["2", "Bob"]]);
+    assert!(db.get_table("Logs").is_none());
+    assert_eq!(db.merge(shard(&["4, 'Dan'"]), MergePolicy::Error), Ok(1));
+
+    // A same-named table with other columns collides as a whole, resolved by the policy
+    let mut mismatched = Database::new();
+    execute_sql(&mut mismatched, "CREATE TABLE Users (id INT PRIMARY KEY, name INT)").unwrap();
+    execute_sql(&mut mismatched, "INSERT INTO Users VALUES (7, 70)").unwrap();
+    assert!(matches!(
+        db.merge(mismatched.clone(), MergePolicy::Error),
+        Err(SqlError::Parse(msg)) if msg.contains("Users")
+    ));
+    assert_eq!(db.merge(mismatched.clone(), MergePolicy::Skip), Ok(0));
+    assert_eq!(names(&mut db).len(), 3);
+    assert_eq!(db.merge(mismatched, MergePolicy::Overwrite), Ok(1));
+    assert_eq!(names(&mut db), vec![vec!["7", "70"]]);
+}
+
+#[test]
+fn not_operator_unit() {
+    let mut db = products_db();
+    let names = |db: &mut Database, condition: &str| -> Vec<String> {
+        let sql = format!("SELECT name FROM Products WHERE {} ORDER BY id", condition);
+        select(db, &sql).into_iter().map(|r| r[0].clone()).collect()
+    };
+
+    // NOT binds to the comparison that follows, with or without parentheses
+    assert_eq!(names(&mut db, "NOT (id == 1)"), vec!["Pencil", "Eraser"]);
+    assert_eq!(names(&mut db, "NOT id == 1"), names(&mut db, "NOT (id == 1)"));
+    assert!(names(&mut db, "NOT (stock > 0)").is_empty());
+
+    // Double negation cancels, and NOT binds tighter than AND / OR
+    assert_eq!(names(&mut db, "NOT NOT id == 1"), vec!["Pen"]);
+    assert_eq!(names(&mut db, "not (NOT (id == 1))"), vec!["Pen"]);
+    assert_eq!(names(&mut db, "NOT id == 1 AND stock > 40"), vec!["Pencil"]);
+    assert_eq!(names(&mut db, "NOT (id == 1 OR id == 2)"), vec!["Eraser"]);
+    assert_eq!(names(&mut db, "id == 3 OR NOT price < 2.0"), vec!["Pen", "Eraser"]);
+
+    // Postfix NOT forms are unaffected and compose with the prefix operator
+    assert_eq!(names(&mut db, "name NOT LIKE 'Pen%'"), vec!["Eraser"]);
+    assert_eq!(names(&mut db, "NOT name NOT IN ('Pen', 'Eraser')"), vec!["Pen", "Eraser"]);
+    assert_eq!(names(&mut db, "NOT stock NOT BETWEEN 40 AND 60"), vec!["Pencil"]);
+
+    // A comparison with NULL is unknown, and so is its NOT, so a NULL row matches neither
+    db.insert_values("Products", vec![4.into(), "Ruler".into(), 1.5.into(), Value::Null])
+        .unwrap();
+    assert_eq!(names(&mut db, "NOT (stock > 1)"), Vec::<String>::new());
+    assert_eq!(names(&mut db, "NOT stock == 30"), vec!["Pen", "Pencil"]);
+    assert_eq!(names(&mut db, "NOT stock == 30"), names(&mut db, "stock != 30"));
+    assert_eq!(names(&mut db, "NOT stock IN (30, 50)"), vec!["Pen"]);
+    assert_eq!(names(&mut db, "NOT stock BETWEEN 0 AND 60"), vec!["Pen"]);
+    // Unknown OR true is true, unknown AND false is false
+    assert_eq!(names(&mut db, "stock > 1 OR id == 4"), vec!["Pen", "Pencil", "Eraser", "Ruler"]);
+    assert_eq!(names(&mut db, "NOT (stock > 1 AND id == 1)"), vec!["Pencil", "Eraser", "Ruler"]);
+    assert!(names(&mut db, "NOT (stock > 1 OR id == 1)").is_empty());
+    // A list holding NULL leaves values not in it unknown
+    assert!(names(&mut db, "stock NOT IN (30, NULL)").is_empty());
+}
+
+#[test]
+fn union_unit() {
+    let mut db = Database::new();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Customers (id INT PRIMARY KEY, name STRING, city STRING);
+         CREATE TABLE Suppliers (id INT PRIMARY KEY, name STRING, rating FLOAT);
+         INSERT INTO Customers VALUES (1, 'Acme', 'Oslo');
+         INSERT INTO Customers VALUES (2, 'Globex', 'Rome');
+         INSERT INTO Suppliers VALUES (1, 'Initech', 4.5);
+         INSERT INTO Suppliers VALUES (2, 'Acme', 3.0);",
+    )
+    .unwrap();
+
+    // UNION drops the duplicate 'Acme', UNION ALL keeps it; labels come from the first SELECT
+    let union = "SELECT name FROM Customers UNION SELECT name FROM Suppliers";
+    assert_eq!(
+        execute_sql(&mut db, union),
+        Ok(SqlOutcome::Selected {
+            columns: vec!["name".to_string()],
+            rows: vec![vec!["Acme".into()], vec!["Globex".into()], vec!["Initech".into()]],
+        })
+    );
+    let names = select(
+        &mut db,
+        "SELECT name FROM Customers union all SELECT name FROM Suppliers WHERE rating < 4.0",
+    );
+    assert_eq!(names, vec![vec!["Acme"], vec!["Globex"], vec!["Acme"]]);
+
+    // Int and Float columns may be combined; each SELECT keeps its own clauses
+    let rows = select(
+        &mut db,
+        "SELECT id, name FROM Customers WHERE id == 2 UNION SELECT rating, name FROM Suppliers",
+    );
+    assert_eq!(rows, vec![vec!["2.0", "Globex"], vec!["4.5", "Initech"], vec!["3.0", "Acme"]]);
+
+    // Rows are widened to the merged types before deduplication, so INT 1 equals FLOAT 1.0
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Ints (x INT); CREATE TABLE Floats (x FLOAT);
+         INSERT INTO Ints VALUES (1); INSERT INTO Floats VALUES (1.0);",
+    )
+    .unwrap();
+    let rows = select(&mut db, "SELECT x FROM Ints UNION SELECT x FROM Floats");
+    assert_eq!(rows, vec![vec!["1.0"]]);
+
+    // Mismatched column counts and types are rejected
+    assert_eq!(
+        execute_sql(&mut db, "SELECT id, name FROM Customers UNION SELECT name FROM Suppliers"),
+        Err(SqlError::ColumnCountMismatch { expected: 2, got: 1 })
+    );
+    assert!(matches!(
+        execute_sql(&mut db, "SELECT city FROM Customers UNION SELECT rating FROM Suppliers"),
+        Err(SqlError::Parse(_))
+    ));
+}
+
+#[test]
+fn case_insensitive_keywords_unit() {
+    let mut db = Database::new();
+    execute_sql(
+        &mut db,
+        "create table Notes (id int primary key, body string not null, tag string default 'misc')",
+    )
+    .unwrap();
+    execute_sql(&mut db, "insert into Notes values (1, 'x where y', 'a')").unwrap();
+    execute_sql(&mut db, "Insert Into Notes (id, body) Values (2, ' values (9) ')").unwrap();
+    execute_sql(&mut db, "INSERT INTO Notes VALUES (3, 'set from', 'a')").unwrap();
+
+    // Keywords inside string literals are left alone
+    let rows = select(&mut db, "select body, tag from Notes where id < 3 order by id");
+    assert_eq!(rows, vec![vec!["x where y", "a"], vec![" values (9) ", "misc"]]);
+
+    // Mixed case and newlines between clauses read the same as the uppercase form
+    let upper = select(&mut db, "SELECT tag, COUNT(*) FROM Notes GROUP BY tag ORDER BY tag");
+    let mixed = select(&mut db, "Select tag, count(*)\nFrom Notes\nGroup  By tag\nOrder By tag");
+    assert_eq!(mixed, upper);
+    assert_eq!(upper, vec![vec!["a", "2"], vec!["misc", "1"]]);
+
+    execute_sql(&mut db, "update Notes set tag = 'b' where body == 'set from'").unwrap();
+    execute_sql(&mut db, "delete from Notes where tag == 'a'").unwrap();
+    let rows = select(&mut db, "SELECT id, tag FROM Notes order by id desc");
+    assert_eq!(rows, vec![vec!["3", "b"], vec!["2", "misc"]]);
+
+    // Identifiers stay case-sensitive
+    assert_eq!(
+        execute_sql(&mut db, "select * from notes"),
+        Err(SqlError::TableNotFound("notes".to_string()))
+    );
+}
+
+#[test]
+fn quoted_identifiers_unit() {
+    let mut db = Database::new();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE \"Guest List\" (id INT PRIMARY KEY, \"first name\" STRING, `order` INT)",
+    )
+    .unwrap();
+    execute_sql(&mut db, "INSERT INTO \"Guest List\" VALUES (1, 'Ann', 2)").unwrap();
+    let insert = "INSERT INTO `Guest List` (`order`, \"first name\", id) VALUES (1, 'Bo', 2)";
+    execute_sql(&mut db, insert).unwrap();
+    let schema = &db.tables["Guest List"].schema;
+    assert_eq!(schema.columns[1].name, "first name");
+    assert_eq!(schema.columns[2].name, "order");
+
+    // Quoted names may contain spaces and keywords and are returned without their quotes
+    let query =
+        "SELECT \"first name\", `order` AS \"Seat No\" FROM \"Guest List\" ORDER BY `order`";
+    assert_eq!(
+        execute_sql(&mut db, query),
+        Ok(SqlOutcome::Selected {
+            columns: vec!["first name".to_string(), "Seat No".to_string()],
+            rows: vec![vec!["Bo".into(), "1".into()], vec!["Ann".into(), "2".into()]],
+        })
+    );
+    let rows = select(&mut db, "SELECT id FROM `Guest List` WHERE \"first name\" == 'Ann'");
+    assert_eq!(rows, vec![vec!["1"]]);
+    let rows = select(&mut db, "SELECT id FROM `Guest List` WHERE `order` > 1 OR id == \"2\"");
+    assert_eq!(rows, vec![vec!["1"], vec!["2"]]);
+
+    execute_sql(&mut db, "UPDATE \"Guest List\" SET \"first name\" = 'Cy' WHERE `order` == 1")
+        .unwrap();
+    execute_sql(&mut db, "DELETE FROM \"Guest List\" WHERE \"first name\" == 'Ann'").unwrap();
+    assert_eq!(select(&mut db, "SELECT \"first name\" FROM \"Guest List\""), vec![vec!["Cy"]]);
+
+    // Quoted identifiers are case-sensitive
+    assert_eq!(
+        execute_sql(&mut db, "SELECT \"First Name\" FROM \"Guest List\""),
+        Err(SqlError::ColumnNotFound("First Name".to_string()))
+    );
+
+    // Commas inside a quoted name do not split the projection or GROUP BY lists
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Pairs (id INT PRIMARY KEY, \"a,b\" STRING);
+         INSERT INTO Pairs VALUES (1, 'x'); INSERT INTO Pairs VALUES (2, 'x');
+         INSERT INTO Pairs VALUES (3, 'y');",
+    )
+    .unwrap();
+    let query = "SELECT \"a,b\", id FROM Pairs WHERE id == 3";
+    assert_eq!(
+        execute_sql(&mut db, query),
+        Ok(SqlOutcome::Selected {
+            columns: vec!["a,b".to_string(), "id".to_string()],
+            rows: vec![vec!["y".into(), "3".into()]],
+        })
+    );
+    let rows = select(
+        &mut db,
+        "SELECT \"a,b\", COUNT(*) FROM Pairs GROUP BY \"a,b\" ORDER BY \"a,b\"",
+    );
+    assert_eq!(rows, vec![vec!["x", "2"], vec!["y", "1"]]);
+}
+
+#[test]
+fn string_literal_escapes_unit() {
+    let mut db = Database::new();
+    execute_sql(&mut db, "CREATE TABLE Notes (id INT, body STRING DEFAULT 'n''a')").unwrap();
+    execute_sql(&mut db, "INSERT INTO Notes VALUES (1, 'it''s here')").unwrap();
+    let insert = "INSERT INTO Notes VALUES (2, 'line\\nbreak, tab\\t, slash \\\\')";
+    execute_sql(&mut db, insert).unwrap();
+    execute_sql(&mut db, "INSERT INTO Notes VALUES (3, \"say \"\"hi\"\"\")").unwrap();
+    execute_sql(&mut db, "INSERT INTO Notes (id) VALUES (4)").unwrap();
+
+    let rows = select(&mut db, "SELECT body FROM Notes ORDER BY id");
+    assert_eq!(
+        rows,
+        vec![
+            vec!["it's here"],
+            vec!["line\nbreak, tab\t, slash \\"],
+            vec!["say \"hi\""],
+            vec!["n'a"],
+        ]
+    );
+
+    // The decoded value is what WHERE, LIKE and IN compare against
+    let rows = select(&mut db, "SELECT id FROM Notes WHERE body == 'it''s here'");
+    assert_eq!(rows, vec![vec!["1"]]);
+    let rows = select(&mut db, "SELECT id FROM Notes WHERE body LIKE '%''%'");
+    assert_eq!(rows, vec![vec!["1"], vec!["4"]]);
+    let rows = select(
+        &mut db,
+        "SELECT id FROM Notes WHERE body IN ('n''a', 'x') OR body LIKE 'line\\n%'",
+    );
+    assert_eq!(rows, vec![vec!["2"], vec!["4"]]);
+    execute_sql(&mut db, "UPDATE Notes SET body = 'don''t' WHERE body == 'n''a'").unwrap();
+    assert_eq!(select(&mut db, "SELECT body FROM Notes WHERE id == 4"), vec![vec!["don't"]]);
+
+    // Bound parameters round-trip through the same escaping
+    let stmt = PreparedStatement::prepare("SELECT id FROM Notes WHERE body == ?").unwrap();
+    let sql = stmt.bind(&["line\nbreak, tab\t, slash \\".into()]).unwrap();
+    assert_eq!(select(&mut db, &sql), vec![vec!["2"]]);
+}
+
+#[test]
+fn having_unit() {
+    let mut db = Database::new();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Orders (id INT PRIMARY KEY, customer STRING, total FLOAT);
+         INSERT INTO Orders VALUES (1, 'Ann', 10.0);
+         INSERT INTO Orders VALUES (2, 'Bo', 5.0);
+         INSERT INTO Orders VALUES (3, 'Ann', 7.5);
+         INSERT INTO Orders VALUES (4, 'Cy', 40.0);
+         INSERT INTO Orders VALUES (5, 'Ann', 2.5);
+         INSERT INTO Orders VALUES (6, 'Bo', 1.0);",
+    )
+    .unwrap();
+
+    // Only groups whose count exceeds the threshold are kept
+    let sql = "SELECT customer, COUNT(*) FROM Orders GROUP BY customer HAVING COUNT(*) > 1";
+    assert_eq!(select(&mut db, sql), vec![vec!["Ann", "3"], vec!["Bo", "2"]]);
+    let sql = "SELECT customer, COUNT(*) AS n FROM Orders GROUP BY customer having n >= 3";
+    assert_eq!(select(&mut db, sql), vec![vec!["Ann", "3"]]);
+
+    // WHERE filters rows before grouping; HAVING filters the groups, and may use aggregates
+    // and grouped columns that are not projected
+    let sql = "SELECT customer FROM Orders WHERE total > 2.0 GROUP BY customer \
+               HAVING sum(total) < 30 AND NOT customer == 'Cy' ORDER BY customer";
+    assert_eq!(select(&mut db, sql), vec![vec!["Ann"], vec!["Bo"]]);
+    let sql = "SELECT customer, MAX(total) FROM Orders GROUP BY customer \
+               HAVING COUNT(*) == 1 OR AVG(total) > 6.0 LIMIT 5";
+    assert_eq!(
+        execute_sql(&mut db, sql),
+        Ok(SqlOutcome::Selected {
+            columns: vec!["customer".to_string(), "MAX(total)".to_string()],
+            rows: vec![vec!["Ann".into(), "10.0".into()], vec!["Cy".into(), "40.0".into()]],
+        })
+    );
+
+    // Without GROUP BY all rows form one group
+    assert!(select(&mut db, "SELECT COUNT(*) FROM Orders HAVING COUNT(*) > 10").is_empty());
+    assert_eq!(select(&mut db, "SELECT COUNT(*) FROM Orders HAVING COUNT(*) > 5"), vec![vec!["6"]]);
+
+    // HAVING can only see the grouped rows
+    assert_eq!(
+        execute_sql(&mut db, "SELECT customer FROM Orders GROUP BY customer HAVING total > 1"),
+        Err(SqlError::ColumnNotFound("total".to_string()))
+    );
+}
+
+#[test]
+fn multi_column_order_by_unit() {
+    let mut db = Database::new();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Staff (id INT PRIMARY KEY, dept STRING, level INT, hired DATE);
+         INSERT INTO Staff VALUES (1, 'Ops', 2, '2021-03-01');
+         INSERT INTO Staff VALUES (2, 'Dev', 1, '2020-01-15');
+         INSERT INTO Staff VALUES (3, 'Ops', 3, '2019-07-30T08:00');
+         INSERT INTO Staff VALUES (4, 'Dev', 3, '2019-07-30 09:30');
+         INSERT INTO Staff VALUES (5, 'Dev', 1, '2022-11-02');
+         INSERT INTO Staff VALUES (6, 'Art', 2, '2018-05-05');",
+    )
+    .unwrap();
+
+    // Ties on dept fall through to level; rows equal on both keys keep their order
+    let rows = select(&mut db, "SELECT id, dept, level FROM Staff ORDER BY dept ASC, level DESC");
+    assert_eq!(
+        rows,
+        vec![
+            vec!["6", "Art", "2"],
+            vec!["4", "Dev", "3"],
+            vec!["2", "Dev", "1"],
+            vec!["5", "Dev", "1"],
+            vec!["3", "Ops", "3"],
+            vec!["1", "Ops", "2"],
+        ]
+    );
+    let rows = select(&mut db, "SELECT id FROM Staff ORDER BY level desc, hired, id DESC LIMIT 4");
+    assert_eq!(rows, vec![vec!["3"], vec!["4"], vec!["6"], vec!["1"]]);
+
+    assert_eq!(
+        execute_sql(&mut db, "SELECT * FROM Staff ORDER BY dept, grade DESC"),
+        Err(SqlError::ColumnNotFound("grade".to_string()))
+    );
+    assert!(matches!(
+        execute_sql(&mut db, "SELECT * FROM Staff ORDER BY dept, level UP"),
+        Err(SqlError::Parse(_))
+    ));
+    let plan = select(&mut db, "EXPLAIN SELECT * FROM Staff ORDER BY dept, level DESC");
+    assert_eq!(plan.last().unwrap(), &vec!["Sort: dept ASC, level DESC"]);
+}
+
+#[test]
+fn self_referencing_foreign_key_unit() {
+    let mut db = Database::new();
+    execute_sql(
+        &mut db,
+        "CREATE TABLE Staff (id INT PRIMARY KEY, boss INT, \
+         FOREIGN KEY (boss) REFERENCES Staff(id))",
+    )
+    .unwrap();
+    // A row may reference itself or an earlier row, but not a missing one
+    execute_sql(&mut db, "INSERT INTO Staff VALUES (1, 1)").unwrap();
+    execute_sql(&mut db, "INSERT INTO Staff VALUES (2, 1)").unwrap();
+    assert!(matches!(
+        execute_sql(&mut db, "INSERT INTO Staff VALUES (3, 4)"),
+        Err(SqlError::ForeignKeyViolation(_))
+    ));
+
+    // Renumbering a referenced row is refused; moving both rows' references with it is not
+    assert!(matches!(
+        execute_sql(&mut db, "UPDATE Staff SET id = 9 WHERE id == 1"),
+        Err(SqlError::ForeignKeyViolation(_))
+    ));
+    execute_sql(&mut db, "UPDATE Staff SET boss = 2 WHERE id == 2").unwrap();
+    execute_sql(&mut db, "UPDATE Staff SET id = 5, boss = 5 WHERE id == 2").unwrap();
+    let rows = select(&mut db, "SELECT id, boss FROM Staff ORDER BY id");
+    assert_eq!(rows, vec![vec!["1", "1"], vec!["5", "5"]]);
+    assert_eq!(execute_sql(&mut db, "TRUNCATE TABLE Staff"), Ok(SqlOutcome::Truncated(2)));
 }